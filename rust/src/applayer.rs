@@ -497,7 +497,28 @@ extern {
                                                      pp_min_depth: u16, pp_max_depth: u16) -> c_int;
     pub fn AppLayerProtoDetectConfProtoDetectionEnabled(ipproto: *const c_char, proto: *const c_char) -> c_int;
     pub fn AppLayerProtoDetectConfProtoDetectionEnabledDefault(ipproto: *const c_char, proto: *const c_char, default: bool) -> c_int;
+    #[cfg(not(test))]
     pub fn AppLayerRequestProtocolTLSUpgrade(flow: *const Flow) -> bool;
+    pub fn AppLayerRegisterExpectationProto(ipproto: u8, alproto: AppProto);
+}
+
+/// Test variant of `AppLayerRequestProtocolTLSUpgrade`, as the unit tests
+/// are not linked against the C code. The upgrade is never accepted.
+#[cfg(test)]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn AppLayerRequestProtocolTLSUpgrade(_flow: *const Flow) -> bool {
+    false
+}
+
+// Defined in app-layer-expectation.h
+/// cbindgen:ignore
+extern {
+    pub fn AppLayerExpectationCreate(f: *const Flow, direction: c_int, src: u16, dst: u16,
+                                     alproto: AppProto, data: *mut c_void) -> c_int;
+    pub fn AppLayerExpectationCreateAddr(f: *const Flow, direction: c_int, src_addr: *const u8,
+                                         dst_addr: *const u8, addr_len: u8, src: u16, dst: u16,
+                                         alproto: AppProto, data: *mut c_void) -> c_int;
+    pub fn AppLayerExpectationGetData(f: *const Flow) -> *mut c_void;
 }
 
 // Defined in app-layer-parser.h
@@ -514,6 +535,7 @@ pub const APP_LAYER_TX_SKIP_INSPECT_FLAG: u64 = BIT_U64!(62);
 
 /// cbindgen:ignore
 extern {
+    #[cfg(not(test))]
     pub fn AppLayerParserStateSetFlag(state: *mut c_void, flag: u16);
     pub fn AppLayerParserStateIssetFlag(state: *mut c_void, flag: u16) -> u16;
     pub fn AppLayerParserSetStreamDepth(ipproto: u8, alproto: AppProto, stream_depth: u32);
@@ -522,6 +544,12 @@ extern {
     pub fn AppLayerParserRegisterParserAcceptableDataDirection(ipproto: u8, alproto: AppProto, dir: u8);
 }
 
+/// Test variant of `AppLayerParserStateSetFlag`, as the unit tests are not
+/// linked against the C code.
+#[cfg(test)]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn AppLayerParserStateSetFlag(_state: *mut c_void, _flag: u16) {}
+
 #[repr(C)]
 pub struct AppLayerGetTxIterTuple {
     tx_ptr: *mut std::os::raw::c_void,
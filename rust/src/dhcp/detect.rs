@@ -24,8 +24,9 @@ use crate::detect::uint::{
     rs_detect_u64_free, rs_detect_u64_match, rs_detect_u64_parse, DetectUintData,
 };
 use crate::detect::{
-    DetectHelperBufferRegister, DetectHelperKeywordRegister, DetectSignatureSetAppProto,
-    SCSigTableElmt, SigMatchAppendSMToList,
+    DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
+    DetectHelperGetData, DetectHelperKeywordRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
 };
 use std::os::raw::{c_int, c_void};
 
@@ -46,6 +47,8 @@ static mut G_DHCP_REBINDING_TIME_KW_ID: c_int = 0;
 static mut G_DHCP_REBINDING_TIME_BUFFER_ID: c_int = 0;
 static mut G_DHCP_RENEWAL_TIME_KW_ID: c_int = 0;
 static mut G_DHCP_RENEWAL_TIME_BUFFER_ID: c_int = 0;
+static mut G_DHCP_FINGERPRINT_BUFFER_ID: c_int = 0;
+static mut G_DHCP_FINGERPRINT_HASH_BUFFER_ID: c_int = 0;
 
 unsafe extern "C" fn dhcp_detect_leasetime_setup(
     de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
@@ -164,6 +167,84 @@ unsafe extern "C" fn dhcp_detect_renewaltime_match(
     return 0;
 }
 
+unsafe extern "C" fn dhcp_detect_fingerprint_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_DHCP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_DHCP_FINGERPRINT_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn dhcp_detect_fingerprint_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, DHCPTransaction);
+    if !tx.fingerprint.is_empty() {
+        *buffer = tx.fingerprint.as_ptr();
+        *buffer_len = tx.fingerprint.len() as u32;
+        return true;
+    }
+    return false;
+}
+
+unsafe extern "C" fn dhcp_detect_fingerprint_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        dhcp_detect_fingerprint_get,
+    );
+}
+
+unsafe extern "C" fn dhcp_detect_fingerprint_hash_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_DHCP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_DHCP_FINGERPRINT_HASH_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn dhcp_detect_fingerprint_hash_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, DHCPTransaction);
+    if !tx.fingerprint_hash.is_empty() {
+        *buffer = tx.fingerprint_hash.as_ptr();
+        *buffer_len = tx.fingerprint_hash.len() as u32;
+        return true;
+    }
+    return false;
+}
+
+unsafe extern "C" fn dhcp_detect_fingerprint_hash_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        dhcp_detect_fingerprint_hash_get,
+    );
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ScDetectDHCPRegister() {
     let kw = SCSigTableElmt {
@@ -214,4 +295,42 @@ pub unsafe extern "C" fn ScDetectDHCPRegister() {
         true,
         true,
     );
+    let kw = SCSigTableElmt {
+        name: b"dhcp.fingerprint\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the DHCP client fingerprint\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/dhcp-keywords.html#dhcp-fingerprint\0".as_ptr() as *const libc::c_char,
+        Setup: dhcp_detect_fingerprint_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_dhcp_fingerprint_kw_id = DetectHelperKeywordRegister(&kw);
+    G_DHCP_FINGERPRINT_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"dhcp.fingerprint\0".as_ptr() as *const libc::c_char,
+        b"DHCP client fingerprint\0".as_ptr() as *const libc::c_char,
+        ALPROTO_DHCP,
+        true,
+        true,
+        dhcp_detect_fingerprint_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"dhcp.fingerprint_hash\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the md5 of the DHCP client fingerprint\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/dhcp-keywords.html#dhcp-fingerprint-hash\0".as_ptr() as *const libc::c_char,
+        Setup: dhcp_detect_fingerprint_hash_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_dhcp_fingerprint_hash_kw_id = DetectHelperKeywordRegister(&kw);
+    G_DHCP_FINGERPRINT_HASH_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"dhcp.fingerprint_hash\0".as_ptr() as *const libc::c_char,
+        b"DHCP client fingerprint md5\0".as_ptr() as *const libc::c_char,
+        ALPROTO_DHCP,
+        true,
+        true,
+        dhcp_detect_fingerprint_hash_get_data,
+    );
 }
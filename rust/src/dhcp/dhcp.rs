@@ -19,6 +19,9 @@ use crate::applayer::{self, *};
 use crate::core;
 use crate::core::{ALPROTO_UNKNOWN, AppProto, Flow, IPPROTO_UDP};
 use crate::dhcp::parser::*;
+use digest::Digest;
+use digest::Update;
+use md5::Md5;
 use std;
 use std::ffi::CString;
 
@@ -79,19 +82,65 @@ pub enum DHCPEvent {
 pub struct DHCPTransaction {
     tx_id: u64,
     pub message: DHCPMessage,
+    /// Client fingerprint and its md5, empty if the message is not a
+    /// request with a parameter request list.
+    pub fingerprint: Vec<u8>,
+    pub fingerprint_hash: Vec<u8>,
     tx_data: applayer::AppLayerTxData,
 }
 
 impl DHCPTransaction {
     pub fn new(id: u64, message: DHCPMessage) -> DHCPTransaction {
+        let mut fingerprint = Vec::new();
+        let mut fingerprint_hash = Vec::new();
+        if let Some(fp) = dhcp_fingerprint(&message) {
+            fingerprint_hash.extend(format!("{:x}", Md5::new().chain(&fp).finalize()).as_bytes());
+            fingerprint = fp;
+        }
         DHCPTransaction {
             tx_id: id,
             message,
+            fingerprint,
+            fingerprint_hash,
             tx_data: applayer::AppLayerTxData::new(),
         }
     }
 }
 
+/// Builds the client fingerprint, in the style of Fingerbank: the
+/// parameter request list (option 55) as comma separated decimal
+/// codes, in the order sent by the client, followed by "|" and the
+/// vendor class identifier (option 60) if present.
+fn dhcp_fingerprint(message: &DHCPMessage) -> Option<Vec<u8>> {
+    if message.header.opcode != BOOTP_REQUEST {
+        return None;
+    }
+    let mut params = None;
+    let mut vendor_class = None;
+    for option in &message.options {
+        if let DHCPOptionWrapper::Generic(ref generic) = option.option {
+            match option.code {
+                DHCP_OPT_PARAMETER_LIST => params = Some(&generic.data),
+                DHCP_OPT_VENDOR_CLASS_ID => vendor_class = Some(&generic.data),
+                _ => {}
+            }
+        }
+    }
+    let params = params?;
+    if params.is_empty() {
+        return None;
+    }
+    let codes: Vec<String> = params.iter().map(|c| c.to_string()).collect();
+    let mut fingerprint = codes.join(",").into_bytes();
+    if let Some(vendor_class) = vendor_class {
+        if !vendor_class.is_empty() {
+            fingerprint.push(b'|');
+            fingerprint.extend_from_slice(vendor_class);
+        }
+    }
+    Some(fingerprint)
+}
+
 impl Transaction for DHCPTransaction {
     fn id(&self) -> u64 {
         self.tx_id
@@ -314,3 +363,26 @@ pub unsafe extern "C" fn rs_dhcp_register_parser() {
         SCLogDebug!("Protocol detector and parser disabled for DHCP.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhcp_fingerprint() {
+        let pcap = include_bytes!("discover.pcap");
+        let payload = &pcap[24 + 16 + 42..];
+        let (_, message) = dhcp_parse(payload).unwrap();
+        let tx = DHCPTransaction::new(1, message);
+        assert_eq!(tx.fingerprint, b"1,3,6,42");
+        assert_eq!(tx.fingerprint_hash.len(), 32);
+
+        // Replies are not fingerprinted.
+        let pcap = include_bytes!("offer.pcap");
+        let payload = &pcap[24 + 16 + 42..];
+        let (_, message) = dhcp_parse(payload).unwrap();
+        let tx = DHCPTransaction::new(2, message);
+        assert!(tx.fingerprint.is_empty());
+        assert!(tx.fingerprint_hash.is_empty());
+    }
+}
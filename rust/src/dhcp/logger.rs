@@ -92,6 +92,11 @@ impl DHCPLogger {
                       &format_addr_hex(&header.clienthw))?;
         js.set_string("assigned_ip", &dns_print_addr(&header.yourip))?;
 
+        if !tx.fingerprint.is_empty() {
+            js.set_string_from_bytes("fingerprint", &tx.fingerprint)?;
+            js.set_string_from_bytes("fingerprint_hash", &tx.fingerprint_hash)?;
+        }
+
         if self.extended {
             js.set_string("client_ip", &dns_print_addr(&header.clientip))?;
             if header.opcode == BOOTP_REPLY {
@@ -248,7 +253,7 @@ impl DHCPLogger {
 
 }
 
-fn format_addr_hex(input: &[u8]) -> String {
+pub(crate) fn format_addr_hex(input: &[u8]) -> String {
     let parts: Vec<String> = input.iter()
         .map(|b| format!("{:02x}", b))
         .collect();
@@ -0,0 +1,436 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::applayer::{self, *};
+use crate::conf::conf_get;
+use crate::core::{self, AppProto, Direction, Flow, ALPROTO_UNKNOWN, IPPROTO_UDP};
+use crate::dhcpv6::parser::*;
+use lazy_static::lazy_static;
+use std;
+use std::ffi::CString;
+use std::net::Ipv6Addr;
+
+pub(super) static mut ALPROTO_DHCPV6: AppProto = ALPROTO_UNKNOWN;
+
+/// Server addresses and DUIDs from the configuration. When set, server
+/// messages from other servers are flagged.
+struct DHCPv6AuthorizedServers {
+    servers: Vec<[u8; 16]>,
+    duids: Vec<Vec<u8>>,
+}
+
+lazy_static! {
+    static ref DHCPV6_AUTHORIZED_SERVERS: DHCPv6AuthorizedServers = dhcpv6_load_config();
+}
+
+extern "C" {
+    fn FlowGetIPv6Addresses(flow: *const Flow, src: *mut u8, dst: *mut u8) -> bool;
+}
+
+#[derive(AppLayerEvent)]
+pub enum DHCPv6Event {
+    MalformedOptions,
+    /// Advertise or Reply without a Server Identifier option
+    MissingServerId,
+    /// Server message from a server which is not an authorized one
+    UnauthorizedServer,
+}
+
+/// As for DHCP, each message is its own transaction.
+pub struct DHCPv6Transaction {
+    tx_id: u64,
+    pub message: DHCPv6Message,
+    tx_data: applayer::AppLayerTxData,
+}
+
+impl DHCPv6Transaction {
+    pub fn new(id: u64, message: DHCPv6Message) -> DHCPv6Transaction {
+        DHCPv6Transaction {
+            tx_id: id,
+            message,
+            tx_data: applayer::AppLayerTxData::new(),
+        }
+    }
+}
+
+impl Transaction for DHCPv6Transaction {
+    fn id(&self) -> u64 {
+        self.tx_id
+    }
+}
+
+#[derive(Default)]
+pub struct DHCPv6State {
+    state_data: AppLayerStateData,
+
+    // Internal transaction ID.
+    tx_id: u64,
+
+    // List of transactions.
+    transactions: Vec<DHCPv6Transaction>,
+}
+
+impl State<DHCPv6Transaction> for DHCPv6State {
+    fn get_transaction_count(&self) -> usize {
+        self.transactions.len()
+    }
+
+    fn get_transaction_by_index(&self, index: usize) -> Option<&DHCPv6Transaction> {
+        self.transactions.get(index)
+    }
+}
+
+/// Returns true if the message is sent by a server
+fn is_server_message(msg_type: u8) -> bool {
+    matches!(
+        msg_type,
+        DHCPV6_MSG_ADVERTISE | DHCPV6_MSG_REPLY | DHCPV6_MSG_RECONFIGURE | DHCPV6_MSG_RELAY_REPL
+    )
+}
+
+/// Source address of the packet, if the flow is IPv6
+fn packet_source(flow: *const Flow, direction: Direction) -> Option<[u8; 16]> {
+    if flow.is_null() {
+        return None;
+    }
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    if unsafe { !FlowGetIPv6Addresses(flow, src.as_mut_ptr(), dst.as_mut_ptr()) } {
+        return None;
+    }
+    match direction {
+        Direction::ToServer => Some(src),
+        Direction::ToClient => Some(dst),
+    }
+}
+
+impl DHCPv6State {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Checks a server message against the authorized servers.
+    ///
+    /// Returns the events to set on the transaction.
+    fn check_server(
+        message: &DHCPv6Message, source: Option<[u8; 16]>, servers: &[[u8; 16]], duids: &[Vec<u8>],
+    ) -> Vec<DHCPv6Event> {
+        let mut events = Vec::new();
+        if !is_server_message(message.msg_type) {
+            return events;
+        }
+        if let (Some(source), false) = (source, servers.is_empty()) {
+            if !servers.contains(&source) {
+                events.push(DHCPv6Event::UnauthorizedServer);
+            }
+        }
+        let inner = message.inner_message();
+        if inner.msg_type == DHCPV6_MSG_ADVERTISE || inner.msg_type == DHCPV6_MSG_REPLY {
+            match inner.server_id() {
+                Some(duid) => {
+                    if !duids.is_empty() && !duids.iter().any(|d| d == duid) && events.is_empty() {
+                        events.push(DHCPv6Event::UnauthorizedServer);
+                    }
+                }
+                None => events.push(DHCPv6Event::MissingServerId),
+            }
+        }
+        events
+    }
+
+    pub fn parse(&mut self, flow: *const Flow, input: &[u8], direction: Direction) -> bool {
+        match dhcpv6_parse(input) {
+            Ok((_, message)) => {
+                let source = packet_source(flow, direction);
+                let events = Self::check_server(
+                    &message,
+                    source,
+                    &DHCPV6_AUTHORIZED_SERVERS.servers,
+                    &DHCPV6_AUTHORIZED_SERVERS.duids,
+                );
+                let malformed_options = message.malformed_options;
+                self.tx_id += 1;
+                let mut transaction = DHCPv6Transaction::new(self.tx_id, message);
+                if malformed_options {
+                    transaction
+                        .tx_data
+                        .set_event(DHCPv6Event::MalformedOptions as u8);
+                }
+                for event in events {
+                    transaction.tx_data.set_event(event as u8);
+                }
+                self.transactions.push(transaction);
+                return true;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    pub fn get_tx(&mut self, tx_id: u64) -> Option<&DHCPv6Transaction> {
+        self.transactions.iter().find(|tx| tx.tx_id == tx_id + 1)
+    }
+
+    fn free_tx(&mut self, tx_id: u64) {
+        if let Some(index) = self
+            .transactions
+            .iter()
+            .position(|tx| tx.tx_id == tx_id + 1)
+        {
+            self.transactions.remove(index);
+        }
+    }
+}
+
+/// Loads the authorized servers from app-layer.protocols.dhcpv6
+fn dhcpv6_load_config() -> DHCPv6AuthorizedServers {
+    let mut servers = Vec::new();
+    for i in 0.. {
+        let key = format!("app-layer.protocols.dhcpv6.authorized-servers.{}", i);
+        let val = match conf_get(&key) {
+            Some(val) => val,
+            None => break,
+        };
+        match val.parse::<Ipv6Addr>() {
+            Ok(addr) => servers.push(addr.octets()),
+            Err(_) => {
+                SCLogError!("Invalid DHCPv6 authorized server address {}", val);
+            }
+        }
+    }
+    let mut duids = Vec::new();
+    for i in 0.. {
+        let key = format!("app-layer.protocols.dhcpv6.authorized-server-duids.{}", i);
+        let val = match conf_get(&key) {
+            Some(val) => val,
+            None => break,
+        };
+        let hex: String = val.chars().filter(|c| *c != ':').collect();
+        let duid: Result<Vec<u8>, _> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("-"), 16))
+            .collect();
+        match duid {
+            Ok(duid) if !duid.is_empty() => duids.push(duid),
+            _ => {
+                SCLogError!("Invalid DHCPv6 authorized server DUID {}", val);
+            }
+        }
+    }
+    DHCPv6AuthorizedServers { servers, duids }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_probing_parser(
+    _flow: *const Flow, _direction: u8, input: *const u8, input_len: u32, _rdir: *mut u8,
+) -> AppProto {
+    if input.is_null() {
+        return ALPROTO_UNKNOWN;
+    }
+
+    let slice = build_slice!(input, input_len as usize);
+    match dhcpv6_parse_header(slice) {
+        Ok((_, _)) => {
+            return ALPROTO_DHCPV6;
+        }
+        _ => {
+            return ALPROTO_UNKNOWN;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_dhcpv6_tx_get_alstate_progress(
+    _tx: *mut std::os::raw::c_void, _direction: u8,
+) -> std::os::raw::c_int {
+    // As this is a stateless parser, simply use 1.
+    return 1;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_state_get_tx(
+    state: *mut std::os::raw::c_void, tx_id: u64,
+) -> *mut std::os::raw::c_void {
+    let state = cast_pointer!(state, DHCPv6State);
+    match state.get_tx(tx_id) {
+        Some(tx) => {
+            return tx as *const _ as *mut _;
+        }
+        None => {
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_state_get_tx_count(state: *mut std::os::raw::c_void) -> u64 {
+    let state = cast_pointer!(state, DHCPv6State);
+    return state.tx_id;
+}
+
+unsafe extern "C" fn rs_dhcpv6_parse_ts(
+    flow: *const core::Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
+) -> AppLayerResult {
+    let state = cast_pointer!(state, DHCPv6State);
+    if state.parse(flow, stream_slice.as_slice(), Direction::ToServer) {
+        return AppLayerResult::ok();
+    }
+    return AppLayerResult::err();
+}
+
+unsafe extern "C" fn rs_dhcpv6_parse_tc(
+    flow: *const core::Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
+) -> AppLayerResult {
+    let state = cast_pointer!(state, DHCPv6State);
+    if state.parse(flow, stream_slice.as_slice(), Direction::ToClient) {
+        return AppLayerResult::ok();
+    }
+    return AppLayerResult::err();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_state_tx_free(state: *mut std::os::raw::c_void, tx_id: u64) {
+    let state = cast_pointer!(state, DHCPv6State);
+    state.free_tx(tx_id);
+}
+
+#[no_mangle]
+pub extern "C" fn rs_dhcpv6_state_new(
+    _orig_state: *mut std::os::raw::c_void, _orig_proto: AppProto,
+) -> *mut std::os::raw::c_void {
+    let state = DHCPv6State::new();
+    let boxed = Box::new(state);
+    return Box::into_raw(boxed) as *mut _;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_state_free(state: *mut std::os::raw::c_void) {
+    std::mem::drop(Box::from_raw(state as *mut DHCPv6State));
+}
+
+export_tx_data_get!(rs_dhcpv6_get_tx_data, DHCPv6Transaction);
+export_state_data_get!(rs_dhcpv6_get_state_data, DHCPv6State);
+
+const PARSER_NAME: &[u8] = b"dhcpv6\0";
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_register_parser() {
+    SCLogDebug!("Registering DHCPv6 parser.");
+    let ports = CString::new("[546,547]").unwrap();
+    let parser = RustParser {
+        name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
+        default_port: ports.as_ptr(),
+        ipproto: IPPROTO_UDP,
+        probe_ts: Some(rs_dhcpv6_probing_parser),
+        probe_tc: Some(rs_dhcpv6_probing_parser),
+        min_depth: 0,
+        max_depth: 16,
+        state_new: rs_dhcpv6_state_new,
+        state_free: rs_dhcpv6_state_free,
+        tx_free: rs_dhcpv6_state_tx_free,
+        parse_ts: rs_dhcpv6_parse_ts,
+        parse_tc: rs_dhcpv6_parse_tc,
+        get_tx_count: rs_dhcpv6_state_get_tx_count,
+        get_tx: rs_dhcpv6_state_get_tx,
+        tx_comp_st_ts: 1,
+        tx_comp_st_tc: 1,
+        tx_get_progress: rs_dhcpv6_tx_get_alstate_progress,
+        get_eventinfo: Some(DHCPv6Event::get_event_info),
+        get_eventinfo_byid: Some(DHCPv6Event::get_event_info_by_id),
+        localstorage_new: None,
+        localstorage_free: None,
+        get_tx_files: None,
+        get_tx_iterator: Some(applayer::state_get_tx_iterator::<DHCPv6State, DHCPv6Transaction>),
+        get_tx_data: rs_dhcpv6_get_tx_data,
+        get_state_data: rs_dhcpv6_get_state_data,
+        apply_tx_config: None,
+        flags: 0,
+        get_frame_id_by_name: None,
+        get_frame_name_by_id: None,
+    };
+
+    let ip_proto_str = CString::new("udp").unwrap();
+
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
+        ALPROTO_DHCPV6 = alproto;
+        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+            let _ = AppLayerRegisterParser(&parser, alproto);
+        }
+        // load the configuration now rather than on the first packet
+        lazy_static::initialize(&DHCPV6_AUTHORIZED_SERVERS);
+    } else {
+        SCLogDebug!("Protocol detector and parser disabled for DHCPv6.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advertise(server_id: bool) -> DHCPv6Message {
+        let mut options = Vec::new();
+        if server_id {
+            options.push(DHCPv6Option::ServerId(vec![0x00, 0x03, 0x00, 0x01, 0x01]));
+        }
+        DHCPv6Message {
+            msg_type: DHCPV6_MSG_ADVERTISE,
+            txid: 1,
+            relay: None,
+            options,
+            malformed_options: false,
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_check_server() {
+        let server: Ipv6Addr = "fe80::1".parse().unwrap();
+        let rogue: Ipv6Addr = "fe80::bad".parse().unwrap();
+        let servers = [server.octets()];
+
+        // nothing configured
+        let events = DHCPv6State::check_server(&advertise(true), Some(rogue.octets()), &[], &[]);
+        assert!(events.is_empty());
+
+        let events =
+            DHCPv6State::check_server(&advertise(true), Some(server.octets()), &servers, &[]);
+        assert!(events.is_empty());
+        let events =
+            DHCPv6State::check_server(&advertise(true), Some(rogue.octets()), &servers, &[]);
+        assert!(matches!(events[..], [DHCPv6Event::UnauthorizedServer]));
+
+        let events = DHCPv6State::check_server(
+            &advertise(true),
+            Some(server.octets()),
+            &[],
+            &[vec![0x00, 0x03, 0x00, 0x01, 0x02]],
+        );
+        assert!(matches!(events[..], [DHCPv6Event::UnauthorizedServer]));
+
+        let events = DHCPv6State::check_server(&advertise(false), None, &[], &[]);
+        assert!(matches!(events[..], [DHCPv6Event::MissingServerId]));
+
+        // client messages are not checked
+        let mut solicit = advertise(false);
+        solicit.msg_type = DHCPV6_MSG_SOLICIT;
+        let events = DHCPv6State::check_server(&solicit, Some(rogue.octets()), &servers, &[]);
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use std;
+use std::os::raw::c_void;
+
+use crate::conf::ConfNode;
+use crate::dhcp::logger::format_addr_hex;
+use crate::dhcpv6::dhcpv6::DHCPv6Transaction;
+use crate::dhcpv6::parser::*;
+use crate::dns::log::dns_print_addr;
+use crate::jsonbuilder::{JsonBuilder, JsonError};
+
+pub struct DHCPv6Logger {
+    extended: bool,
+}
+
+pub fn dhcpv6_msg_type_string(msg_type: u8) -> &'static str {
+    match msg_type {
+        DHCPV6_MSG_SOLICIT => "solicit",
+        DHCPV6_MSG_ADVERTISE => "advertise",
+        DHCPV6_MSG_REQUEST => "request",
+        DHCPV6_MSG_CONFIRM => "confirm",
+        DHCPV6_MSG_RENEW => "renew",
+        DHCPV6_MSG_REBIND => "rebind",
+        DHCPV6_MSG_REPLY => "reply",
+        DHCPV6_MSG_RELEASE => "release",
+        DHCPV6_MSG_DECLINE => "decline",
+        DHCPV6_MSG_RECONFIGURE => "reconfigure",
+        DHCPV6_MSG_INFORMATION_REQUEST => "information_request",
+        DHCPV6_MSG_RELAY_FORW => "relay_forw",
+        DHCPV6_MSG_RELAY_REPL => "relay_repl",
+        _ => "unknown",
+    }
+}
+
+fn status_code_string(code: u16) -> Option<&'static str> {
+    match code {
+        0 => Some("success"),
+        1 => Some("unspec_fail"),
+        2 => Some("no_addrs_avail"),
+        3 => Some("no_binding"),
+        4 => Some("not_on_link"),
+        5 => Some("use_multicast"),
+        6 => Some("no_prefix_avail"),
+        _ => None,
+    }
+}
+
+impl DHCPv6Logger {
+    pub fn new(conf: ConfNode) -> Self {
+        return Self {
+            extended: conf.get_child_bool("extended"),
+        };
+    }
+
+    pub fn do_log(&self, tx: &DHCPv6Transaction) -> bool {
+        if !self.extended {
+            return tx.message.inner_message().msg_type == DHCPV6_MSG_REPLY;
+        }
+        return true;
+    }
+
+    pub fn log(&self, tx: &DHCPv6Transaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
+        let message = tx.message.inner_message();
+
+        js.open_object("dhcpv6")?;
+
+        js.set_string("type", dhcpv6_msg_type_string(message.msg_type))?;
+        if !message.is_relay() {
+            js.set_uint("id", message.txid as u64)?;
+        }
+
+        if self.extended && tx.message.is_relay() {
+            self.log_relays(&tx.message, js)?;
+        }
+
+        let mut assigned = Vec::new();
+        let mut prefixes = Vec::new();
+        let mut ia_times = false;
+        for option in &message.options {
+            match option {
+                DHCPv6Option::ClientId(duid) => {
+                    js.set_string("client_duid", &format_addr_hex(duid))?;
+                }
+                DHCPv6Option::ServerId(duid) => {
+                    js.set_string("server_duid", &format_addr_hex(duid))?;
+                }
+                DHCPv6Option::IANA(ia) | DHCPv6Option::IAPD(ia) => {
+                    for option in &ia.options {
+                        match option {
+                            DHCPv6Option::IAAddr(addr) => assigned.push(addr),
+                            DHCPv6Option::IAPrefix(prefix) => prefixes.push(prefix),
+                            _ => {}
+                        }
+                    }
+                    if self.extended && ia.t1 != 0 && !ia_times {
+                        ia_times = true;
+                        js.set_uint("renewal_time", ia.t1 as u64)?;
+                        js.set_uint("rebinding_time", ia.t2 as u64)?;
+                    }
+                }
+                DHCPv6Option::ClientFqdn(fqdn) if !fqdn.name.is_empty() => {
+                    js.set_string_from_bytes("hostname", &fqdn.name)?;
+                }
+                DHCPv6Option::StatusCode(status) => {
+                    match status_code_string(status.code) {
+                        Some(s) => js.set_string("status", s)?,
+                        None => js.set_string("status", &status.code.to_string())?,
+                    };
+                    if self.extended && !status.message.is_empty() {
+                        js.set_string_from_bytes("status_message", &status.message)?;
+                    }
+                }
+                DHCPv6Option::OptionRequest(codes) if self.extended => {
+                    self.log_opt_parameters(js, codes)?;
+                }
+                DHCPv6Option::DnsServers(servers) if self.extended => {
+                    js.open_array("dns_servers")?;
+                    for server in servers {
+                        js.append_string(&dns_print_addr(server))?;
+                    }
+                    js.close()?;
+                }
+                DHCPv6Option::DomainList(names) if self.extended => {
+                    js.open_array("domain_list")?;
+                    for name in names {
+                        js.append_string_from_bytes(name)?;
+                    }
+                    js.close()?;
+                }
+                DHCPv6Option::Preference(preference) if self.extended => {
+                    js.set_uint("preference", *preference as u64)?;
+                }
+                DHCPv6Option::ElapsedTime(elapsed) if self.extended => {
+                    // in hundredths of a second
+                    js.set_uint("elapsed_time", *elapsed as u64)?;
+                }
+                DHCPv6Option::RapidCommit if self.extended => {
+                    js.set_bool("rapid_commit", true)?;
+                }
+                DHCPv6Option::VendorClass(enterprise, _) if self.extended => {
+                    js.set_uint("vendor_class_enterprise", *enterprise as u64)?;
+                }
+                _ => {}
+            }
+        }
+
+        if !assigned.is_empty() {
+            js.open_array("assigned_ips")?;
+            for addr in &assigned {
+                js.append_string(&dns_print_addr(&addr.address))?;
+            }
+            js.close()?;
+            if self.extended {
+                js.set_uint("lease_time", assigned[0].valid_lifetime as u64)?;
+            }
+        }
+        if !prefixes.is_empty() {
+            js.open_array("delegated_prefixes")?;
+            for prefix in &prefixes {
+                js.append_string(&format!(
+                    "{}/{}",
+                    dns_print_addr(&prefix.prefix),
+                    prefix.prefix_len
+                ))?;
+            }
+            js.close()?;
+        }
+
+        js.close()?;
+
+        return Ok(());
+    }
+
+    fn log_relays(&self, message: &DHCPv6Message, js: &mut JsonBuilder) -> Result<(), JsonError> {
+        js.open_array("relays")?;
+        let mut msg = Some(message);
+        while let Some(relay_msg) = msg {
+            if let Some(ref relay) = relay_msg.relay {
+                js.start_object()?;
+                js.set_string("type", dhcpv6_msg_type_string(relay_msg.msg_type))?;
+                js.set_uint("hop_count", relay.hop_count as u64)?;
+                js.set_string("link_address", &dns_print_addr(&relay.link_address))?;
+                js.set_string("peer_address", &dns_print_addr(&relay.peer_address))?;
+                js.close()?;
+            }
+            msg = relay_msg.relayed_message();
+        }
+        js.close()?;
+        Ok(())
+    }
+
+    fn log_opt_parameters(&self, js: &mut JsonBuilder, codes: &[u16]) -> Result<(), JsonError> {
+        js.open_array("params")?;
+        for code in codes {
+            let param = match *code {
+                DHCPV6_OPT_PREFERENCE => "preference",
+                DHCPV6_OPT_VENDOR_CLASS => "vendor_class",
+                DHCPV6_OPT_DNS_SERVERS => "dns_servers",
+                DHCPV6_OPT_DOMAIN_LIST => "domain_list",
+                DHCPV6_OPT_CLIENT_FQDN => "client_fqdn",
+                _ => "",
+            };
+            if !param.is_empty() {
+                js.append_string(param)?;
+            }
+        }
+        js.close()?;
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_dhcpv6_logger_new(conf: *const c_void) -> *mut std::os::raw::c_void {
+    let conf = ConfNode::wrap(conf);
+    let boxed = Box::new(DHCPv6Logger::new(conf));
+    return Box::into_raw(boxed) as *mut _;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_logger_free(logger: *mut std::os::raw::c_void) {
+    std::mem::drop(Box::from_raw(logger as *mut DHCPv6Logger));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_logger_log(
+    logger: *mut std::os::raw::c_void, tx: *mut std::os::raw::c_void, js: &mut JsonBuilder,
+) -> bool {
+    let logger = cast_pointer!(logger, DHCPv6Logger);
+    let tx = cast_pointer!(tx, DHCPv6Transaction);
+    logger.log(tx, js).is_ok()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_logger_do_log(
+    logger: *mut std::os::raw::c_void, tx: *mut std::os::raw::c_void,
+) -> bool {
+    let logger = cast_pointer!(logger, DHCPv6Logger);
+    let tx = cast_pointer!(tx, DHCPv6Transaction);
+    logger.do_log(tx)
+}
@@ -0,0 +1,584 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+// References:
+//   https://www.rfc-editor.org/rfc/rfc8415 (DHCPv6)
+//   https://www.rfc-editor.org/rfc/rfc3646 (DNS configuration options)
+//   https://www.rfc-editor.org/rfc/rfc4704 (Client FQDN option)
+
+use nom7::bytes::complete::take;
+use nom7::error::{make_error, ErrorKind};
+use nom7::number::complete::{be_u16, be_u24, be_u32, be_u8};
+use nom7::{Err, IResult};
+
+// DHCPv6 message types.
+pub const DHCPV6_MSG_SOLICIT: u8 = 1;
+pub const DHCPV6_MSG_ADVERTISE: u8 = 2;
+pub const DHCPV6_MSG_REQUEST: u8 = 3;
+pub const DHCPV6_MSG_CONFIRM: u8 = 4;
+pub const DHCPV6_MSG_RENEW: u8 = 5;
+pub const DHCPV6_MSG_REBIND: u8 = 6;
+pub const DHCPV6_MSG_REPLY: u8 = 7;
+pub const DHCPV6_MSG_RELEASE: u8 = 8;
+pub const DHCPV6_MSG_DECLINE: u8 = 9;
+pub const DHCPV6_MSG_RECONFIGURE: u8 = 10;
+pub const DHCPV6_MSG_INFORMATION_REQUEST: u8 = 11;
+pub const DHCPV6_MSG_RELAY_FORW: u8 = 12;
+pub const DHCPV6_MSG_RELAY_REPL: u8 = 13;
+
+// DHCPv6 option codes. Names based on IANA naming:
+// https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml
+pub const DHCPV6_OPT_CLIENTID: u16 = 1;
+pub const DHCPV6_OPT_SERVERID: u16 = 2;
+pub const DHCPV6_OPT_IA_NA: u16 = 3;
+pub const DHCPV6_OPT_IAADDR: u16 = 5;
+pub const DHCPV6_OPT_ORO: u16 = 6;
+pub const DHCPV6_OPT_PREFERENCE: u16 = 7;
+pub const DHCPV6_OPT_ELAPSED_TIME: u16 = 8;
+pub const DHCPV6_OPT_RELAY_MSG: u16 = 9;
+pub const DHCPV6_OPT_STATUS_CODE: u16 = 13;
+pub const DHCPV6_OPT_RAPID_COMMIT: u16 = 14;
+pub const DHCPV6_OPT_VENDOR_CLASS: u16 = 16;
+pub const DHCPV6_OPT_DNS_SERVERS: u16 = 23;
+pub const DHCPV6_OPT_DOMAIN_LIST: u16 = 24;
+pub const DHCPV6_OPT_IA_PD: u16 = 25;
+pub const DHCPV6_OPT_IAPREFIX: u16 = 26;
+pub const DHCPV6_OPT_CLIENT_FQDN: u16 = 39;
+
+/// Maximum number of nested Relay-forward/Relay-reply messages,
+/// HOP_COUNT_LIMIT in RFC 8415 section 7.6
+pub const DHCPV6_MAX_HOPS: u8 = 8;
+
+/// Maximum nesting of options, relayed messages included. Identity
+/// Associations only carry address or prefix options so this leaves
+/// enough room for a relayed message carrying them.
+pub const DHCPV6_MAX_DEPTH: u8 = 16;
+
+pub struct DHCPv6Message {
+    pub msg_type: u8,
+
+    /// Transaction id, not set for relay messages
+    pub txid: u32,
+
+    /// Header of Relay-forward and Relay-reply messages
+    pub relay: Option<DHCPv6RelayHeader>,
+
+    pub options: Vec<DHCPv6Option>,
+
+    /// Set to true if an option, or the options of a relayed message,
+    /// failed to parse
+    pub malformed_options: bool,
+}
+
+pub struct DHCPv6RelayHeader {
+    pub hop_count: u8,
+    pub link_address: Vec<u8>,
+    pub peer_address: Vec<u8>,
+}
+
+/// Identity Association for non-temporary addresses or prefix delegation
+pub struct DHCPv6IA {
+    pub iaid: u32,
+    pub t1: u32,
+    pub t2: u32,
+    pub options: Vec<DHCPv6Option>,
+}
+
+pub struct DHCPv6IAAddress {
+    pub address: Vec<u8>,
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+}
+
+pub struct DHCPv6IAPrefix {
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+    pub prefix_len: u8,
+    pub prefix: Vec<u8>,
+}
+
+pub struct DHCPv6StatusCode {
+    pub code: u16,
+    pub message: Vec<u8>,
+}
+
+pub struct DHCPv6ClientFqdn {
+    pub flags: u8,
+    pub name: Vec<u8>,
+}
+
+pub enum DHCPv6Option {
+    ClientId(Vec<u8>),
+    ServerId(Vec<u8>),
+    IANA(DHCPv6IA),
+    IAAddr(DHCPv6IAAddress),
+    IAPD(DHCPv6IA),
+    IAPrefix(DHCPv6IAPrefix),
+    OptionRequest(Vec<u16>),
+    Preference(u8),
+    ElapsedTime(u16),
+    RelayMessage(Box<DHCPv6Message>),
+    StatusCode(DHCPv6StatusCode),
+    RapidCommit,
+    VendorClass(u32, Vec<Vec<u8>>),
+    DnsServers(Vec<Vec<u8>>),
+    DomainList(Vec<Vec<u8>>),
+    ClientFqdn(DHCPv6ClientFqdn),
+    Generic(u16, Vec<u8>),
+}
+
+impl DHCPv6Option {
+    pub fn code(&self) -> u16 {
+        match self {
+            DHCPv6Option::ClientId(_) => DHCPV6_OPT_CLIENTID,
+            DHCPv6Option::ServerId(_) => DHCPV6_OPT_SERVERID,
+            DHCPv6Option::IANA(_) => DHCPV6_OPT_IA_NA,
+            DHCPv6Option::IAAddr(_) => DHCPV6_OPT_IAADDR,
+            DHCPv6Option::IAPD(_) => DHCPV6_OPT_IA_PD,
+            DHCPv6Option::IAPrefix(_) => DHCPV6_OPT_IAPREFIX,
+            DHCPv6Option::OptionRequest(_) => DHCPV6_OPT_ORO,
+            DHCPv6Option::Preference(_) => DHCPV6_OPT_PREFERENCE,
+            DHCPv6Option::ElapsedTime(_) => DHCPV6_OPT_ELAPSED_TIME,
+            DHCPv6Option::RelayMessage(_) => DHCPV6_OPT_RELAY_MSG,
+            DHCPv6Option::StatusCode(_) => DHCPV6_OPT_STATUS_CODE,
+            DHCPv6Option::RapidCommit => DHCPV6_OPT_RAPID_COMMIT,
+            DHCPv6Option::VendorClass(_, _) => DHCPV6_OPT_VENDOR_CLASS,
+            DHCPv6Option::DnsServers(_) => DHCPV6_OPT_DNS_SERVERS,
+            DHCPv6Option::DomainList(_) => DHCPV6_OPT_DOMAIN_LIST,
+            DHCPv6Option::ClientFqdn(_) => DHCPV6_OPT_CLIENT_FQDN,
+            DHCPv6Option::Generic(code, _) => *code,
+        }
+    }
+}
+
+impl DHCPv6Message {
+    pub fn is_relay(&self) -> bool {
+        self.msg_type == DHCPV6_MSG_RELAY_FORW || self.msg_type == DHCPV6_MSG_RELAY_REPL
+    }
+
+    /// Message relayed by a Relay-forward or Relay-reply message
+    pub fn relayed_message(&self) -> Option<&DHCPv6Message> {
+        self.options.iter().find_map(|option| match option {
+            DHCPv6Option::RelayMessage(msg) => Some(msg.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// Innermost message, once all the relay messages are unwrapped
+    pub fn inner_message(&self) -> &DHCPv6Message {
+        let mut msg = self;
+        while let Some(relayed) = msg.relayed_message() {
+            msg = relayed;
+        }
+        msg
+    }
+
+    pub fn server_id(&self) -> Option<&[u8]> {
+        self.options.iter().find_map(|option| match option {
+            DHCPv6Option::ServerId(duid) => Some(duid.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+fn parse_address(i: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    let (i, address) = take(16_usize)(i)?;
+    Ok((i, address.to_vec()))
+}
+
+/// Parses a domain name in the uncompressed DNS wire format, RFC 8415
+/// section 10. The name of a Client FQDN option may be partial, without
+/// the terminating zero-length label.
+fn parse_domain_name(i: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    let mut name = Vec::new();
+    let mut i = i;
+    while !i.is_empty() {
+        let (rem, len) = be_u8(i)?;
+        if len == 0 {
+            return Ok((rem, name));
+        }
+        if len > 63 {
+            return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+        }
+        let (rem, label) = take(len as usize)(rem)?;
+        if !name.is_empty() {
+            name.push(b'.');
+        }
+        name.extend_from_slice(label);
+        i = rem;
+    }
+    Ok((i, name))
+}
+
+fn parse_ia(i: &[u8], with_times: bool, depth: u8) -> IResult<&[u8], DHCPv6IA> {
+    if depth >= DHCPV6_MAX_DEPTH {
+        return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+    }
+    let (i, iaid) = be_u32(i)?;
+    let (i, t1, t2) = if with_times {
+        let (i, t1) = be_u32(i)?;
+        let (i, t2) = be_u32(i)?;
+        (i, t1, t2)
+    } else {
+        (i, 0, 0)
+    };
+    let (options, malformed) = parse_options(i, depth + 1);
+    if malformed {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    Ok((
+        &[],
+        DHCPv6IA {
+            iaid,
+            t1,
+            t2,
+            options,
+        },
+    ))
+}
+
+fn parse_option_data(code: u16, i: &[u8], depth: u8) -> IResult<&[u8], DHCPv6Option> {
+    match code {
+        DHCPV6_OPT_CLIENTID => Ok((&[], DHCPv6Option::ClientId(i.to_vec()))),
+        DHCPV6_OPT_SERVERID => Ok((&[], DHCPv6Option::ServerId(i.to_vec()))),
+        DHCPV6_OPT_IA_NA => {
+            let (i, ia) = parse_ia(i, true, depth)?;
+            Ok((i, DHCPv6Option::IANA(ia)))
+        }
+        DHCPV6_OPT_IA_PD => {
+            let (i, ia) = parse_ia(i, true, depth)?;
+            Ok((i, DHCPv6Option::IAPD(ia)))
+        }
+        DHCPV6_OPT_IAADDR => {
+            let (i, address) = parse_address(i)?;
+            let (i, preferred_lifetime) = be_u32(i)?;
+            let (_, valid_lifetime) = be_u32(i)?;
+            // the IAaddr-options are ignored
+            Ok((
+                &[],
+                DHCPv6Option::IAAddr(DHCPv6IAAddress {
+                    address,
+                    preferred_lifetime,
+                    valid_lifetime,
+                }),
+            ))
+        }
+        DHCPV6_OPT_IAPREFIX => {
+            let (i, preferred_lifetime) = be_u32(i)?;
+            let (i, valid_lifetime) = be_u32(i)?;
+            let (i, prefix_len) = be_u8(i)?;
+            let (_, prefix) = parse_address(i)?;
+            Ok((
+                &[],
+                DHCPv6Option::IAPrefix(DHCPv6IAPrefix {
+                    preferred_lifetime,
+                    valid_lifetime,
+                    prefix_len,
+                    prefix,
+                }),
+            ))
+        }
+        DHCPV6_OPT_ORO => {
+            if i.len() % 2 != 0 {
+                return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+            }
+            let codes = i
+                .chunks(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            Ok((&[], DHCPv6Option::OptionRequest(codes)))
+        }
+        DHCPV6_OPT_PREFERENCE => {
+            let (i, preference) = be_u8(i)?;
+            Ok((i, DHCPv6Option::Preference(preference)))
+        }
+        DHCPV6_OPT_ELAPSED_TIME => {
+            let (i, elapsed) = be_u16(i)?;
+            Ok((i, DHCPv6Option::ElapsedTime(elapsed)))
+        }
+        DHCPV6_OPT_RELAY_MSG => {
+            let (i, msg) = parse_message(i, depth + 1)?;
+            Ok((i, DHCPv6Option::RelayMessage(Box::new(msg))))
+        }
+        DHCPV6_OPT_STATUS_CODE => {
+            let (i, code) = be_u16(i)?;
+            Ok((
+                &[],
+                DHCPv6Option::StatusCode(DHCPv6StatusCode {
+                    code,
+                    message: i.to_vec(),
+                }),
+            ))
+        }
+        DHCPV6_OPT_RAPID_COMMIT => Ok((i, DHCPv6Option::RapidCommit)),
+        DHCPV6_OPT_VENDOR_CLASS => {
+            let (mut i, enterprise) = be_u32(i)?;
+            let mut data = Vec::new();
+            while !i.is_empty() {
+                let (rem, len) = be_u16(i)?;
+                let (rem, value) = take(len as usize)(rem)?;
+                data.push(value.to_vec());
+                i = rem;
+            }
+            Ok((i, DHCPv6Option::VendorClass(enterprise, data)))
+        }
+        DHCPV6_OPT_DNS_SERVERS => {
+            if i.len() % 16 != 0 {
+                return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+            }
+            let servers = i.chunks(16).map(|a| a.to_vec()).collect();
+            Ok((&[], DHCPv6Option::DnsServers(servers)))
+        }
+        DHCPV6_OPT_DOMAIN_LIST => {
+            let mut names = Vec::new();
+            let mut i = i;
+            while !i.is_empty() {
+                let (rem, name) = parse_domain_name(i)?;
+                names.push(name);
+                i = rem;
+            }
+            Ok((i, DHCPv6Option::DomainList(names)))
+        }
+        DHCPV6_OPT_CLIENT_FQDN => {
+            let (i, flags) = be_u8(i)?;
+            let (i, name) = parse_domain_name(i)?;
+            Ok((
+                i,
+                DHCPv6Option::ClientFqdn(DHCPv6ClientFqdn { flags, name }),
+            ))
+        }
+        _ => Ok((&[], DHCPv6Option::Generic(code, i.to_vec()))),
+    }
+}
+
+/// Parses all the options of a message or of an option.
+///
+/// Options which fail to parse are skipped, as their length is known,
+/// and reported as malformed along with the options overflowing the input.
+fn parse_options(i: &[u8], depth: u8) -> (Vec<DHCPv6Option>, bool) {
+    let mut options = Vec::new();
+    let mut malformed = false;
+    let mut i = i;
+    while !i.is_empty() {
+        let (rem, code) = match be_u16::<_, ()>(i) {
+            Ok(r) => r,
+            Err(_) => return (options, true),
+        };
+        let (rem, len) = match be_u16::<_, ()>(rem) {
+            Ok(r) => r,
+            Err(_) => return (options, true),
+        };
+        if rem.len() < len as usize {
+            return (options, true);
+        }
+        let (data, rem) = rem.split_at(len as usize);
+        match parse_option_data(code, data, depth) {
+            Ok((trailing, option)) => {
+                if !trailing.is_empty() {
+                    malformed = true;
+                }
+                if let DHCPv6Option::RelayMessage(ref msg) = option {
+                    malformed |= msg.malformed_options;
+                }
+                options.push(option);
+            }
+            Err(_) => {
+                malformed = true;
+            }
+        }
+        i = rem;
+    }
+    (options, malformed)
+}
+
+fn parse_message(i: &[u8], depth: u8) -> IResult<&[u8], DHCPv6Message> {
+    let (i, msg_type) = be_u8(i)?;
+    if msg_type == DHCPV6_MSG_RELAY_FORW || msg_type == DHCPV6_MSG_RELAY_REPL {
+        if depth >= DHCPV6_MAX_HOPS {
+            return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+        }
+        let (i, hop_count) = be_u8(i)?;
+        let (i, link_address) = parse_address(i)?;
+        let (i, peer_address) = parse_address(i)?;
+        let (options, malformed_options) = parse_options(i, depth);
+        return Ok((
+            &[],
+            DHCPv6Message {
+                msg_type,
+                txid: 0,
+                relay: Some(DHCPv6RelayHeader {
+                    hop_count,
+                    link_address,
+                    peer_address,
+                }),
+                options,
+                malformed_options,
+            },
+        ));
+    }
+    let (i, txid) = be_u24(i)?;
+    let (options, malformed_options) = parse_options(i, depth);
+    Ok((
+        &[],
+        DHCPv6Message {
+            msg_type,
+            txid,
+            relay: None,
+            options,
+            malformed_options,
+        },
+    ))
+}
+
+pub fn dhcpv6_parse(i: &[u8]) -> IResult<&[u8], DHCPv6Message> {
+    parse_message(i, 0)
+}
+
+/// Parses the header of a client/server message, or of a relay message,
+/// for protocol detection.
+pub fn dhcpv6_parse_header(i: &[u8]) -> IResult<&[u8], u8> {
+    let (rem, msg_type) = be_u8(i)?;
+    match msg_type {
+        DHCPV6_MSG_SOLICIT..=DHCPV6_MSG_INFORMATION_REQUEST => {
+            let (rem, _txid) = be_u24(rem)?;
+            Ok((rem, msg_type))
+        }
+        DHCPV6_MSG_RELAY_FORW | DHCPV6_MSG_RELAY_REPL => {
+            let (rem, _hop_count) = be_u8(rem)?;
+            let (rem, _addresses) = take(32_usize)(rem)?;
+            Ok((rem, msg_type))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhcpv6_parse_solicit() {
+        let buf = [
+            0x01, 0x10, 0x08, 0x74, // Solicit, transaction id
+            0x00, 0x01, 0x00, 0x0e, // client identifier, DUID-LLT
+            0x00, 0x01, 0x00, 0x01, 0x1c, 0x39, 0xcf, 0x88, 0x08, 0x00, 0x27, 0xfe, 0x8f, 0x95,
+            0x00, 0x06, 0x00, 0x04, 0x00, 0x17, 0x00, 0x18, // ORO: DNS servers, domain list
+            0x00, 0x08, 0x00, 0x02, 0x00, 0x00, // elapsed time
+            0x00, 0x19, 0x00, 0x0c, 0x27, 0xfe, 0x8f, 0x95, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x00,
+            0x15, 0x18, // IA_PD without prefix
+            0x00, 0x27, 0x00, 0x08, 0x00, 0x04, 0x68, 0x6f, 0x73, 0x74, 0x03,
+            0x6c, // FQDN, truncated label
+        ];
+        let (_, msg) = dhcpv6_parse(&buf).unwrap();
+        assert_eq!(msg.msg_type, DHCPV6_MSG_SOLICIT);
+        assert_eq!(msg.txid, 0x100874);
+        assert!(msg.malformed_options);
+        assert_eq!(msg.options.len(), 4);
+        match msg.options[0] {
+            DHCPv6Option::ClientId(ref duid) => assert_eq!(duid.len(), 14),
+            _ => panic!("not a client id"),
+        }
+        match msg.options[1] {
+            DHCPv6Option::OptionRequest(ref codes) => {
+                assert_eq!(codes, &[DHCPV6_OPT_DNS_SERVERS, DHCPV6_OPT_DOMAIN_LIST])
+            }
+            _ => panic!("not an ORO"),
+        }
+        match msg.options[3] {
+            DHCPv6Option::IAPD(ref ia) => {
+                assert_eq!(ia.iaid, 0x27fe8f95);
+                assert_eq!(ia.t1, 3600);
+                assert_eq!(ia.t2, 5400);
+                assert!(ia.options.is_empty());
+            }
+            _ => panic!("not an IA_PD"),
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_relay_reply() {
+        let mut buf = vec![0x0d, 0x00];
+        buf.extend_from_slice(&[
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+        ]);
+        buf.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02]);
+        let inner = [
+            0x02, 0x00, 0x00, 0x01, // Advertise
+            0x00, 0x02, 0x00, 0x04, 0x00, 0x03, 0x00, 0x01, // server identifier
+            0x00, 0x17, 0x00, 0x10, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0x35, // DNS server
+            0x00, 0x18, 0x00, 0x0d, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63,
+            0x6f, 0x6d, 0x00, // domain list: example.com
+        ];
+        buf.extend_from_slice(&[0x00, 0x09, 0x00, inner.len() as u8]);
+        buf.extend_from_slice(&inner);
+        let (_, msg) = dhcpv6_parse(&buf).unwrap();
+        assert!(msg.is_relay());
+        assert!(!msg.malformed_options);
+        assert_eq!(msg.relay.as_ref().unwrap().link_address[0], 0x20);
+        let inner = msg.inner_message();
+        assert_eq!(inner.msg_type, DHCPV6_MSG_ADVERTISE);
+        assert_eq!(inner.server_id(), Some(&[0x00, 0x03, 0x00, 0x01][..]));
+        match inner.options[2] {
+            DHCPv6Option::DomainList(ref names) => assert_eq!(names, &[b"example.com".to_vec()]),
+            _ => panic!("not a domain list"),
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_nested_ia() {
+        // IA_NA carrying an IA address
+        let mut ia = vec![0x00, 0x05, 0x00, 0x18];
+        ia.extend_from_slice(&[
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+        ]);
+        ia.extend_from_slice(&[0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x1c, 0x20]);
+        // wrap it in IA_NA options, far deeper than allowed
+        for _ in 0..2000 {
+            let mut outer = vec![0x00, 0x03];
+            outer.extend_from_slice(&(ia.len() as u16 + 12).to_be_bytes());
+            outer.extend_from_slice(&[0x27, 0xfe, 0x8f, 0x95, 0, 0, 0, 0, 0, 0, 0, 0]);
+            outer.extend_from_slice(&ia);
+            ia = outer;
+        }
+        let mut buf = vec![0x03, 0x00, 0x00, 0x01]; // Request, transaction id
+        buf.extend_from_slice(&ia[..]);
+        let (_, msg) = dhcpv6_parse(&buf).unwrap();
+        assert!(msg.malformed_options);
+        assert!(msg.options.is_empty());
+
+        // a single IA_NA is fine
+        let mut buf = vec![0x03, 0x00, 0x00, 0x01];
+        buf.extend_from_slice(&ia[ia.len() - 44..]);
+        let (_, msg) = dhcpv6_parse(&buf).unwrap();
+        assert!(!msg.malformed_options);
+        match msg.options[0] {
+            DHCPv6Option::IANA(ref ia) => match ia.options[0] {
+                DHCPv6Option::IAAddr(ref addr) => assert_eq!(addr.valid_lifetime, 7200),
+                _ => panic!("not an IA address"),
+            },
+            _ => panic!("not an IA_NA"),
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_header() {
+        assert!(dhcpv6_parse_header(&[0x01, 0x00, 0x00, 0x01]).is_ok());
+        assert!(dhcpv6_parse_header(&[0x0e, 0x00, 0x00, 0x01]).is_err());
+        assert!(dhcpv6_parse_header(&[0x0c, 0x00, 0x00]).is_err());
+    }
+}
@@ -81,6 +81,7 @@ pub const DNS_RECORD_TYPE_TLSA: u16 = 52;
 pub const DNS_RECORD_TYPE_HIP: u16 = 55;
 pub const DNS_RECORD_TYPE_CDS: u16 = 59;
 pub const DNS_RECORD_TYPE_CDNSKEY: u16 = 60;
+pub const DNS_RECORD_TYPE_SVCB: u16 = 64;
 pub const DNS_RECORD_TYPE_HTTPS: u16 = 65;
 pub const DNS_RECORD_TYPE_SPF: u16 = 99; // Obsolete
 pub const DNS_RECORD_TYPE_TKEY: u16 = 249;
@@ -88,6 +89,7 @@ pub const DNS_RECORD_TYPE_TSIG: u16 = 250;
 pub const DNS_RECORD_TYPE_MAILA: u16 = 254; // Obsolete
 pub const DNS_RECORD_TYPE_ANY: u16 = 255;
 pub const DNS_RECORD_TYPE_URI: u16 = 256;
+pub const DNS_RECORD_TYPE_CAA: u16 = 257;
 
 /// DNS error codes.
 pub const DNS_RCODE_NOERROR: u16 = 0;
@@ -197,6 +199,122 @@ pub struct DNSRDataSRV {
     pub target: Vec<u8>,
 }
 
+/// Service parameters of SVCB and HTTPS records (RFC 9460)
+pub const DNS_SVCPARAM_MANDATORY: u16 = 0;
+pub const DNS_SVCPARAM_ALPN: u16 = 1;
+pub const DNS_SVCPARAM_NO_DEFAULT_ALPN: u16 = 2;
+pub const DNS_SVCPARAM_PORT: u16 = 3;
+pub const DNS_SVCPARAM_IPV4HINT: u16 = 4;
+pub const DNS_SVCPARAM_ECH: u16 = 5;
+pub const DNS_SVCPARAM_IPV6HINT: u16 = 6;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DNSSvcParam {
+    /// Keys which must be understood by the client
+    Mandatory(Vec<u16>),
+    /// Protocol identifiers
+    Alpn(Vec<Vec<u8>>),
+    NoDefaultAlpn,
+    Port(u16),
+    /// IPv4 addresses, as 4 bytes each
+    Ipv4Hint(Vec<Vec<u8>>),
+    /// Encrypted ClientHello configuration list
+    Ech(Vec<u8>),
+    /// IPv6 addresses, as 16 bytes each
+    Ipv6Hint(Vec<Vec<u8>>),
+    Unknown(u16, Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataSVCB {
+    /// Priority, 0 for alias mode
+    pub priority: u16,
+    /// Target name
+    pub target: Vec<u8>,
+    /// Service parameters
+    pub params: Vec<DNSSvcParam>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataCAA {
+    /// Flags, including the issuer critical flag
+    pub flags: u8,
+    /// Property tag
+    pub tag: Vec<u8>,
+    /// Property value
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataDS {
+    /// Key tag of the referenced DNSKEY
+    pub key_tag: u16,
+    /// Algorithm number of the referenced DNSKEY
+    pub algorithm: u8,
+    /// Digest type
+    pub digest_type: u8,
+    /// Digest
+    pub digest: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataDNSKEY {
+    /// Flags, including zone key and secure entry point
+    pub flags: u16,
+    /// Protocol, always 3
+    pub protocol: u8,
+    /// Algorithm number
+    pub algorithm: u8,
+    /// Public key
+    pub public_key: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataRRSIG {
+    /// Type of the RRset covered by this signature
+    pub type_covered: u16,
+    /// Algorithm number
+    pub algorithm: u8,
+    /// Number of labels in the original owner name
+    pub labels: u8,
+    /// TTL of the covered RRset
+    pub original_ttl: u32,
+    /// Signature expiration (seconds since epoch)
+    pub expiration: u32,
+    /// Signature inception (seconds since epoch)
+    pub inception: u32,
+    /// Key tag of the signing DNSKEY
+    pub key_tag: u16,
+    /// Owner name of the signing DNSKEY
+    pub signer_name: Vec<u8>,
+    /// Signature
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataNSEC {
+    /// Next owner name in the canonical ordering of the zone
+    pub next_domain_name: Vec<u8>,
+    /// Record types present at the owner name
+    pub types: Vec<u16>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DNSRDataNSEC3 {
+    /// Hash algorithm
+    pub hash_algorithm: u8,
+    /// Flags, including opt-out
+    pub flags: u8,
+    /// Number of additional hash iterations
+    pub iterations: u16,
+    /// Salt
+    pub salt: Vec<u8>,
+    /// Next hashed owner name, not base32 encoded
+    pub next_hashed_owner: Vec<u8>,
+    /// Record types present at the original owner name
+    pub types: Vec<u16>,
+}
+
 /// Represents RData of various formats
 #[derive(Debug, PartialEq, Eq)]
 pub enum DNSRData {
@@ -216,6 +334,14 @@ pub enum DNSRData {
     SRV(DNSRDataSRV),
     SSHFP(DNSRDataSSHFP),
     OPT(Vec<DNSRDataOPT>),
+    SVCB(DNSRDataSVCB),
+    HTTPS(DNSRDataSVCB),
+    CAA(DNSRDataCAA),
+    DS(DNSRDataDS),
+    DNSKEY(DNSRDataDNSKEY),
+    RRSIG(DNSRDataRRSIG),
+    NSEC(DNSRDataNSEC),
+    NSEC3(DNSRDataNSEC3),
     // RData for remaining types is sometimes ignored
     Unknown(Vec<u8>),
 }
@@ -485,7 +611,9 @@ impl DNSState {
         tx.tx_data.set_event(event as u8);
     }
 
-    fn parse_request(&mut self, input: &[u8], is_tcp: bool, frame: Option<Frame>, flow: *const core::Flow,) -> bool {
+    fn parse_request(
+        &mut self, input: &[u8], is_tcp: bool, frame: Option<Frame>, flow: *const core::Flow,
+    ) -> bool {
         match dns_parse_request(input) {
             Ok(mut tx) => {
                 self.tx_id += 1;
@@ -542,7 +670,9 @@ impl DNSState {
         self.parse_response(input, false, frame, flow)
     }
 
-    fn parse_response(&mut self, input: &[u8], is_tcp: bool, frame: Option<Frame>, flow: *const core::Flow) -> bool {
+    fn parse_response(
+        &mut self, input: &[u8], is_tcp: bool, frame: Option<Frame>, flow: *const core::Flow,
+    ) -> bool {
         match dns_parse_response(input) {
             Ok(mut tx) => {
                 self.tx_id += 1;
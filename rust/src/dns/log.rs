@@ -84,6 +84,7 @@ pub const LOG_URI: u64 = BIT_U64!(59);
 pub const LOG_FORMAT_GROUPED: u64 = BIT_U64!(60);
 pub const LOG_FORMAT_DETAILED: u64 = BIT_U64!(61);
 pub const LOG_HTTPS: u64 = BIT_U64!(62);
+pub const LOG_CAA: u64 = BIT_U64!(63);
 
 pub const DNS_LOG_VERSION_1: u8 = 1;
 pub const DNS_LOG_VERSION_2: u8 = 2;
@@ -250,7 +251,8 @@ fn dns_log_rrtype_enabled(rtype: u16, flags: u64) -> bool {
         DNS_RECORD_TYPE_CDNSKEY => {
             return flags & LOG_CDNSKEY != 0;
         }
-        DNS_RECORD_TYPE_HTTPS => {
+        // no bit left for SVCB, logged along with its HTTPS variant
+        DNS_RECORD_TYPE_HTTPS | DNS_RECORD_TYPE_SVCB => {
             return flags & LOG_HTTPS != 0;
         }
         DNS_RECORD_TYPE_SPF => {
@@ -271,6 +273,9 @@ fn dns_log_rrtype_enabled(rtype: u16, flags: u64) -> bool {
         DNS_RECORD_TYPE_URI => {
             return flags & LOG_URI != 0;
         }
+        DNS_RECORD_TYPE_CAA => {
+            return flags & LOG_CAA != 0;
+        }
         _ => {
             return false;
         }
@@ -327,7 +332,9 @@ pub fn dns_rrtype_string(rrtype: u16) -> String {
         DNS_RECORD_TYPE_HIP => "HIP",
         DNS_RECORD_TYPE_CDS => "CDS",
         DNS_RECORD_TYPE_CDNSKEY => "CDSNKEY",
+        DNS_RECORD_TYPE_SVCB => "SVCB",
         DNS_RECORD_TYPE_HTTPS => "HTTPS",
+        DNS_RECORD_TYPE_CAA => "CAA",
         DNS_RECORD_TYPE_MAILA => "MAILA",
         DNS_RECORD_TYPE_URI => "URI",
         DNS_RECORD_TYPE_MB => "MB",
@@ -456,6 +463,168 @@ fn dns_log_srv(srv: &DNSRDataSRV) -> Result<JsonBuilder, JsonError> {
     return Ok(js);
 }
 
+/// Log SVCB and HTTPS section fields.
+fn dns_log_svcb(svcb: &DNSRDataSVCB) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_uint("priority", svcb.priority as u64)?;
+    js.set_string_from_bytes("target", &svcb.target)?;
+    for param in &svcb.params {
+        match param {
+            DNSSvcParam::Mandatory(keys) => {
+                js.open_array("mandatory")?;
+                for key in keys {
+                    js.append_uint(*key as u64)?;
+                }
+                js.close()?;
+            }
+            DNSSvcParam::Alpn(ids) => {
+                js.open_array("alpn")?;
+                for id in ids {
+                    js.append_string_from_bytes(id)?;
+                }
+                js.close()?;
+            }
+            DNSSvcParam::NoDefaultAlpn => {
+                js.set_bool("no_default_alpn", true)?;
+            }
+            DNSSvcParam::Port(port) => {
+                js.set_uint("port", *port as u64)?;
+            }
+            DNSSvcParam::Ipv4Hint(addrs) => {
+                js.open_array("ipv4hint")?;
+                for addr in addrs {
+                    js.append_string(&dns_print_addr(addr))?;
+                }
+                js.close()?;
+            }
+            DNSSvcParam::Ech(ech) => {
+                js.set_base64("ech", ech)?;
+            }
+            DNSSvcParam::Ipv6Hint(addrs) => {
+                js.open_array("ipv6hint")?;
+                for addr in addrs {
+                    js.append_string(&dns_print_addr(addr))?;
+                }
+                js.close()?;
+            }
+            DNSSvcParam::Unknown(_, _) => {}
+        }
+    }
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log CAA section fields.
+fn dns_log_caa(caa: &DNSRDataCAA) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_uint("flags", caa.flags as u64)?;
+    js.set_string_from_bytes("tag", &caa.tag)?;
+    js.set_string_from_bytes("value", &caa.value)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log DS section fields.
+fn dns_log_ds(ds: &DNSRDataDS) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_uint("key_tag", ds.key_tag as u64)?;
+    js.set_uint("algorithm", ds.algorithm as u64)?;
+    js.set_uint("digest_type", ds.digest_type as u64)?;
+    js.set_hex("digest", &ds.digest)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log DNSKEY section fields.
+fn dns_log_dnskey(dnskey: &DNSRDataDNSKEY) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_uint("flags", dnskey.flags as u64)?;
+    js.set_uint("protocol", dnskey.protocol as u64)?;
+    js.set_uint("algorithm", dnskey.algorithm as u64)?;
+    js.set_base64("public_key", &dnskey.public_key)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log RRSIG section fields.
+fn dns_log_rrsig(rrsig: &DNSRDataRRSIG) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_string("type_covered", &dns_rrtype_string(rrsig.type_covered))?;
+    js.set_uint("algorithm", rrsig.algorithm as u64)?;
+    js.set_uint("labels", rrsig.labels as u64)?;
+    js.set_uint("original_ttl", rrsig.original_ttl as u64)?;
+    js.set_uint("expiration", rrsig.expiration as u64)?;
+    js.set_uint("inception", rrsig.inception as u64)?;
+    js.set_uint("key_tag", rrsig.key_tag as u64)?;
+    js.set_string_from_bytes("signer_name", &rrsig.signer_name)?;
+    js.set_base64("signature", &rrsig.signature)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+fn dns_log_types(js: &mut JsonBuilder, types: &[u16]) -> Result<(), JsonError> {
+    js.open_array("types")?;
+    for rrtype in types {
+        js.append_string(&dns_rrtype_string(*rrtype))?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+/// Log NSEC section fields.
+fn dns_log_nsec(nsec: &DNSRDataNSEC) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_string_from_bytes("next_domain_name", &nsec.next_domain_name)?;
+    dns_log_types(&mut js, &nsec.types)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log NSEC3 section fields.
+fn dns_log_nsec3(nsec3: &DNSRDataNSEC3) -> Result<JsonBuilder, JsonError> {
+    let mut js = JsonBuilder::try_new_object()?;
+
+    js.set_uint("hash_algorithm", nsec3.hash_algorithm as u64)?;
+    js.set_uint("flags", nsec3.flags as u64)?;
+    js.set_uint("iterations", nsec3.iterations as u64)?;
+    js.set_hex("salt", &nsec3.salt)?;
+    js.set_hex("next_hashed_owner", &nsec3.next_hashed_owner)?;
+    dns_log_types(&mut js, &nsec3.types)?;
+
+    js.close()?;
+    return Ok(js);
+}
+
+/// Log the section fields of SVCB, HTTPS, CAA and DNSSEC records, returning
+/// None for the other types.
+fn dns_log_rdata_section(rdata: &DNSRData) -> Result<Option<JsonBuilder>, JsonError> {
+    let js = match rdata {
+        DNSRData::SVCB(svcb) | DNSRData::HTTPS(svcb) => dns_log_svcb(svcb)?,
+        DNSRData::CAA(caa) => dns_log_caa(caa)?,
+        DNSRData::DS(ds) => dns_log_ds(ds)?,
+        DNSRData::DNSKEY(dnskey) => dns_log_dnskey(dnskey)?,
+        DNSRData::RRSIG(rrsig) => dns_log_rrsig(rrsig)?,
+        DNSRData::NSEC(nsec) => dns_log_nsec(nsec)?,
+        DNSRData::NSEC3(nsec3) => dns_log_nsec3(nsec3)?,
+        _ => {
+            return Ok(None);
+        }
+    };
+    return Ok(Some(js));
+}
+
 fn dns_log_json_answer_detail(answer: &DNSAnswerEntry) -> Result<JsonBuilder, JsonError> {
     let mut jsa = JsonBuilder::try_new_object()?;
 
@@ -491,6 +660,30 @@ fn dns_log_json_answer_detail(answer: &DNSAnswerEntry) -> Result<JsonBuilder, Js
             }
             jsa.close()?;
         }
+        DNSRData::SVCB(svcb) => {
+            jsa.set_object("svcb", &dns_log_svcb(svcb)?)?;
+        }
+        DNSRData::HTTPS(https) => {
+            jsa.set_object("https", &dns_log_svcb(https)?)?;
+        }
+        DNSRData::CAA(caa) => {
+            jsa.set_object("caa", &dns_log_caa(caa)?)?;
+        }
+        DNSRData::DS(ds) => {
+            jsa.set_object("ds", &dns_log_ds(ds)?)?;
+        }
+        DNSRData::DNSKEY(dnskey) => {
+            jsa.set_object("dnskey", &dns_log_dnskey(dnskey)?)?;
+        }
+        DNSRData::RRSIG(rrsig) => {
+            jsa.set_object("rrsig", &dns_log_rrsig(rrsig)?)?;
+        }
+        DNSRData::NSEC(nsec) => {
+            jsa.set_object("nsec", &dns_log_nsec(nsec)?)?;
+        }
+        DNSRData::NSEC3(nsec3) => {
+            jsa.set_object("nsec3", &dns_log_nsec3(nsec3)?)?;
+        }
         _ => {}
     }
 
@@ -594,7 +787,17 @@ fn dns_log_json_answer(
                             a.append_object(&dns_log_srv(srv)?)?;
                         }
                     }
-                    _ => {}
+                    _ => {
+                        if let Some(section) = dns_log_rdata_section(&answer.data)? {
+                            if !answer_types.contains_key(&type_string) {
+                                answer_types
+                                    .insert(type_string.to_string(), JsonBuilder::try_new_array()?);
+                            }
+                            if let Some(a) = answer_types.get_mut(&type_string) {
+                                a.append_object(&section)?;
+                            }
+                        }
+                    }
                 }
             }
 
@@ -715,7 +918,17 @@ fn dns_log_json_answers(
                             a.append_object(&dns_log_srv(srv)?)?;
                         }
                     }
-                    _ => {}
+                    _ => {
+                        if let Some(section) = dns_log_rdata_section(&answer.data)? {
+                            if !answer_types.contains_key(&type_string) {
+                                answer_types
+                                    .insert(type_string.to_string(), JsonBuilder::try_new_array()?);
+                            }
+                            if let Some(a) = answer_types.get_mut(&type_string) {
+                                a.append_object(&section)?;
+                            }
+                        }
+                    }
                 }
             }
 
@@ -903,7 +1116,7 @@ pub extern "C" fn SCDnsLogEnabled(tx: &DNSTransaction, flags: u64) -> bool {
         // Should be unreachable...
         return false;
     };
-    
+
     for query in &message.queries {
         if dns_log_rrtype_enabled(query.rrtype, flags) {
             return true;
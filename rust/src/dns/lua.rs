@@ -180,6 +180,27 @@ pub extern "C" fn SCDnsLuaGetAnswerTable(clua: &mut CLuaState, tx: &mut DNSTrans
                     lua.pushstring(&String::from_utf8_lossy(&srv.target));
                     lua.settable(-3);
                 }
+                DNSRData::SVCB(ref svcb) | DNSRData::HTTPS(ref svcb) => {
+                    lua.pushstring("addr");
+                    lua.pushstring(&String::from_utf8_lossy(&svcb.target));
+                    lua.settable(-3);
+                }
+                DNSRData::CAA(ref caa) => {
+                    lua.pushstring("addr");
+                    lua.pushstring(&String::from_utf8_lossy(&caa.value));
+                    lua.settable(-3);
+                }
+                DNSRData::RRSIG(ref rrsig) => {
+                    lua.pushstring("addr");
+                    lua.pushstring(&String::from_utf8_lossy(&rrsig.signer_name));
+                    lua.settable(-3);
+                }
+                DNSRData::NSEC(ref nsec) => {
+                    lua.pushstring("addr");
+                    lua.pushstring(&String::from_utf8_lossy(&nsec.next_domain_name));
+                    lua.settable(-3);
+                }
+                DNSRData::DS(_) | DNSRData::DNSKEY(_) | DNSRData::NSEC3(_) => {}
                 DNSRData::OPT(ref opt) => {
                     if !opt.is_empty() {
                         lua.pushstring("addr");
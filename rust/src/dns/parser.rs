@@ -18,9 +18,9 @@
 //! Nom parsers for DNS.
 
 use crate::dns::dns::*;
-use nom7::combinator::{complete, rest};
+use nom7::combinator::{all_consuming, complete, rest};
 use nom7::error::ErrorKind;
-use nom7::multi::{count, length_data, many_m_n};
+use nom7::multi::{count, length_data, many0, many_m_n};
 use nom7::number::streaming::{be_u16, be_u32, be_u8};
 use nom7::{error_position, Err, IResult};
 
@@ -311,6 +311,186 @@ fn dns_parse_rdata_opt(input: &[u8]) -> IResult<&[u8], DNSRData> {
     Ok((i, DNSRData::OPT(dns_rdata_opt_vec)))
 }
 
+fn dns_parse_svc_param_value(key: u16, value: &[u8]) -> IResult<&[u8], DNSSvcParam> {
+    match key {
+        DNS_SVCPARAM_MANDATORY => {
+            let (i, keys) = all_consuming(many0(complete(be_u16)))(value)?;
+            Ok((i, DNSSvcParam::Mandatory(keys)))
+        }
+        DNS_SVCPARAM_ALPN => {
+            let (i, ids) = all_consuming(many0(complete(length_data(be_u8))))(value)?;
+            Ok((
+                i,
+                DNSSvcParam::Alpn(ids.iter().map(|id| id.to_vec()).collect()),
+            ))
+        }
+        DNS_SVCPARAM_NO_DEFAULT_ALPN if value.is_empty() => Ok((value, DNSSvcParam::NoDefaultAlpn)),
+        DNS_SVCPARAM_PORT => {
+            let (i, port) = all_consuming(be_u16)(value)?;
+            Ok((i, DNSSvcParam::Port(port)))
+        }
+        DNS_SVCPARAM_IPV4HINT if !value.is_empty() && value.len() % 4 == 0 => Ok((
+            &[],
+            DNSSvcParam::Ipv4Hint(value.chunks(4).map(|a| a.to_vec()).collect()),
+        )),
+        DNS_SVCPARAM_ECH => Ok((&[], DNSSvcParam::Ech(value.to_vec()))),
+        DNS_SVCPARAM_IPV6HINT if !value.is_empty() && value.len() % 16 == 0 => Ok((
+            &[],
+            DNSSvcParam::Ipv6Hint(value.chunks(16).map(|a| a.to_vec()).collect()),
+        )),
+        _ => Ok((&[], DNSSvcParam::Unknown(key, value.to_vec()))),
+    }
+}
+
+fn dns_parse_rdata_svcb<'a>(input: &'a [u8], message: &'a [u8]) -> IResult<&'a [u8], DNSRDataSVCB> {
+    let (mut i, priority) = be_u16(input)?;
+    let (j, target) = dns_parse_name(i, message)?;
+    i = j;
+    let mut params = Vec::new();
+    while !i.is_empty() {
+        let (j, key) = be_u16(i)?;
+        let (j, value) = length_data(be_u16)(j)?;
+        i = j;
+        // keep malformed values of known keys as unknown ones
+        let param = match dns_parse_svc_param_value(key, value) {
+            Ok((_, param)) => param,
+            Err(_) => DNSSvcParam::Unknown(key, value.to_vec()),
+        };
+        params.push(param);
+    }
+    Ok((
+        i,
+        DNSRDataSVCB {
+            priority,
+            target,
+            params,
+        },
+    ))
+}
+
+fn dns_parse_rdata_caa(input: &[u8]) -> IResult<&[u8], DNSRData> {
+    let (i, flags) = be_u8(input)?;
+    let (i, tag) = length_data(be_u8)(i)?;
+    let (i, value) = rest(i)?;
+    Ok((
+        i,
+        DNSRData::CAA(DNSRDataCAA {
+            flags,
+            tag: tag.to_vec(),
+            value: value.to_vec(),
+        }),
+    ))
+}
+
+fn dns_parse_rdata_ds(input: &[u8]) -> IResult<&[u8], DNSRData> {
+    let (i, key_tag) = be_u16(input)?;
+    let (i, algorithm) = be_u8(i)?;
+    let (i, digest_type) = be_u8(i)?;
+    let (i, digest) = rest(i)?;
+    Ok((
+        i,
+        DNSRData::DS(DNSRDataDS {
+            key_tag,
+            algorithm,
+            digest_type,
+            digest: digest.to_vec(),
+        }),
+    ))
+}
+
+fn dns_parse_rdata_dnskey(input: &[u8]) -> IResult<&[u8], DNSRData> {
+    let (i, flags) = be_u16(input)?;
+    let (i, protocol) = be_u8(i)?;
+    let (i, algorithm) = be_u8(i)?;
+    let (i, public_key) = rest(i)?;
+    Ok((
+        i,
+        DNSRData::DNSKEY(DNSRDataDNSKEY {
+            flags,
+            protocol,
+            algorithm,
+            public_key: public_key.to_vec(),
+        }),
+    ))
+}
+
+fn dns_parse_rdata_rrsig<'a>(input: &'a [u8], message: &'a [u8]) -> IResult<&'a [u8], DNSRData> {
+    let (i, type_covered) = be_u16(input)?;
+    let (i, algorithm) = be_u8(i)?;
+    let (i, labels) = be_u8(i)?;
+    let (i, original_ttl) = be_u32(i)?;
+    let (i, expiration) = be_u32(i)?;
+    let (i, inception) = be_u32(i)?;
+    let (i, key_tag) = be_u16(i)?;
+    let (i, signer_name) = dns_parse_name(i, message)?;
+    let (i, signature) = rest(i)?;
+    Ok((
+        i,
+        DNSRData::RRSIG(DNSRDataRRSIG {
+            type_covered,
+            algorithm,
+            labels,
+            original_ttl,
+            expiration,
+            inception,
+            key_tag,
+            signer_name,
+            signature: signature.to_vec(),
+        }),
+    ))
+}
+
+/// Parse the type bit maps of NSEC and NSEC3 records (RFC 4034 section 4.1.2)
+fn dns_parse_type_bitmaps(input: &[u8]) -> IResult<&[u8], Vec<u16>> {
+    let mut types = Vec::new();
+    let mut i = input;
+    while !i.is_empty() {
+        let (j, window) = be_u8(i)?;
+        let (j, bitmap) = length_data(be_u8)(j)?;
+        i = j;
+        for (n, byte) in bitmap.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    types.push(((window as u16) << 8) | ((n as u16) << 3) | bit);
+                }
+            }
+        }
+    }
+    Ok((i, types))
+}
+
+fn dns_parse_rdata_nsec<'a>(input: &'a [u8], message: &'a [u8]) -> IResult<&'a [u8], DNSRData> {
+    let (i, next_domain_name) = dns_parse_name(input, message)?;
+    let (i, types) = dns_parse_type_bitmaps(i)?;
+    Ok((
+        i,
+        DNSRData::NSEC(DNSRDataNSEC {
+            next_domain_name,
+            types,
+        }),
+    ))
+}
+
+fn dns_parse_rdata_nsec3(input: &[u8]) -> IResult<&[u8], DNSRData> {
+    let (i, hash_algorithm) = be_u8(input)?;
+    let (i, flags) = be_u8(i)?;
+    let (i, iterations) = be_u16(i)?;
+    let (i, salt) = length_data(be_u8)(i)?;
+    let (i, next_hashed_owner) = length_data(be_u8)(i)?;
+    let (i, types) = dns_parse_type_bitmaps(i)?;
+    Ok((
+        i,
+        DNSRData::NSEC3(DNSRDataNSEC3 {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt: salt.to_vec(),
+            next_hashed_owner: next_hashed_owner.to_vec(),
+            types,
+        }),
+    ))
+}
+
 fn dns_parse_rdata_unknown(input: &[u8]) -> IResult<&[u8], DNSRData> {
     rest(input).map(|(input, data)| (input, DNSRData::Unknown(data.to_vec())))
 }
@@ -331,6 +511,18 @@ fn dns_parse_rdata<'a>(
         DNS_RECORD_TYPE_SSHFP => dns_parse_rdata_sshfp(input),
         DNS_RECORD_TYPE_SRV => dns_parse_rdata_srv(input, message),
         DNS_RECORD_TYPE_OPT => dns_parse_rdata_opt(input),
+        DNS_RECORD_TYPE_SVCB => {
+            dns_parse_rdata_svcb(input, message).map(|(i, svcb)| (i, DNSRData::SVCB(svcb)))
+        }
+        DNS_RECORD_TYPE_HTTPS => {
+            dns_parse_rdata_svcb(input, message).map(|(i, svcb)| (i, DNSRData::HTTPS(svcb)))
+        }
+        DNS_RECORD_TYPE_CAA => dns_parse_rdata_caa(input),
+        DNS_RECORD_TYPE_DS => dns_parse_rdata_ds(input),
+        DNS_RECORD_TYPE_DNSKEY => dns_parse_rdata_dnskey(input),
+        DNS_RECORD_TYPE_RRSIG => dns_parse_rdata_rrsig(input, message),
+        DNS_RECORD_TYPE_NSEC => dns_parse_rdata_nsec(input, message),
+        DNS_RECORD_TYPE_NSEC3 => dns_parse_rdata_nsec3(input),
         _ => dns_parse_rdata_unknown(input),
     }
 }
@@ -912,4 +1104,161 @@ mod tests {
             panic!("Expected DNSRData::SRV");
         }
     }
+
+    #[test]
+    fn test_dns_parse_rdata_https() {
+        let data: &[u8] = &[
+            0x00, 0x01, // priority: 1
+            0x00, // target: .
+            0x00, 0x01, 0x00, 0x06, 0x02, 0x68, 0x33, 0x02, 0x68, 0x32, // alpn: h3,h2
+            0x00, 0x03, 0x00, 0x02, 0x01, 0xbb, // port: 443
+            0x00, 0x04, 0x00, 0x08, 0x68, 0x10, 0x84, 0xe5, 0x68, 0x10, 0x85,
+            0xe5, // ipv4hint: 104.16.132.229,104.16.133.229
+            0x00, 0x05, 0x00, 0x04, 0x00, 0x02, 0xab, 0xcd, // ech
+            0x00, 0x06, 0x00, 0x10, 0x26, 0x06, 0x47, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x68, 0x10, 0x84, 0xe5, // ipv6hint: 2606:4700::6810:84e5
+            0x00, 0x04, 0x00, 0x03, 0x01, 0x02, 0x03, // malformed ipv4hint
+        ];
+
+        let (rem, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_HTTPS).unwrap();
+        assert_eq!(rem.len(), 0);
+
+        if let DNSRData::HTTPS(https) = rdata {
+            assert_eq!(https.priority, 1);
+            assert!(https.target.is_empty());
+            assert_eq!(
+                https.params,
+                vec![
+                    DNSSvcParam::Alpn(vec![b"h3".to_vec(), b"h2".to_vec()]),
+                    DNSSvcParam::Port(443),
+                    DNSSvcParam::Ipv4Hint(vec![
+                        vec![0x68, 0x10, 0x84, 0xe5],
+                        vec![0x68, 0x10, 0x85, 0xe5]
+                    ]),
+                    DNSSvcParam::Ech(vec![0x00, 0x02, 0xab, 0xcd]),
+                    DNSSvcParam::Ipv6Hint(vec![data[43..59].to_vec()]),
+                    DNSSvcParam::Unknown(DNS_SVCPARAM_IPV4HINT, vec![0x01, 0x02, 0x03]),
+                ]
+            );
+        } else {
+            panic!("Expected DNSRData::HTTPS");
+        }
+    }
+
+    #[test]
+    fn test_dns_parse_rdata_caa() {
+        let data: &[u8] = &[
+            0x00, // flags
+            0x05, 0x69, 0x73, 0x73, 0x75, 0x65, // tag: issue
+            0x6c, 0x65, 0x74, 0x73, 0x65, 0x6e, 0x63, 0x72, 0x79, 0x70, 0x74, 0x2e, 0x6f, 0x72,
+            0x67, // value: letsencrypt.org
+        ];
+
+        let (rem, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_CAA).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(
+            rdata,
+            DNSRData::CAA(DNSRDataCAA {
+                flags: 0,
+                tag: b"issue".to_vec(),
+                value: b"letsencrypt.org".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_dns_parse_rdata_dnssec() {
+        // DS: key tag 2371, algorithm 13, digest type 2 (SHA-256)
+        let data: &[u8] = &[0x09, 0x43, 0x0d, 0x02, 0xc9, 0x88, 0xec, 0x42];
+        let (_, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_DS).unwrap();
+        assert_eq!(
+            rdata,
+            DNSRData::DS(DNSRDataDS {
+                key_tag: 2371,
+                algorithm: 13,
+                digest_type: 2,
+                digest: vec![0xc9, 0x88, 0xec, 0x42],
+            })
+        );
+
+        // DNSKEY: key signing key, algorithm 13
+        let data: &[u8] = &[0x01, 0x01, 0x03, 0x0d, 0x99, 0xdb, 0x2c, 0xc1];
+        let (_, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_DNSKEY).unwrap();
+        assert_eq!(
+            rdata,
+            DNSRData::DNSKEY(DNSRDataDNSKEY {
+                flags: 257,
+                protocol: 3,
+                algorithm: 13,
+                public_key: vec![0x99, 0xdb, 0x2c, 0xc1],
+            })
+        );
+
+        // RRSIG of an A RRset signed by example.com
+        let data: &[u8] = &[
+            0x00, 0x01, 0x0d, 0x02, 0x00, 0x00, 0x0e, 0x10, 0x66, 0x5a, 0x1c, 0x80, 0x66, 0x47,
+            0xa7, 0x80, 0x86, 0xc9, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03, 0x63,
+            0x6f, 0x6d, 0x00, 0x01, 0x02, 0x03, 0x04,
+        ];
+        let (_, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_RRSIG).unwrap();
+        assert_eq!(
+            rdata,
+            DNSRData::RRSIG(DNSRDataRRSIG {
+                type_covered: DNS_RECORD_TYPE_A,
+                algorithm: 13,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 0x665a1c80,
+                inception: 0x6647a780,
+                key_tag: 34505,
+                signer_name: b"example.com".to_vec(),
+                signature: vec![0x01, 0x02, 0x03, 0x04],
+            })
+        );
+    }
+
+    #[test]
+    fn test_dns_parse_rdata_nsec() {
+        // RFC 4034 section 4.3: host.example.com. A MX RRSIG NSEC TYPE1234
+        let data: &[u8] = &[
+            0x04, 0x68, 0x6f, 0x73, 0x74, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x03,
+            0x63, 0x6f, 0x6d, 0x00, 0x00, 0x06, 0x40, 0x01, 0x00, 0x00, 0x00, 0x03, 0x04, 0x1b,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
+        ];
+        let (rem, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_NSEC).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(
+            rdata,
+            DNSRData::NSEC(DNSRDataNSEC {
+                next_domain_name: b"host.example.com".to_vec(),
+                types: vec![
+                    DNS_RECORD_TYPE_A,
+                    DNS_RECORD_TYPE_MX,
+                    DNS_RECORD_TYPE_RRSIG,
+                    DNS_RECORD_TYPE_NSEC,
+                    1234
+                ],
+            })
+        );
+
+        // NSEC3: SHA-1, opt-out, 10 iterations, salt aabbccdd
+        let data: &[u8] = &[
+            0x01, 0x01, 0x00, 0x0a, 0x04, 0xaa, 0xbb, 0xcc, 0xdd, 0x04, 0x01, 0x02, 0x03, 0x04,
+            0x00, 0x01, 0x40,
+        ];
+        let (rem, rdata) = dns_parse_rdata(data, data, DNS_RECORD_TYPE_NSEC3).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(
+            rdata,
+            DNSRData::NSEC3(DNSRDataNSEC3 {
+                hash_algorithm: 1,
+                flags: 1,
+                iterations: 10,
+                salt: vec![0xaa, 0xbb, 0xcc, 0xdd],
+                next_hashed_owner: vec![0x01, 0x02, 0x03, 0x04],
+                types: vec![DNS_RECORD_TYPE_A],
+            })
+        );
+    }
 }
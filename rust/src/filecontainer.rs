@@ -25,9 +25,18 @@ use crate::core::*;
 // Defined in util-file.h
 /// cbindgen:ignore
 extern {
+    #[cfg(not(test))]
     pub fn FileFlowFlagsToFlags(flow_file_flags: u16, flags: u8) -> u16;
 }
 
+/// Test variant of `FileFlowFlagsToFlags`, as the unit tests are not
+/// linked against the C code.
+#[cfg(test)]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn FileFlowFlagsToFlags(_flow_file_flags: u16, _flags: u8) -> u16 {
+    0
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct FileContainer {
@@ -38,6 +38,7 @@ extern {
         dir: i32, frame_type: u8,
     ) -> *const CFrame;
     fn AppLayerFrameAddEventById(flow: *const Flow, dir: i32, id: i64, event: u8);
+    #[cfg(not(test))]
     fn AppLayerFrameSetLengthById(flow: *const Flow, dir: i32, id: i64, len: i64);
     #[cfg(not(test))]
     fn AppLayerFrameSetTxIdById(flow: *const Flow, dir: i32, id: i64, tx_id: u64);
@@ -113,6 +114,7 @@ impl Frame {
         }
     }
 
+    #[cfg(not(test))]
     #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn set_len(&self, flow: *const Flow, len: i64) {
         unsafe {
@@ -120,6 +122,12 @@ impl Frame {
         };
     }
 
+    /// A variation of `set_len` for use when running Rust unit tests as
+    /// the C functions for building a frame are not available for
+    /// linkage.
+    #[cfg(test)]
+    pub fn set_len(&self, _flow: *const Flow, _len: i64) {}
+
     #[cfg(not(test))]
     #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn set_tx(&self, flow: *const Flow, tx_id: u64) {
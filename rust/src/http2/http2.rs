@@ -151,7 +151,7 @@ pub struct DohHttp2Tx {
 
 #[derive(Debug)]
 pub struct HTTP2Transaction {
-    tx_id: u64,
+    pub tx_id: u64,
     pub stream_id: u32,
     pub state: HTTP2TransactionState,
     child_stream_id: u32,
@@ -295,7 +295,7 @@ impl HTTP2Transaction {
         self.ft_tc.file_flags = unsafe { FileFlowFlagsToFlags(flow_file_flags, STREAM_TOCLIENT) };
     }
 
-    fn decompress<'a>(
+    pub fn decompress<'a>(
         &'a mut self, input: &'a [u8], output: &'a mut Vec<u8>, dir: Direction,
         sfcm: &'static SuricataFileContext, over: bool, flow: *const Flow,
     ) -> io::Result<()> {
@@ -377,7 +377,7 @@ impl HTTP2Transaction {
         return Ok(());
     }
 
-    fn handle_frame(
+    pub fn handle_frame(
         &mut self, header: &parser::HTTP2FrameHeader, data: &HTTP2FrameTypeData, dir: Direction,
     ) -> Option<Vec<u8>> {
         //handle child_stream_id changes
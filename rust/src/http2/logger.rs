@@ -207,10 +207,9 @@ fn log_http2_frames(frames: &[HTTP2Frame], js: &mut JsonBuilder) -> Result<bool,
     return Ok(has_settings || has_error_code || has_priority);
 }
 
-fn log_http2(tx: &HTTP2Transaction, js: &mut JsonBuilder) -> Result<bool, JsonError> {
-    js.open_object("http")?;
-    js.set_string("version", "2")?;
-
+/// Logs the request and response headers of a transaction, and the
+/// fields common to all HTTP versions, in an already open "http" object
+pub fn log_http_headers(tx: &HTTP2Transaction, js: &mut JsonBuilder) -> Result<bool, JsonError> {
     let mut common: HashMap<HeaderName, &Vec<u8>> = HashMap::new();
 
     let mut has_headers = false;
@@ -266,6 +265,15 @@ fn log_http2(tx: &HTTP2Transaction, js: &mut JsonBuilder) -> Result<bool, JsonEr
         }
     }
 
+    return Ok(has_headers);
+}
+
+fn log_http2(tx: &HTTP2Transaction, js: &mut JsonBuilder) -> Result<bool, JsonError> {
+    js.open_object("http")?;
+    js.set_string("version", "2")?;
+
+    let has_headers = log_http_headers(tx, js)?;
+
     // The rest of http2 logging is placed in an "http2" object.
     js.open_object("http2")?;
 
@@ -22,7 +22,7 @@
 mod decompression;
 pub mod detect;
 pub mod http2;
-mod huffman;
+pub mod huffman;
 pub mod logger;
-mod parser;
+pub mod parser;
 mod range;
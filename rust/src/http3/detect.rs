@@ -0,0 +1,32 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::quic::quic::QuicTransaction;
+use std::ptr;
+
+/// Gets the HTTP/2 representation of a HTTP/3 request, so that the
+/// HTTP/2 getters can be used for detection
+#[no_mangle]
+pub unsafe extern "C" fn SCHttp3TxGetHttp2Tx(
+    tx: *mut std::os::raw::c_void,
+) -> *mut std::os::raw::c_void {
+    let tx = cast_pointer!(tx, QuicTransaction);
+    match &mut tx.http3 {
+        Some(h3) => &mut h3.http as *mut _ as *mut _,
+        None => ptr::null_mut(),
+    }
+}
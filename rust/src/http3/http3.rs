@@ -0,0 +1,630 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::parser::{
+    http3_parse_frame_header, http3_parse_settings, http3_var_uint, Http3FrameType,
+    Http3StreamType, HTTP3_SETTINGS_QPACK_MAX_TABLE_CAPACITY,
+};
+use super::qpack::{QpackDecoder, QpackError};
+use crate::applayer::AppLayerTxData;
+use crate::core::{AppProto, Direction, Flow, ALPROTO_UNKNOWN};
+use crate::http2::http2::{
+    HTTP2Frame, HTTP2FrameTypeData, HTTP2Transaction, HTTP2TransactionState,
+    SURICATA_HTTP2_FILE_CONFIG,
+};
+use crate::http2::parser::{
+    HTTP2FrameHeader, HTTP2FrameHeaderBlock, HTTP2FrameHeaders, HTTP2FrameType,
+    HTTP2_FLAG_HEADER_END_HEADERS, HTTP2_FLAG_HEADER_EOS,
+};
+use nom7::Err;
+use num::FromPrimitive;
+use std::collections::{BTreeMap, HashMap};
+
+pub static mut ALPROTO_HTTP3: AppProto = ALPROTO_UNKNOWN;
+
+// maximum size of a buffered frame, DATA payloads being streamed
+const HTTP3_MAX_REASS: usize = 102400;
+// maximum size of out of order data kept for a stream
+const HTTP3_MAX_PENDING: usize = 65536;
+const HTTP3_MAX_STREAMS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Http3Error {
+    InvalidFrame,
+    InvalidQpack,
+    TooManyStreams,
+    FailedDecompression,
+}
+
+/// What the request streams carry, in order
+#[derive(Debug)]
+pub enum Http3Message {
+    Headers {
+        stream_id: u64,
+        dir: Direction,
+        blocks: Vec<HTTP2FrameHeaderBlock>,
+        fin: bool,
+    },
+    Data {
+        stream_id: u64,
+        dir: Direction,
+        data: Vec<u8>,
+        fin: bool,
+    },
+    Error(Http3Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Http3StreamKind {
+    Request,
+    Control,
+    QpackEncoder,
+    // unidirectional stream whose type is not known yet
+    Unknown,
+    // ignored, or finished
+    Skip,
+}
+
+struct Http3Stream {
+    kind: Http3StreamKind,
+    // offset of the end of buf in the stream
+    offset: u64,
+    buf: Vec<u8>,
+    // data received after a gap
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_len: usize,
+    fin: Option<u64>,
+    // bytes of the current DATA frame not yet received
+    data_left: u64,
+    // bytes of the current unhandled frame not yet received
+    skip_left: u64,
+    // waiting for QPACK encoder instructions
+    blocked: bool,
+}
+
+impl Http3Stream {
+    fn new(kind: Http3StreamKind) -> Self {
+        Self {
+            kind,
+            offset: 0,
+            buf: Vec::new(),
+            pending: BTreeMap::new(),
+            pending_len: 0,
+            fin: None,
+            data_left: 0,
+            skip_left: 0,
+            blocked: false,
+        }
+    }
+
+    /// Adds the data of a STREAM frame, returns false if too much data
+    /// is waiting for a gap to be filled
+    fn add(&mut self, offset: u64, data: &[u8], fin: bool) -> bool {
+        let end = offset + data.len() as u64;
+        if fin {
+            self.fin = Some(end);
+        }
+        if end <= self.offset {
+            // retransmission
+            return true;
+        }
+        if offset > self.offset {
+            // a retransmitted chunk replaces the one at the same offset
+            let replaced = self.pending.get(&offset).map_or(0, |c| c.len());
+            if data.len() <= replaced {
+                return true;
+            }
+            if self.pending_len - replaced + data.len() > HTTP3_MAX_PENDING {
+                return false;
+            }
+            self.pending_len = self.pending_len - replaced + data.len();
+            self.pending.insert(offset, data.to_vec());
+            return true;
+        }
+        self.buf
+            .extend_from_slice(&data[(self.offset - offset) as usize..]);
+        self.offset = end;
+        while let Some((&start, _)) = self.pending.iter().next() {
+            if start > self.offset {
+                break;
+            }
+            let chunk = self.pending.remove(&start).unwrap();
+            self.pending_len -= chunk.len();
+            let end = start + chunk.len() as u64;
+            if end > self.offset {
+                self.buf
+                    .extend_from_slice(&chunk[(self.offset - start) as usize..]);
+                self.offset = end;
+            }
+        }
+        return true;
+    }
+
+    fn is_finished(&self) -> bool {
+        self.fin == Some(self.offset) && self.pending.is_empty() && self.data_left == 0
+    }
+
+    fn skip(&mut self) {
+        self.kind = Http3StreamKind::Skip;
+        self.buf = Vec::new();
+        self.pending.clear();
+        self.pending_len = 0;
+    }
+
+    /// Parses the frames of a request or control stream, returns the
+    /// number of bytes consumed
+    fn parse_frames(
+        &mut self, stream_id: u64, dir: Direction, decoders: &mut [QpackDecoder; 2],
+        messages: &mut Vec<Http3Message>,
+    ) -> Result<usize, Http3Error> {
+        let request = self.kind == Http3StreamKind::Request;
+        let mut pos = 0;
+        while pos < self.buf.len() {
+            let input = &self.buf[pos..];
+            if self.data_left > 0 || self.skip_left > 0 {
+                let left = std::cmp::max(self.data_left, self.skip_left);
+                let len = std::cmp::min(left, input.len() as u64) as usize;
+                if self.data_left > 0 {
+                    messages.push(Http3Message::Data {
+                        stream_id,
+                        dir,
+                        data: input[..len].to_vec(),
+                        fin: false,
+                    });
+                    self.data_left -= len as u64;
+                } else {
+                    self.skip_left -= len as u64;
+                }
+                pos += len;
+                continue;
+            }
+            let (rest, header) = match http3_parse_frame_header(input) {
+                Ok(r) => r,
+                Err(Err::Incomplete(_)) => break,
+                Err(_) => return Err(Http3Error::InvalidFrame),
+            };
+            let hlen = input.len() - rest.len();
+            let ftype = Http3FrameType::from_u64(header.ftype);
+            let complete = (rest.len() as u64) >= header.length;
+            match ftype {
+                Some(Http3FrameType::Data) if request => {
+                    self.data_left = header.length;
+                }
+                Some(Http3FrameType::Headers) | Some(Http3FrameType::Settings) => {
+                    if (ftype == Some(Http3FrameType::Headers)) != request {
+                        return Err(Http3Error::InvalidFrame);
+                    }
+                    if !complete {
+                        if header.length > HTTP3_MAX_REASS as u64 {
+                            return Err(Http3Error::InvalidFrame);
+                        }
+                        break;
+                    }
+                    let payload = &rest[..header.length as usize];
+                    if request {
+                        match decoders[dir.index()].decode_field_section(payload) {
+                            Ok(blocks) => {
+                                messages.push(Http3Message::Headers {
+                                    stream_id,
+                                    dir,
+                                    blocks,
+                                    fin: false,
+                                });
+                            }
+                            Err(QpackError::Blocked) => {
+                                self.blocked = true;
+                                break;
+                            }
+                            Err(QpackError::Invalid) => {
+                                messages.push(Http3Message::Error(Http3Error::InvalidQpack));
+                            }
+                        }
+                        self.blocked = false;
+                    } else if let Ok((_, settings)) = http3_parse_settings(payload) {
+                        for setting in settings {
+                            if setting.id == HTTP3_SETTINGS_QPACK_MAX_TABLE_CAPACITY {
+                                // the sender decodes what its peer encodes
+                                let peer = if dir == Direction::ToServer {
+                                    Direction::ToClient
+                                } else {
+                                    Direction::ToServer
+                                };
+                                decoders[peer.index()].max_capacity = setting.value;
+                            }
+                        }
+                    }
+                    pos += hlen + header.length as usize;
+                    continue;
+                }
+                Some(Http3FrameType::Data) | Some(Http3FrameType::PushPromise) if !request => {
+                    return Err(Http3Error::InvalidFrame);
+                }
+                _ => {
+                    self.skip_left = header.length;
+                }
+            }
+            pos += hlen;
+        }
+        Ok(pos)
+    }
+
+    /// Parses the buffered data, returns true if QPACK encoder
+    /// instructions were applied
+    fn parse(
+        &mut self, stream_id: u64, dir: Direction, decoders: &mut [QpackDecoder; 2],
+        messages: &mut Vec<Http3Message>,
+    ) -> bool {
+        let start = messages.len();
+        if self.kind == Http3StreamKind::Unknown {
+            match http3_var_uint(&self.buf) {
+                Ok((rest, stype)) => {
+                    let consumed = self.buf.len() - rest.len();
+                    self.buf.drain(..consumed);
+                    self.kind = match Http3StreamType::from_u64(stype) {
+                        Some(Http3StreamType::Control) => Http3StreamKind::Control,
+                        Some(Http3StreamType::QpackEncoder) => Http3StreamKind::QpackEncoder,
+                        // push streams, QPACK acknowledgments or reserved types
+                        _ => Http3StreamKind::Skip,
+                    };
+                }
+                Err(_) => return false,
+            }
+        }
+        let mut progress = false;
+        let r = match self.kind {
+            Http3StreamKind::QpackEncoder => {
+                match decoders[dir.index()].parse_encoder_stream(&self.buf) {
+                    Ok(n) => {
+                        progress = n > 0;
+                        Ok(n)
+                    }
+                    Err(_) => Err(Http3Error::InvalidQpack),
+                }
+            }
+            Http3StreamKind::Request | Http3StreamKind::Control => {
+                self.parse_frames(stream_id, dir, decoders, messages)
+            }
+            _ => Ok(self.buf.len()),
+        };
+        match r {
+            Ok(n) => {
+                self.buf.drain(..n);
+                if self.buf.len() > HTTP3_MAX_REASS {
+                    messages.push(Http3Message::Error(Http3Error::InvalidFrame));
+                    self.skip();
+                }
+            }
+            Err(e) => {
+                messages.push(Http3Message::Error(e));
+                self.skip();
+            }
+        }
+        if self.kind == Http3StreamKind::Request && !self.blocked && self.is_finished() {
+            match messages[start..].last_mut() {
+                Some(Http3Message::Headers { fin, .. }) | Some(Http3Message::Data { fin, .. }) => {
+                    *fin = true;
+                }
+                _ => {
+                    messages.push(Http3Message::Data {
+                        stream_id,
+                        dir,
+                        data: Vec::new(),
+                        fin: true,
+                    });
+                }
+            }
+            self.skip();
+        }
+        return progress;
+    }
+}
+
+/// HTTP/3 state of a QUIC connection, from its STREAM frames
+#[derive(Default)]
+pub struct Http3State {
+    // by stream id and direction index
+    streams: HashMap<(u64, usize), Http3Stream>,
+    // QPACK decoders by direction index
+    decoders: [QpackDecoder; 2],
+}
+
+impl Http3State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the data of a STREAM frame.
+    ///
+    /// Returns the headers and bodies of the requests and responses, in
+    /// order, with the errors met.
+    pub fn parse_stream(
+        &mut self, stream_id: u64, offset: u64, fin: bool, data: &[u8], dir: Direction,
+    ) -> Vec<Http3Message> {
+        let mut messages = Vec::new();
+        let kind = match stream_id & 0x03 {
+            // client-initiated bidirectional streams carry requests
+            0x00 => Http3StreamKind::Request,
+            // unidirectional streams, from the client then from the server
+            0x02 if dir == Direction::ToServer => Http3StreamKind::Unknown,
+            0x03 if dir == Direction::ToClient => Http3StreamKind::Unknown,
+            _ => return messages,
+        };
+        let key = (stream_id, dir.index());
+        if !self.streams.contains_key(&key) && self.streams.len() >= HTTP3_MAX_STREAMS {
+            // forget about the finished streams
+            self.streams
+                .retain(|_, stream| stream.kind != Http3StreamKind::Skip);
+            if self.streams.len() >= HTTP3_MAX_STREAMS {
+                messages.push(Http3Message::Error(Http3Error::TooManyStreams));
+                return messages;
+            }
+        }
+        let stream = self
+            .streams
+            .entry(key)
+            .or_insert_with(|| Http3Stream::new(kind));
+        if stream.kind == Http3StreamKind::Skip {
+            return messages;
+        }
+        if !stream.add(offset, data, fin) {
+            messages.push(Http3Message::Error(Http3Error::InvalidFrame));
+            stream.skip();
+            return messages;
+        }
+        if stream.parse(stream_id, dir, &mut self.decoders, &mut messages) {
+            // new dynamic table entries may unblock request streams
+            for (&(sid, index), stream) in self.streams.iter_mut() {
+                if index == dir.index() && stream.blocked {
+                    stream.parse(sid, dir, &mut self.decoders, &mut messages);
+                }
+            }
+        }
+        return messages;
+    }
+}
+
+/// HTTP/3 request, using the HTTP/2 representation of headers and the
+/// HTTP/2 file handling
+#[derive(Debug)]
+pub struct Http3Transaction {
+    pub stream_id: u64,
+    pub http: HTTP2Transaction,
+}
+
+impl Http3Transaction {
+    pub fn new(stream_id: u64, tx_id: u64) -> Self {
+        let mut http = HTTP2Transaction::new();
+        http.tx_id = tx_id;
+        http.stream_id = stream_id as u32;
+        http.state = HTTP2TransactionState::HTTP2StateOpen;
+        Self { stream_id, http }
+    }
+
+    /// Applies the headers or body of a request stream, the files being
+    /// accounted in the outer transaction data
+    pub fn handle_message(
+        &mut self, message: Http3Message, tx_data: &mut AppLayerTxData, flow: *const Flow,
+    ) -> Result<(), Http3Error> {
+        self.http.update_file_flags(tx_data.file_flags);
+        let mut flags = 0;
+        let (dir, ftype, data, body) = match message {
+            Http3Message::Headers {
+                dir, blocks, fin, ..
+            } => {
+                flags |= HTTP2_FLAG_HEADER_END_HEADERS;
+                if fin {
+                    flags |= HTTP2_FLAG_HEADER_EOS;
+                }
+                let data = HTTP2FrameTypeData::HEADERS(HTTP2FrameHeaders {
+                    padlength: None,
+                    priority: None,
+                    blocks,
+                });
+                (dir, HTTP2FrameType::Headers, data, None)
+            }
+            Http3Message::Data { dir, data, fin, .. } => {
+                if fin {
+                    flags |= HTTP2_FLAG_HEADER_EOS;
+                }
+                (
+                    dir,
+                    HTTP2FrameType::Data,
+                    HTTP2FrameTypeData::DATA,
+                    Some(data),
+                )
+            }
+            Http3Message::Error(_) => return Ok(()),
+        };
+        let header = HTTP2FrameHeader {
+            length: 0,
+            ftype: ftype as u8,
+            flags,
+            reserved: 0,
+            stream_id: self.http.stream_id,
+        };
+        self.http.handle_frame(&header, &data, dir);
+        let frame = HTTP2Frame { header, data };
+        if dir == Direction::ToServer {
+            self.http.frames_ts.push(frame);
+        } else {
+            self.http.frames_tc.push(frame);
+        }
+        if let Some(body) = body {
+            let file_open = if dir == Direction::ToServer {
+                self.http.ft_ts.file_open
+            } else {
+                self.http.ft_tc.file_open
+            };
+            if body.is_empty() && !file_open {
+                // end of a stream without body
+                return Ok(());
+            }
+            if let Some(sfcm) = unsafe { SURICATA_HTTP2_FILE_CONFIG } {
+                let over = flags & HTTP2_FLAG_HEADER_EOS != 0;
+                let opened = self.http.tx_data.files_opened;
+                let mut output = Vec::new();
+                let r = self
+                    .http
+                    .decompress(&body, &mut output, dir, sfcm, over, flow);
+                tx_data.files_opened += self.http.tx_data.files_opened - opened;
+                if r.is_err() {
+                    return Err(Http3Error::FailedDecompression);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(message: &Http3Message) -> Vec<(&[u8], &[u8])> {
+        match message {
+            Http3Message::Headers { blocks, .. } => blocks
+                .iter()
+                .map(|b| (b.name.as_slice(), b.value.as_slice()))
+                .collect(),
+            _ => panic!("not headers: {:?}", message),
+        }
+    }
+
+    #[test]
+    fn test_http3_request() {
+        let mut state = Http3State::new();
+        // HEADERS with GET, https, /index.html, :authority example.com
+        let request = [
+            0x01, 0x19, 0x00, 0x00, 0xd1, 0xd7, 0x51, 0x0b, 0x2f, 0x69, 0x6e, 0x64, 0x65, 0x78,
+            0x2e, 0x68, 0x74, 0x6d, 0x6c, 0x50, 0x06, 0x65, 0x78, 0x2e, 0x63, 0x6f, 0x6d,
+        ];
+        // split over two STREAM frames, the second one first
+        let messages = state.parse_stream(0, 10, true, &request[10..], Direction::ToServer);
+        assert!(messages.is_empty());
+        let messages = state.parse_stream(0, 0, false, &request[..10], Direction::ToServer);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            headers(&messages[0]),
+            vec![
+                (&b":method"[..], &b"GET"[..]),
+                (&b":scheme"[..], &b"https"[..]),
+                (&b":path"[..], &b"/index.html"[..]),
+                (&b":authority"[..], &b"ex.com"[..]),
+            ]
+        );
+        assert!(matches!(
+            messages[0],
+            Http3Message::Headers { fin: true, .. }
+        ));
+        // retransmission is ignored
+        let messages = state.parse_stream(0, 0, false, &request[..10], Direction::ToServer);
+        assert!(messages.is_empty());
+
+        // response HEADERS :status 200, then DATA split in two frames
+        let response = [
+            0x01, 0x03, 0x00, 0x00, 0xd9, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c, 0x6f,
+        ];
+        let messages = state.parse_stream(0, 0, false, &response[..9], Direction::ToClient);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(headers(&messages[0]), vec![(&b":status"[..], &b"200"[..])]);
+        match &messages[1] {
+            Http3Message::Data { data, fin, .. } => {
+                assert_eq!(data, b"he");
+                assert!(!fin);
+            }
+            _ => panic!("not data"),
+        }
+        let messages = state.parse_stream(0, 9, false, &response[9..], Direction::ToClient);
+        assert_eq!(messages.len(), 1);
+        let messages = state.parse_stream(0, 12, true, &[], Direction::ToClient);
+        assert_eq!(messages.len(), 1);
+        assert!(
+            matches!(&messages[0], Http3Message::Data { data, fin: true, .. } if data.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_http3_stream_retransmission() {
+        let mut stream = Http3Stream::new(Http3StreamKind::Request);
+        // the same out of order chunk, retransmitted more than fits
+        for _ in 0..(HTTP3_MAX_PENDING / 4 + 1) {
+            assert!(stream.add(4, b"efgh", false));
+        }
+        assert_eq!(stream.pending_len, 4);
+        // a longer retransmission replaces it
+        assert!(stream.add(4, b"efghij", false));
+        assert_eq!(stream.pending_len, 6);
+        assert!(stream.add(0, b"abcd", false));
+        assert_eq!(stream.buf, b"abcdefghij");
+        assert_eq!(stream.pending_len, 0);
+        assert!(stream.pending.is_empty());
+    }
+
+    #[test]
+    fn test_http3_blocked_stream() {
+        let mut state = Http3State::new();
+        // server control stream with SETTINGS_QPACK_MAX_TABLE_CAPACITY 220
+        let control = [0x00, 0x04, 0x03, 0x01, 0x40, 0xdc];
+        assert!(state
+            .parse_stream(3, 0, false, &control, Direction::ToClient)
+            .is_empty());
+        assert_eq!(
+            state.decoders[Direction::ToServer.index()].max_capacity,
+            220
+        );
+
+        // request referencing the dynamic table, RFC 9204 appendix B.2
+        let request = [0x01, 0x04, 0x03, 0x81, 0x10, 0x11];
+        assert!(state
+            .parse_stream(0, 0, true, &request, Direction::ToServer)
+            .is_empty());
+        // client encoder stream
+        let encoder = [
+            0x02, 0x3f, 0xbd, 0x01, 0xc0, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78, 0x61, 0x6d,
+            0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0xc1, 0x0c, 0x2f, 0x73, 0x61, 0x6d, 0x70,
+            0x6c, 0x65, 0x2f, 0x70, 0x61, 0x74, 0x68,
+        ];
+        let messages = state.parse_stream(2, 0, false, &encoder, Direction::ToServer);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            headers(&messages[0]),
+            vec![
+                (&b":authority"[..], &b"www.example.com"[..]),
+                (&b":path"[..], &b"/sample/path"[..]),
+            ]
+        );
+        assert!(matches!(
+            messages[0],
+            Http3Message::Headers { fin: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_http3_invalid_frame() {
+        let mut state = Http3State::new();
+        // DATA frame on the control stream
+        let control = [0x00, 0x00, 0x01, 0x00];
+        let messages = state.parse_stream(2, 0, false, &control, Direction::ToServer);
+        assert!(matches!(
+            messages[..],
+            [Http3Message::Error(Http3Error::InvalidFrame)]
+        ));
+        // the stream is then ignored
+        let messages = state.parse_stream(2, 4, false, &control, Direction::ToServer);
+        assert!(messages.is_empty());
+    }
+}
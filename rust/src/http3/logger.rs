@@ -0,0 +1,51 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::http3::Http3Transaction;
+use crate::http2::logger::log_http_headers;
+use crate::jsonbuilder::{JsonBuilder, JsonError};
+use crate::quic::logger::rs_quic_to_json;
+use crate::quic::quic::QuicTransaction;
+
+fn log_http3(tx: &Http3Transaction, js: &mut JsonBuilder) -> Result<bool, JsonError> {
+    js.open_object("http")?;
+    js.set_string("version", "3")?;
+
+    let has_headers = log_http_headers(&tx.http, js)?;
+
+    js.open_object("http3")?;
+    js.set_uint("stream_id", tx.stream_id)?;
+    js.close()?; // http3
+    js.close()?; // http
+
+    return Ok(has_headers);
+}
+
+/// Logs a request of a HTTP/3 flow, or one of its QUIC packets
+#[no_mangle]
+pub unsafe extern "C" fn rs_http3_to_json(
+    tx: *mut std::os::raw::c_void, js: &mut JsonBuilder,
+) -> bool {
+    let qtx = cast_pointer!(tx, QuicTransaction);
+    if let Some(h3) = &qtx.http3 {
+        if let Ok(x) = log_http3(h3, js) {
+            return x;
+        }
+        return false;
+    }
+    return rs_quic_to_json(tx, js);
+}
@@ -0,0 +1,133 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+// References:
+//   https://www.rfc-editor.org/rfc/rfc9114 (HTTP/3)
+
+use nom7::bytes::streaming::take;
+use nom7::combinator::complete;
+use nom7::multi::many0;
+use nom7::number::streaming::be_u8;
+use nom7::sequence::pair;
+use nom7::IResult;
+
+#[repr(u64)]
+#[derive(Clone, Copy, PartialEq, Eq, FromPrimitive, Debug)]
+pub enum Http3FrameType {
+    Data = 0x00,
+    Headers = 0x01,
+    CancelPush = 0x03,
+    Settings = 0x04,
+    PushPromise = 0x05,
+    GoAway = 0x07,
+    MaxPushId = 0x0d,
+}
+
+#[repr(u64)]
+#[derive(Clone, Copy, PartialEq, Eq, FromPrimitive, Debug)]
+pub enum Http3StreamType {
+    Control = 0x00,
+    Push = 0x01,
+    QpackEncoder = 0x02,
+    QpackDecoder = 0x03,
+}
+
+pub const HTTP3_SETTINGS_QPACK_MAX_TABLE_CAPACITY: u64 = 0x01;
+
+/// Variable-length integer, as used by QUIC and HTTP/3.
+///
+/// Unlike the one of the QUIC parser, this one is streaming, as HTTP/3
+/// frames span multiple STREAM frames.
+pub fn http3_var_uint(input: &[u8]) -> IResult<&[u8], u64> {
+    let (rest, first) = be_u8(input)?;
+    let len = 1usize << (first >> 6);
+    let (rest, bytes) = take(len - 1)(rest)?;
+    let value = bytes
+        .iter()
+        .fold((first & 0x3F) as u64, |acc, &b| (acc << 8) | b as u64);
+    Ok((rest, value))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Http3FrameHeader {
+    pub ftype: u64,
+    pub length: u64,
+}
+
+pub fn http3_parse_frame_header(input: &[u8]) -> IResult<&[u8], Http3FrameHeader> {
+    let (rest, ftype) = http3_var_uint(input)?;
+    let (rest, length) = http3_var_uint(rest)?;
+    Ok((rest, Http3FrameHeader { ftype, length }))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Http3Setting {
+    pub id: u64,
+    pub value: u64,
+}
+
+/// Parses the payload of a complete SETTINGS frame
+pub fn http3_parse_settings(input: &[u8]) -> IResult<&[u8], Vec<Http3Setting>> {
+    many0(complete(|i| {
+        let (i, (id, value)) = pair(http3_var_uint, http3_var_uint)(i)?;
+        Ok((i, Http3Setting { id, value }))
+    }))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http3_var_uint() {
+        // examples from RFC 9000 appendix A.1
+        let buf = [0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c];
+        assert_eq!(http3_var_uint(&buf), Ok((&[][..], 151288809941952652)));
+        let buf = [0x9d, 0x7f, 0x3e, 0x7d];
+        assert_eq!(http3_var_uint(&buf), Ok((&[][..], 494878333)));
+        let buf = [0x7b, 0xbd, 0x01];
+        assert_eq!(http3_var_uint(&buf), Ok((&[0x01][..], 15293)));
+        let buf = [0x25];
+        assert_eq!(http3_var_uint(&buf), Ok((&[][..], 37)));
+        let buf = [0x9d, 0x7f];
+        assert!(matches!(
+            http3_var_uint(&buf),
+            Err(nom7::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_http3_parse_frame_header() {
+        // SETTINGS with QPACK_MAX_TABLE_CAPACITY 4096 and QPACK_BLOCKED_STREAMS 16
+        let buf = [0x04, 0x05, 0x01, 0x50, 0x00, 0x07, 0x10, 0x33];
+        let (rest, hdr) = http3_parse_frame_header(&buf).unwrap();
+        assert_eq!(
+            hdr,
+            Http3FrameHeader {
+                ftype: Http3FrameType::Settings as u64,
+                length: 5
+            }
+        );
+        let (rest, settings) = http3_parse_settings(&rest[..hdr.length as usize]).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(settings.len(), 2);
+        assert_eq!(settings[0].id, HTTP3_SETTINGS_QPACK_MAX_TABLE_CAPACITY);
+        assert_eq!(settings[0].value, 4096);
+        assert_eq!(settings[1].id, 0x07);
+        assert_eq!(settings[1].value, 16);
+    }
+}
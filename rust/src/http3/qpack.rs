@@ -0,0 +1,577 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+// References:
+//   https://www.rfc-editor.org/rfc/rfc9204 (QPACK)
+
+use crate::common::nom7::bits;
+use crate::http2::huffman;
+use crate::http2::parser::{HTTP2FrameHeaderBlock, HTTP2HeaderDecodeStatus};
+use nom7::bytes::streaming::take;
+use nom7::error::{make_error, ErrorKind};
+use nom7::multi::many0;
+use nom7::number::streaming::be_u8;
+use nom7::{Err, IResult};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+// RFC 9204 section 3.2.1, overhead of a dynamic table entry
+const QPACK_ENTRY_OVERHEAD: u64 = 32;
+
+// RFC 9204 appendix A
+static QPACK_STATIC_TABLE: [(&str, &str); 99] = [
+    (":authority", ""),
+    (":path", "/"),
+    ("age", "0"),
+    ("content-disposition", ""),
+    ("content-length", "0"),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("referer", ""),
+    ("set-cookie", ""),
+    (":method", "CONNECT"),
+    (":method", "DELETE"),
+    (":method", "GET"),
+    (":method", "HEAD"),
+    (":method", "OPTIONS"),
+    (":method", "POST"),
+    (":method", "PUT"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "103"),
+    (":status", "200"),
+    (":status", "304"),
+    (":status", "404"),
+    (":status", "503"),
+    ("accept", "*/*"),
+    ("accept", "application/dns-message"),
+    ("accept-encoding", "gzip, deflate, br"),
+    ("accept-ranges", "bytes"),
+    ("access-control-allow-headers", "cache-control"),
+    ("access-control-allow-headers", "content-type"),
+    ("access-control-allow-origin", "*"),
+    ("cache-control", "max-age=0"),
+    ("cache-control", "max-age=2592000"),
+    ("cache-control", "max-age=604800"),
+    ("cache-control", "no-cache"),
+    ("cache-control", "no-store"),
+    ("cache-control", "public, max-age=31536000"),
+    ("content-encoding", "br"),
+    ("content-encoding", "gzip"),
+    ("content-type", "application/dns-message"),
+    ("content-type", "application/javascript"),
+    ("content-type", "application/json"),
+    ("content-type", "application/x-www-form-urlencoded"),
+    ("content-type", "image/gif"),
+    ("content-type", "image/jpeg"),
+    ("content-type", "image/png"),
+    ("content-type", "text/css"),
+    ("content-type", "text/html; charset=utf-8"),
+    ("content-type", "text/plain"),
+    ("content-type", "text/plain;charset=utf-8"),
+    ("range", "bytes=0-"),
+    ("strict-transport-security", "max-age=31536000"),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains",
+    ),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains; preload",
+    ),
+    ("vary", "accept-encoding"),
+    ("vary", "origin"),
+    ("x-content-type-options", "nosniff"),
+    ("x-xss-protection", "1; mode=block"),
+    (":status", "100"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "302"),
+    (":status", "400"),
+    (":status", "403"),
+    (":status", "421"),
+    (":status", "425"),
+    (":status", "500"),
+    ("accept-language", ""),
+    ("access-control-allow-credentials", "FALSE"),
+    ("access-control-allow-credentials", "TRUE"),
+    ("access-control-allow-headers", "*"),
+    ("access-control-allow-methods", "get"),
+    ("access-control-allow-methods", "get, post, options"),
+    ("access-control-allow-methods", "options"),
+    ("access-control-expose-headers", "content-length"),
+    ("access-control-request-headers", "content-type"),
+    ("access-control-request-method", "get"),
+    ("access-control-request-method", "post"),
+    ("alt-svc", "clear"),
+    ("authorization", ""),
+    (
+        "content-security-policy",
+        "script-src 'none'; object-src 'none'; base-uri 'none'",
+    ),
+    ("early-data", "1"),
+    ("expect-ct", ""),
+    ("forwarded", ""),
+    ("if-range", ""),
+    ("origin", ""),
+    ("purpose", "prefetch"),
+    ("server", ""),
+    ("timing-allow-origin", "*"),
+    ("upgrade-insecure-requests", "1"),
+    ("user-agent", ""),
+    ("x-forwarded-for", ""),
+    ("x-frame-options", "deny"),
+    ("x-frame-options", "sameorigin"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QpackError {
+    /// The field section references entries not yet received on the
+    /// encoder stream
+    Blocked,
+    Invalid,
+}
+
+/// Integer with a N-bit prefix, RFC 7541 section 5.1
+fn qpack_parse_int(input: &[u8], prefix: u8) -> IResult<&[u8], u64> {
+    let mask = ((1u16 << prefix) - 1) as u8;
+    let (mut rest, first) = be_u8(input)?;
+    let mut value = (first & mask) as u64;
+    if value < mask as u64 {
+        return Ok((rest, value));
+    }
+    let mut shift = 0;
+    loop {
+        let (r, b) = be_u8(rest)?;
+        rest = r;
+        if shift > 56 {
+            return Err(Err::Error(make_error(input, ErrorKind::TooLarge)));
+        }
+        value = value
+            .checked_add(((b & 0x7F) as u64) << shift)
+            .ok_or_else(|| Err::Error(make_error(input, ErrorKind::TooLarge)))?;
+        if b & 0x80 == 0 {
+            return Ok((rest, value));
+        }
+        shift += 7;
+    }
+}
+
+/// String literal whose length has a N-bit prefix, the Huffman flag
+/// being the bit just above the prefix
+fn qpack_parse_string(input: &[u8], prefix: u8) -> IResult<&[u8], Vec<u8>> {
+    let huffman = input.first().map(|&b| b & (1 << prefix) != 0);
+    let (rest, len) = qpack_parse_int(input, prefix)?;
+    let (rest, data) = take(len as usize)(rest)?;
+    if huffman == Some(true) {
+        let (_, val) = bits(many0(huffman::http2_decode_huffman))(data)?;
+        return Ok((rest, val));
+    }
+    Ok((rest, data.to_vec()))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum QpackEncoderInstruction {
+    SetCapacity(u64),
+    InsertNameRef {
+        static_table: bool,
+        index: u64,
+        value: Vec<u8>,
+    },
+    InsertLiteral {
+        name: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Duplicate(u64),
+}
+
+fn qpack_parse_encoder_instruction(input: &[u8]) -> IResult<&[u8], QpackEncoderInstruction> {
+    let (_, first) = be_u8(input)?;
+    if first & 0x80 != 0 {
+        let (rest, index) = qpack_parse_int(input, 6)?;
+        let (rest, value) = qpack_parse_string(rest, 7)?;
+        let static_table = first & 0x40 != 0;
+        Ok((
+            rest,
+            QpackEncoderInstruction::InsertNameRef {
+                static_table,
+                index,
+                value,
+            },
+        ))
+    } else if first & 0x40 != 0 {
+        let (rest, name) = qpack_parse_string(input, 5)?;
+        let (rest, value) = qpack_parse_string(rest, 7)?;
+        Ok((rest, QpackEncoderInstruction::InsertLiteral { name, value }))
+    } else if first & 0x20 != 0 {
+        let (rest, capacity) = qpack_parse_int(input, 5)?;
+        Ok((rest, QpackEncoderInstruction::SetCapacity(capacity)))
+    } else {
+        let (rest, index) = qpack_parse_int(input, 5)?;
+        Ok((rest, QpackEncoderInstruction::Duplicate(index)))
+    }
+}
+
+fn qpack_header_block(name: Rc<Vec<u8>>, value: Rc<Vec<u8>>) -> HTTP2FrameHeaderBlock {
+    HTTP2FrameHeaderBlock {
+        name,
+        value,
+        error: HTTP2HeaderDecodeStatus::HTTP2HeaderDecodeSuccess,
+        sizeupdate: 0,
+    }
+}
+
+fn qpack_static_entry(index: u64) -> Option<HTTP2FrameHeaderBlock> {
+    let (name, value) = QPACK_STATIC_TABLE.get(index as usize)?;
+    Some(qpack_header_block(
+        Rc::new(name.as_bytes().to_vec()),
+        Rc::new(value.as_bytes().to_vec()),
+    ))
+}
+
+/// QPACK decoder for the field sections sent in one direction, with the
+/// dynamic table built from the instructions of the encoder stream of
+/// the same direction.
+#[derive(Default)]
+pub struct QpackDecoder {
+    // oldest entries first
+    table: VecDeque<HTTP2FrameHeaderBlock>,
+    size: u64,
+    capacity: u64,
+    /// SETTINGS_QPACK_MAX_TABLE_CAPACITY announced by the peer decoder
+    pub max_capacity: u64,
+    inserted: u64,
+}
+
+impl QpackDecoder {
+    fn get_absolute(&self, index: u64) -> Option<&HTTP2FrameHeaderBlock> {
+        let dropped = self.inserted - self.table.len() as u64;
+        if index < dropped {
+            return None;
+        }
+        self.table.get((index - dropped) as usize)
+    }
+
+    fn evict(&mut self, needed: u64) {
+        while self.size + needed > self.capacity {
+            match self.table.pop_front() {
+                Some(entry) => {
+                    self.size -=
+                        entry.name.len() as u64 + entry.value.len() as u64 + QPACK_ENTRY_OVERHEAD;
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn insert(&mut self, name: Rc<Vec<u8>>, value: Rc<Vec<u8>>) -> Result<(), QpackError> {
+        let size = name.len() as u64 + value.len() as u64 + QPACK_ENTRY_OVERHEAD;
+        if size > self.capacity {
+            return Err(QpackError::Invalid);
+        }
+        self.evict(size);
+        self.size += size;
+        self.table.push_back(qpack_header_block(name, value));
+        self.inserted += 1;
+        Ok(())
+    }
+
+    fn apply(&mut self, instruction: QpackEncoderInstruction) -> Result<(), QpackError> {
+        match instruction {
+            QpackEncoderInstruction::SetCapacity(capacity) => {
+                if self.max_capacity > 0 && capacity > self.max_capacity {
+                    return Err(QpackError::Invalid);
+                }
+                self.capacity = capacity;
+                self.evict(0);
+                Ok(())
+            }
+            QpackEncoderInstruction::InsertNameRef {
+                static_table,
+                index,
+                value,
+            } => {
+                let name = if static_table {
+                    qpack_static_entry(index).ok_or(QpackError::Invalid)?.name
+                } else {
+                    let absolute = self
+                        .inserted
+                        .checked_sub(index + 1)
+                        .ok_or(QpackError::Invalid)?;
+                    self.get_absolute(absolute)
+                        .ok_or(QpackError::Invalid)?
+                        .name
+                        .clone()
+                };
+                self.insert(name, Rc::new(value))
+            }
+            QpackEncoderInstruction::InsertLiteral { name, value } => {
+                self.insert(Rc::new(name), Rc::new(value))
+            }
+            QpackEncoderInstruction::Duplicate(index) => {
+                let absolute = self
+                    .inserted
+                    .checked_sub(index + 1)
+                    .ok_or(QpackError::Invalid)?;
+                let entry = self.get_absolute(absolute).ok_or(QpackError::Invalid)?;
+                let (name, value) = (entry.name.clone(), entry.value.clone());
+                self.insert(name, value)
+            }
+        }
+    }
+
+    /// Applies the instructions of the encoder stream.
+    ///
+    /// Returns the number of bytes consumed, an incomplete instruction
+    /// being left for the next call.
+    pub fn parse_encoder_stream(&mut self, input: &[u8]) -> Result<usize, QpackError> {
+        let mut rest = input;
+        while !rest.is_empty() {
+            match qpack_parse_encoder_instruction(rest) {
+                Ok((r, instruction)) => {
+                    self.apply(instruction)?;
+                    rest = r;
+                }
+                Err(Err::Incomplete(_)) => break,
+                Err(_) => return Err(QpackError::Invalid),
+            }
+        }
+        Ok(input.len() - rest.len())
+    }
+
+    /// RFC 9204 section 4.5.1.1
+    fn decode_required_insert_count(&self, encoded: u64) -> Result<u64, QpackError> {
+        if encoded == 0 {
+            return Ok(0);
+        }
+        // without the peer settings, rely on the capacity set by the encoder
+        let max_capacity = if self.max_capacity > 0 {
+            self.max_capacity
+        } else {
+            self.capacity
+        };
+        let max_entries = max_capacity / QPACK_ENTRY_OVERHEAD;
+        let full_range = 2 * max_entries;
+        if encoded > full_range {
+            return Err(QpackError::Invalid);
+        }
+        let max_value = self.inserted + max_entries;
+        let max_wrapped = (max_value / full_range) * full_range;
+        let mut required = max_wrapped + encoded - 1;
+        if required > max_value {
+            if required <= full_range {
+                return Err(QpackError::Invalid);
+            }
+            required -= full_range;
+        }
+        if required == 0 {
+            return Err(QpackError::Invalid);
+        }
+        Ok(required)
+    }
+
+    fn get_field(
+        &self, absolute: u64, required: u64,
+    ) -> Result<&HTTP2FrameHeaderBlock, QpackError> {
+        if absolute >= required {
+            return Err(QpackError::Invalid);
+        }
+        self.get_absolute(absolute).ok_or(QpackError::Invalid)
+    }
+
+    fn decode_field_line<'a>(
+        &self, input: &'a [u8], base: u64, required: u64,
+    ) -> Result<(&'a [u8], HTTP2FrameHeaderBlock), QpackError> {
+        let first = input[0];
+        let relative = |index: u64| base.checked_sub(index + 1).ok_or(QpackError::Invalid);
+        if first & 0x80 != 0 {
+            // indexed field line
+            let (rest, index) = qpack_parse_int(input, 6).map_err(|_| QpackError::Invalid)?;
+            let block = if first & 0x40 != 0 {
+                qpack_static_entry(index).ok_or(QpackError::Invalid)?
+            } else {
+                self.get_field(relative(index)?, required)?.clone()
+            };
+            Ok((rest, block))
+        } else if first & 0x40 != 0 {
+            // literal field line with name reference
+            let (rest, index) = qpack_parse_int(input, 4).map_err(|_| QpackError::Invalid)?;
+            let (rest, value) = qpack_parse_string(rest, 7).map_err(|_| QpackError::Invalid)?;
+            let name = if first & 0x10 != 0 {
+                qpack_static_entry(index).ok_or(QpackError::Invalid)?.name
+            } else {
+                self.get_field(relative(index)?, required)?.name.clone()
+            };
+            Ok((rest, qpack_header_block(name, Rc::new(value))))
+        } else if first & 0x20 != 0 {
+            // literal field line with literal name
+            let (rest, name) = qpack_parse_string(input, 3).map_err(|_| QpackError::Invalid)?;
+            let (rest, value) = qpack_parse_string(rest, 7).map_err(|_| QpackError::Invalid)?;
+            Ok((rest, qpack_header_block(Rc::new(name), Rc::new(value))))
+        } else if first & 0x10 != 0 {
+            // indexed field line with post-base index
+            let (rest, index) = qpack_parse_int(input, 4).map_err(|_| QpackError::Invalid)?;
+            let absolute = base.checked_add(index).ok_or(QpackError::Invalid)?;
+            Ok((rest, self.get_field(absolute, required)?.clone()))
+        } else {
+            // literal field line with post-base name reference
+            let (rest, index) = qpack_parse_int(input, 3).map_err(|_| QpackError::Invalid)?;
+            let (rest, value) = qpack_parse_string(rest, 7).map_err(|_| QpackError::Invalid)?;
+            let absolute = base.checked_add(index).ok_or(QpackError::Invalid)?;
+            let name = self.get_field(absolute, required)?.name.clone();
+            Ok((rest, qpack_header_block(name, Rc::new(value))))
+        }
+    }
+
+    /// Decodes the field section of a complete HEADERS frame
+    pub fn decode_field_section(
+        &self, input: &[u8],
+    ) -> Result<Vec<HTTP2FrameHeaderBlock>, QpackError> {
+        let (rest, encoded) = qpack_parse_int(input, 8).map_err(|_| QpackError::Invalid)?;
+        let sign = rest.first().map(|&b| b & 0x80 != 0);
+        let (mut rest, delta) = qpack_parse_int(rest, 7).map_err(|_| QpackError::Invalid)?;
+        let required = self.decode_required_insert_count(encoded)?;
+        if required > self.inserted {
+            return Err(QpackError::Blocked);
+        }
+        let base = if sign == Some(true) {
+            required.checked_sub(delta + 1).ok_or(QpackError::Invalid)?
+        } else {
+            required.checked_add(delta).ok_or(QpackError::Invalid)?
+        };
+        let mut blocks = Vec::new();
+        while !rest.is_empty() {
+            let (r, block) = self.decode_field_line(rest, base, required)?;
+            blocks.push(block);
+            rest = r;
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(block: &HTTP2FrameHeaderBlock) -> (&[u8], &[u8]) {
+        (&block.name, &block.value)
+    }
+
+    #[test]
+    fn test_qpack_static_table() {
+        // RFC 9204 appendix B.1, literal field line with name reference
+        let decoder = QpackDecoder::default();
+        let buf = [
+            0x00, 0x00, 0x51, 0x0b, 0x2f, 0x69, 0x6e, 0x64, 0x65, 0x78, 0x2e, 0x68, 0x74, 0x6d,
+            0x6c,
+        ];
+        let blocks = decoder.decode_field_section(&buf).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(header(&blocks[0]), (&b":path"[..], &b"/index.html"[..]));
+    }
+
+    #[test]
+    fn test_qpack_dynamic_table() {
+        // RFC 9204 appendix B.2, dynamic table
+        let mut decoder = QpackDecoder {
+            max_capacity: 220,
+            ..Default::default()
+        };
+        let encoder = [
+            0x3f, 0xbd, 0x01, 0xc0, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70,
+            0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0xc1, 0x0c, 0x2f, 0x73, 0x61, 0x6d, 0x70, 0x6c,
+            0x65, 0x2f, 0x70, 0x61, 0x74, 0x68,
+        ];
+        let request = [0x03, 0x81, 0x10, 0x11];
+
+        // not yet received encoder instructions
+        assert_eq!(
+            decoder.decode_field_section(&request).err(),
+            Some(QpackError::Blocked)
+        );
+        // an incomplete instruction is left for later
+        assert_eq!(decoder.parse_encoder_stream(&encoder[..10]), Ok(3));
+        assert_eq!(decoder.parse_encoder_stream(&encoder[3..]), Ok(31));
+        assert_eq!(decoder.capacity, 220);
+        assert_eq!(decoder.size, 106);
+
+        let blocks = decoder.decode_field_section(&request).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            header(&blocks[0]),
+            (&b":authority"[..], &b"www.example.com"[..])
+        );
+        assert_eq!(header(&blocks[1]), (&b":path"[..], &b"/sample/path"[..]));
+
+        // duplicate of the relative index 1, ie :authority
+        assert_eq!(decoder.parse_encoder_stream(&[0x01]), Ok(1));
+        assert_eq!(decoder.inserted, 3);
+        // indexed field line relative to base 3
+        let blocks = decoder.decode_field_section(&[0x04, 0x00, 0x80]).unwrap();
+        assert_eq!(
+            header(&blocks[0]),
+            (&b":authority"[..], &b"www.example.com"[..])
+        );
+    }
+
+    #[test]
+    fn test_qpack_huffman() {
+        // literal field line with literal name, Huffman encoded
+        // custom-key: custom-value as in RFC 7541 appendix C.4
+        let decoder = QpackDecoder::default();
+        let buf = [
+            0x00, 0x00, 0x2f, 0x01, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f, 0x89, 0x25,
+            0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf,
+        ];
+        let blocks = decoder.decode_field_section(&buf).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(
+            header(&blocks[0]),
+            (&b"custom-key"[..], &b"custom-value"[..])
+        );
+    }
+
+    #[test]
+    fn test_qpack_invalid() {
+        let decoder = QpackDecoder::default();
+        // static index out of the table
+        assert_eq!(
+            decoder
+                .decode_field_section(&[0x00, 0x00, 0xff, 0x40])
+                .err(),
+            Some(QpackError::Invalid)
+        );
+        // dynamic reference without any table
+        assert_eq!(
+            decoder.decode_field_section(&[0x00, 0x00, 0x80]).err(),
+            Some(QpackError::Invalid)
+        );
+        // truncated value
+        assert_eq!(
+            decoder
+                .decode_field_section(&[0x00, 0x00, 0x51, 0x0b, 0x2f])
+                .err(),
+            Some(QpackError::Invalid)
+        );
+    }
+}
@@ -0,0 +1,290 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Correlation of the Kerberos exchanges of a client host.
+//!
+//! A tracker is kept in the host storage of each Kerberos client. It links
+//! the AS and TGS exchanges of each client principal, across flows, and
+//! counts the distinct service principal names (SPN) for which service
+//! tickets were issued within a time window.
+
+use super::krb5::KRB5Transaction;
+use crate::core::Flow;
+use kerberos_parser::krb5::{EncryptionType, MessageType};
+use std::collections::HashMap;
+use std::os::raw::c_void;
+
+/// Maximum number of principals tracked per host
+const KRB5_HOST_MAX_PRINCIPALS: usize = 64;
+/// Maximum number of SPNs tracked per principal
+const KRB5_PRINCIPAL_MAX_SPNS: usize = 1024;
+
+pub(super) static mut KRB5_CORRELATION: bool = false;
+pub(super) static mut KRB5_CORRELATION_WINDOW: u64 = 300;
+pub(super) static mut KRB5_KERBEROASTING_THRESHOLD: usize = 10;
+
+extern "C" {
+    pub fn SCKrb5HostStorageRegister();
+    pub fn SCKrb5HostUpdate(flow: *const Flow, tx: *mut c_void) -> bool;
+}
+
+/// Summary of the exchanges of a principal, logged with the transaction
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct KRB5PrincipalSummary {
+    pub principal: String,
+    /// An AS-REQ was seen for this principal
+    pub as_req: bool,
+    /// An AS-REP was seen for this principal
+    pub as_rep: bool,
+    /// Number of TGS exchanges of this principal
+    pub tgs_exchanges: u64,
+    /// Distinct SPNs of service tickets within the window
+    pub spns: usize,
+    /// Distinct SPNs of RC4 encrypted service tickets within the window
+    pub rc4_spns: usize,
+    /// Duration of the window, in seconds
+    pub window: u64,
+}
+
+#[derive(Debug, Default)]
+struct KRB5ServiceTicket {
+    last_seen: u64,
+    rc4: bool,
+}
+
+#[derive(Debug, Default)]
+struct KRB5PrincipalTimeline {
+    last_seen: u64,
+    as_req: bool,
+    as_rep: bool,
+    tgs_exchanges: u64,
+    spns: HashMap<String, KRB5ServiceTicket>,
+}
+
+impl KRB5PrincipalTimeline {
+    fn expire(&mut self, ts: u64, window: u64) {
+        self.spns.retain(|_, t| t.last_seen + window >= ts);
+    }
+
+    /// Record a service ticket, returns true if this is the RC4 service
+    /// ticket reaching the Kerberoasting threshold
+    fn add_service_ticket(&mut self, spn: String, ts: u64, rc4: bool, threshold: usize) -> bool {
+        self.tgs_exchanges += 1;
+        if let Some(t) = self.spns.get_mut(&spn) {
+            t.last_seen = ts;
+            if rc4 && !t.rc4 {
+                t.rc4 = true;
+                return self.rc4_spns() == threshold;
+            }
+            return false;
+        }
+        if self.spns.len() >= KRB5_PRINCIPAL_MAX_SPNS {
+            return false;
+        }
+        self.spns
+            .insert(spn, KRB5ServiceTicket { last_seen: ts, rc4 });
+        return rc4 && self.rc4_spns() == threshold;
+    }
+
+    fn rc4_spns(&self) -> usize {
+        self.spns.values().filter(|t| t.rc4).count()
+    }
+
+    fn summary(&self, principal: &str, window: u64) -> KRB5PrincipalSummary {
+        KRB5PrincipalSummary {
+            principal: principal.to_string(),
+            as_req: self.as_req,
+            as_rep: self.as_rep,
+            tgs_exchanges: self.tgs_exchanges,
+            spns: self.spns.len(),
+            rc4_spns: self.rc4_spns(),
+            window,
+        }
+    }
+}
+
+/// Kerberos exchanges of a client host, stored in the host storage
+#[derive(Debug, Default)]
+pub struct KRB5HostTracker {
+    principals: HashMap<String, KRB5PrincipalTimeline>,
+}
+
+fn is_rc4(etype: EncryptionType) -> bool {
+    etype == EncryptionType::RC4_HMAC || etype == EncryptionType::RC4_HMAC_EXP
+}
+
+impl KRB5HostTracker {
+    fn get_timeline(&mut self, principal: &str, ts: u64) -> &mut KRB5PrincipalTimeline {
+        if !self.principals.contains_key(principal)
+            && self.principals.len() >= KRB5_HOST_MAX_PRINCIPALS
+        {
+            // evict the principal seen least recently
+            if let Some(oldest) = self
+                .principals
+                .iter()
+                .min_by_key(|(_, t)| t.last_seen)
+                .map(|(p, _)| p.clone())
+            {
+                self.principals.remove(&oldest);
+            }
+        }
+        let timeline = self.principals.entry(principal.to_string()).or_default();
+        timeline.last_seen = ts;
+        timeline
+    }
+
+    fn timed_out(&self, ts: u64, window: u64) -> bool {
+        self.principals.values().all(|t| t.last_seen + window < ts)
+    }
+
+    /// Update the timeline of the client principal of a transaction.
+    ///
+    /// Stores the summary of the principal in the transaction, and returns
+    /// true if the Kerberoasting threshold was reached.
+    pub fn update(
+        &mut self, tx: &mut KRB5Transaction, ts: u64, window: u64, threshold: usize,
+    ) -> bool {
+        let cname = match tx.cname {
+            Some(ref cname) => cname,
+            // TGS-REQ carry the client name in the encrypted authenticator
+            None => return false,
+        };
+        let principal = match tx.realm {
+            Some(ref realm) => format!("{}@{}", cname, realm.0),
+            None => format!("{}", cname),
+        };
+        let timeline = self.get_timeline(&principal, ts);
+        timeline.expire(ts, window);
+        let mut kerberoasting = false;
+        match tx.msg_type {
+            MessageType::KRB_AS_REQ => timeline.as_req = true,
+            MessageType::KRB_AS_REP => timeline.as_rep = true,
+            MessageType::KRB_TGS_REP => {
+                if let Some(ref sname) = tx.sname {
+                    let rc4 = tx.ticket_etype.map_or(false, is_rc4);
+                    kerberoasting =
+                        timeline.add_service_ticket(format!("{}", sname), ts, rc4, threshold);
+                }
+            }
+            _ => return false,
+        }
+        tx.summary = Some(timeline.summary(&principal, window));
+        return kerberoasting;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_krb5_host_tracker_new() -> *mut c_void {
+    let tracker = KRB5HostTracker::default();
+    return Box::into_raw(Box::new(tracker)) as *mut _;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_krb5_host_tracker_free(tracker: *mut c_void) {
+    std::mem::drop(Box::from_raw(tracker as *mut KRB5HostTracker));
+}
+
+/// Returns true if no principal of the host was seen within the window
+#[no_mangle]
+pub unsafe extern "C" fn rs_krb5_host_tracker_timed_out(tracker: *mut c_void, ts: u64) -> bool {
+    let tracker = cast_pointer!(tracker, KRB5HostTracker);
+    return tracker.timed_out(ts, KRB5_CORRELATION_WINDOW);
+}
+
+/// Called by `SCKrb5HostUpdate` with the host locked
+#[no_mangle]
+pub unsafe extern "C" fn rs_krb5_host_tracker_update(
+    tracker: *mut c_void, tx: *mut c_void, ts: u64,
+) -> bool {
+    let tracker = cast_pointer!(tracker, KRB5HostTracker);
+    let tx = cast_pointer!(tx, KRB5Transaction);
+    return tracker.update(
+        tx,
+        ts,
+        KRB5_CORRELATION_WINDOW,
+        KRB5_KERBEROASTING_THRESHOLD,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Direction;
+    use kerberos_parser::krb5::{NameType, PrincipalName, Realm};
+
+    fn principal(names: &[&str]) -> PrincipalName {
+        PrincipalName {
+            name_type: NameType::KRB_NT_PRINCIPAL,
+            name_string: names.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn tgs_rep(spn: &str, etype: EncryptionType) -> KRB5Transaction {
+        let mut tx = KRB5Transaction::new(Direction::ToClient, 1);
+        tx.msg_type = MessageType::KRB_TGS_REP;
+        tx.cname = Some(principal(&["alice"]));
+        tx.realm = Some(Realm("CORP.LOCAL".to_string()));
+        tx.sname = Some(principal(&["MSSQLSvc", spn]));
+        tx.ticket_etype = Some(etype);
+        tx
+    }
+
+    #[test]
+    fn test_krb5_host_tracker_kerberoasting() {
+        let mut tracker = KRB5HostTracker::default();
+
+        let mut tx = KRB5Transaction::new(Direction::ToServer, 1);
+        tx.msg_type = MessageType::KRB_AS_REQ;
+        tx.cname = Some(principal(&["alice"]));
+        tx.realm = Some(Realm("CORP.LOCAL".to_string()));
+        assert!(!tracker.update(&mut tx, 100, 60, 3));
+        let summary = tx.summary.unwrap();
+        assert_eq!(summary.principal, "alice@CORP.LOCAL");
+        assert!(summary.as_req);
+        assert!(!summary.as_rep);
+
+        let mut tx = tgs_rep("db1", EncryptionType::AES256_CTS_HMAC_SHA1_96);
+        assert!(!tracker.update(&mut tx, 101, 60, 3));
+        let mut tx = tgs_rep("db2", EncryptionType::RC4_HMAC);
+        assert!(!tracker.update(&mut tx, 102, 60, 3));
+        // same SPN again does not count twice
+        let mut tx = tgs_rep("db2", EncryptionType::RC4_HMAC);
+        assert!(!tracker.update(&mut tx, 103, 60, 3));
+        let mut tx = tgs_rep("db3", EncryptionType::RC4_HMAC);
+        assert!(!tracker.update(&mut tx, 104, 60, 3));
+        let mut tx = tgs_rep("db4", EncryptionType::RC4_HMAC);
+        assert!(tracker.update(&mut tx, 105, 60, 3));
+        let summary = tx.summary.unwrap();
+        assert!(summary.as_req);
+        assert_eq!(summary.tgs_exchanges, 5);
+        assert_eq!(summary.spns, 4);
+        assert_eq!(summary.rc4_spns, 3);
+        // only fires once
+        let mut tx = tgs_rep("db5", EncryptionType::RC4_HMAC);
+        assert!(!tracker.update(&mut tx, 106, 60, 3));
+
+        // SPNs out of the window are forgotten
+        let mut tx = tgs_rep("db6", EncryptionType::RC4_HMAC);
+        assert!(!tracker.update(&mut tx, 200, 60, 3));
+        let summary = tx.summary.unwrap();
+        assert_eq!(summary.spns, 1);
+        assert_eq!(summary.rc4_spns, 1);
+
+        assert!(!tracker.timed_out(260, 60));
+        assert!(tracker.timed_out(261, 60));
+    }
+}
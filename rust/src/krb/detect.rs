@@ -17,9 +17,16 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
-use crate::krb::krb5::{test_weak_encryption, KRB5Transaction};
+use super::krb5::{
+    test_weak_encryption, KRB5Transaction, ALPROTO_KRB5, KRB5_KDC_OPTIONS, KRB5_PADATA_TYPES,
+};
+use crate::detect::uint::{detect_match_uint, detect_parse_uint, DetectUintData, DetectUintMode};
+use crate::detect::{
+    DetectHelperBufferRegister, DetectHelperKeywordRegister, DetectSignatureSetAppProto,
+    SCSigTableElmt, SigMatchAppendSMToList,
+};
 
-use kerberos_parser::krb5::EncryptionType;
+use kerberos_parser::krb5::{EncryptionType, PAType};
 
 use nom7::branch::alt;
 use nom7::bytes::complete::{is_a, tag, take_while, take_while1};
@@ -29,6 +36,14 @@ use nom7::multi::many1;
 use nom7::IResult;
 
 use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+
+static mut G_KRB5_KDC_OPTIONS_KW_ID: c_int = 0;
+static mut G_KRB5_KDC_OPTIONS_BUFFER_ID: c_int = 0;
+static mut G_KRB5_PADATA_KW_ID: c_int = 0;
+static mut G_KRB5_PADATA_BUFFER_ID: c_int = 0;
+static mut G_KRB5_REQ_ENCRYPTION_KW_ID: c_int = 0;
+static mut G_KRB5_REQ_ENCRYPTION_BUFFER_ID: c_int = 0;
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_krb5_tx_get_msgtype(tx: &mut KRB5Transaction, ptr: *mut u32) {
@@ -103,7 +118,6 @@ impl DetectKrb5TicketEncryptionList {
     }
 }
 
-
 // Suppress large enum variant lint as the LIST is very large compared
 // to the boolean variant.
 #[derive(Debug)]
@@ -225,6 +239,16 @@ pub unsafe extern "C" fn rs_krb5_detect_encryption_parse(
     return std::ptr::null_mut();
 }
 
+fn detect_encryption_list_match(l: &DetectKrb5TicketEncryptionList, x: EncryptionType) -> bool {
+    let vali = x.0;
+    if vali < 0 && ((-vali) as usize) < KRB_TICKET_FASTARRAY_SIZE {
+        return l.negative[(-vali) as usize];
+    } else if vali >= 0 && (vali as usize) < KRB_TICKET_FASTARRAY_SIZE {
+        return l.positive[vali as usize];
+    }
+    return l.other.contains(&x);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rs_krb5_detect_encryption_match(
     tx: &mut KRB5Transaction, ctx: &DetectKrb5TicketEncryptionData,
@@ -237,21 +261,8 @@ pub unsafe extern "C" fn rs_krb5_detect_encryption_match(
                 }
             }
             DetectKrb5TicketEncryptionData::LIST(l) => {
-                let vali = x.0;
-                if vali < 0 && ((-vali) as usize) < KRB_TICKET_FASTARRAY_SIZE {
-                    if l.negative[(-vali) as usize] {
-                        return 1;
-                    }
-                } else if vali >= 0 && (vali as usize) < KRB_TICKET_FASTARRAY_SIZE {
-                    if l.positive[vali as usize] {
-                        return 1;
-                    }
-                } else {
-                    for &val in l.other.iter() {
-                        if x == val {
-                            return 1;
-                        }
-                    }
+                if detect_encryption_list_match(l, x) {
+                    return 1;
                 }
             }
         }
@@ -265,10 +276,266 @@ pub unsafe extern "C" fn rs_krb5_detect_encryption_free(ctx: &mut DetectKrb5Tick
     std::mem::drop(Box::from_raw(ctx));
 }
 
+/// Parses the krb5.kdc_options argument: either a comma-separated list of
+/// option names, each optionally negated, or an integer match on the
+/// 32-bit KDCOptions value
+pub fn detect_parse_kdc_options(s: &str) -> Option<DetectUintData<u32>> {
+    if let Ok((_, ctx)) = detect_parse_uint::<u32>(s) {
+        return Some(ctx);
+    }
+    let mut mask = 0;
+    let mut value = 0;
+    for item in s.split(',') {
+        let item = item.trim();
+        let (neg, name) = match item.strip_prefix('!') {
+            Some(name) => (true, name.trim_start()),
+            None => (false, item),
+        };
+        let (_, flag) = KRB5_KDC_OPTIONS.iter().find(|(n, _)| *n == name)?;
+        mask |= flag;
+        if !neg {
+            value |= flag;
+        }
+    }
+    Some(DetectUintData {
+        arg1: mask,
+        arg2: value,
+        mode: DetectUintMode::DetectUintModeBitmask,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DetectKrb5PaData {
+    pub padata_type: PAType,
+    pub negated: bool,
+}
+
+fn padata_type_from_str(s: &str) -> Option<PAType> {
+    match KRB5_PADATA_TYPES.iter().find(|(n, _)| *n == s) {
+        Some((_, t)) => Some(*t),
+        None => Some(PAType(s.parse::<i32>().ok()?)),
+    }
+}
+
+/// Parses the krb5.padata argument: an optionally negated PA-DATA type,
+/// as a name or an integer
+pub fn detect_parse_padata(s: &str) -> Option<DetectKrb5PaData> {
+    let s = s.trim();
+    let (negated, s) = match s.strip_prefix('!') {
+        Some(s) => (true, s.trim_start()),
+        None => (false, s),
+    };
+    let padata_type = padata_type_from_str(s)?;
+    Some(DetectKrb5PaData {
+        padata_type,
+        negated,
+    })
+}
+
+unsafe extern "C" fn krb5_detect_kdc_options_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_KRB5) != 0 {
+        return -1;
+    }
+    let ctx = match CStr::from_ptr(raw)
+        .to_str()
+        .ok()
+        .and_then(detect_parse_kdc_options)
+    {
+        Some(ctx) => Box::into_raw(Box::new(ctx)) as *mut c_void,
+        None => return -1,
+    };
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_KRB5_KDC_OPTIONS_KW_ID,
+        ctx,
+        G_KRB5_KDC_OPTIONS_BUFFER_ID,
+    )
+    .is_null()
+    {
+        krb5_detect_kdc_options_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn krb5_detect_kdc_options_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, KRB5Transaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    if let Some(options) = tx.kdc_options {
+        if detect_match_uint(ctx, options) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn krb5_detect_kdc_options_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    std::mem::drop(Box::from_raw(ctx));
+}
+
+unsafe extern "C" fn krb5_detect_padata_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_KRB5) != 0 {
+        return -1;
+    }
+    let ctx = match CStr::from_ptr(raw)
+        .to_str()
+        .ok()
+        .and_then(detect_parse_padata)
+    {
+        Some(ctx) => Box::into_raw(Box::new(ctx)) as *mut c_void,
+        None => return -1,
+    };
+    if SigMatchAppendSMToList(de, s, G_KRB5_PADATA_KW_ID, ctx, G_KRB5_PADATA_BUFFER_ID).is_null() {
+        krb5_detect_padata_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn krb5_detect_padata_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, KRB5Transaction);
+    let ctx = cast_pointer!(ctx, DetectKrb5PaData);
+    // only requests carry PA-DATA we keep track of
+    if tx.kdc_options.is_some() && tx.padata.contains(&ctx.padata_type) != ctx.negated {
+        return 1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn krb5_detect_padata_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectKrb5PaData);
+    std::mem::drop(Box::from_raw(ctx));
+}
+
+unsafe extern "C" fn krb5_detect_req_encryption_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_KRB5) != 0 {
+        return -1;
+    }
+    let ctx = rs_krb5_detect_encryption_parse(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_KRB5_REQ_ENCRYPTION_KW_ID,
+        ctx,
+        G_KRB5_REQ_ENCRYPTION_BUFFER_ID,
+    )
+    .is_null()
+    {
+        krb5_detect_req_encryption_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn krb5_detect_req_encryption_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, KRB5Transaction);
+    let ctx = cast_pointer!(ctx, DetectKrb5TicketEncryptionData);
+    if tx.req_etypes.is_empty() {
+        return 0;
+    }
+    let matched = match ctx {
+        // weak means that the client only offers weak encryption types
+        DetectKrb5TicketEncryptionData::WEAK(w) => {
+            tx.req_etypes.iter().all(|&x| test_weak_encryption(x)) == *w
+        }
+        DetectKrb5TicketEncryptionData::LIST(l) => tx
+            .req_etypes
+            .iter()
+            .any(|&x| detect_encryption_list_match(l, x)),
+    };
+    return matched as c_int;
+}
+
+unsafe extern "C" fn krb5_detect_req_encryption_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectKrb5TicketEncryptionData);
+    std::mem::drop(Box::from_raw(ctx));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectKrb5Register() {
+    let kw = SCSigTableElmt {
+        name: b"krb5.kdc_options\0".as_ptr() as *const libc::c_char,
+        desc: b"match Kerberos 5 KDC options of requests\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/kerberos-keywords.html#krb5-kdc-options\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(krb5_detect_kdc_options_match),
+        Setup: krb5_detect_kdc_options_setup,
+        Free: Some(krb5_detect_kdc_options_free),
+        flags: 0,
+    };
+    G_KRB5_KDC_OPTIONS_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_KRB5_KDC_OPTIONS_BUFFER_ID = DetectHelperBufferRegister(
+        b"krb5.kdc_options\0".as_ptr() as *const libc::c_char,
+        ALPROTO_KRB5,
+        false,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"krb5.padata\0".as_ptr() as *const libc::c_char,
+        desc: b"match Kerberos 5 pre-authentication data types of requests\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/kerberos-keywords.html#krb5-padata\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(krb5_detect_padata_match),
+        Setup: krb5_detect_padata_setup,
+        Free: Some(krb5_detect_padata_free),
+        flags: 0,
+    };
+    G_KRB5_PADATA_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_KRB5_PADATA_BUFFER_ID = DetectHelperBufferRegister(
+        b"krb5.padata\0".as_ptr() as *const libc::c_char,
+        ALPROTO_KRB5,
+        false,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"krb5.req_encryption\0".as_ptr() as *const libc::c_char,
+        desc: b"match Kerberos 5 encryption types requested by the client\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/kerberos-keywords.html#krb5-req-encryption\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(krb5_detect_req_encryption_match),
+        Setup: krb5_detect_req_encryption_setup,
+        Free: Some(krb5_detect_req_encryption_free),
+        flags: 0,
+    };
+    G_KRB5_REQ_ENCRYPTION_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_KRB5_REQ_ENCRYPTION_BUFFER_ID = DetectHelperBufferRegister(
+        b"krb5.req_encryption\0".as_ptr() as *const libc::c_char,
+        ALPROTO_KRB5,
+        false,
+        true,
+    );
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::krb::krb5::{
+        padata_type_string, KRB5_KDC_OPTION_CANONICALIZE, KRB5_KDC_OPTION_FORWARDABLE,
+        KRB5_KDC_OPTION_REQUEST_ANONYMOUS, KRB5_PA_FOR_USER,
+    };
 
     #[test]
     fn test_detect_parse_encryption() {
@@ -328,4 +595,49 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_detect_parse_kdc_options() {
+        let ctx = detect_parse_kdc_options("forwardable, !canonicalize").unwrap();
+        assert_eq!(
+            ctx.arg1,
+            KRB5_KDC_OPTION_FORWARDABLE | KRB5_KDC_OPTION_CANONICALIZE
+        );
+        assert_eq!(ctx.arg2, KRB5_KDC_OPTION_FORWARDABLE);
+        assert_eq!(ctx.mode, DetectUintMode::DetectUintModeBitmask);
+        // typical AS-REQ: forwardable, renewable, canonicalize, renewable-ok
+        assert!(!detect_match_uint(&ctx, 0x4081_0010));
+        assert!(detect_match_uint(&ctx, 0x4080_0010));
+
+        let ctx = detect_parse_kdc_options("&0x8000=0x8000").unwrap();
+        assert!(detect_match_uint(&ctx, KRB5_KDC_OPTION_REQUEST_ANONYMOUS));
+
+        assert!(detect_parse_kdc_options("forwardable,unknown").is_none());
+        assert!(detect_parse_kdc_options("").is_none());
+    }
+
+    #[test]
+    fn test_detect_parse_padata() {
+        assert_eq!(
+            detect_parse_padata(" !enc-timestamp"),
+            Some(DetectKrb5PaData {
+                padata_type: PAType::PA_ENC_TS,
+                negated: true
+            })
+        );
+        assert_eq!(
+            detect_parse_padata("129"),
+            Some(DetectKrb5PaData {
+                padata_type: KRB5_PA_FOR_USER,
+                negated: false
+            })
+        );
+        assert!(detect_parse_padata("enc_timestamp").is_none());
+        // the names match the ones logged
+        for (name, padata_type) in KRB5_PADATA_TYPES {
+            assert_eq!(padata_type_string(*padata_type), *name);
+            assert_eq!(padata_type_from_str(name), Some(*padata_type));
+        }
+        assert_eq!(padata_type_string(PAType(138)), "138");
+    }
 }
@@ -22,11 +22,13 @@ use std::ffi::CString;
 use nom7::{Err, IResult};
 use nom7::number::streaming::be_u32;
 use der_parser::der::der_read_element_header;
-use der_parser::ber::Class;
+use der_parser::ber::{Class, Tag};
 use kerberos_parser::krb5_parser;
-use kerberos_parser::krb5::{EncryptionType,ErrorCode,MessageType,PrincipalName,Realm,KrbError};
+use kerberos_parser::krb5::{EncryptionType,ErrorCode,KdcReq,MessageType,PAType,PrincipalName,Realm,KrbError};
 use asn1_rs::FromDer;
+use super::correlation::*;
 use crate::applayer::{self, *};
+use crate::conf::{conf_get, conf_get_bool};
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_FAILED,ALPROTO_UNKNOWN,Direction, IPPROTO_TCP, IPPROTO_UDP};
 
@@ -34,6 +36,67 @@ use crate::core::{AppProto,Flow,ALPROTO_FAILED,ALPROTO_UNKNOWN,Direction, IPPROT
 pub enum KRB5Event {
     MalformedData,
     WeakEncryption,
+    Kerberoasting,
+}
+
+/// PA-DATA types not defined by kerberos-parser (RFC4556 and MS-SFU)
+pub const KRB5_PA_PK_AS_REQ: PAType = PAType(16);
+pub const KRB5_PA_FOR_USER: PAType = PAType(129);
+
+/// KDC options, as bits of the 32-bit KDCOptions bit string (RFC4120 section 5.4.1)
+pub const KRB5_KDC_OPTION_FORWARDABLE: u32 = 0x4000_0000;
+pub const KRB5_KDC_OPTION_FORWARDED: u32 = 0x2000_0000;
+pub const KRB5_KDC_OPTION_PROXIABLE: u32 = 0x1000_0000;
+pub const KRB5_KDC_OPTION_PROXY: u32 = 0x0800_0000;
+pub const KRB5_KDC_OPTION_ALLOW_POSTDATE: u32 = 0x0400_0000;
+pub const KRB5_KDC_OPTION_POSTDATED: u32 = 0x0200_0000;
+pub const KRB5_KDC_OPTION_RENEWABLE: u32 = 0x0080_0000;
+pub const KRB5_KDC_OPTION_CNAME_IN_ADDL_TKT: u32 = 0x0002_0000;
+pub const KRB5_KDC_OPTION_CANONICALIZE: u32 = 0x0001_0000;
+pub const KRB5_KDC_OPTION_REQUEST_ANONYMOUS: u32 = 0x0000_8000;
+pub const KRB5_KDC_OPTION_DISABLE_TRANSITED_CHECK: u32 = 0x0000_0020;
+pub const KRB5_KDC_OPTION_RENEWABLE_OK: u32 = 0x0000_0010;
+pub const KRB5_KDC_OPTION_ENC_TKT_IN_SKEY: u32 = 0x0000_0008;
+pub const KRB5_KDC_OPTION_RENEW: u32 = 0x0000_0002;
+pub const KRB5_KDC_OPTION_VALIDATE: u32 = 0x0000_0001;
+
+/// Names of the KDC options, as used in logs and rules
+pub const KRB5_KDC_OPTIONS: &[(&str, u32)] = &[
+    ("forwardable", KRB5_KDC_OPTION_FORWARDABLE),
+    ("forwarded", KRB5_KDC_OPTION_FORWARDED),
+    ("proxiable", KRB5_KDC_OPTION_PROXIABLE),
+    ("proxy", KRB5_KDC_OPTION_PROXY),
+    ("allow-postdate", KRB5_KDC_OPTION_ALLOW_POSTDATE),
+    ("postdated", KRB5_KDC_OPTION_POSTDATED),
+    ("renewable", KRB5_KDC_OPTION_RENEWABLE),
+    ("cname-in-addl-tkt", KRB5_KDC_OPTION_CNAME_IN_ADDL_TKT),
+    ("canonicalize", KRB5_KDC_OPTION_CANONICALIZE),
+    ("request-anonymous", KRB5_KDC_OPTION_REQUEST_ANONYMOUS),
+    ("disable-transited-check", KRB5_KDC_OPTION_DISABLE_TRANSITED_CHECK),
+    ("renewable-ok", KRB5_KDC_OPTION_RENEWABLE_OK),
+    ("enc-tkt-in-skey", KRB5_KDC_OPTION_ENC_TKT_IN_SKEY),
+    ("renew", KRB5_KDC_OPTION_RENEW),
+    ("validate", KRB5_KDC_OPTION_VALIDATE),
+];
+
+/// Names of the PA-DATA types, as used in logs and rules
+pub const KRB5_PADATA_TYPES: &[(&str, PAType)] = &[
+    ("tgs-req", PAType::PA_TGS_REQ),
+    ("enc-timestamp", PAType::PA_ENC_TS),
+    ("pw-salt", PAType::PA_PW_SALT),
+    ("etype-info", PAType::PA_ETYPE_INFO),
+    ("pk-as-req", KRB5_PA_PK_AS_REQ),
+    ("etype-info2", PAType::PA_ETYPE_INFO2),
+    ("pac-request", PAType::PA_PAC_REQUEST),
+    ("for-user", KRB5_PA_FOR_USER),
+];
+
+/// Returns the name of a PA-DATA type, or its number if it has none
+pub fn padata_type_string(padata_type: PAType) -> String {
+    match KRB5_PADATA_TYPES.iter().find(|(_, t)| *t == padata_type) {
+        Some((name, _)) => name.to_string(),
+        None => padata_type.0.to_string(),
+    }
 }
 
 pub struct KRB5State {
@@ -86,6 +149,21 @@ pub struct KRB5Transaction {
     /// Message type of request. For using in responses.
     pub req_type: Option<MessageType>,
 
+    /// KDC options (only in AS-REQ and TGS-REQ)
+    pub kdc_options: Option<u32>,
+
+    /// Types of the pre-authentication data (only in AS-REQ and TGS-REQ)
+    pub padata: Vec<PAType>,
+
+    /// Value of the include-pac flag of PA-PAC-REQUEST, if present
+    pub pac_request: Option<bool>,
+
+    /// Encryption types requested by the client (only in AS-REQ and TGS-REQ)
+    pub req_etypes: Vec<EncryptionType>,
+
+    /// Exchanges of the client principal seen from this host
+    pub summary: Option<KRB5PrincipalSummary>,
+
     /// The internal transaction id
     id: u64,
 
@@ -121,7 +199,7 @@ impl KRB5State {
     /// Parse a Kerberos request message
     ///
     /// Returns 0 in case of success, or -1 on error
-    fn parse(&mut self, i: &[u8], direction: Direction) -> i32 {
+    fn parse(&mut self, i: &[u8], direction: Direction, flow: *const Flow) -> i32 {
         match der_read_element_header(i) {
             Ok((_rem,hdr)) => {
                 // Kerberos messages start with an APPLICATION header
@@ -132,11 +210,13 @@ impl KRB5State {
                         if let Ok((_,kdc_req)) = req {
                             let mut tx = self.new_tx(direction);
                             tx.msg_type = MessageType::KRB_AS_REQ;
+                            tx.set_request(&kdc_req);
                             tx.cname = kdc_req.req_body.cname;
                             tx.realm = Some(kdc_req.req_body.realm);
                             tx.sname = kdc_req.req_body.sname;
                             tx.etype = None;
                             self.transactions.push(tx);
+                            self.update_host(flow);
                         };
                         self.req_id = 10;
                     },
@@ -156,6 +236,7 @@ impl KRB5State {
                             tx.ticket_etype = Some(kdc_rep.ticket.enc_part.etype);
                             tx.etype = Some(kdc_rep.enc_part.etype);
                             self.transactions.push(tx);
+                            self.update_host(flow);
                             if test_weak_encryption(kdc_rep.enc_part.etype) {
                                 self.set_event(KRB5Event::WeakEncryption);
                             }
@@ -167,6 +248,7 @@ impl KRB5State {
                         if let Ok((_,kdc_req)) = req {
                             let mut tx = self.new_tx(direction);
                             tx.msg_type = MessageType::KRB_TGS_REQ;
+                            tx.set_request(&kdc_req);
                             tx.cname = kdc_req.req_body.cname;
                             tx.realm = Some(kdc_req.req_body.realm);
                             tx.sname = kdc_req.req_body.sname;
@@ -191,6 +273,7 @@ impl KRB5State {
                             tx.sname = Some(kdc_rep.ticket.sname);
                             tx.etype = Some(kdc_rep.enc_part.etype);
                             self.transactions.push(tx);
+                            self.update_host(flow);
                             if test_weak_encryption(kdc_rep.enc_part.etype) {
                                 self.set_event(KRB5Event::WeakEncryption);
                             }
@@ -261,6 +344,19 @@ impl KRB5State {
         }
     }
 
+    /// Update the authentication timeline of the client host with the most
+    /// recent transaction
+    fn update_host(&mut self, flow: *const Flow) {
+        if flow.is_null() || unsafe { !KRB5_CORRELATION } {
+            return;
+        }
+        if let Some(tx) = self.transactions.last_mut() {
+            if unsafe { SCKrb5HostUpdate(flow, tx as *mut _ as *mut std::os::raw::c_void) } {
+                self.set_event(KRB5Event::Kerberoasting);
+            }
+        }
+    }
+
     /// Set an event. The event is set on the most recent transaction.
     fn set_event(&mut self, event: KRB5Event) {
         if let Some(tx) = self.transactions.last_mut() {
@@ -280,11 +376,49 @@ impl KRB5Transaction {
             ticket_etype: None,
             error_code: None,
             req_type: None,
+            kdc_options: None,
+            padata: Vec::new(),
+            pac_request: None,
+            req_etypes: Vec::new(),
+            summary: None,
             id,
             tx_data: applayer::AppLayerTxData::for_direction(direction),
         };
         return krbtx;
     }
+
+    /// Store the KDC options, pre-authentication data and requested
+    /// encryption types of an AS-REQ or TGS-REQ
+    fn set_request(&mut self, kdc_req: &KdcReq) {
+        let options = &kdc_req.req_body.kdc_options;
+        self.kdc_options = Some(
+            (0..32)
+                .filter(|&bit| options.is_set(bit))
+                .fold(0, |acc, bit| acc | (0x8000_0000 >> bit)),
+        );
+        for padata in kdc_req.padata.iter() {
+            if padata.padata_type == PAType::PA_PAC_REQUEST {
+                self.pac_request = parse_pac_request(padata.padata_value);
+            }
+            self.padata.push(padata.padata_type);
+        }
+        self.req_etypes = kdc_req.req_body.etype.clone();
+    }
+}
+
+/// Parse the include-pac flag of a KERB-PA-PAC-REQUEST (MS-KILE section 2.2.3)
+///
+/// KERB-PA-PAC-REQUEST ::= SEQUENCE {
+///     include-pac[0] BOOLEAN
+/// }
+fn parse_pac_request(i: &[u8]) -> Option<bool> {
+    let (i, hdr) = der_read_element_header(i).ok()?;
+    if hdr.tag() != Tag::Sequence { return None; }
+    let (i, hdr) = der_read_element_header(i).ok()?;
+    if hdr.class() != Class::ContextSpecific || hdr.tag().0 != 0 { return None; }
+    let (i, hdr) = der_read_element_header(i).ok()?;
+    if hdr.tag() != Tag::Boolean { return None; }
+    i.first().map(|&b| b != 0)
 }
 
 /// Return true if Kerberos `EncryptionType` is weak
@@ -356,7 +490,7 @@ pub extern "C" fn rs_krb5_tx_get_alstate_progress(_tx: *mut std::os::raw::c_void
     1
 }
 
-static mut ALPROTO_KRB5 : AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_KRB5 : AppProto = ALPROTO_UNKNOWN;
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_krb5_probing_parser(_flow: *const Flow,
@@ -428,7 +562,7 @@ pub unsafe extern "C" fn rs_krb5_probing_parser_tcp(_flow: *const Flow,
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rs_krb5_parse_request(_flow: *const core::Flow,
+pub unsafe extern "C" fn rs_krb5_parse_request(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        stream_slice: StreamSlice,
@@ -436,14 +570,14 @@ pub unsafe extern "C" fn rs_krb5_parse_request(_flow: *const core::Flow,
                                        ) -> AppLayerResult {
     let buf = stream_slice.as_slice();
     let state = cast_pointer!(state,KRB5State);
-    if state.parse(buf, Direction::ToServer) < 0 {
+    if state.parse(buf, Direction::ToServer, flow) < 0 {
         return AppLayerResult::err();
     }
     AppLayerResult::ok()
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rs_krb5_parse_response(_flow: *const core::Flow,
+pub unsafe extern "C" fn rs_krb5_parse_response(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        stream_slice: StreamSlice,
@@ -451,14 +585,14 @@ pub unsafe extern "C" fn rs_krb5_parse_response(_flow: *const core::Flow,
                                        ) -> AppLayerResult {
     let buf = stream_slice.as_slice();
     let state = cast_pointer!(state,KRB5State);
-    if state.parse(buf, Direction::ToClient) < 0 {
+    if state.parse(buf, Direction::ToClient, flow) < 0 {
         return AppLayerResult::err();
     }
     AppLayerResult::ok()
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
+pub unsafe extern "C" fn rs_krb5_parse_request_tcp(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        stream_slice: StreamSlice,
@@ -501,7 +635,7 @@ pub unsafe extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
             }
         }
         if cur_i.len() >= state.record_ts {
-            if state.parse(cur_i, Direction::ToServer) < 0 {
+            if state.parse(cur_i, Direction::ToServer, flow) < 0 {
                 return AppLayerResult::err();
             }
             state.record_ts = 0;
@@ -516,7 +650,7 @@ pub unsafe extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rs_krb5_parse_response_tcp(_flow: *const core::Flow,
+pub unsafe extern "C" fn rs_krb5_parse_response_tcp(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        stream_slice: StreamSlice,
@@ -559,7 +693,7 @@ pub unsafe extern "C" fn rs_krb5_parse_response_tcp(_flow: *const core::Flow,
             }
         }
         if cur_i.len() >= state.record_tc {
-            if state.parse(cur_i, Direction::ToClient) < 0 {
+            if state.parse(cur_i, Direction::ToClient, flow) < 0 {
                 return AppLayerResult::err();
             }
             state.record_tc = 0;
@@ -581,6 +715,24 @@ const PARSER_NAME : &[u8] = b"krb5\0";
 #[no_mangle]
 pub unsafe extern "C" fn rs_register_krb5_parser() {
     let default_port = CString::new("88").unwrap();
+    if conf_get_bool("app-layer.protocols.krb5.correlation.enabled") {
+        if let Some(val) = conf_get("app-layer.protocols.krb5.correlation.window") {
+            if let Ok(v) = val.parse::<u64>() {
+                KRB5_CORRELATION_WINDOW = v;
+            } else {
+                SCLogError!("Invalid value for krb5.correlation.window");
+            }
+        }
+        if let Some(val) = conf_get("app-layer.protocols.krb5.correlation.kerberoasting-threshold") {
+            if let Ok(v) = val.parse::<usize>() {
+                KRB5_KERBEROASTING_THRESHOLD = v;
+            } else {
+                SCLogError!("Invalid value for krb5.correlation.kerberoasting-threshold");
+            }
+        }
+        SCKrb5HostStorageRegister();
+        KRB5_CORRELATION = true;
+    }
     let mut parser = RustParser {
         name               : PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port       : default_port.as_ptr(),
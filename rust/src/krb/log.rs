@@ -18,7 +18,8 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use crate::jsonbuilder::{JsonBuilder, JsonError};
-use crate::krb::krb5::{KRB5Transaction,test_weak_encryption};
+use crate::krb::krb5::{KRB5Transaction,KRB5_KDC_OPTIONS,KRB5_PA_FOR_USER,padata_type_string,test_weak_encryption};
+use kerberos_parser::krb5::PAType;
 
 fn krb5_log_response(jsb: &mut JsonBuilder, tx: &mut KRB5Transaction) -> Result<(), JsonError>
 {
@@ -64,6 +65,44 @@ fn krb5_log_response(jsb: &mut JsonBuilder, tx: &mut KRB5Transaction) -> Result<
         jsb.set_string("ticket_encryption", &refs)?;
         jsb.set_bool("ticket_weak_encryption", test_weak_encryption(x))?;
     }
+    if let Some(options) = tx.kdc_options {
+        // request
+        jsb.open_array("kdc_options")?;
+        for (name, flag) in KRB5_KDC_OPTIONS {
+            if options & flag != 0 {
+                jsb.append_string(name)?;
+            }
+        }
+        jsb.close()?;
+        jsb.open_array("padata")?;
+        for padata in &tx.padata {
+            jsb.append_string(&padata_type_string(*padata))?;
+        }
+        jsb.close()?;
+        jsb.set_bool("preauth", tx.padata.contains(&PAType::PA_ENC_TS))?;
+        if let Some(include_pac) = tx.pac_request {
+            jsb.set_bool("pac_request", include_pac)?;
+        }
+        if tx.padata.contains(&KRB5_PA_FOR_USER) {
+            jsb.set_bool("s4u2self", true)?;
+        }
+        jsb.open_array("req_encryption")?;
+        for etype in &tx.req_etypes {
+            jsb.append_string(&format!("{:?}", etype))?;
+        }
+        jsb.close()?;
+    }
+    if let Some(ref summary) = tx.summary {
+        jsb.open_object("summary")?;
+        jsb.set_string("principal", &summary.principal)?;
+        jsb.set_bool("as_req", summary.as_req)?;
+        jsb.set_bool("as_rep", summary.as_rep)?;
+        jsb.set_uint("tgs_exchanges", summary.tgs_exchanges)?;
+        jsb.set_uint("spns", summary.spns as u64)?;
+        jsb.set_uint("rc4_spns", summary.rc4_spns as u64)?;
+        jsb.set_uint("window", summary.window)?;
+        jsb.close()?;
+    }
     jsb.close()?;
 
     return Ok(());
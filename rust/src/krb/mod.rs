@@ -19,6 +19,7 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
+pub mod correlation;
 pub mod krb5;
 pub mod detect;
 pub mod log;
@@ -0,0 +1,694 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::filters::{Filter, Substring};
+use super::ldap::{LdapTransaction, ALPROTO_LDAP};
+use super::types::{LdapResultCode, ProtocolOp, ProtocolOpCode};
+use crate::detect::uint::{
+    detect_match_uint, detect_parse_uint_enum, rs_detect_u32_free, rs_detect_u8_free,
+    DetectUintData,
+};
+use crate::detect::{
+    DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
+    DetectHelperGetData, DetectHelperGetMultiData, DetectHelperKeywordRegister,
+    DetectHelperMultiBufferMpmRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
+};
+
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+static mut G_LDAP_REQUEST_OPERATION_KW_ID: c_int = 0;
+static mut G_LDAP_REQUEST_OPERATION_BUFFER_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_OPERATION_KW_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_OPERATION_BUFFER_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_RESULT_CODE_KW_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_RESULT_CODE_BUFFER_ID: c_int = 0;
+static mut G_LDAP_REQUEST_DN_BUFFER_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_DN_BUFFER_ID: c_int = 0;
+static mut G_LDAP_RESPONSES_MESSAGE_BUFFER_ID: c_int = 0;
+static mut G_LDAP_REQUEST_FILTER_VALUE_BUFFER_ID: c_int = 0;
+static mut G_LDAP_REQUEST_FILTER_ATTRIBUTE_BUFFER_ID: c_int = 0;
+
+/// Collect the assertion values of a search filter, depth first
+fn ldap_filter_values<'a>(filter: &'a Filter, values: &mut Vec<&'a [u8]>) {
+    match filter {
+        Filter::And(filters) | Filter::Or(filters) => {
+            for f in filters {
+                ldap_filter_values(f, values);
+            }
+        }
+        Filter::Not(f) => ldap_filter_values(f, values),
+        Filter::EqualityMatch(ava)
+        | Filter::GreaterOrEqual(ava)
+        | Filter::LessOrEqual(ava)
+        | Filter::ApproxMatch(ava) => values.push(&ava.assertion_value),
+        Filter::Substrings(sf) => {
+            for s in &sf.substrings {
+                match s {
+                    Substring::Initial(val) | Substring::Any(val) | Substring::Final(val) => {
+                        values.push(&val.0)
+                    }
+                }
+            }
+        }
+        Filter::ExtensibleMatch(mra) => values.push(&mra.assertion_value.0),
+        // no value to match on
+        Filter::Present(_) => {}
+    }
+}
+
+/// Collect the attribute descriptions of a search filter, depth first
+fn ldap_filter_attributes<'a>(filter: &'a Filter, attributes: &mut Vec<&'a [u8]>) {
+    match filter {
+        Filter::And(filters) | Filter::Or(filters) => {
+            for f in filters {
+                ldap_filter_attributes(f, attributes);
+            }
+        }
+        Filter::Not(f) => ldap_filter_attributes(f, attributes),
+        Filter::EqualityMatch(ava)
+        | Filter::GreaterOrEqual(ava)
+        | Filter::LessOrEqual(ava)
+        | Filter::ApproxMatch(ava) => attributes.push(ava.attribute_desc.0.as_bytes()),
+        Filter::Substrings(sf) => attributes.push(sf.filter_type.0.as_bytes()),
+        Filter::Present(attribute) => attributes.push(attribute.0.as_bytes()),
+        Filter::ExtensibleMatch(mra) => {
+            if let Some(attribute) = &mra.rule_type {
+                attributes.push(attribute.0.as_bytes());
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn ldap_parse_protocol_op(
+    ustr: *const std::os::raw::c_char,
+) -> *mut DetectUintData<u8> {
+    let ft_name: &CStr = CStr::from_ptr(ustr); //unsafe
+    if let Ok(s) = ft_name.to_str() {
+        if let Some(ctx) = detect_parse_uint_enum::<u8, ProtocolOpCode>(s) {
+            let boxed = Box::new(ctx);
+            return Box::into_raw(boxed) as *mut _;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe extern "C" fn ldap_parse_result_code(
+    ustr: *const std::os::raw::c_char,
+) -> *mut DetectUintData<u32> {
+    let ft_name: &CStr = CStr::from_ptr(ustr); //unsafe
+    if let Ok(s) = ft_name.to_str() {
+        if let Some(ctx) = detect_parse_uint_enum::<u32, LdapResultCode>(s) {
+            let boxed = Box::new(ctx);
+            return Box::into_raw(boxed) as *mut _;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe extern "C" fn ldap_detect_request_operation_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    let ctx = ldap_parse_protocol_op(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_LDAP_REQUEST_OPERATION_KW_ID,
+        ctx,
+        G_LDAP_REQUEST_OPERATION_BUFFER_ID,
+    )
+    .is_null()
+    {
+        ldap_detect_operation_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_request_operation_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    if let Some(request) = &tx.request {
+        if detect_match_uint(ctx, request.protocol_op.to_u8()) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_operation_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    rs_detect_u8_free(ctx);
+}
+
+unsafe extern "C" fn ldap_detect_responses_operation_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    let ctx = ldap_parse_protocol_op(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_LDAP_RESPONSES_OPERATION_KW_ID,
+        ctx,
+        G_LDAP_RESPONSES_OPERATION_BUFFER_ID,
+    )
+    .is_null()
+    {
+        ldap_detect_operation_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_operation_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    for response in &tx.responses {
+        if detect_match_uint(ctx, response.protocol_op.to_u8()) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_result_code_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    let ctx = ldap_parse_result_code(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_LDAP_RESPONSES_RESULT_CODE_KW_ID,
+        ctx,
+        G_LDAP_RESPONSES_RESULT_CODE_BUFFER_ID,
+    )
+    .is_null()
+    {
+        ldap_detect_responses_result_code_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_result_code_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    for response in &tx.responses {
+        if let Some(result) = response.protocol_op.get_result() {
+            if detect_match_uint(ctx, result.result_code.0) {
+                return 1;
+            }
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_result_code_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    rs_detect_u32_free(ctx);
+}
+
+unsafe extern "C" fn ldap_detect_request_dn_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_LDAP_REQUEST_DN_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_request_dn_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    if let Some(request) = &tx.request {
+        if let Some(dn) = request.protocol_op.get_request_dn() {
+            *buffer = dn.0.as_ptr();
+            *buffer_len = dn.0.len() as u32;
+            return true;
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn ldap_detect_request_dn_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        ldap_detect_request_dn_get,
+    );
+}
+
+unsafe extern "C" fn ldap_detect_responses_dn_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_LDAP_RESPONSES_DN_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_dn_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    let dn = tx
+        .responses
+        .iter()
+        .filter_map(|response| match &response.protocol_op {
+            ProtocolOp::SearchResultEntry(entry) => Some(&entry.object_name),
+            op => op.get_result().map(|result| &result.matched_dn),
+        })
+        .nth(local_id as usize);
+    if let Some(dn) = dn {
+        *buffer = dn.0.as_ptr();
+        *buffer_len = dn.0.len() as u32;
+        return true;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn ldap_detect_responses_dn_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        ldap_detect_responses_dn_get,
+    );
+}
+
+unsafe extern "C" fn ldap_detect_responses_message_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_LDAP_RESPONSES_MESSAGE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_responses_message_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    let message = tx
+        .responses
+        .iter()
+        .filter_map(|response| response.protocol_op.get_result())
+        .map(|result| &result.diagnostic_message)
+        .nth(local_id as usize);
+    if let Some(message) = message {
+        *buffer = message.0.as_ptr();
+        *buffer_len = message.0.len() as u32;
+        return true;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn ldap_detect_responses_message_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        ldap_detect_responses_message_get,
+    );
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_value_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_LDAP_REQUEST_FILTER_VALUE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_value_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    if let Some(request) = &tx.request {
+        if let ProtocolOp::SearchRequest(req) = &request.protocol_op {
+            let mut values = Vec::new();
+            ldap_filter_values(&req.filter, &mut values);
+            if let Some(value) = values.get(local_id as usize) {
+                *buffer = value.as_ptr();
+                *buffer_len = value.len() as u32;
+                return true;
+            }
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_value_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        ldap_detect_request_filter_value_get,
+    );
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_attribute_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_LDAP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_LDAP_REQUEST_FILTER_ATTRIBUTE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_attribute_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, LdapTransaction);
+    if let Some(request) = &tx.request {
+        if let ProtocolOp::SearchRequest(req) = &request.protocol_op {
+            let mut attributes = Vec::new();
+            ldap_filter_attributes(&req.filter, &mut attributes);
+            if let Some(attribute) = attributes.get(local_id as usize) {
+                *buffer = attribute.as_ptr();
+                *buffer_len = attribute.len() as u32;
+                return true;
+            }
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn ldap_detect_request_filter_attribute_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        ldap_detect_request_filter_attribute_get,
+    );
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectLdapRegister() {
+    let kw = SCSigTableElmt {
+        name: b"ldap.request.operation\0".as_ptr() as *const libc::c_char,
+        desc: b"match LDAP request operation\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-request-operation\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ldap_detect_request_operation_match),
+        Setup: ldap_detect_request_operation_setup,
+        Free: Some(ldap_detect_operation_free),
+        flags: 0,
+    };
+    G_LDAP_REQUEST_OPERATION_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_LDAP_REQUEST_OPERATION_BUFFER_ID = DetectHelperBufferRegister(
+        b"ldap.request.operation\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        false,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.responses.operation\0".as_ptr() as *const libc::c_char,
+        desc: b"match LDAP response operations\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-responses-operation\0".as_ptr()
+            as *const libc::c_char,
+        AppLayerTxMatch: Some(ldap_detect_responses_operation_match),
+        Setup: ldap_detect_responses_operation_setup,
+        Free: Some(ldap_detect_operation_free),
+        flags: 0,
+    };
+    G_LDAP_RESPONSES_OPERATION_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_LDAP_RESPONSES_OPERATION_BUFFER_ID = DetectHelperBufferRegister(
+        b"ldap.responses.operation\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        true,
+        false,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.responses.result_code\0".as_ptr() as *const libc::c_char,
+        desc: b"match LDAP response result codes\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-responses-result-code\0".as_ptr()
+            as *const libc::c_char,
+        AppLayerTxMatch: Some(ldap_detect_responses_result_code_match),
+        Setup: ldap_detect_responses_result_code_setup,
+        Free: Some(ldap_detect_responses_result_code_free),
+        flags: 0,
+    };
+    G_LDAP_RESPONSES_RESULT_CODE_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_LDAP_RESPONSES_RESULT_CODE_BUFFER_ID = DetectHelperBufferRegister(
+        b"ldap.responses.result_code\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        true,
+        false,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.request.dn\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the LDAP request distinguished name\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-request-dn\0".as_ptr() as *const libc::c_char,
+        Setup: ldap_detect_request_dn_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_ldap_request_dn_kw_id = DetectHelperKeywordRegister(&kw);
+    G_LDAP_REQUEST_DN_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"ldap.request.dn\0".as_ptr() as *const libc::c_char,
+        b"LDAP request distinguished name\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        false,
+        true,
+        ldap_detect_request_dn_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.responses.dn\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the LDAP responses distinguished names\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-responses-dn\0".as_ptr() as *const libc::c_char,
+        Setup: ldap_detect_responses_dn_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_ldap_responses_dn_kw_id = DetectHelperKeywordRegister(&kw);
+    G_LDAP_RESPONSES_DN_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"ldap.responses.dn\0".as_ptr() as *const libc::c_char,
+        b"LDAP responses distinguished names\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        true,
+        false,
+        ldap_detect_responses_dn_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.responses.message\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the LDAP responses diagnostic messages\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-responses-message\0".as_ptr() as *const libc::c_char,
+        Setup: ldap_detect_responses_message_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_ldap_responses_message_kw_id = DetectHelperKeywordRegister(&kw);
+    G_LDAP_RESPONSES_MESSAGE_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"ldap.responses.message\0".as_ptr() as *const libc::c_char,
+        b"LDAP responses diagnostic messages\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        true,
+        false,
+        ldap_detect_responses_message_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.request.filter_value\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the assertion values of a LDAP search filter\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-request-filter-value\0".as_ptr()
+            as *const libc::c_char,
+        Setup: ldap_detect_request_filter_value_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_ldap_request_filter_value_kw_id = DetectHelperKeywordRegister(&kw);
+    G_LDAP_REQUEST_FILTER_VALUE_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"ldap.request.filter_value\0".as_ptr() as *const libc::c_char,
+        b"LDAP search filter values\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        false,
+        true,
+        ldap_detect_request_filter_value_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ldap.request.filter_attribute\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the attribute descriptions of a LDAP search filter\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/ldap-keywords.html#ldap-request-filter-attribute\0".as_ptr()
+            as *const libc::c_char,
+        Setup: ldap_detect_request_filter_attribute_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_ldap_request_filter_attribute_kw_id = DetectHelperKeywordRegister(&kw);
+    G_LDAP_REQUEST_FILTER_ATTRIBUTE_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"ldap.request.filter_attribute\0".as_ptr() as *const libc::c_char,
+        b"LDAP search filter attributes\0".as_ptr() as *const libc::c_char,
+        ALPROTO_LDAP,
+        false,
+        true,
+        ldap_detect_request_filter_attribute_get_data,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detect::uint::DetectUintMode;
+    use crate::ldap::filters::AttributeValueAssertion;
+    use crate::ldap::types::LdapString;
+
+    #[test]
+    fn test_ldap_parse_operation() {
+        let ctx = detect_parse_uint_enum::<u8, ProtocolOpCode>("search_request").unwrap();
+        assert_eq!(ctx.arg1, 3);
+        assert_eq!(ctx.mode, DetectUintMode::DetectUintModeEqual);
+        let ctx = detect_parse_uint_enum::<u8, ProtocolOpCode>(">10").unwrap();
+        assert_eq!(ctx.arg1, 10);
+        assert_eq!(ctx.mode, DetectUintMode::DetectUintModeGt);
+        assert!(detect_parse_uint_enum::<u8, ProtocolOpCode>("invalidopt").is_none());
+    }
+
+    #[test]
+    fn test_ldap_filter_buffers() {
+        // (&(objectClass=user)(!(cn=krbtgt))(servicePrincipalName=*))
+        let filter = Filter::And(vec![
+            Filter::EqualityMatch(AttributeValueAssertion {
+                attribute_desc: LdapString("objectClass".to_string()),
+                assertion_value: b"user".to_vec(),
+            }),
+            Filter::Not(Box::new(Filter::EqualityMatch(AttributeValueAssertion {
+                attribute_desc: LdapString("cn".to_string()),
+                assertion_value: b"krbtgt".to_vec(),
+            }))),
+            Filter::Present(LdapString("servicePrincipalName".to_string())),
+        ]);
+        let mut values = Vec::new();
+        ldap_filter_values(&filter, &mut values);
+        assert_eq!(values, vec![&b"user"[..], b"krbtgt"]);
+        let mut attributes = Vec::new();
+        ldap_filter_attributes(&filter, &mut attributes);
+        assert_eq!(
+            attributes,
+            vec![&b"objectClass"[..], b"cn", b"servicePrincipalName"]
+        );
+    }
+
+    #[test]
+    fn test_ldap_parse_result_code() {
+        let ctx = detect_parse_uint_enum::<u32, LdapResultCode>("invalid_credentials").unwrap();
+        assert_eq!(ctx.arg1, 49);
+        let ctx = detect_parse_uint_enum::<u32, LdapResultCode>("e_sync_refresh_required").unwrap();
+        assert_eq!(ctx.arg1, 4096);
+    }
+}
@@ -33,7 +33,9 @@ static LDAP_MAX_TX_DEFAULT: usize = 256;
 
 static mut LDAP_MAX_TX: usize = LDAP_MAX_TX_DEFAULT;
 
-static mut ALPROTO_LDAP: AppProto = ALPROTO_UNKNOWN;
+const LDAP_STARTTLS_OID: &str = "1.3.6.1.4.1.1466.20037";
+
+pub(super) static mut ALPROTO_LDAP: AppProto = ALPROTO_UNKNOWN;
 
 #[derive(AppLayerFrameType)]
 pub enum LdapFrameType {
@@ -92,6 +94,10 @@ pub struct LdapState {
     response_frame: Option<Frame>,
     request_gap: bool,
     response_gap: bool,
+    // a successful SASL bind may have negotiated a security layer
+    sasl_layer: bool,
+    // the rest of the stream is TLS or SASL wrapped, and cannot be parsed
+    opaque: bool,
 }
 
 impl State<LdapTransaction> for LdapState {
@@ -115,6 +121,8 @@ impl LdapState {
             response_frame: None,
             request_gap: false,
             response_gap: false,
+            sasl_layer: false,
+            opaque: false,
         }
     }
 
@@ -176,9 +184,11 @@ impl LdapState {
         })
     }
 
-    fn parse_request(&mut self, flow: *const Flow, stream_slice: StreamSlice) -> AppLayerResult {
+    fn parse_request(
+        &mut self, flow: *const Flow, pstate: *mut c_void, stream_slice: StreamSlice,
+    ) -> AppLayerResult {
         let input = stream_slice.as_slice();
-        if input.is_empty() {
+        if input.is_empty() || self.opaque {
             return AppLayerResult::ok();
         }
 
@@ -200,6 +210,10 @@ impl LdapState {
 
         let mut start = input;
         while !start.is_empty() {
+            if self.is_sasl_wrapped(start) {
+                self.set_opaque(pstate);
+                return AppLayerResult::ok();
+            }
             if self.request_frame.is_none() {
                 self.request_frame = Frame::new(
                     flow,
@@ -237,9 +251,11 @@ impl LdapState {
         return AppLayerResult::ok();
     }
 
-    fn parse_response(&mut self, flow: *const Flow, stream_slice: StreamSlice) -> AppLayerResult {
+    fn parse_response(
+        &mut self, flow: *const Flow, pstate: *mut c_void, stream_slice: StreamSlice,
+    ) -> AppLayerResult {
         let input = stream_slice.as_slice();
-        if input.is_empty() {
+        if input.is_empty() || self.opaque {
             return AppLayerResult::ok();
         }
 
@@ -261,6 +277,10 @@ impl LdapState {
 
         let mut start = input;
         while !start.is_empty() {
+            if self.is_sasl_wrapped(start) {
+                self.set_opaque(pstate);
+                return AppLayerResult::ok();
+            }
             if self.response_frame.is_none() {
                 self.response_frame = Frame::new(
                     flow,
@@ -278,9 +298,22 @@ impl LdapState {
                     if let Some(tx) = self.find_request(response.message_id) {
                         tx.complete = tx_is_complete(&response.protocol_op, Direction::ToClient);
                         let tx_id = tx.id();
+                        let upgrade = tx_upgrade(tx, &response);
                         tx.responses.push_back(response);
                         let consumed = start.len() - rem.len();
                         self.set_frame_tc(flow, tx_id, consumed as i64);
+                        match upgrade {
+                            Some(LdapUpgrade::StartTls) => {
+                                SCLogDebug!("StartTLS accepted");
+                                self.start_tls(flow, pstate);
+                                return AppLayerResult::ok();
+                            }
+                            Some(LdapUpgrade::Sasl) => {
+                                SCLogDebug!("SASL bind with a security layer succeeded");
+                                self.sasl_layer = true;
+                            }
+                            None => {}
+                        }
                     } else if let ProtocolOp::ExtendedResponse(_) = response.protocol_op {
                         // this is an unsolicited notification, which means
                         // there is no request
@@ -435,6 +468,35 @@ impl LdapState {
         }
     }
 
+    /// After a SASL bind that can negotiate signing or sealing, messages are
+    /// either plain LDAP messages or SASL buffers: a 4 bytes length followed
+    /// by the wrapped message. LDAP messages always start with a BER sequence.
+    fn is_sasl_wrapped(&self, input: &[u8]) -> bool {
+        self.sasl_layer && input[0] != 0x30
+    }
+
+    /// Hand the flow over to the TLS parser
+    fn start_tls(&mut self, flow: *const Flow, pstate: *mut c_void) {
+        if unsafe { AppLayerRequestProtocolTLSUpgrade(flow) } {
+            return;
+        }
+        // don't try to parse the TLS records as LDAP
+        self.set_opaque(pstate);
+    }
+
+    fn set_opaque(&mut self, pstate: *mut c_void) {
+        SCLogDebug!("LDAP stream is opaque from now on");
+        self.opaque = true;
+        unsafe {
+            AppLayerParserStateSetFlag(
+                pstate,
+                APP_LAYER_PARSER_NO_INSPECTION
+                    | APP_LAYER_PARSER_NO_REASSEMBLY
+                    | APP_LAYER_PARSER_BYPASS_READY,
+            );
+        }
+    }
+
     fn on_request_gap(&mut self, _size: u32) {
         self.request_gap = true;
     }
@@ -444,6 +506,38 @@ impl LdapState {
     }
 }
 
+enum LdapUpgrade {
+    StartTls,
+    Sasl,
+}
+
+/// Check if a response completes a StartTLS operation or a SASL bind that
+/// can negotiate a security layer
+fn tx_upgrade(tx: &LdapTransaction, response: &LdapMessage) -> Option<LdapUpgrade> {
+    let request = tx.request.as_ref()?;
+    match (&request.protocol_op, &response.protocol_op) {
+        (ProtocolOp::ExtendedRequest(req), ProtocolOp::ExtendedResponse(resp))
+            if req.request_name.0 == LDAP_STARTTLS_OID && resp.result.result_code.0 == 0 =>
+        {
+            Some(LdapUpgrade::StartTls)
+        }
+        (ProtocolOp::BindRequest(req), ProtocolOp::BindResponse(resp))
+            if resp.result.result_code.0 == 0 =>
+        {
+            match &req.authentication {
+                AuthenticationChoice::Sasl(sasl)
+                    if sasl.mechanism.0.eq_ignore_ascii_case("GSSAPI")
+                        || sasl.mechanism.0.eq_ignore_ascii_case("GSS-SPNEGO") =>
+                {
+                    Some(LdapUpgrade::Sasl)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 fn tx_is_complete(op: &ProtocolOp, dir: Direction) -> bool {
     match dir {
         Direction::ToServer => match op {
@@ -538,7 +632,7 @@ unsafe extern "C" fn SCLdapParseRequest(
     if stream_slice.is_gap() {
         state.on_request_gap(stream_slice.gap_size());
     } else {
-        return state.parse_request(flow, stream_slice);
+        return state.parse_request(flow, pstate, stream_slice);
     }
     AppLayerResult::ok()
 }
@@ -559,7 +653,7 @@ unsafe extern "C" fn SCLdapParseResponse(
     if stream_slice.is_gap() {
         state.on_response_gap(stream_slice.gap_size());
     } else {
-        return state.parse_response(flow, stream_slice);
+        return state.parse_response(flow, pstate, stream_slice);
     }
     AppLayerResult::ok()
 }
@@ -728,3 +822,92 @@ pub unsafe extern "C" fn SCRegisterLdapUdpParser() {
         SCLogDebug!("Protocol detection and parser disabled for LDAP/UDP.");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // StartTLS ExtendedRequest, message id 1
+    const STARTTLS_REQUEST: &[u8] = &[
+        0x30, 0x1d, 0x02, 0x01, 0x01, 0x77, 0x18, 0x80, 0x16, 0x31, 0x2e, 0x33, 0x2e, 0x36, 0x2e,
+        0x31, 0x2e, 0x34, 0x2e, 0x31, 0x2e, 0x31, 0x34, 0x36, 0x36, 0x2e, 0x32, 0x30, 0x30, 0x33,
+        0x37,
+    ];
+    // TLS ClientHello record header
+    const TLS_RECORD: &[u8] = &[0x16, 0x03, 0x01, 0x00, 0xf1, 0x01, 0x00, 0x00, 0xed];
+    // UnbindRequest, message id 2
+    const UNBIND_REQUEST: &[u8] = &[0x30, 0x05, 0x02, 0x01, 0x02, 0x42, 0x00];
+
+    fn parse_ts(state: &mut LdapState, buf: &[u8]) -> AppLayerResult {
+        let slice = StreamSlice::from_slice(buf, STREAM_TOSERVER, 0);
+        state.parse_request(std::ptr::null(), std::ptr::null_mut(), slice)
+    }
+
+    fn parse_tc(state: &mut LdapState, buf: &[u8]) -> AppLayerResult {
+        let slice = StreamSlice::from_slice(buf, STREAM_TOCLIENT, 0);
+        state.parse_response(std::ptr::null(), std::ptr::null_mut(), slice)
+    }
+
+    #[test]
+    fn test_ldap_starttls() {
+        let mut state = LdapState::new();
+        assert_eq!(parse_ts(&mut state, STARTTLS_REQUEST), AppLayerResult::ok());
+        // ExtendedResponse, success
+        let buf = [
+            0x30, 0x0c, 0x02, 0x01, 0x01, 0x78, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00,
+        ];
+        assert_eq!(parse_tc(&mut state, &buf), AppLayerResult::ok());
+        assert!(state.opaque);
+        assert_eq!(state.transactions.len(), 1);
+        assert!(state.transactions[0].complete);
+
+        // TLS records are not parsed as LDAP
+        assert_eq!(parse_ts(&mut state, TLS_RECORD), AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_ldap_starttls_failed() {
+        let mut state = LdapState::new();
+        assert_eq!(parse_ts(&mut state, STARTTLS_REQUEST), AppLayerResult::ok());
+        // ExtendedResponse, protocolError
+        let buf = [
+            0x30, 0x0c, 0x02, 0x01, 0x01, 0x78, 0x07, 0x0a, 0x01, 0x02, 0x04, 0x00, 0x04, 0x00,
+        ];
+        assert_eq!(parse_tc(&mut state, &buf), AppLayerResult::ok());
+        assert!(!state.opaque);
+
+        // the session goes on in clear
+        assert_eq!(parse_ts(&mut state, UNBIND_REQUEST), AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 2);
+    }
+
+    #[test]
+    fn test_ldap_sasl_gssapi_wrapped() {
+        let mut state = LdapState::new();
+        // BindRequest, SASL GSSAPI
+        let buf = [
+            0x30, 0x14, 0x02, 0x01, 0x01, 0x60, 0x0f, 0x02, 0x01, 0x03, 0x04, 0x00, 0xa3, 0x08,
+            0x04, 0x06, 0x47, 0x53, 0x53, 0x41, 0x50, 0x49,
+        ];
+        assert_eq!(parse_ts(&mut state, &buf), AppLayerResult::ok());
+        // BindResponse, success
+        let buf = [
+            0x30, 0x0c, 0x02, 0x01, 0x01, 0x61, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00,
+        ];
+        assert_eq!(parse_tc(&mut state, &buf), AppLayerResult::ok());
+        assert!(state.sasl_layer);
+        assert!(!state.opaque);
+
+        // plain LDAP messages are still parsed
+        assert_eq!(parse_ts(&mut state, UNBIND_REQUEST), AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 2);
+        assert!(!state.opaque);
+
+        // SASL buffer: 4 bytes length, then the wrapped message
+        let buf = [0x00, 0x00, 0x00, 0x04, 0x05, 0x04, 0xff, 0x00];
+        assert_eq!(parse_ts(&mut state, &buf), AppLayerResult::ok());
+        assert!(state.opaque);
+        assert_eq!(state.transactions.len(), 2);
+    }
+}
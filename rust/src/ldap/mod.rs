@@ -17,6 +17,7 @@
 
 // written by Giuseppe Longo <giuseppe@glongo.it>
 
+pub mod detect;
 pub mod filters;
 pub mod ldap;
 pub mod logger;
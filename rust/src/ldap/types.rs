@@ -23,7 +23,9 @@ use std::fmt::{Display, Formatter};
 
 use asn1_rs::{FromBer, ParseResult};
 use ldap_parser::error::LdapError;
+use suricata_derive::{EnumStringU32, EnumStringU8};
 
+use crate::detect::EnumString;
 use crate::ldap::filters::*;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -45,86 +47,92 @@ pub struct ResultCode(pub u32);
 
 impl Display for ResultCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self.0 {
-            0 => write!(f, "success"),
-            1 => write!(f, "operations_error"),
-            2 => write!(f, "protocol_error"),
-            3 => write!(f, "time_limit_exceeded"),
-            4 => write!(f, "size_limit_exceeded"),
-            5 => write!(f, "compare_false"),
-            6 => write!(f, "compare_true"),
-            7 => write!(f, "auth_method_not_supported"),
-            8 => write!(f, "stronger_auth_required"),
-            10 => write!(f, "referral"),
-            11 => write!(f, "admin_limit_exceeded"),
-            12 => write!(f, "unavailable_critical_extension"),
-            13 => write!(f, "confidentiality_required"),
-            14 => write!(f, "sasl_bind_in_progress"),
-            16 => write!(f, "no_such_attribute"),
-            17 => write!(f, "undefined_attribute_type"),
-            18 => write!(f, "inappropriate_matching"),
-            19 => write!(f, "constraint_violation"),
-            20 => write!(f, "attribute_or_value_exists"),
-            21 => write!(f, "invalid_attribute_syntax"),
-            32 => write!(f, "no_such_object"),
-            33 => write!(f, "alias_problem"),
-            34 => write!(f, "invalid_dns_syntax"),
-            35 => write!(f, "is_leaf"),
-            36 => write!(f, "alias_dereferencing_problem"),
-            48 => write!(f, "inappropriate_authentication"),
-            49 => write!(f, "invalid_credentials"),
-            50 => write!(f, "insufficient_access_rights"),
-            51 => write!(f, "busy"),
-            52 => write!(f, "unavailable"),
-            53 => write!(f, "unwilling_to_perform"),
-            54 => write!(f, "loop_detect"),
-            60 => write!(f, "sort_control_missing"),
-            61 => write!(f, "offset_range_error"),
-            64 => write!(f, "naming_violation"),
-            65 => write!(f, "object_class_violation"),
-            66 => write!(f, "not_allowed_on_non_leaf"),
-            67 => write!(f, "not_allowed_on_rdn"),
-            68 => write!(f, "entry_already_exists"),
-            69 => write!(f, "object_class_mods_prohibited"),
-            70 => write!(f, "results_too_large"),
-            71 => write!(f, "affects_multiple_dsas"),
-            76 => write!(f, "control_error"),
-            80 => write!(f, "other"),
-            81 => write!(f, "server_down"),
-            82 => write!(f, "local_error"),
-            83 => write!(f, "encoding_error"),
-            84 => write!(f, "decoding_error"),
-            85 => write!(f, "timeout"),
-            86 => write!(f, "auth_unknown"),
-            87 => write!(f, "filter_error"),
-            88 => write!(f, "user_canceled"),
-            89 => write!(f, "param_error"),
-            90 => write!(f, "no_memory"),
-            91 => write!(f, "connect_error"),
-            92 => write!(f, "not_supported"),
-            93 => write!(f, "control_not_found"),
-            94 => write!(f, "no_results_returned"),
-            95 => write!(f, "more_results_to_return"),
-            96 => write!(f, "client_loop"),
-            97 => write!(f, "referral_limit_exceeded"),
-            100 => write!(f, "invalid_response"),
-            101 => write!(f, "ambiguous_response"),
-            112 => write!(f, "tls_not_supported"),
-            113 => write!(f, "intermediate_response"),
-            114 => write!(f, "unknown_type"),
-            118 => write!(f, "canceled"),
-            119 => write!(f, "no_such_operation"),
-            120 => write!(f, "too_late"),
-            121 => write!(f, "cannot_cancel"),
-            122 => write!(f, "assertion_failed"),
-            123 => write!(f, "authorization_denied"),
-            4096 => write!(f, "e_sync_refresh_required"),
-            16654 => write!(f, "no_operation"),
-            _ => write!(f, "{}", self.0),
+        match LdapResultCode::from_u(self.0) {
+            Some(code) => write!(f, "{}", code.to_str()),
+            None => write!(f, "{}", self.0),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumStringU32)]
+#[repr(u32)]
+pub enum LdapResultCode {
+    Success = 0,
+    OperationsError = 1,
+    ProtocolError = 2,
+    TimeLimitExceeded = 3,
+    SizeLimitExceeded = 4,
+    CompareFalse = 5,
+    CompareTrue = 6,
+    AuthMethodNotSupported = 7,
+    StrongerAuthRequired = 8,
+    Referral = 10,
+    AdminLimitExceeded = 11,
+    UnavailableCriticalExtension = 12,
+    ConfidentialityRequired = 13,
+    SaslBindInProgress = 14,
+    NoSuchAttribute = 16,
+    UndefinedAttributeType = 17,
+    InappropriateMatching = 18,
+    ConstraintViolation = 19,
+    AttributeOrValueExists = 20,
+    InvalidAttributeSyntax = 21,
+    NoSuchObject = 32,
+    AliasProblem = 33,
+    InvalidDnsSyntax = 34,
+    IsLeaf = 35,
+    AliasDereferencingProblem = 36,
+    InappropriateAuthentication = 48,
+    InvalidCredentials = 49,
+    InsufficientAccessRights = 50,
+    Busy = 51,
+    Unavailable = 52,
+    UnwillingToPerform = 53,
+    LoopDetect = 54,
+    SortControlMissing = 60,
+    OffsetRangeError = 61,
+    NamingViolation = 64,
+    ObjectClassViolation = 65,
+    NotAllowedOnNonLeaf = 66,
+    NotAllowedOnRdn = 67,
+    EntryAlreadyExists = 68,
+    ObjectClassModsProhibited = 69,
+    ResultsTooLarge = 70,
+    AffectsMultipleDsas = 71,
+    ControlError = 76,
+    Other = 80,
+    ServerDown = 81,
+    LocalError = 82,
+    EncodingError = 83,
+    DecodingError = 84,
+    Timeout = 85,
+    AuthUnknown = 86,
+    FilterError = 87,
+    UserCanceled = 88,
+    ParamError = 89,
+    NoMemory = 90,
+    ConnectError = 91,
+    NotSupported = 92,
+    ControlNotFound = 93,
+    NoResultsReturned = 94,
+    MoreResultsToReturn = 95,
+    ClientLoop = 96,
+    ReferralLimitExceeded = 97,
+    InvalidResponse = 100,
+    AmbiguousResponse = 101,
+    TlsNotSupported = 112,
+    IntermediateResponse = 113,
+    UnknownType = 114,
+    Canceled = 118,
+    NoSuchOperation = 119,
+    TooLate = 120,
+    CannotCancel = 121,
+    AssertionFailed = 122,
+    AuthorizationDenied = 123,
+    ESyncRefreshRequired = 4096,
+    NoOperation = 16654,
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct MessageID(pub u32);
 
@@ -328,6 +336,92 @@ impl Display for ProtocolOp {
     }
 }
 
+/// Protocol operation codes, i.e. the application tags of RFC 4511
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumStringU8)]
+#[repr(u8)]
+pub enum ProtocolOpCode {
+    BindRequest = 0,
+    BindResponse = 1,
+    UnbindRequest = 2,
+    SearchRequest = 3,
+    SearchResultEntry = 4,
+    SearchResultDone = 5,
+    ModifyRequest = 6,
+    ModifyResponse = 7,
+    AddRequest = 8,
+    AddResponse = 9,
+    DelRequest = 10,
+    DelResponse = 11,
+    ModDnRequest = 12,
+    ModDnResponse = 13,
+    CompareRequest = 14,
+    CompareResponse = 15,
+    AbandonRequest = 16,
+    SearchResultReference = 19,
+    ExtendedRequest = 23,
+    ExtendedResponse = 24,
+    IntermediateResponse = 25,
+}
+
+impl ProtocolOp {
+    pub fn to_u8(&self) -> u8 {
+        let code = match self {
+            ProtocolOp::BindRequest(_) => ProtocolOpCode::BindRequest,
+            ProtocolOp::BindResponse(_) => ProtocolOpCode::BindResponse,
+            ProtocolOp::UnbindRequest => ProtocolOpCode::UnbindRequest,
+            ProtocolOp::SearchRequest(_) => ProtocolOpCode::SearchRequest,
+            ProtocolOp::SearchResultEntry(_) => ProtocolOpCode::SearchResultEntry,
+            ProtocolOp::SearchResultDone(_) => ProtocolOpCode::SearchResultDone,
+            ProtocolOp::SearchResultReference(_) => ProtocolOpCode::SearchResultReference,
+            ProtocolOp::ModifyRequest(_) => ProtocolOpCode::ModifyRequest,
+            ProtocolOp::ModifyResponse(_) => ProtocolOpCode::ModifyResponse,
+            ProtocolOp::AddRequest(_) => ProtocolOpCode::AddRequest,
+            ProtocolOp::AddResponse(_) => ProtocolOpCode::AddResponse,
+            ProtocolOp::DelRequest(_) => ProtocolOpCode::DelRequest,
+            ProtocolOp::DelResponse(_) => ProtocolOpCode::DelResponse,
+            ProtocolOp::ModDnRequest(_) => ProtocolOpCode::ModDnRequest,
+            ProtocolOp::ModDnResponse(_) => ProtocolOpCode::ModDnResponse,
+            ProtocolOp::CompareRequest(_) => ProtocolOpCode::CompareRequest,
+            ProtocolOp::CompareResponse(_) => ProtocolOpCode::CompareResponse,
+            ProtocolOp::ExtendedRequest(_) => ProtocolOpCode::ExtendedRequest,
+            ProtocolOp::ExtendedResponse(_) => ProtocolOpCode::ExtendedResponse,
+            ProtocolOp::IntermediateResponse(_) => ProtocolOpCode::IntermediateResponse,
+            // only AbandonRequest is mapped to Unknown
+            ProtocolOp::Unknown => ProtocolOpCode::AbandonRequest,
+        };
+        code as u8
+    }
+
+    /// Result of a response operation, if it carries one
+    pub fn get_result(&self) -> Option<&LdapResult> {
+        match self {
+            ProtocolOp::BindResponse(resp) => Some(&resp.result),
+            ProtocolOp::SearchResultDone(result)
+            | ProtocolOp::AddResponse(result)
+            | ProtocolOp::DelResponse(result)
+            | ProtocolOp::ModDnResponse(result)
+            | ProtocolOp::CompareResponse(result) => Some(result),
+            ProtocolOp::ModifyResponse(resp) => Some(&resp.result),
+            ProtocolOp::ExtendedResponse(resp) => Some(&resp.result),
+            _ => None,
+        }
+    }
+
+    /// Distinguished name a request operates on
+    pub fn get_request_dn(&self) -> Option<&LdapDN> {
+        match self {
+            ProtocolOp::BindRequest(req) => Some(&req.name),
+            ProtocolOp::SearchRequest(req) => Some(&req.base_object),
+            ProtocolOp::ModifyRequest(req) => Some(&req.object),
+            ProtocolOp::AddRequest(req) => Some(&req.entry),
+            ProtocolOp::DelRequest(dn) => Some(dn),
+            ProtocolOp::ModDnRequest(req) => Some(&req.entry),
+            ProtocolOp::CompareRequest(req) => Some(&req.entry),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct LdapMessage {
     pub message_id: MessageID,
@@ -108,7 +108,9 @@ pub mod snmp;
 pub mod ntp;
 pub mod tftp;
 pub mod dhcp;
+pub mod dhcpv6;
 pub mod sip;
+pub mod rtp;
 pub mod rfb;
 pub mod mqtt;
 pub mod pgsql;
@@ -123,6 +125,7 @@ pub mod mime;
 pub mod ssh;
 pub mod http2;
 pub mod quic;
+pub mod http3;
 pub mod bittorrent_dht;
 pub mod plugin;
 pub mod lzma;
@@ -0,0 +1,166 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::ntp::{NTPTransaction, ALPROTO_NTP};
+use crate::detect::uint::{
+    rs_detect_u8_free, rs_detect_u8_match, rs_detect_u8_parse, DetectUintData,
+};
+use crate::detect::{
+    DetectHelperBufferRegister, DetectHelperKeywordRegister, DetectSignatureSetAppProto,
+    SCSigTableElmt, SigMatchAppendSMToList,
+};
+use std::os::raw::{c_int, c_void};
+
+static mut G_NTP_MODE_KW_ID: c_int = 0;
+static mut G_NTP_MODE_BUFFER_ID: c_int = 0;
+static mut G_NTP_STRATUM_KW_ID: c_int = 0;
+static mut G_NTP_STRATUM_BUFFER_ID: c_int = 0;
+static mut G_NTP_CONTROL_OPCODE_KW_ID: c_int = 0;
+static mut G_NTP_CONTROL_OPCODE_BUFFER_ID: c_int = 0;
+
+unsafe fn ntp_detect_u8_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char, kw_id: c_int, buffer_id: c_int,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_NTP) != 0 {
+        return -1;
+    }
+    let ctx = rs_detect_u8_parse(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(de, s, kw_id, ctx, buffer_id).is_null() {
+        ntp_detect_u8_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ntp_detect_u8_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    rs_detect_u8_free(ctx);
+}
+
+unsafe extern "C" fn ntp_detect_mode_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    ntp_detect_u8_setup(de, s, raw, G_NTP_MODE_KW_ID, G_NTP_MODE_BUFFER_ID)
+}
+
+unsafe extern "C" fn ntp_detect_mode_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, NTPTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    return rs_detect_u8_match(tx.mode, ctx);
+}
+
+unsafe extern "C" fn ntp_detect_stratum_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    ntp_detect_u8_setup(de, s, raw, G_NTP_STRATUM_KW_ID, G_NTP_STRATUM_BUFFER_ID)
+}
+
+unsafe extern "C" fn ntp_detect_stratum_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, NTPTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    if let Some(ref header) = tx.header {
+        return rs_detect_u8_match(header.stratum, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ntp_detect_control_opcode_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    ntp_detect_u8_setup(
+        de,
+        s,
+        raw,
+        G_NTP_CONTROL_OPCODE_KW_ID,
+        G_NTP_CONTROL_OPCODE_BUFFER_ID,
+    )
+}
+
+unsafe extern "C" fn ntp_detect_control_opcode_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, NTPTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    if let Some(ref control) = tx.control {
+        return rs_detect_u8_match(control.opcode, ctx);
+    }
+    return 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectNTPRegister() {
+    let kw = SCSigTableElmt {
+        name: b"ntp.mode\0".as_ptr() as *const libc::c_char,
+        desc: b"match NTP mode\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ntp-keywords.html#ntp-mode\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ntp_detect_mode_match),
+        Setup: ntp_detect_mode_setup,
+        Free: Some(ntp_detect_u8_free),
+        flags: 0,
+    };
+    G_NTP_MODE_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_NTP_MODE_BUFFER_ID = DetectHelperBufferRegister(
+        b"ntp.mode\0".as_ptr() as *const libc::c_char,
+        ALPROTO_NTP,
+        true,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ntp.stratum\0".as_ptr() as *const libc::c_char,
+        desc: b"match NTP stratum\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ntp-keywords.html#ntp-stratum\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ntp_detect_stratum_match),
+        Setup: ntp_detect_stratum_setup,
+        Free: Some(ntp_detect_u8_free),
+        flags: 0,
+    };
+    G_NTP_STRATUM_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_NTP_STRATUM_BUFFER_ID = DetectHelperBufferRegister(
+        b"ntp.stratum\0".as_ptr() as *const libc::c_char,
+        ALPROTO_NTP,
+        true,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ntp.control_opcode\0".as_ptr() as *const libc::c_char,
+        desc: b"match NTP control (mode 6) opcode or private (mode 7) request code\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ntp-keywords.html#ntp-control-opcode\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ntp_detect_control_opcode_match),
+        Setup: ntp_detect_control_opcode_setup,
+        Free: Some(ntp_detect_u8_free),
+        flags: 0,
+    };
+    G_NTP_CONTROL_OPCODE_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_NTP_CONTROL_OPCODE_BUFFER_ID = DetectHelperBufferRegister(
+        b"ntp.control_opcode\0".as_ptr() as *const libc::c_char,
+        ALPROTO_NTP,
+        true,
+        true,
+    );
+}
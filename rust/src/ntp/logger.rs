@@ -0,0 +1,139 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::ntp::{NTPHeader, NTPTransaction};
+use super::parser::NTPControlHeader;
+use crate::jsonbuilder::{JsonBuilder, JsonError};
+use crate::x509::time::format_timestamp;
+use std::net::Ipv4Addr;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+fn ntp_mode_string(mode: u8) -> &'static str {
+    match mode {
+        1 => "symmetric_active",
+        2 => "symmetric_passive",
+        3 => "client",
+        4 => "server",
+        5 => "broadcast",
+        6 => "control",
+        7 => "private",
+        _ => "reserved",
+    }
+}
+
+/// Formats a 32.32 fixed point NTP timestamp, or returns None if the
+/// timestamp is not set.
+fn ntp_timestamp_string(ts: u64) -> Option<String> {
+    if ts == 0 {
+        return None;
+    }
+    let secs = (ts >> 32) as i64 - NTP_UNIX_OFFSET;
+    let usecs = ((ts & 0xffff_ffff) * 1_000_000) >> 32;
+    let formatted = format_timestamp(secs).ok()?;
+    Some(format!("{}.{:06}", formatted, usecs))
+}
+
+/// The reference id is a 4 character code for stratum 0 (kiss code) and
+/// 1 (reference clock), and an address, or an address hash, otherwise.
+fn ntp_reference_id_string(stratum: u8, ref_id: u32) -> String {
+    if stratum <= 1 {
+        let bytes = ref_id.to_be_bytes();
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        return String::from_utf8_lossy(&bytes[..len]).to_string();
+    }
+    Ipv4Addr::from(ref_id).to_string()
+}
+
+/// Converts a 16.16 fixed point duration to seconds
+fn ntp_short_to_secs(val: u32) -> f64 {
+    val as f64 / 65536.0
+}
+
+fn log_header(header: &NTPHeader, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.set_uint("leap_indicator", header.leap_indicator as u64)?;
+    js.set_uint("stratum", header.stratum as u64)?;
+    js.set_int("poll", header.poll as i64)?;
+    js.set_int("precision", header.precision as i64)?;
+    js.set_string(
+        "reference_id",
+        &ntp_reference_id_string(header.stratum, header.ref_id),
+    )?;
+    js.set_float("root_delay", ntp_short_to_secs(header.root_delay))?;
+    js.set_float("root_dispersion", ntp_short_to_secs(header.root_dispersion))?;
+    for (key, ts) in [
+        ("reference_timestamp", header.ts_ref),
+        ("origin_timestamp", header.ts_orig),
+        ("receive_timestamp", header.ts_recv),
+        ("transmit_timestamp", header.ts_xmit),
+    ] {
+        if let Some(ts) = ntp_timestamp_string(ts) {
+            js.set_string(key, &ts)?;
+        }
+    }
+    Ok(())
+}
+
+fn log_control(control: &NTPControlHeader, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.set_uint("control_opcode", control.opcode as u64)?;
+    js.set_bool("response", control.response)?;
+    js.set_uint("sequence", control.sequence as u64)?;
+    if let Some(implementation) = control.implementation {
+        js.set_uint("implementation", implementation as u64)?;
+    }
+    Ok(())
+}
+
+fn log_ntp(tx: &NTPTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("ntp")?;
+    js.set_uint("version", tx.version as u64)?;
+    js.set_string("mode", ntp_mode_string(tx.mode))?;
+    if let Some(ref header) = tx.header {
+        log_header(header, js)?;
+    }
+    if let Some(ref control) = tx.control {
+        log_control(control, js)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_ntp_log_json(
+    tx: *mut std::os::raw::c_void, js: &mut JsonBuilder,
+) -> bool {
+    let tx = cast_pointer!(tx, NTPTransaction);
+    log_ntp(tx, js).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_log_values() {
+        assert_eq!(
+            ntp_timestamp_string(0xe9b5_1c80_8000_0000).unwrap(),
+            "2024-04-01T11:44:00.500000"
+        );
+        assert_eq!(ntp_timestamp_string(0), None);
+        assert_eq!(ntp_reference_id_string(1, 0x47505300), "GPS");
+        assert_eq!(ntp_reference_id_string(2, 0xc0a80101), "192.168.1.1");
+        assert_eq!(ntp_short_to_secs(0x0001_8000), 1.5);
+    }
+}
@@ -20,3 +20,6 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 pub mod ntp;
+pub mod parser;
+pub mod logger;
+pub mod detect;
@@ -19,6 +19,7 @@
 
 extern crate ntp_parser;
 use self::ntp_parser::*;
+use super::parser::{parse_ntp_control, NTPControlHeader};
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_UNKNOWN,ALPROTO_FAILED,Direction};
 use crate::applayer::{self, *};
@@ -49,11 +50,55 @@ pub struct NTPState {
     tx_id: u64,
 }
 
+/// Header fields of a NTPv3 or NTPv4 message
+#[derive(Debug, Default)]
+pub struct NTPHeader {
+    pub leap_indicator: u8,
+    pub stratum: u8,
+    pub poll: i8,
+    pub precision: i8,
+    pub root_delay: u32,
+    pub root_dispersion: u32,
+    pub ref_id: u32,
+    pub ts_ref: u64,
+    pub ts_orig: u64,
+    pub ts_recv: u64,
+    pub ts_xmit: u64,
+}
+
+macro_rules! ntp_header {
+    ($pkt:expr) => {
+        NTPHeader {
+            leap_indicator: $pkt.li,
+            stratum: $pkt.stratum,
+            poll: $pkt.poll,
+            precision: $pkt.precision,
+            root_delay: $pkt.root_delay,
+            root_dispersion: $pkt.root_dispersion,
+            ref_id: $pkt.ref_id,
+            ts_ref: $pkt.ts_ref,
+            ts_orig: $pkt.ts_orig,
+            ts_recv: $pkt.ts_recv,
+            ts_xmit: $pkt.ts_xmit,
+        }
+    };
+}
+
+/// Each message is its own transaction.
 #[derive(Debug, Default)]
 pub struct NTPTransaction {
     /// The NTP reference ID
     pub xid: u32,
 
+    pub version: u8,
+    pub mode: u8,
+
+    /// Set for NTPv3/v4 messages (modes 1 to 5)
+    pub header: Option<NTPHeader>,
+
+    /// Set for control and private messages (modes 6 and 7)
+    pub control: Option<NTPControlHeader>,
+
     /// The internal transaction id
     id: u64,
 
@@ -87,19 +132,29 @@ impl NTPState {
     ///
     /// Returns 0 if successful, or -1 on error
     fn parse(&mut self, i: &[u8], direction: Direction) -> i32 {
+        // control and private messages are not handled by ntp_parser
+        if let Ok((_, control)) = parse_ntp_control(i) {
+            let mut tx = self.new_tx(direction);
+            tx.version = control.version;
+            tx.mode = control.mode;
+            tx.control = Some(control);
+            self.transactions.push(tx);
+            return 0;
+        }
         match parse_ntp(i) {
             Ok((_,ref msg)) => {
                 // SCLogDebug!("parse_ntp: {:?}",msg);
-                let (mode, ref_id) = match msg {
-                    NtpPacket::V3(pkt) => (pkt.mode, pkt.ref_id),
-                    NtpPacket::V4(pkt) => (pkt.mode, pkt.ref_id),
+                let (version, mode, header) = match msg {
+                    NtpPacket::V3(pkt) => (pkt.version, pkt.mode, ntp_header!(pkt)),
+                    NtpPacket::V4(pkt) => (pkt.version, pkt.mode, ntp_header!(pkt)),
                 };
-                if mode == NtpMode::SymmetricActive || mode == NtpMode::Client {
-                    let mut tx = self.new_tx(direction);
-                    // use the reference id as identifier
-                    tx.xid = ref_id;
-                    self.transactions.push(tx);
-                }
+                let mut tx = self.new_tx(direction);
+                // use the reference id as identifier
+                tx.xid = header.ref_id;
+                tx.version = version;
+                tx.mode = mode.0;
+                tx.header = Some(header);
+                self.transactions.push(tx);
                 0
             },
             Err(Err::Incomplete(_)) => {
@@ -151,6 +206,10 @@ impl NTPTransaction {
     pub fn new(direction: Direction, id: u64) -> NTPTransaction {
         NTPTransaction {
             xid: 0,
+            version: 0,
+            mode: 0,
+            header: None,
+            control: None,
             id,
             tx_data: applayer::AppLayerTxData::for_direction(direction),
         }
@@ -237,7 +296,7 @@ pub extern "C" fn rs_ntp_tx_get_alstate_progress(_tx: *mut std::os::raw::c_void,
     1
 }
 
-static mut ALPROTO_NTP : AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_NTP : AppProto = ALPROTO_UNKNOWN;
 
 #[no_mangle]
 pub extern "C" fn ntp_probing_parser(_flow: *const Flow,
@@ -250,6 +309,9 @@ pub extern "C" fn ntp_probing_parser(_flow: *const Flow,
     }
     let slice: &[u8] = unsafe { std::slice::from_raw_parts(input as *mut u8, input_len as usize) };
     let alproto = unsafe{ ALPROTO_NTP };
+    if parse_ntp_control(slice).is_ok() {
+        return alproto;
+    }
     match parse_ntp(slice) {
         Ok((_, _)) => {
             // parse_ntp already checks for supported version (3 or 4)
@@ -336,5 +398,26 @@ mod tests {
 
         let mut state = NTPState::new();
         assert_eq!(0, state.parse(REQ, Direction::ToServer));
+        let tx = state.transactions.last().unwrap();
+        assert_eq!(tx.version, 4);
+        assert_eq!(tx.mode, 3);
+        let header = tx.header.as_ref().unwrap();
+        assert_eq!(header.stratum, 0);
+        assert_eq!(header.ts_xmit, 0x1857abc34a5f2cfe);
+        assert!(tx.control.is_none());
+    }
+
+    #[test]
+    fn test_ntp_parse_control() {
+        // mode 7 MON_GETLIST_1 request
+        let mut req = vec![0x17, 0x00, 0x03, 0x2a];
+        req.extend_from_slice(&[0u8; 44]);
+
+        let mut state = NTPState::new();
+        assert_eq!(0, state.parse(&req, Direction::ToServer));
+        let tx = state.transactions.last().unwrap();
+        assert_eq!(tx.mode, 7);
+        assert!(tx.header.is_none());
+        assert_eq!(tx.control.as_ref().unwrap().opcode, 42);
     }
 }
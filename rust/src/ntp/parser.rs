@@ -0,0 +1,145 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Parser for the NTP control (mode 6) and private (mode 7) messages,
+//! which are not handled by ntp-parser.
+
+use nom7::combinator::verify;
+use nom7::number::streaming::{be_u16, be_u8};
+use nom7::IResult;
+
+pub const NTP_MODE_CONTROL: u8 = 6;
+pub const NTP_MODE_PRIVATE: u8 = 7;
+
+/// Header of a control or private message
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NTPControlHeader {
+    pub version: u8,
+    pub mode: u8,
+    pub response: bool,
+    /// Control message opcode (mode 6) or private request code (mode 7)
+    pub opcode: u8,
+    pub sequence: u16,
+    /// Implementation number, mode 7 only
+    pub implementation: Option<u8>,
+}
+
+/// Returns the mode and version of a NTP message from its first byte
+pub fn ntp_mode_version(b: u8) -> (u8, u8) {
+    (b & 0x07, (b >> 3) & 0x07)
+}
+
+// LI | VN | Mode, R | E | M | Opcode, Sequence, Status, Association ID,
+// Offset, Count
+fn parse_control_message(i: &[u8]) -> IResult<&[u8], NTPControlHeader> {
+    let (i, b0) = verify(be_u8, |&b| {
+        let (mode, version) = ntp_mode_version(b);
+        mode == NTP_MODE_CONTROL && (1..=4).contains(&version)
+    })(i)?;
+    let (i, b1) = be_u8(i)?;
+    let (i, sequence) = be_u16(i)?;
+    let (i, _status) = be_u16(i)?;
+    let (i, _association_id) = be_u16(i)?;
+    let (i, _offset) = be_u16(i)?;
+    let (i, _count) = be_u16(i)?;
+    let (mode, version) = ntp_mode_version(b0);
+    Ok((
+        i,
+        NTPControlHeader {
+            version,
+            mode,
+            response: b1 & 0x80 != 0,
+            opcode: b1 & 0x1f,
+            sequence,
+            implementation: None,
+        },
+    ))
+}
+
+// R | M | VN | Mode, A | Sequence, Implementation, Request code, then
+// Err | Number of items, MBZ | Size of item
+fn parse_private_message(i: &[u8]) -> IResult<&[u8], NTPControlHeader> {
+    let (i, b0) = verify(be_u8, |&b| {
+        let (mode, version) = ntp_mode_version(b);
+        mode == NTP_MODE_PRIVATE && (1..=4).contains(&version)
+    })(i)?;
+    let (i, b1) = be_u8(i)?;
+    let (i, implementation) = be_u8(i)?;
+    let (i, opcode) = be_u8(i)?;
+    let (i, _nitems) = be_u16(i)?;
+    let (i, _itemsize) = be_u16(i)?;
+    let (mode, version) = ntp_mode_version(b0);
+    Ok((
+        i,
+        NTPControlHeader {
+            version,
+            mode,
+            response: b0 & 0x80 != 0,
+            opcode,
+            sequence: (b1 & 0x7f) as u16,
+            implementation: Some(implementation),
+        },
+    ))
+}
+
+/// Parses the header of a control or private message. The data is
+/// not parsed.
+pub fn parse_ntp_control(i: &[u8]) -> IResult<&[u8], NTPControlHeader> {
+    match i.first() {
+        Some(&b) if ntp_mode_version(b).0 == NTP_MODE_PRIVATE => parse_private_message(i),
+        _ => parse_control_message(i),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ntp_control_readvar() {
+        // mode 6 readvar request, as sent by ntpq -c rv
+        let buf: &[u8] = &[
+            0x16, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let (rem, hdr) = parse_ntp_control(buf).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(hdr.version, 2);
+        assert_eq!(hdr.mode, NTP_MODE_CONTROL);
+        assert_eq!(hdr.opcode, 2);
+        assert_eq!(hdr.sequence, 1);
+        assert!(!hdr.response);
+        assert_eq!(hdr.implementation, None);
+    }
+
+    #[test]
+    fn test_parse_ntp_private_monlist() {
+        // mode 7 MON_GETLIST_1 request, as used for amplification
+        let mut buf = vec![0x17, 0x00, 0x03, 0x2a, 0x00, 0x00, 0x00, 0x00];
+        buf.extend_from_slice(&[0u8; 40]);
+        let (rem, hdr) = parse_ntp_control(&buf).unwrap();
+        assert_eq!(rem.len(), 40);
+        assert_eq!(hdr.version, 2);
+        assert_eq!(hdr.mode, NTP_MODE_PRIVATE);
+        assert_eq!(hdr.opcode, 42);
+        assert_eq!(hdr.implementation, Some(3));
+        assert!(!hdr.response);
+
+        // client mode message
+        let buf: &[u8] = &[0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert!(parse_ntp_control(buf).is_err());
+    }
+}
@@ -0,0 +1,607 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! PostgreSQL detection keywords
+
+use super::parser::{
+    PgsqlAuthMethod, PgsqlBEMessage, PgsqlErrorNoticeFieldType, PgsqlFEMessage, PgsqlParameters,
+};
+use super::pgsql::{PgsqlTransaction, PgsqlTxProgress, ALPROTO_PGSQL};
+use crate::detect::uint::{
+    detect_match_uint, detect_parse_uint_enum, rs_detect_u32_free, rs_detect_u64_free,
+    rs_detect_u64_match, rs_detect_u64_parse, DetectUintData,
+};
+use crate::detect::{
+    DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
+    DetectHelperGetData, DetectHelperKeywordRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
+};
+
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+static mut G_PGSQL_QUERY_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_USER_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_DATABASE_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_APPLICATION_NAME_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_ERROR_CODE_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_ERROR_MESSAGE_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_ROW_COUNT_KW_ID: c_int = 0;
+static mut G_PGSQL_ROW_COUNT_BUFFER_ID: c_int = 0;
+static mut G_PGSQL_AUTH_METHOD_KW_ID: c_int = 0;
+static mut G_PGSQL_AUTH_METHOD_BUFFER_ID: c_int = 0;
+
+/// Get the statement text, be it from a simple query or from the extended query protocol
+fn pgsql_tx_get_query(tx: &PgsqlTransaction) -> Option<&[u8]> {
+    for request in &tx.requests {
+        match request {
+            PgsqlFEMessage::SimpleQuery(query) => return Some(&query.payload),
+            PgsqlFEMessage::Parse(parse) => return Some(&parse.query),
+            PgsqlFEMessage::Bind(bind) => {
+                if let Some(query) = &bind.query {
+                    return Some(query);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn pgsql_tx_get_startup_param(tx: &PgsqlTransaction, name: PgsqlParameters) -> Option<&[u8]> {
+    for request in &tx.requests {
+        if let PgsqlFEMessage::StartupMessage(startup) = request {
+            if name == PgsqlParameters::User {
+                return Some(&startup.params.user.value);
+            }
+            if let Some(params) = &startup.params.optional_params {
+                for param in params {
+                    if param.name == name {
+                        return Some(&param.value);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+fn pgsql_tx_get_error_field(
+    tx: &PgsqlTransaction, field_type: PgsqlErrorNoticeFieldType,
+) -> Option<&[u8]> {
+    for response in &tx.responses {
+        if let PgsqlBEMessage::ErrorResponse(error) = response {
+            for field in &error.message_body {
+                if field.field_type == field_type {
+                    return Some(&field.field_value);
+                }
+            }
+        }
+    }
+    None
+}
+
+unsafe fn pgsql_set_buffer(
+    value: Option<&[u8]>, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    if let Some(value) = value {
+        *buffer = value.as_ptr();
+        *buffer_len = value.len() as u32;
+        return true;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn pgsql_detect_query_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_QUERY_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_query_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(pgsql_tx_get_query(tx), buffer, buffer_len);
+}
+
+unsafe extern "C" fn pgsql_detect_query_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_query_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_user_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_USER_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_user_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(
+        pgsql_tx_get_startup_param(tx, PgsqlParameters::User),
+        buffer,
+        buffer_len,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_user_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_user_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_database_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_DATABASE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_database_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(
+        pgsql_tx_get_startup_param(tx, PgsqlParameters::Database),
+        buffer,
+        buffer_len,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_database_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_database_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_application_name_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_APPLICATION_NAME_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_application_name_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(
+        pgsql_tx_get_startup_param(tx, PgsqlParameters::ApplicationName),
+        buffer,
+        buffer_len,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_application_name_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_application_name_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_error_code_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_ERROR_CODE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_error_code_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(
+        pgsql_tx_get_error_field(tx, PgsqlErrorNoticeFieldType::CodeSqlStateCode),
+        buffer,
+        buffer_len,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_error_code_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_error_code_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_error_message_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_PGSQL_ERROR_MESSAGE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_error_message_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    return pgsql_set_buffer(
+        pgsql_tx_get_error_field(tx, PgsqlErrorNoticeFieldType::Message),
+        buffer,
+        buffer_len,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_error_message_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        pgsql_detect_error_message_get,
+    );
+}
+
+unsafe extern "C" fn pgsql_detect_row_count_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    let ctx = rs_detect_u64_parse(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_PGSQL_ROW_COUNT_KW_ID,
+        ctx,
+        G_PGSQL_ROW_COUNT_BUFFER_ID,
+    )
+    .is_null()
+    {
+        pgsql_detect_row_count_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_row_count_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u64>);
+    // rows keep coming in until the response is over
+    if tx.tx_res_state < PgsqlTxProgress::TxDone {
+        return 0;
+    }
+    return rs_detect_u64_match(tx.get_row_cnt().saturating_add(tx.copy_row_cnt), ctx);
+}
+
+unsafe extern "C" fn pgsql_detect_row_count_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u64>);
+    rs_detect_u64_free(ctx);
+}
+
+unsafe extern "C" fn pgsql_parse_auth_method(
+    ustr: *const std::os::raw::c_char,
+) -> *mut DetectUintData<u32> {
+    let ft_name: &CStr = CStr::from_ptr(ustr); //unsafe
+    if let Ok(s) = ft_name.to_str() {
+        if let Some(ctx) = detect_parse_uint_enum::<u32, PgsqlAuthMethod>(s) {
+            let boxed = Box::new(ctx);
+            return Box::into_raw(boxed) as *mut _;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe extern "C" fn pgsql_detect_auth_method_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_PGSQL) != 0 {
+        return -1;
+    }
+    let ctx = pgsql_parse_auth_method(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(
+        de,
+        s,
+        G_PGSQL_AUTH_METHOD_KW_ID,
+        ctx,
+        G_PGSQL_AUTH_METHOD_BUFFER_ID,
+    )
+    .is_null()
+    {
+        pgsql_detect_auth_method_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_auth_method_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    for response in &tx.responses {
+        if let Some(auth_type) = response.get_auth_type() {
+            if detect_match_uint(ctx, auth_type) {
+                return 1;
+            }
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn pgsql_detect_auth_method_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    rs_detect_u32_free(ctx);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectPgsqlRegister() {
+    let kw = SCSigTableElmt {
+        name: b"pgsql.query\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the PostgreSQL query statement\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-query\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_query_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_query_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_QUERY_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.query\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL query\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        false,
+        true,
+        pgsql_detect_query_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.user\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the PostgreSQL user from the startup message\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-user\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_user_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_user_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_USER_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.user\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL user\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        false,
+        true,
+        pgsql_detect_user_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.database\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the PostgreSQL database from the startup message\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-database\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_database_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_database_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_DATABASE_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.database\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL database\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        false,
+        true,
+        pgsql_detect_database_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.application_name\0".as_ptr() as *const libc::c_char,
+        desc:
+            b"sticky buffer to match on the PostgreSQL application name from the startup message\0"
+                .as_ptr() as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-application-name\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_application_name_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_application_name_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_APPLICATION_NAME_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.application_name\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL application name\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        false,
+        true,
+        pgsql_detect_application_name_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.error_code\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the SQLSTATE code of a PostgreSQL error response\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-error-code\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_error_code_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_error_code_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_ERROR_CODE_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.error_code\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL error code\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        true,
+        false,
+        pgsql_detect_error_code_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.error_message\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the message of a PostgreSQL error response\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-error-message\0".as_ptr() as *const libc::c_char,
+        Setup: pgsql_detect_error_message_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_pgsql_error_message_kw_id = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_ERROR_MESSAGE_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"pgsql.error_message\0".as_ptr() as *const libc::c_char,
+        b"PostgreSQL error message\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        true,
+        false,
+        pgsql_detect_error_message_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.row_count\0".as_ptr() as *const libc::c_char,
+        desc: b"match on the number of rows returned or copied in a PostgreSQL transaction\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-row-count\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(pgsql_detect_row_count_match),
+        Setup: pgsql_detect_row_count_setup,
+        Free: Some(pgsql_detect_row_count_free),
+        flags: 0,
+    };
+    G_PGSQL_ROW_COUNT_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_ROW_COUNT_BUFFER_ID = DetectHelperBufferRegister(
+        b"pgsql.row_count\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        true,
+        false,
+    );
+    let kw = SCSigTableElmt {
+        name: b"pgsql.auth_method\0".as_ptr() as *const libc::c_char,
+        desc: b"match on the authentication method requested by the PostgreSQL backend\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/pgsql-keywords.html#pgsql-auth-method\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(pgsql_detect_auth_method_match),
+        Setup: pgsql_detect_auth_method_setup,
+        Free: Some(pgsql_detect_auth_method_free),
+        flags: 0,
+    };
+    G_PGSQL_AUTH_METHOD_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_PGSQL_AUTH_METHOD_BUFFER_ID = DetectHelperBufferRegister(
+        b"pgsql.auth_method\0".as_ptr() as *const libc::c_char,
+        ALPROTO_PGSQL,
+        true,
+        false,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detect::uint::DetectUintMode;
+
+    #[test]
+    fn test_pgsql_parse_auth_method() {
+        let ctx = detect_parse_uint_enum::<u32, PgsqlAuthMethod>("md5_password").unwrap();
+        assert_eq!(ctx.arg1, 5);
+        assert_eq!(ctx.mode, DetectUintMode::DetectUintModeEqual);
+        let ctx = detect_parse_uint_enum::<u32, PgsqlAuthMethod>("kerberos_v5").unwrap();
+        assert_eq!(ctx.arg1, 2);
+        assert!(detect_parse_uint_enum::<u32, PgsqlAuthMethod>("invalidopt").is_none());
+    }
+}
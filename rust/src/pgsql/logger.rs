@@ -26,11 +26,14 @@ use std;
 
 pub const PGSQL_LOG_PASSWORDS: u32 = BIT_U32!(0);
 
+/// Bound parameter values are truncated to this size when logged
+const PGSQL_LOG_PARAM_MAX_LEN: usize = 256;
+
 fn log_pgsql(tx: &PgsqlTransaction, flags: u32, js: &mut JsonBuilder) -> Result<(), JsonError> {
     js.open_object("pgsql")?;
     js.set_uint("tx_id", tx.tx_id)?;
-    if let Some(request) = &tx.request {
-        js.set_object("request", &log_request(request, flags)?)?;
+    if !tx.requests.is_empty() {
+        js.set_object("request", &log_request_object(tx, flags)?)?;
     } else if tx.responses.is_empty() {
         SCLogDebug!("Suricata created an empty PGSQL transaction");
         // TODO Log anomaly event instead?
@@ -48,8 +51,33 @@ fn log_pgsql(tx: &PgsqlTransaction, flags: u32, js: &mut JsonBuilder) -> Result<
     Ok(())
 }
 
-fn log_request(req: &PgsqlFEMessage, flags: u32) -> Result<JsonBuilder, JsonError> {
-    let mut js = JsonBuilder::try_new_object()?;
+fn log_request_object(tx: &PgsqlTransaction, flags: u32) -> Result<JsonBuilder, JsonError> {
+    let mut jb = JsonBuilder::try_new_object()?;
+    let mut array_open = false;
+    for request in &tx.requests {
+        if request.is_extended_query() {
+            if let PgsqlFEMessage::Sync(_) | PgsqlFEMessage::Flush(_) = request {
+                // Nothing worth logging in these
+                continue;
+            }
+            if !array_open {
+                jb.open_array("extended_query")?;
+                array_open = true;
+            }
+            jb.append_object(&log_extended_query_message(request)?)?;
+        } else {
+            if array_open {
+                jb.close()?;
+                array_open = false;
+            }
+            log_request(request, flags, &mut jb)?;
+        }
+    }
+    jb.close()?;
+    Ok(jb)
+}
+
+fn log_request(req: &PgsqlFEMessage, flags: u32, js: &mut JsonBuilder) -> Result<(), JsonError> {
     match req {
         PgsqlFEMessage::StartupMessage(StartupPacket {
             length: _,
@@ -108,6 +136,25 @@ fn log_request(req: &PgsqlFEMessage, flags: u32) -> Result<JsonBuilder, JsonErro
         }) => {
             js.set_string("message", req.to_str())?;
         }
+        PgsqlFEMessage::CopyFail(RegularPacket {
+            identifier: _,
+            length: _,
+            payload,
+        }) => {
+            js.set_string_from_bytes(req.to_str(), payload)?;
+        }
+        PgsqlFEMessage::CopyData(_) | PgsqlFEMessage::CopyDone(_) => {
+            // CopyData is accounted for in the transaction, not stored
+        }
+        PgsqlFEMessage::Parse(_)
+        | PgsqlFEMessage::Bind(_)
+        | PgsqlFEMessage::Describe(_)
+        | PgsqlFEMessage::Execute(_)
+        | PgsqlFEMessage::Close(_)
+        | PgsqlFEMessage::Sync(_)
+        | PgsqlFEMessage::Flush(_) => {
+            // We take care of these in log_extended_query_message
+        }
         PgsqlFEMessage::UnknownMessageType(RegularPacket {
             identifier: _,
             length: _,
@@ -116,8 +163,73 @@ fn log_request(req: &PgsqlFEMessage, flags: u32) -> Result<JsonBuilder, JsonErro
             // We don't want to log these, for now. Cf redmine: #6576
         }
     }
-    js.close()?;
-    Ok(js)
+    Ok(())
+}
+
+fn log_extended_query_message(req: &PgsqlFEMessage) -> Result<JsonBuilder, JsonError> {
+    let mut jb = JsonBuilder::try_new_object()?;
+    jb.set_string("message", req.to_str())?;
+    match req {
+        PgsqlFEMessage::Parse(ParseMessage {
+            identifier: _,
+            length: _,
+            statement_name,
+            query,
+            param_types,
+        }) => {
+            jb.set_string_from_bytes("statement_name", statement_name)?;
+            jb.set_string_from_bytes("query", query)?;
+            jb.set_uint("parameter_count", param_types.len() as u64)?;
+        }
+        PgsqlFEMessage::Bind(bind) => {
+            jb.set_string_from_bytes("portal_name", &bind.portal_name)?;
+            jb.set_string_from_bytes("statement_name", &bind.statement_name)?;
+            if let Some(query) = &bind.query {
+                jb.set_string_from_bytes("query", query)?;
+            }
+            if !bind.params.is_empty() {
+                jb.open_array("parameters")?;
+                for (index, param) in bind.params.iter().enumerate() {
+                    jb.start_object()?;
+                    if bind.param_format(index) == 1 {
+                        jb.set_string("format", "binary")?;
+                    } else {
+                        jb.set_string("format", "text")?;
+                    }
+                    if param.value_length < 0 {
+                        jb.set_bool("null", true)?;
+                    } else {
+                        jb.set_string_from_bytes_limited(
+                            "value",
+                            &param.value,
+                            PGSQL_LOG_PARAM_MAX_LEN,
+                        )?;
+                    }
+                    jb.close()?;
+                }
+                jb.close()?;
+            }
+        }
+        PgsqlFEMessage::Describe(msg) | PgsqlFEMessage::Close(msg) => {
+            if msg.is_portal() {
+                jb.set_string_from_bytes("portal_name", &msg.name)?;
+            } else {
+                jb.set_string_from_bytes("statement_name", &msg.name)?;
+            }
+        }
+        PgsqlFEMessage::Execute(ExecuteMessage {
+            identifier: _,
+            length: _,
+            portal_name,
+            max_rows,
+        }) => {
+            jb.set_string_from_bytes("portal_name", portal_name)?;
+            jb.set_uint("max_rows", (*max_rows).into())?;
+        }
+        _ => {}
+    }
+    jb.close()?;
+    Ok(jb)
 }
 
 fn log_response_object(tx: &PgsqlTransaction) -> Result<JsonBuilder, JsonError> {
@@ -138,6 +250,61 @@ fn log_response_object(tx: &PgsqlTransaction) -> Result<JsonBuilder, JsonError>
             log_response(response, &mut jb)?;
         }
     }
+    if array_open {
+        jb.close()?;
+    }
+    if tx.responses.iter().any(|r| r.is_extended_query()) {
+        jb.open_array("extended_query")?;
+        for response in tx.responses.iter().filter(|r| r.is_extended_query()) {
+            jb.append_object(&log_extended_query_response(response)?)?;
+        }
+        jb.close()?;
+    }
+    if let Some(copy_response) = tx.get_copy_response() {
+        jb.set_object("copy", &log_copy(tx, copy_response)?)?;
+    }
+    jb.close()?;
+    Ok(jb)
+}
+
+fn log_extended_query_response(res: &PgsqlBEMessage) -> Result<JsonBuilder, JsonError> {
+    let mut jb = JsonBuilder::try_new_object()?;
+    jb.set_string("message", res.to_str())?;
+    if let PgsqlBEMessage::ParameterDescription(msg) = res {
+        jb.set_uint("parameter_count", msg.param_count.into())?;
+    }
+    jb.close()?;
+    Ok(jb)
+}
+
+fn log_copy(tx: &PgsqlTransaction, res: &PgsqlBEMessage) -> Result<JsonBuilder, JsonError> {
+    let mut jb = JsonBuilder::try_new_object()?;
+    let msg = match res {
+        PgsqlBEMessage::CopyInResponse(msg) => {
+            jb.set_string("direction", "in")?;
+            msg
+        }
+        PgsqlBEMessage::CopyOutResponse(msg) => {
+            jb.set_string("direction", "out")?;
+            msg
+        }
+        PgsqlBEMessage::CopyBothResponse(msg) => {
+            jb.set_string("direction", "both")?;
+            msg
+        }
+        _ => {
+            jb.close()?;
+            return Ok(jb);
+        }
+    };
+    if msg.format == 1 {
+        jb.set_string("format", "binary")?;
+    } else {
+        jb.set_string("format", "text")?;
+    }
+    jb.set_uint("column_count", msg.column_count.into())?;
+    jb.set_uint("rows", tx.copy_row_cnt)?;
+    jb.set_uint("data_size", tx.copy_data_size)?;
     jb.close()?;
     Ok(jb)
 }
@@ -240,6 +407,22 @@ fn log_response(res: &PgsqlBEMessage, jb: &mut JsonBuilder) -> Result<(), JsonEr
             jb.set_uint("data_rows", *row_cnt)?;
             jb.set_uint("data_size", *data_size)?;
         }
+        PgsqlBEMessage::ParseComplete(_)
+        | PgsqlBEMessage::BindComplete(_)
+        | PgsqlBEMessage::CloseComplete(_)
+        | PgsqlBEMessage::NoData(_)
+        | PgsqlBEMessage::PortalSuspended(_)
+        | PgsqlBEMessage::ParameterDescription(_) => {
+            // We take care of these in log_extended_query_response
+        }
+        PgsqlBEMessage::CopyInResponse(_)
+        | PgsqlBEMessage::CopyOutResponse(_)
+        | PgsqlBEMessage::CopyBothResponse(_) => {
+            // We take care of these in log_copy
+        }
+        PgsqlBEMessage::CopyData(_) | PgsqlBEMessage::CopyDone(_) => {
+            // CopyData is accounted for in the transaction, not stored
+        }
         PgsqlBEMessage::NotificationResponse(NotificationResponse {
             identifier: _,
             length: _,
@@ -25,11 +25,12 @@ use nom7::bytes::streaming::{tag, take, take_until, take_until1};
 use nom7::character::streaming::{alphanumeric1, char};
 use nom7::combinator::{all_consuming, cond, eof, map_parser, opt, peek, verify};
 use nom7::error::{make_error, ErrorKind};
-use nom7::multi::{many1, many_m_n, many_till};
+use nom7::multi::{count, many1, many_m_n, many_till};
 use nom7::number::streaming::{be_i16, be_i32};
 use nom7::number::streaming::{be_u16, be_u32, be_u8};
 use nom7::sequence::{terminated, tuple};
 use nom7::{Err, IResult};
+use suricata_derive::EnumStringU32;
 
 pub const PGSQL_LENGTH_FIELD: u32 = 4;
 
@@ -250,10 +251,34 @@ pub enum PgsqlBEMessage {
     RowDescription(RowDescriptionMessage),
     ConsolidatedDataRow(ConsolidatedDataRowPacket),
     NotificationResponse(NotificationResponse),
+    ParseComplete(NoPayloadMessage),
+    BindComplete(NoPayloadMessage),
+    CloseComplete(NoPayloadMessage),
+    NoData(NoPayloadMessage),
+    PortalSuspended(NoPayloadMessage),
+    ParameterDescription(ParameterDescriptionMessage),
+    CopyInResponse(CopyResponseMessage),
+    CopyOutResponse(CopyResponseMessage),
+    CopyBothResponse(CopyResponseMessage),
+    CopyData(RegularPacket),
+    CopyDone(NoPayloadMessage),
     UnknownMessageType(RegularPacket),
 }
 
 impl PgsqlBEMessage {
+    /// Whether this message acknowledges a message of the Extended Query sub-protocol
+    pub fn is_extended_query(&self) -> bool {
+        matches!(
+            self,
+            PgsqlBEMessage::ParseComplete(_)
+                | PgsqlBEMessage::BindComplete(_)
+                | PgsqlBEMessage::CloseComplete(_)
+                | PgsqlBEMessage::NoData(_)
+                | PgsqlBEMessage::PortalSuspended(_)
+                | PgsqlBEMessage::ParameterDescription(_)
+        )
+    }
+
     pub fn to_str(&self) -> &'static str {
         match self {
             PgsqlBEMessage::SSLResponse(SSLResponseMessage::SSLAccepted) => "ssl_accepted",
@@ -279,10 +304,34 @@ impl PgsqlBEMessage {
             }
             PgsqlBEMessage::ConsolidatedDataRow(_) => "data_row",
             PgsqlBEMessage::NotificationResponse(_) => "notification_response",
+            PgsqlBEMessage::ParseComplete(_) => "parse_complete",
+            PgsqlBEMessage::BindComplete(_) => "bind_complete",
+            PgsqlBEMessage::CloseComplete(_) => "close_complete",
+            PgsqlBEMessage::NoData(_) => "no_data",
+            PgsqlBEMessage::PortalSuspended(_) => "portal_suspended",
+            PgsqlBEMessage::ParameterDescription(_) => "parameter_description",
+            PgsqlBEMessage::CopyInResponse(_) => "copy_in_response",
+            PgsqlBEMessage::CopyOutResponse(_) => "copy_out_response",
+            PgsqlBEMessage::CopyBothResponse(_) => "copy_both_response",
+            PgsqlBEMessage::CopyData(_) => "copy_data",
+            PgsqlBEMessage::CopyDone(_) => "copy_done",
             PgsqlBEMessage::UnknownMessageType(_) => "unknown_message_type",
         }
     }
 
+    pub fn get_auth_type(&self) -> Option<u32> {
+        match self {
+            PgsqlBEMessage::AuthenticationOk(message)
+            | PgsqlBEMessage::AuthenticationCleartextPassword(message)
+            | PgsqlBEMessage::AuthenticationMD5Password(message)
+            | PgsqlBEMessage::AuthenticationSSPI(message)
+            | PgsqlBEMessage::AuthenticationSASLContinue(message)
+            | PgsqlBEMessage::AuthenticationSASLFinal(message) => Some(message.auth_type),
+            PgsqlBEMessage::AuthenticationSASL(message) => Some(message.auth_type),
+            _ => None,
+        }
+    }
+
     pub fn get_backendkey_info(&self) -> (u32, u32) {
         match self {
             PgsqlBEMessage::BackendKeyData(message) => {
@@ -321,6 +370,96 @@ pub struct CancelRequestMessage {
     pub backend_key: u32,
 }
 
+/// Messages with no content other than their identifier and length (e.g. Sync, ParseComplete)
+#[derive(Debug, PartialEq, Eq)]
+pub struct NoPayloadMessage {
+    pub identifier: u8,
+    pub length: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMessage {
+    pub identifier: u8,
+    pub length: u32,
+    // An empty name selects the unnamed prepared statement
+    pub statement_name: Vec<u8>,
+    pub query: Vec<u8>,
+    pub param_types: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BindMessage {
+    pub identifier: u8,
+    pub length: u32,
+    pub portal_name: Vec<u8>,
+    pub statement_name: Vec<u8>,
+    // "The parameter format codes. Each must presently be zero (text) or one (binary)."
+    pub param_formats: Vec<u16>,
+    // A value_length of -1 indicates a NULL parameter value
+    pub params: Vec<ColumnFieldValue>,
+    pub result_formats: Vec<u16>,
+    // Not part of the message: the text of the prepared statement being bound,
+    // if the parser saw it being prepared. Filled in by the state.
+    pub query: Option<Vec<u8>>,
+}
+
+impl BindMessage {
+    /// Get the format code that applies to the parameter at `index`
+    ///
+    /// No format codes means all parameters are text, a single one applies
+    /// to all parameters.
+    pub fn param_format(&self, index: usize) -> u16 {
+        match self.param_formats.len() {
+            0 => 0,
+            1 => self.param_formats[0],
+            _ => self.param_formats.get(index).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// Describe and Close messages, which target either a prepared statement or a portal
+#[derive(Debug, PartialEq, Eq)]
+pub struct StatementPortalMessage {
+    pub identifier: u8,
+    pub length: u32,
+    // 'S' for a prepared statement, 'P' for a portal
+    pub object_type: u8,
+    pub name: Vec<u8>,
+}
+
+impl StatementPortalMessage {
+    pub fn is_portal(&self) -> bool {
+        self.object_type == b'P'
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExecuteMessage {
+    pub identifier: u8,
+    pub length: u32,
+    pub portal_name: Vec<u8>,
+    // Zero means "no limit"
+    pub max_rows: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParameterDescriptionMessage {
+    pub identifier: u8,
+    pub length: u32,
+    pub param_count: u16,
+    pub param_types: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CopyResponseMessage {
+    pub identifier: u8,
+    pub length: u32,
+    // 0 for textual, 1 for binary
+    pub format: u8,
+    pub column_count: u16,
+    pub column_formats: Vec<u16>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PgsqlFEMessage {
     SSLRequest(DummyStartupPacket),
@@ -329,6 +468,16 @@ pub enum PgsqlFEMessage {
     SASLInitialResponse(SASLInitialResponsePacket),
     SASLResponse(RegularPacket),
     SimpleQuery(RegularPacket),
+    Parse(ParseMessage),
+    Bind(BindMessage),
+    Describe(StatementPortalMessage),
+    Execute(ExecuteMessage),
+    Close(StatementPortalMessage),
+    Sync(NoPayloadMessage),
+    Flush(NoPayloadMessage),
+    CopyData(RegularPacket),
+    CopyDone(NoPayloadMessage),
+    CopyFail(RegularPacket),
     CancelRequest(CancelRequestMessage),
     Terminate(TerminationMessage),
     UnknownMessageType(RegularPacket),
@@ -343,11 +492,51 @@ impl PgsqlFEMessage {
             PgsqlFEMessage::SASLInitialResponse(_) => "sasl_initial_response",
             PgsqlFEMessage::SASLResponse(_) => "sasl_response",
             PgsqlFEMessage::SimpleQuery(_) => "simple_query",
+            PgsqlFEMessage::Parse(_) => "parse",
+            PgsqlFEMessage::Bind(_) => "bind",
+            PgsqlFEMessage::Describe(_) => "describe",
+            PgsqlFEMessage::Execute(_) => "execute",
+            PgsqlFEMessage::Close(_) => "close",
+            PgsqlFEMessage::Sync(_) => "sync",
+            PgsqlFEMessage::Flush(_) => "flush",
+            PgsqlFEMessage::CopyData(_) => "copy_data",
+            PgsqlFEMessage::CopyDone(_) => "copy_done",
+            PgsqlFEMessage::CopyFail(_) => "copy_fail",
             PgsqlFEMessage::CancelRequest(_) => "cancel_request",
             PgsqlFEMessage::Terminate(_) => "termination_message",
             PgsqlFEMessage::UnknownMessageType(_) => "unknown_message_type",
         }
     }
+
+    /// Whether this message is part of the Extended Query sub-protocol
+    pub fn is_extended_query(&self) -> bool {
+        matches!(
+            self,
+            PgsqlFEMessage::Parse(_)
+                | PgsqlFEMessage::Bind(_)
+                | PgsqlFEMessage::Describe(_)
+                | PgsqlFEMessage::Execute(_)
+                | PgsqlFEMessage::Close(_)
+                | PgsqlFEMessage::Sync(_)
+                | PgsqlFEMessage::Flush(_)
+        )
+    }
+}
+
+/// Authentication request codes, as sent by the backend
+#[derive(Clone, Debug, EnumStringU32)]
+#[repr(u32)]
+pub enum PgsqlAuthMethod {
+    Ok = 0,
+    KerberosV5 = 2,
+    CleartextPassword = 3,
+    Md5Password = 5,
+    Gss = 7,
+    GssContinue = 8,
+    Sspi = 9,
+    Sasl = 10,
+    SaslContinue = 11,
+    SaslFinal = 12,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -700,6 +889,147 @@ fn parse_simple_query(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
     ))
 }
 
+fn parse_parse_message(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'P')(i)?;
+    // length (u32) + two empty str fields + param count (u16)
+    let (i, length) = verify(be_u32, |&x| x >= 8)(i)?;
+    let (i, message) = map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+        let (b, statement_name) = take_until_and_consume(b"\x00")(b)?;
+        let (b, query) = take_until_and_consume(b"\x00")(b)?;
+        let (b, param_count) = be_u16(b)?;
+        let (b, param_types) = count(be_u32, param_count.into())(b)?;
+        Ok((
+            b,
+            ParseMessage {
+                identifier,
+                length,
+                statement_name: statement_name.to_vec(),
+                query: query.to_vec(),
+                param_types,
+            },
+        ))
+    })(i)?;
+    Ok((i, PgsqlFEMessage::Parse(message)))
+}
+
+fn parse_bind_message(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'B')(i)?;
+    // length (u32) + two empty str fields + three counters (u16)
+    let (i, length) = verify(be_u32, |&x| x >= 12)(i)?;
+    let (i, message) = map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+        let (b, portal_name) = take_until_and_consume(b"\x00")(b)?;
+        let (b, statement_name) = take_until_and_consume(b"\x00")(b)?;
+        let (b, format_count) = be_u16(b)?;
+        let (b, param_formats) = count(be_u16, format_count.into())(b)?;
+        let (b, param_count) = be_u16(b)?;
+        // parameter values are encoded just like DataRow column values
+        let (b, params) = count(parse_data_row_value, param_count.into())(b)?;
+        let (b, result_format_count) = be_u16(b)?;
+        let (b, result_formats) = count(be_u16, result_format_count.into())(b)?;
+        Ok((
+            b,
+            BindMessage {
+                identifier,
+                length,
+                portal_name: portal_name.to_vec(),
+                statement_name: statement_name.to_vec(),
+                param_formats,
+                params,
+                result_formats,
+                query: None,
+            },
+        ))
+    })(i)?;
+    Ok((i, PgsqlFEMessage::Bind(message)))
+}
+
+fn parse_statement_portal_message(i: &[u8]) -> IResult<&[u8], StatementPortalMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'D' || x == b'C')(i)?;
+    // length (u32) + object type (u8) + empty str field
+    let (i, length) = verify(be_u32, |&x| x >= 6)(i)?;
+    let (i, (object_type, name)) = map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+        let (b, object_type) = verify(be_u8, |&x| x == b'S' || x == b'P')(b)?;
+        let (b, name) = take_until_and_consume(b"\x00")(b)?;
+        Ok((b, (object_type, name)))
+    })(i)?;
+    Ok((
+        i,
+        StatementPortalMessage {
+            identifier,
+            length,
+            object_type,
+            name: name.to_vec(),
+        },
+    ))
+}
+
+fn parse_describe_message(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, message) = parse_statement_portal_message(i)?;
+    Ok((i, PgsqlFEMessage::Describe(message)))
+}
+
+fn parse_close_message(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, message) = parse_statement_portal_message(i)?;
+    Ok((i, PgsqlFEMessage::Close(message)))
+}
+
+fn parse_execute_message(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'E')(i)?;
+    // length (u32) + empty str field + max rows (u32)
+    let (i, length) = verify(be_u32, |&x| x >= 9)(i)?;
+    let (i, (portal_name, max_rows)) = map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+        let (b, portal_name) = take_until_and_consume(b"\x00")(b)?;
+        let (b, max_rows) = be_u32(b)?;
+        Ok((b, (portal_name, max_rows)))
+    })(i)?;
+    Ok((
+        i,
+        PgsqlFEMessage::Execute(ExecuteMessage {
+            identifier,
+            length,
+            portal_name: portal_name.to_vec(),
+            max_rows,
+        }),
+    ))
+}
+
+fn parse_no_payload_message(i: &[u8]) -> IResult<&[u8], NoPayloadMessage> {
+    let (i, identifier) = be_u8(i)?;
+    let (i, length) = verify(be_u32, |&x| x == PGSQL_LENGTH_FIELD)(i)?;
+    Ok((i, NoPayloadMessage { identifier, length }))
+}
+
+fn parse_copy_data(i: &[u8]) -> IResult<&[u8], RegularPacket> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'd')(i)?;
+    let (i, length) = parse_length(i)?;
+    let (i, data) = take(length - PGSQL_LENGTH_FIELD)(i)?;
+    Ok((
+        i,
+        RegularPacket {
+            identifier,
+            length,
+            payload: data.to_vec(),
+        },
+    ))
+}
+
+fn parse_copy_fail(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'f')(i)?;
+    let (i, length) = verify(be_u32, |&x| x > PGSQL_LENGTH_FIELD)(i)?;
+    let (i, message) = map_parser(
+        take(length - PGSQL_LENGTH_FIELD),
+        take_until_and_consume(b"\x00"),
+    )(i)?;
+    Ok((
+        i,
+        PgsqlFEMessage::CopyFail(RegularPacket {
+            identifier,
+            length,
+            payload: message.to_vec(),
+        }),
+    ))
+}
+
 fn parse_cancel_request(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
     let (i, pid) = be_u32(i)?;
     let (i, backend_key) = be_u32(i)?;
@@ -724,6 +1054,28 @@ pub fn parse_request(i: &[u8]) -> IResult<&[u8], PgsqlFEMessage> {
     let (i, message) = match tag {
         b'\0' => pgsql_parse_startup_packet(i)?,
         b'Q' => parse_simple_query(i)?,
+        b'P' => parse_parse_message(i)?,
+        b'B' => parse_bind_message(i)?,
+        b'D' => parse_describe_message(i)?,
+        b'E' => parse_execute_message(i)?,
+        b'C' => parse_close_message(i)?,
+        b'S' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlFEMessage::Sync(message))
+        }
+        b'H' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlFEMessage::Flush(message))
+        }
+        b'd' => {
+            let (i, message) = parse_copy_data(i)?;
+            (i, PgsqlFEMessage::CopyData(message))
+        }
+        b'c' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlFEMessage::CopyDone(message))
+        }
+        b'f' => parse_copy_fail(i)?,
         b'X' => parse_terminate_message(i)?,
         _ => {
             let (i, identifier) = be_u8(i)?;
@@ -1136,6 +1488,50 @@ fn parse_notification_response(i: &[u8]) -> IResult<&[u8], PgsqlBEMessage> {
     Ok((i, msg))
 }
 
+fn parse_parameter_description(i: &[u8]) -> IResult<&[u8], PgsqlBEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b't')(i)?;
+    let (i, length) = verify(be_u32, |&x| x >= 6)(i)?;
+    let (i, (param_count, param_types)) = map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+        let (b, param_count) = be_u16(b)?;
+        let (b, param_types) = count(be_u32, param_count.into())(b)?;
+        Ok((b, (param_count, param_types)))
+    })(i)?;
+    Ok((
+        i,
+        PgsqlBEMessage::ParameterDescription(ParameterDescriptionMessage {
+            identifier,
+            length,
+            param_count,
+            param_types,
+        }),
+    ))
+}
+
+fn parse_copy_response(i: &[u8]) -> IResult<&[u8], PgsqlBEMessage> {
+    let (i, identifier) = verify(be_u8, |&x| x == b'G' || x == b'H' || x == b'W')(i)?;
+    // length (u32) + format (u8) + column count (u16)
+    let (i, length) = verify(be_u32, |&x| x >= 7)(i)?;
+    let (i, (format, column_count, column_formats)) =
+        map_parser(take(length - PGSQL_LENGTH_FIELD), |b| {
+            let (b, format) = verify(be_u8, |&x| x <= 1)(b)?;
+            let (b, column_count) = be_u16(b)?;
+            let (b, column_formats) = count(be_u16, column_count.into())(b)?;
+            Ok((b, (format, column_count, column_formats)))
+        })(i)?;
+    let message = CopyResponseMessage {
+        identifier,
+        length,
+        format,
+        column_count,
+        column_formats,
+    };
+    match identifier {
+        b'G' => Ok((i, PgsqlBEMessage::CopyInResponse(message))),
+        b'H' => Ok((i, PgsqlBEMessage::CopyOutResponse(message))),
+        _ => Ok((i, PgsqlBEMessage::CopyBothResponse(message))),
+    }
+}
+
 pub fn pgsql_parse_response(i: &[u8]) -> IResult<&[u8], PgsqlBEMessage> {
     let (i, pseudo_header) = peek(tuple((be_u8, be_u32)))(i)?;
     let (i, message) = match pseudo_header.0 {
@@ -1149,6 +1545,36 @@ pub fn pgsql_parse_response(i: &[u8]) -> IResult<&[u8], PgsqlBEMessage> {
         b'T' => parse_row_description(i)?,
         b'A' => parse_notification_response(i)?,
         b'D' => parse_consolidated_data_row(i)?,
+        b'1' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::ParseComplete(message))
+        }
+        b'2' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::BindComplete(message))
+        }
+        b'3' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::CloseComplete(message))
+        }
+        b'n' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::NoData(message))
+        }
+        b's' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::PortalSuspended(message))
+        }
+        b't' => parse_parameter_description(i)?,
+        b'G' | b'H' | b'W' => parse_copy_response(i)?,
+        b'd' => {
+            let (i, message) = parse_copy_data(i)?;
+            (i, PgsqlBEMessage::CopyData(message))
+        }
+        b'c' => {
+            let (i, message) = parse_no_payload_message(i)?;
+            (i, PgsqlBEMessage::CopyDone(message))
+        }
         _ => {
             let (i, identifier) = be_u8(i)?;
             let (i, length) = verify(be_u32, |&x| x > PGSQL_LENGTH_FIELD)(i)?;
@@ -2380,4 +2806,228 @@ mod tests {
 
         assert_eq!(ok_res, result);
     }
+
+    #[test]
+    fn test_parse_extended_query_request() {
+        // Parse: statement "s1", query "SELECT $1", one parameter of type int4 (23)
+        let buf: &[u8] = &[
+            0x50, 0x00, 0x00, 0x00, 0x17, 0x73, 0x31, 0x00, 0x53, 0x45, 0x4c, 0x45, 0x43, 0x54,
+            0x20, 0x24, 0x31, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x17,
+        ];
+        let ok_res = PgsqlFEMessage::Parse(ParseMessage {
+            identifier: b'P',
+            length: 23,
+            statement_name: br#"s1"#.to_vec(),
+            query: br#"SELECT $1"#.to_vec(),
+            param_types: vec![23],
+        });
+        let (rem, result) = parse_request(buf).unwrap();
+        assert_eq!(result, ok_res);
+        assert!(rem.is_empty());
+        assert!(result.is_extended_query());
+
+        let result = parse_request(&buf[0..buf.len() - 1]);
+        assert!(result.is_err());
+
+        // Bind: unnamed portal, statement "s1", one text format code,
+        // two parameters ("42" and NULL), no result format codes
+        let buf: &[u8] = &[
+            0x42, 0x00, 0x00, 0x00, 0x1a, 0x00, 0x73, 0x31, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00, 0x02, 0x34, 0x32, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
+        ];
+        let ok_res = PgsqlFEMessage::Bind(BindMessage {
+            identifier: b'B',
+            length: 26,
+            portal_name: Vec::new(),
+            statement_name: br#"s1"#.to_vec(),
+            param_formats: vec![0],
+            params: vec![
+                ColumnFieldValue {
+                    value_length: 2,
+                    value: br#"42"#.to_vec(),
+                },
+                ColumnFieldValue {
+                    value_length: -1,
+                    value: Vec::new(),
+                },
+            ],
+            result_formats: Vec::new(),
+            query: None,
+        });
+        let (rem, result) = parse_request(buf).unwrap();
+        assert_eq!(result, ok_res);
+        assert!(rem.is_empty());
+        if let PgsqlFEMessage::Bind(bind) = result {
+            assert_eq!(bind.param_format(1), 0);
+        }
+
+        // Describe the unnamed portal
+        let buf: &[u8] = &[0x44, 0x00, 0x00, 0x00, 0x06, 0x50, 0x00];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::Describe(StatementPortalMessage {
+                identifier: b'D',
+                length: 6,
+                object_type: b'P',
+                name: Vec::new(),
+            })
+        );
+
+        // Describe with an invalid object type
+        let buf: &[u8] = &[0x44, 0x00, 0x00, 0x00, 0x06, 0x58, 0x00];
+        assert!(parse_request(buf).is_err());
+
+        // Execute the unnamed portal, no row limit
+        let buf: &[u8] = &[0x45, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::Execute(ExecuteMessage {
+                identifier: b'E',
+                length: 9,
+                portal_name: Vec::new(),
+                max_rows: 0,
+            })
+        );
+
+        // Close statement "s1"
+        let buf: &[u8] = &[0x43, 0x00, 0x00, 0x00, 0x08, 0x53, 0x73, 0x31, 0x00];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::Close(StatementPortalMessage {
+                identifier: b'C',
+                length: 8,
+                object_type: b'S',
+                name: br#"s1"#.to_vec(),
+            })
+        );
+
+        // Sync
+        let buf: &[u8] = &[0x53, 0x00, 0x00, 0x00, 0x04];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::Sync(NoPayloadMessage {
+                identifier: b'S',
+                length: 4,
+            })
+        );
+
+        // Sync with a bogus length
+        let buf: &[u8] = &[0x53, 0x00, 0x00, 0x00, 0x05, 0x00];
+        assert!(parse_request(buf).is_err());
+    }
+
+    #[test]
+    fn test_parse_extended_query_response() {
+        let buf: &[u8] = &[0x31, 0x00, 0x00, 0x00, 0x04];
+        let (_rem, result) = pgsql_parse_response(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlBEMessage::ParseComplete(NoPayloadMessage {
+                identifier: b'1',
+                length: 4,
+            })
+        );
+
+        let buf: &[u8] = &[0x32, 0x00, 0x00, 0x00, 0x04];
+        let (_rem, result) = pgsql_parse_response(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlBEMessage::BindComplete(NoPayloadMessage {
+                identifier: b'2',
+                length: 4,
+            })
+        );
+
+        // ParameterDescription: one parameter of type int4 (23)
+        let buf: &[u8] = &[
+            0x74, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x17,
+        ];
+        let (_rem, result) = pgsql_parse_response(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlBEMessage::ParameterDescription(ParameterDescriptionMessage {
+                identifier: b't',
+                length: 10,
+                param_count: 1,
+                param_types: vec![23],
+            })
+        );
+
+        let result = pgsql_parse_response(&buf[0..9]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_copy_messages() {
+        // CopyOutResponse: text format, two text columns
+        let buf: &[u8] = &[
+            0x48, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let (rem, result) = pgsql_parse_response(buf).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(
+            result,
+            PgsqlBEMessage::CopyOutResponse(CopyResponseMessage {
+                identifier: b'H',
+                length: 11,
+                format: 0,
+                column_count: 2,
+                column_formats: vec![0, 0],
+            })
+        );
+
+        // CopyInResponse with an invalid format
+        let buf: &[u8] = &[0x47, 0x00, 0x00, 0x00, 0x07, 0x02, 0x00, 0x00];
+        assert!(pgsql_parse_response(buf).is_err());
+
+        // CopyData "1\tfoo\n", from the backend and from the frontend
+        let buf: &[u8] = &[
+            0x64, 0x00, 0x00, 0x00, 0x0a, 0x31, 0x09, 0x66, 0x6f, 0x6f, 0x0a,
+        ];
+        let copy_data = RegularPacket {
+            identifier: b'd',
+            length: 10,
+            payload: b"1\tfoo\n".to_vec(),
+        };
+        let (_rem, result) = pgsql_parse_response(buf).unwrap();
+        assert_eq!(result, PgsqlBEMessage::CopyData(copy_data));
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::CopyData(RegularPacket {
+                identifier: b'd',
+                length: 10,
+                payload: b"1\tfoo\n".to_vec(),
+            })
+        );
+        let result = parse_request(&buf[0..8]);
+        assert!(result.is_err());
+
+        // CopyDone
+        let buf: &[u8] = &[0x63, 0x00, 0x00, 0x00, 0x04];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::CopyDone(NoPayloadMessage {
+                identifier: b'c',
+                length: 4,
+            })
+        );
+
+        // CopyFail "oops"
+        let buf: &[u8] = &[0x66, 0x00, 0x00, 0x00, 0x09, 0x6f, 0x6f, 0x70, 0x73, 0x00];
+        let (_rem, result) = parse_request(buf).unwrap();
+        assert_eq!(
+            result,
+            PgsqlFEMessage::CopyFail(RegularPacket {
+                identifier: b'f',
+                length: 9,
+                payload: br#"oops"#.to_vec(),
+            })
+        );
+    }
 }
@@ -23,17 +23,41 @@ use super::parser::{self, ConsolidatedDataRowPacket, PgsqlBEMessage, PgsqlFEMess
 use crate::applayer::*;
 use crate::conf::*;
 use crate::core::{AppProto, Direction, Flow, ALPROTO_FAILED, ALPROTO_UNKNOWN, IPPROTO_TCP, *};
+use crate::filecontainer::*;
+use crate::filetracker::*;
 use nom7::{Err, IResult};
 use std;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::CString;
 
 pub const PGSQL_CONFIG_DEFAULT_STREAM_DEPTH: u32 = 0;
 
-static mut ALPROTO_PGSQL: AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_PGSQL: AppProto = ALPROTO_UNKNOWN;
 
 static mut PGSQL_MAX_TX: usize = 1024;
 
+/// Maximum number of named prepared statements whose text we keep track of
+static mut PGSQL_MAX_PREPARED_STATEMENTS: usize = 128;
+
+/// Maximum number of frontend messages in a transaction, e.g. the Parse, Bind,
+/// Describe and Execute messages of an Extended Query cycle not closed by a Sync
+static mut PGSQL_MAX_REQUESTS_PER_TX: usize = 256;
+
+/// Maximum length of the query text used to name COPY data files
+const PGSQL_COPY_FILE_NAME_MAX_LEN: usize = 256;
+
+pub static mut SURICATA_PGSQL_FILE_CONFIG: Option<&'static SuricataFileContext> = None;
+
+#[no_mangle]
+pub unsafe extern "C" fn SCPgsqlInit(context: &'static mut SuricataFileContext) {
+    SURICATA_PGSQL_FILE_CONFIG = Some(context);
+}
+
+#[derive(AppLayerEvent)]
+pub enum PgsqlEvent {
+    TooManyRequests,
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, PartialOrd, PartialEq, Eq, Debug)]
 pub enum PgsqlTxProgress {
@@ -48,12 +72,21 @@ pub struct PgsqlTransaction {
     pub tx_id: u64,
     pub tx_req_state: PgsqlTxProgress,
     pub tx_res_state: PgsqlTxProgress,
-    pub request: Option<PgsqlFEMessage>,
+    // Usually a single message, but an Extended Query cycle is made of
+    // several messages (e.g. Parse, Bind, Describe, Execute, Sync)
+    pub requests: Vec<PgsqlFEMessage>,
     pub responses: Vec<PgsqlBEMessage>,
 
     pub data_row_cnt: u64,
     pub data_size: u64,
 
+    // CopyData messages seen during a COPY, and their total payload size
+    pub copy_row_cnt: u64,
+    pub copy_data_size: u64,
+    // COPY FROM STDIN and COPY TO STDOUT data, respectively
+    ft_ts: FileTransferTracker,
+    ft_tc: FileTransferTracker,
+
     tx_data: AppLayerTxData,
 }
 
@@ -75,10 +108,14 @@ impl PgsqlTransaction {
             tx_id: 0,
             tx_req_state: PgsqlTxProgress::TxInit,
             tx_res_state: PgsqlTxProgress::TxInit,
-            request: None,
+            requests: Vec::<PgsqlFEMessage>::new(),
             responses: Vec::<PgsqlBEMessage>::new(),
             data_row_cnt: 0,
             data_size: 0,
+            copy_row_cnt: 0,
+            copy_data_size: 0,
+            ft_ts: FileTransferTracker::new(),
+            ft_tc: FileTransferTracker::new(),
             tx_data: AppLayerTxData::new(),
         }
     }
@@ -94,6 +131,94 @@ impl PgsqlTransaction {
     pub fn sum_data_size(&mut self, row_size: u64) {
         self.data_size += row_size;
     }
+
+    pub fn update_file_flags(&mut self, flow_file_flags: u16) {
+        self.ft_ts.file_flags = unsafe { FileFlowFlagsToFlags(flow_file_flags, STREAM_TOSERVER) };
+        self.ft_tc.file_flags = unsafe { FileFlowFlagsToFlags(flow_file_flags, STREAM_TOCLIENT) };
+    }
+
+    /// The COPY response from the backend, if this transaction is a COPY
+    pub fn get_copy_response(&self) -> Option<&PgsqlBEMessage> {
+        self.responses.iter().find(|response| {
+            matches!(
+                response,
+                PgsqlBEMessage::CopyInResponse(_)
+                    | PgsqlBEMessage::CopyOutResponse(_)
+                    | PgsqlBEMessage::CopyBothResponse(_)
+            )
+        })
+    }
+
+    /// Name COPY data files after the query that started the COPY, truncated
+    /// to `PGSQL_COPY_FILE_NAME_MAX_LEN`
+    fn copy_file_name(requests: &[PgsqlFEMessage]) -> &[u8] {
+        for request in requests {
+            let query = match request {
+                PgsqlFEMessage::SimpleQuery(query) => &query.payload,
+                PgsqlFEMessage::Parse(parse) => &parse.query,
+                _ => continue,
+            };
+            return &query[..std::cmp::min(query.len(), PGSQL_COPY_FILE_NAME_MAX_LEN)];
+        }
+        b"COPY"
+    }
+
+    /// Account for a CopyData message, and pass its payload on as file data
+    fn copy_data(&mut self, direction: Direction, data: &[u8]) {
+        self.copy_row_cnt = self.copy_row_cnt.saturating_add(1);
+        self.copy_data_size = self.copy_data_size.saturating_add(data.len() as u64);
+        if let Some(sfcm) = unsafe { SURICATA_PGSQL_FILE_CONFIG } {
+            let xid = self.tx_id as u32;
+            let name = Self::copy_file_name(&self.requests);
+            let ft = if direction == Direction::ToServer {
+                &mut self.ft_ts
+            } else {
+                &mut self.ft_tc
+            };
+            ft.tx_id = self.tx_id - 1;
+            if !ft.is_initialized() {
+                self.tx_data.incr_files_opened();
+            }
+            ft.new_chunk(
+                sfcm,
+                name,
+                data,
+                ft.tracked, //offset = append
+                data.len() as u32,
+                0,
+                false,
+                &xid,
+            );
+        }
+    }
+
+    /// Close the COPY data file, truncating it if the COPY didn't complete
+    fn copy_done(&mut self, direction: Direction, truncated: bool) {
+        if let Some(sfcm) = unsafe { SURICATA_PGSQL_FILE_CONFIG } {
+            let ft = if direction == Direction::ToServer {
+                &mut self.ft_ts
+            } else {
+                &mut self.ft_tc
+            };
+            if !ft.file_open {
+                return;
+            }
+            if truncated {
+                ft.trunc(sfcm);
+            } else {
+                ft.close(sfcm);
+            }
+        }
+    }
+}
+
+impl Drop for PgsqlTransaction {
+    fn drop(&mut self) {
+        if let Some(sfcm) = unsafe { SURICATA_PGSQL_FILE_CONFIG } {
+            self.ft_ts.file.free(sfcm);
+            self.ft_tc.file.free(sfcm);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -106,6 +231,13 @@ pub enum PgsqlStateProgress {
     SASLResponseReceived,
     PasswordMessageReceived,
     SimpleQueryReceived,
+    // First message of an Extended Query cycle
+    ExtendedQueryStarted,
+    // Further Extended Query messages, up until Sync
+    ExtendedQueryReceived,
+    SyncReceived,
+    // Sync outside of an Extended Query cycle, which still gets a ReadyForQuery back
+    LoneSyncReceived,
     CancelRequestReceived,
     ConnectionTerminated,
     // Related to Backend-received messages //
@@ -123,6 +255,14 @@ pub enum PgsqlStateProgress {
     DataRowReceived,
     CommandCompletedReceived,
     ErrorMessageReceived,
+    CopyInResponseReceived,
+    CopyOutResponseReceived,
+    CopyBothResponseReceived,
+    // CopyData sent by the frontend and by the backend, respectively
+    CopyDataInReceived,
+    CopyDataOutReceived,
+    CopyDoneReceived,
+    CopyFailReceived,
     #[cfg(test)]
     UnknownState,
     Finished,
@@ -139,6 +279,10 @@ pub struct PgsqlState {
     backend_pid: u32,
     state_progress: PgsqlStateProgress,
     tx_index_completed: usize,
+    // Prepared statement name -> query text, so that it can be logged along with a Bind
+    prepared_statements: HashMap<Vec<u8>, Vec<u8>>,
+    // Whether the frontend started an Extended Query cycle, and didn't close it with a Sync yet
+    extended_query: bool,
 }
 
 impl State<PgsqlTransaction> for PgsqlState {
@@ -169,6 +313,8 @@ impl PgsqlState {
             backend_pid: 0,
             state_progress: PgsqlStateProgress::IdleState,
             tx_index_completed: 0,
+            prepared_statements: HashMap::new(),
+            extended_query: false,
         }
     }
 
@@ -235,6 +381,8 @@ impl PgsqlState {
             || self.state_progress == PgsqlStateProgress::SASLInitialResponseReceived
             || self.state_progress == PgsqlStateProgress::SASLResponseReceived
             || self.state_progress == PgsqlStateProgress::SimpleQueryReceived
+            || self.state_progress == PgsqlStateProgress::ExtendedQueryStarted
+            || self.state_progress == PgsqlStateProgress::LoneSyncReceived
             || self.state_progress == PgsqlStateProgress::SSLRequestReceived
             || self.state_progress == PgsqlStateProgress::ConnectionTerminated
             || self.state_progress == PgsqlStateProgress::CancelRequestReceived
@@ -253,7 +401,9 @@ impl PgsqlState {
     /// is what helps us keep track of the PgsqlTransactions - when one finished
     /// when the other starts.
     /// State isn't directly updated to avoid reference borrowing conflicts.
-    fn request_next_state(request: &PgsqlFEMessage) -> Option<PgsqlStateProgress> {
+    fn request_next_state(
+        extended_query: bool, request: &PgsqlFEMessage,
+    ) -> Option<PgsqlStateProgress> {
         match request {
             PgsqlFEMessage::SSLRequest(_) => Some(PgsqlStateProgress::SSLRequestReceived),
             PgsqlFEMessage::StartupMessage(_) => Some(PgsqlStateProgress::StartupMessageReceived),
@@ -269,6 +419,29 @@ impl PgsqlState {
 
                 // Important to keep in mind that: "In simple Query mode, the format of retrieved values is always text, except when the given command is a FETCH from a cursor declared with the BINARY option. In that case, the retrieved values are in binary format. The format codes given in the RowDescription message tell which format is being used." (from pgsql official documentation)
             }
+            PgsqlFEMessage::Parse(_)
+            | PgsqlFEMessage::Bind(_)
+            | PgsqlFEMessage::Describe(_)
+            | PgsqlFEMessage::Execute(_)
+            | PgsqlFEMessage::Close(_)
+            | PgsqlFEMessage::Flush(_) => {
+                // All messages up until Sync belong to the same transaction
+                if extended_query {
+                    Some(PgsqlStateProgress::ExtendedQueryReceived)
+                } else {
+                    Some(PgsqlStateProgress::ExtendedQueryStarted)
+                }
+            }
+            PgsqlFEMessage::Sync(_) => {
+                if extended_query {
+                    Some(PgsqlStateProgress::SyncReceived)
+                } else {
+                    Some(PgsqlStateProgress::LoneSyncReceived)
+                }
+            }
+            PgsqlFEMessage::CopyData(_) => Some(PgsqlStateProgress::CopyDataInReceived),
+            PgsqlFEMessage::CopyDone(_) => Some(PgsqlStateProgress::CopyDoneReceived),
+            PgsqlFEMessage::CopyFail(_) => Some(PgsqlStateProgress::CopyFailReceived),
             PgsqlFEMessage::CancelRequest(_) => Some(PgsqlStateProgress::CancelRequestReceived),
             PgsqlFEMessage::Terminate(_) => {
                 SCLogDebug!("Match: Terminate message");
@@ -282,6 +455,29 @@ impl PgsqlState {
         }
     }
 
+    /// Keep track of named prepared statements, and resolve the query a Bind refers to
+    fn track_prepared_statement(&mut self, request: &mut PgsqlFEMessage) {
+        match request {
+            PgsqlFEMessage::Parse(parse) => {
+                if self.prepared_statements.len() < unsafe { PGSQL_MAX_PREPARED_STATEMENTS }
+                    || self.prepared_statements.contains_key(&parse.statement_name)
+                {
+                    self.prepared_statements
+                        .insert(parse.statement_name.clone(), parse.query.clone());
+                } else {
+                    SCLogDebug!("Too many prepared statements, not tracking new one");
+                }
+            }
+            PgsqlFEMessage::Bind(bind) => {
+                bind.query = self.prepared_statements.get(&bind.statement_name).cloned();
+            }
+            PgsqlFEMessage::Close(close) if !close.is_portal() => {
+                self.prepared_statements.remove(&close.name);
+            }
+            _ => {}
+        }
+    }
+
     fn state_based_req_parsing(
         state: PgsqlStateProgress, input: &[u8],
     ) -> IResult<&[u8], parser::PgsqlFEMessage> {
@@ -307,10 +503,14 @@ impl PgsqlState {
             PgsqlStateProgress::SSLRequestReceived
             | PgsqlStateProgress::StartupMessageReceived
             | PgsqlStateProgress::SimpleQueryReceived
+            | PgsqlStateProgress::SyncReceived
+            | PgsqlStateProgress::LoneSyncReceived
             | PgsqlStateProgress::PasswordMessageReceived
             | PgsqlStateProgress::SASLInitialResponseReceived
             | PgsqlStateProgress::SASLResponseReceived
             | PgsqlStateProgress::CancelRequestReceived
+            | PgsqlStateProgress::CopyDoneReceived
+            | PgsqlStateProgress::CopyFailReceived
             | PgsqlStateProgress::ConnectionTerminated => true,
             _ => false,
         }
@@ -343,12 +543,22 @@ impl PgsqlState {
                 &self.state_progress
             );
             match PgsqlState::state_based_req_parsing(self.state_progress, start) {
-                Ok((rem, request)) => {
+                Ok((rem, mut request)) => {
                     start = rem;
-                    let new_state = PgsqlState::request_next_state(&request);
+                    let new_state = PgsqlState::request_next_state(self.extended_query, &request);
+                    if request.is_extended_query() {
+                        self.track_prepared_statement(&mut request);
+                    }
 
                     if let Some(state) = new_state {
                         self.state_progress = state;
+                        // The backend may already be answering (e.g. if the frontend sent a
+                        // Flush), so the cycle is tracked apart from the state progress
+                        self.extended_query = matches!(
+                            state,
+                            PgsqlStateProgress::ExtendedQueryStarted
+                                | PgsqlStateProgress::ExtendedQueryReceived
+                        );
                     };
                     // PostreSQL progress states can be represented as a finite state machine
                     // After the connection phase, the backend/ server will be mostly waiting in a state of `ReadyForQuery`, unless
@@ -359,8 +569,23 @@ impl PgsqlState {
                     // Terminate request.
                     // A simplified finite state machine for PostgreSQL v3 can be found at:
                     // https://samadhiweb.com/blog/2013.04.28.graphviz.postgresv3.html
+                    let mut too_many_requests = false;
                     if let Some(tx) = self.find_or_create_tx() {
-                        tx.request = Some(request);
+                        match &request {
+                            PgsqlFEMessage::CopyData(msg) => {
+                                // Not stored, as that could easily become a burden, memory-wise
+                                tx.copy_data(Direction::ToServer, &msg.payload);
+                                continue;
+                            }
+                            PgsqlFEMessage::CopyDone(_) => {
+                                tx.copy_done(Direction::ToServer, false);
+                            }
+                            PgsqlFEMessage::CopyFail(_) => {
+                                tx.copy_done(Direction::ToServer, true);
+                            }
+                            _ => {}
+                        }
+                        tx.requests.push(request);
                         if let Some(state) = new_state {
                             if Self::request_is_complete(state) {
                                 // The request is always complete at this point
@@ -377,10 +602,24 @@ impl PgsqlState {
                                 );
                             }
                         }
+                        if tx.tx_req_state < PgsqlTxProgress::TxDone
+                            && tx.requests.len() >= unsafe { PGSQL_MAX_REQUESTS_PER_TX }
+                        {
+                            SCLogDebug!("Too many requests in transaction, closing it");
+                            tx.tx_data.set_event(PgsqlEvent::TooManyRequests as u8);
+                            tx.tx_req_state = PgsqlTxProgress::TxDone;
+                            tx.tx_res_state = PgsqlTxProgress::TxDone;
+                            too_many_requests = true;
+                        }
                     } else {
                         // If there isn't a new transaction, we'll consider Suri should move on
                         return AppLayerResult::ok();
                     };
+                    if too_many_requests {
+                        // As if the cycle was closed, so that its next messages go to a new transaction
+                        self.state_progress = PgsqlStateProgress::SyncReceived;
+                        self.extended_query = false;
+                    }
                 }
                 Err(Err::Incomplete(_needed)) => {
                     let consumed = input.len() - start.len();
@@ -458,6 +697,23 @@ impl PgsqlState {
                 Some(PgsqlStateProgress::CommandCompletedReceived)
             }
             PgsqlBEMessage::ErrorResponse(_) => Some(PgsqlStateProgress::ErrorMessageReceived),
+            PgsqlBEMessage::CopyInResponse(_) => Some(PgsqlStateProgress::CopyInResponseReceived),
+            PgsqlBEMessage::CopyOutResponse(_) => Some(PgsqlStateProgress::CopyOutResponseReceived),
+            PgsqlBEMessage::CopyBothResponse(_) => {
+                Some(PgsqlStateProgress::CopyBothResponseReceived)
+            }
+            PgsqlBEMessage::CopyData(_) => Some(PgsqlStateProgress::CopyDataOutReceived),
+            PgsqlBEMessage::CopyDone(_) => Some(PgsqlStateProgress::CopyDoneReceived),
+            PgsqlBEMessage::ParseComplete(_)
+            | PgsqlBEMessage::BindComplete(_)
+            | PgsqlBEMessage::CloseComplete(_)
+            | PgsqlBEMessage::NoData(_)
+            | PgsqlBEMessage::PortalSuspended(_)
+            | PgsqlBEMessage::ParameterDescription(_) => {
+                // Extended Query acknowledgements don't change the state, so that
+                // the frontend can keep sending messages of the same cycle
+                None
+            }
             _ => {
                 // We don't always have to change current state when we see a response...
                 None
@@ -491,6 +747,7 @@ impl PgsqlState {
     }
 
     fn parse_response(&mut self, flow: *const Flow, input: &[u8]) -> AppLayerResult {
+        let flow_file_flags = self.state_data.file_flags;
         // We're not interested in empty responses.
         if input.is_empty() {
             return AppLayerResult::ok();
@@ -524,6 +781,11 @@ impl PgsqlState {
                         if let Some(state) = new_state {
                             if state == PgsqlStateProgress::DataRowReceived {
                                 tx.incr_row_cnt();
+                            } else if state == PgsqlStateProgress::CopyDataOutReceived {
+                                if let PgsqlBEMessage::CopyData(msg) = &response {
+                                    // Not stored, just like DataRow messages
+                                    tx.copy_data(Direction::ToClient, &msg.payload);
+                                }
                             } else if state == PgsqlStateProgress::CommandCompletedReceived
                                 && tx.get_row_cnt() > 0
                             {
@@ -538,6 +800,33 @@ impl PgsqlState {
                                 tx.responses.push(dummy_resp);
                                 tx.responses.push(response);
                             } else {
+                                match state {
+                                    PgsqlStateProgress::CopyInResponseReceived
+                                    | PgsqlStateProgress::CopyOutResponseReceived
+                                    | PgsqlStateProgress::CopyBothResponseReceived => {
+                                        tx.tx_data.file_tx = match state {
+                                            PgsqlStateProgress::CopyInResponseReceived => {
+                                                STREAM_TOSERVER
+                                            }
+                                            PgsqlStateProgress::CopyOutResponseReceived => {
+                                                STREAM_TOCLIENT
+                                            }
+                                            _ => STREAM_TOSERVER | STREAM_TOCLIENT,
+                                        };
+                                        tx.tx_data.update_file_flags(flow_file_flags);
+                                        tx.update_file_flags(tx.tx_data.file_flags);
+                                    }
+                                    PgsqlStateProgress::CopyDoneReceived => {
+                                        tx.copy_done(Direction::ToClient, false);
+                                    }
+                                    PgsqlStateProgress::ErrorMessageReceived
+                                    | PgsqlStateProgress::ReadyForQueryReceived => {
+                                        // A COPY that didn't see its CopyDone was aborted
+                                        tx.copy_done(Direction::ToServer, true);
+                                        tx.copy_done(Direction::ToClient, true);
+                                    }
+                                    _ => {}
+                                }
                                 tx.responses.push(response);
                                 if Self::response_is_complete(state) {
                                     tx.tx_req_state = PgsqlTxProgress::TxDone;
@@ -548,6 +837,11 @@ impl PgsqlState {
                                     );
                                 }
                             }
+                        } else if response.is_extended_query()
+                            && tx.responses.len() < unsafe { PGSQL_MAX_REQUESTS_PER_TX }
+                        {
+                            // Extended Query acknowledgements, at most one per request
+                            tx.responses.push(response);
                         }
                     } else {
                         // If there isn't a new transaction, we'll consider Suri should move on
@@ -626,7 +920,11 @@ pub unsafe extern "C" fn SCPgsqlProbingParserTS(
 
         match parser::parse_request(slice) {
             Ok((_, request)) => {
-                if let PgsqlFEMessage::UnknownMessageType(_) = request {
+                if let PgsqlFEMessage::UnknownMessageType(_)
+                | PgsqlFEMessage::CopyData(_)
+                | PgsqlFEMessage::CopyDone(_)
+                | PgsqlFEMessage::CopyFail(_) = request
+                {
                     return ALPROTO_FAILED;
                 }
                 return ALPROTO_PGSQL;
@@ -656,7 +954,10 @@ pub unsafe extern "C" fn SCPgsqlProbingParserTC(
 
         match parser::pgsql_parse_response(slice) {
             Ok((_, response)) => {
-                if let PgsqlBEMessage::UnknownMessageType(_) = response {
+                if let PgsqlBEMessage::UnknownMessageType(_)
+                | PgsqlBEMessage::CopyData(_)
+                | PgsqlBEMessage::CopyDone(_) = response
+                {
                     return ALPROTO_FAILED;
                 }
                 return ALPROTO_PGSQL;
@@ -767,6 +1068,27 @@ pub extern "C" fn SCPgsqlStateGetTxCount(state: *mut std::os::raw::c_void) -> u6
     return state_safe.tx_id;
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn SCPgsqlGetTxFiles(
+    tx: *mut std::os::raw::c_void, direction: u8,
+) -> AppLayerGetFileState {
+    let tx = cast_pointer!(tx, PgsqlTransaction);
+    if let Some(sfcm) = { SURICATA_PGSQL_FILE_CONFIG } {
+        if direction & STREAM_TOSERVER != 0 {
+            return AppLayerGetFileState {
+                fc: &mut tx.ft_ts.file,
+                cfg: sfcm.files_sbcfg,
+            };
+        } else {
+            return AppLayerGetFileState {
+                fc: &mut tx.ft_tc.file,
+                cfg: sfcm.files_sbcfg,
+            };
+        }
+    }
+    AppLayerGetFileState::err()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn SCPgsqlTxGetALStateProgress(
     tx: *mut std::os::raw::c_void, direction: u8,
@@ -807,11 +1129,11 @@ pub unsafe extern "C" fn SCRegisterPgsqlParser() {
         tx_comp_st_ts: PgsqlTxProgress::TxDone as i32,
         tx_comp_st_tc: PgsqlTxProgress::TxDone as i32,
         tx_get_progress: SCPgsqlTxGetALStateProgress,
-        get_eventinfo: None,
-        get_eventinfo_byid: None,
+        get_eventinfo: Some(PgsqlEvent::get_event_info),
+        get_eventinfo_byid: Some(PgsqlEvent::get_event_info_by_id),
         localstorage_new: None,
         localstorage_free: None,
-        get_tx_files: None,
+        get_tx_files: Some(SCPgsqlGetTxFiles),
         get_tx_iterator: Some(
             crate::applayer::state_get_tx_iterator::<PgsqlState, PgsqlTransaction>,
         ),
@@ -851,6 +1173,20 @@ pub unsafe extern "C" fn SCRegisterPgsqlParser() {
                 SCLogError!("Invalid value for pgsql.max-tx");
             }
         }
+        if let Some(val) = conf_get("app-layer.protocols.pgsql.max-prepared-statements") {
+            if let Ok(v) = val.parse::<usize>() {
+                PGSQL_MAX_PREPARED_STATEMENTS = v;
+            } else {
+                SCLogError!("Invalid value for pgsql.max-prepared-statements");
+            }
+        }
+        if let Some(val) = conf_get("app-layer.protocols.pgsql.max-requests-per-tx") {
+            if let Ok(v) = val.parse::<usize>() {
+                PGSQL_MAX_REQUESTS_PER_TX = v;
+            } else {
+                SCLogError!("Invalid value for pgsql.max-requests-per-tx");
+            }
+        }
     } else {
         SCLogDebug!("Protocol detector and parser disabled for PGSQL.");
     }
@@ -967,4 +1303,218 @@ mod test {
         tx.incr_row_cnt();
         assert_eq!(tx.get_row_cnt(), 1);
     }
+
+    #[test]
+    fn test_extended_query_tx() {
+        let mut state = PgsqlState::new();
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+        // Parse "s1" (SELECT $1), Bind, Describe portal, Execute, Sync
+        let buf: &[u8] = &[
+            0x50, 0x00, 0x00, 0x00, 0x17, 0x73, 0x31, 0x00, 0x53, 0x45, 0x4c, 0x45, 0x43, 0x54,
+            0x20, 0x24, 0x31, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x17, 0x42, 0x00, 0x00, 0x00,
+            0x1a, 0x00, 0x73, 0x31, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x02, 0x34, 0x32, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x44, 0x00, 0x00, 0x00, 0x06,
+            0x50, 0x00, 0x45, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x53, 0x00,
+            0x00, 0x00, 0x04,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.state_progress, PgsqlStateProgress::SyncReceived);
+        assert_eq!(state.transactions.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.requests.len(), 5);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+        assert_eq!(tx.tx_res_state, PgsqlTxProgress::TxInit);
+        if let PgsqlFEMessage::Bind(bind) = &tx.requests[1] {
+            assert_eq!(bind.query, Some(br#"SELECT $1"#.to_vec()));
+        } else {
+            panic!("Expected a Bind message");
+        }
+
+        // The backend answered with ReadyForQuery
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+
+        // Bind "s1" again, Execute, Sync: a new transaction, still aware of the query
+        let buf: &[u8] = &[
+            0x42, 0x00, 0x00, 0x00, 0x1a, 0x00, 0x73, 0x31, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00, 0x02, 0x34, 0x32, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x45,
+            0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x53, 0x00, 0x00, 0x00, 0x04,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 2);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.requests.len(), 3);
+        if let PgsqlFEMessage::Bind(bind) = &tx.requests[0] {
+            assert_eq!(bind.query, Some(br#"SELECT $1"#.to_vec()));
+        } else {
+            panic!("Expected a Bind message");
+        }
+    }
+
+    #[test]
+    fn test_lone_sync_tx() {
+        let mut state = PgsqlState::new();
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+        // Sync
+        let buf: &[u8] = &[0x53, 0x00, 0x00, 0x00, 0x04];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.state_progress, PgsqlStateProgress::LoneSyncReceived);
+        assert_eq!(state.transactions.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+
+        // Execute, Sync: a new transaction
+        let buf: &[u8] = &[
+            0x45, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x53, 0x00, 0x00, 0x00,
+            0x04,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.state_progress, PgsqlStateProgress::SyncReceived);
+        assert_eq!(state.transactions.len(), 2);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.requests.len(), 2);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+    }
+
+    #[test]
+    fn test_too_many_requests_tx() {
+        let mut state = PgsqlState::new();
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+        // Execute messages without a Sync, one more than a transaction can hold
+        let execute: &[u8] = &[0x45, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let max = unsafe { PGSQL_MAX_REQUESTS_PER_TX };
+        let buf = execute.repeat(max + 1);
+        let r = state.parse_request(std::ptr::null_mut(), &buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 2);
+        let tx = state.transactions.front().unwrap();
+        assert_eq!(tx.requests.len(), max);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+        assert_eq!(tx.tx_res_state, PgsqlTxProgress::TxDone);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.requests.len(), 1);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxInit);
+    }
+
+    #[test]
+    fn test_parse_after_simple_query() {
+        let mut state = PgsqlState::new();
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+        // SELECT 1
+        let buf: &[u8] = &[
+            0x51, 0x00, 0x00, 0x00, 0x0d, 0x53, 0x45, 0x4c, 0x45, 0x43, 0x54, 0x20, 0x31, 0x00,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        // CommandComplete, before the ReadyForQuery
+        let buf: &[u8] = &[
+            0x43, 0x00, 0x00, 0x00, 0x0d, 0x53, 0x45, 0x4c, 0x45, 0x43, 0x54, 0x20, 0x31, 0x00,
+        ];
+        let r = state.parse_response(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(
+            state.state_progress,
+            PgsqlStateProgress::CommandCompletedReceived
+        );
+
+        // Parse "SELECT 2", Sync: pipelined, but a transaction of its own
+        let buf: &[u8] = &[
+            0x50, 0x00, 0x00, 0x00, 0x10, 0x00, 0x53, 0x45, 0x4c, 0x45, 0x43, 0x54, 0x20, 0x32,
+            0x00, 0x00, 0x00, 0x53, 0x00, 0x00, 0x00, 0x04,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 2);
+        let tx = state.transactions.front().unwrap();
+        assert_eq!(tx.requests.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.requests.len(), 2);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+
+        // ParseComplete, ReadyForQuery
+        let buf: &[u8] = &[
+            0x31, 0x00, 0x00, 0x00, 0x04, 0x5a, 0x00, 0x00, 0x00, 0x05, 0x49,
+        ];
+        let r = state.parse_response(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.responses.len(), 2);
+        assert!(tx.responses[0].is_extended_query());
+        assert_eq!(tx.tx_res_state, PgsqlTxProgress::TxDone);
+    }
+
+    #[test]
+    fn test_copy_in_tx() {
+        let mut state = PgsqlState::new();
+        state.state_progress = PgsqlStateProgress::ReadyForQueryReceived;
+        // COPY t FROM STDIN
+        let buf: &[u8] = &[
+            0x51, 0x00, 0x00, 0x00, 0x16, 0x43, 0x4f, 0x50, 0x59, 0x20, 0x74, 0x20, 0x46, 0x52,
+            0x4f, 0x4d, 0x20, 0x53, 0x54, 0x44, 0x49, 0x4e, 0x00,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(
+            state.state_progress,
+            PgsqlStateProgress::SimpleQueryReceived
+        );
+
+        // CopyInResponse, text format, no columns
+        let buf: &[u8] = &[0x47, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00];
+        let r = state.parse_response(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(
+            state.state_progress,
+            PgsqlStateProgress::CopyInResponseReceived
+        );
+        assert_eq!(state.transactions.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert!(tx.get_copy_response().is_some());
+        assert_eq!(tx.tx_data.file_tx, STREAM_TOSERVER);
+
+        // Two CopyData rows, then CopyDone
+        let buf: &[u8] = &[
+            0x64, 0x00, 0x00, 0x00, 0x0a, 0x31, 0x09, 0x66, 0x6f, 0x6f, 0x0a, 0x64, 0x00, 0x00,
+            0x00, 0x0a, 0x32, 0x09, 0x62, 0x61, 0x72, 0x0a, 0x63, 0x00, 0x00, 0x00, 0x04,
+        ];
+        let r = state.parse_request(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.state_progress, PgsqlStateProgress::CopyDoneReceived);
+        assert_eq!(state.transactions.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.copy_row_cnt, 2);
+        assert_eq!(tx.copy_data_size, 12);
+        // CopyData isn't kept around
+        assert_eq!(tx.requests.len(), 2);
+        assert_eq!(tx.tx_req_state, PgsqlTxProgress::TxDone);
+        assert_eq!(tx.tx_res_state, PgsqlTxProgress::TxReceived);
+
+        // CommandComplete, ReadyForQuery
+        let buf: &[u8] = &[
+            0x43, 0x00, 0x00, 0x00, 0x0b, 0x43, 0x4f, 0x50, 0x59, 0x20, 0x32, 0x00, 0x5a, 0x00,
+            0x00, 0x00, 0x05, 0x49,
+        ];
+        let r = state.parse_response(std::ptr::null_mut(), buf);
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 1);
+        let tx = state.transactions.back().unwrap();
+        assert_eq!(tx.tx_res_state, PgsqlTxProgress::TxDone);
+    }
+
+    #[test]
+    fn test_copy_file_name() {
+        let mut query = b"COPY t FROM STDIN -- ".to_vec();
+        query.extend_from_slice(&[b'x'; PGSQL_COPY_FILE_NAME_MAX_LEN]);
+        let requests = vec![PgsqlFEMessage::SimpleQuery(parser::RegularPacket {
+            identifier: b'Q',
+            length: query.len() as u32 + 5,
+            payload: query,
+        })];
+        let name = PgsqlTransaction::copy_file_name(&requests);
+        assert_eq!(name.len(), PGSQL_COPY_FILE_NAME_MAX_LEN);
+        assert!(name.starts_with(b"COPY t FROM STDIN"));
+    }
 }
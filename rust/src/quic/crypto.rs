@@ -16,40 +16,140 @@
  */
 
 use aes::cipher::generic_array::GenericArray;
-use aes::Aes128;
 use aes::BlockEncrypt;
 use aes::NewBlockCipher;
+use aes::{Aes128, Aes256};
 use aes_gcm::AeadInPlace;
-use aes_gcm::Aes128Gcm;
 use aes_gcm::NewAead;
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20::cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use chacha20poly1305::ChaCha20Poly1305;
 use hkdf::Hkdf;
-use sha2::Sha256;
+use sha2::{Sha256, Sha384};
 
-pub const AES128_KEY_LEN: usize = 16;
 pub const AES128_TAG_LEN: usize = 16;
 pub const AES128_IV_LEN: usize = 12;
+/// Length of the ciphertext sample used for header protection, the same
+/// for all the QUIC ciphers
+pub const HP_SAMPLE_LEN: usize = 16;
 
-pub struct HeaderProtectionKey(Aes128);
+const QUIC_V2: u32 = 0x6b3343cf;
 
-impl HeaderProtectionKey {
-    fn new(secret: &[u8], version: u32) -> Self {
+/// TLS 1.3 cipher suites QUIC packets can be protected with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicCipher {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl QuicCipher {
+    pub fn from_tls_id(id: u16) -> Option<QuicCipher> {
+        match id {
+            0x1301 => Some(QuicCipher::Aes128Gcm),
+            0x1302 => Some(QuicCipher::Aes256Gcm),
+            0x1303 => Some(QuicCipher::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        match self {
+            QuicCipher::Aes128Gcm => 16,
+            QuicCipher::Aes256Gcm | QuicCipher::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    /// Length of the secrets, the output of the cipher suite hash
+    pub fn secret_len(&self) -> usize {
+        match self {
+            QuicCipher::Aes256Gcm => 48,
+            QuicCipher::Aes128Gcm | QuicCipher::ChaCha20Poly1305 => 32,
+        }
+    }
+}
+
+/// HKDF-Expand-Label of a TLS secret, with the hash of the cipher suite
+fn expand_label(cipher: QuicCipher, secret: &[u8], label: &[u8], version: u32, okm: &mut [u8]) {
+    let mut full_label = if version == QUIC_V2 {
+        b"quicv2 ".to_vec()
+    } else {
+        b"quic ".to_vec()
+    };
+    full_label.extend_from_slice(label);
+    if cipher == QuicCipher::Aes256Gcm {
+        let hk = Hkdf::<Sha384>::from_prk(secret).unwrap();
+        hkdf_expand_label_sha384(&hk, &full_label, okm, okm.len() as u16);
+    } else {
         let hk = Hkdf::<Sha256>::from_prk(secret).unwrap();
-        let mut secret = [0u8; AES128_KEY_LEN];
-        let quichp = if version == 0x6b3343cf {
-            b"quicv2 hp" as &[u8]
-        } else {
-            b"quic hp" as &[u8]
+        hkdf_expand_label(&hk, &full_label, okm, okm.len() as u16);
+    }
+}
+
+enum HeaderCipher {
+    Aes128(Box<Aes128>),
+    Aes256(Box<Aes256>),
+    ChaCha20([u8; 32]),
+}
+
+pub struct HeaderProtectionKey(HeaderCipher);
+
+impl HeaderProtectionKey {
+    fn new(cipher: QuicCipher, secret: &[u8], version: u32) -> Self {
+        let mut key = vec![0u8; cipher.key_len()];
+        expand_label(cipher, secret, b"hp", version, &mut key);
+        let hc = match cipher {
+            QuicCipher::Aes128Gcm => {
+                HeaderCipher::Aes128(Box::new(Aes128::new(GenericArray::from_slice(&key))))
+            }
+            QuicCipher::Aes256Gcm => {
+                HeaderCipher::Aes256(Box::new(Aes256::new(GenericArray::from_slice(&key))))
+            }
+            QuicCipher::ChaCha20Poly1305 => {
+                let mut k = [0u8; 32];
+                k.copy_from_slice(&key);
+                HeaderCipher::ChaCha20(k)
+            }
         };
-        hkdf_expand_label(&hk, quichp, &mut secret, AES128_KEY_LEN as u16);
-        return Self(Aes128::new(GenericArray::from_slice(&secret)));
+        return Self(hc);
+    }
+
+    /// Header protection mask, RFC 9001 section 5.4
+    fn mask(&self, sample: &[u8]) -> [u8; 5] {
+        let mut mask = [0u8; 5];
+        match &self.0 {
+            HeaderCipher::Aes128(aes) => {
+                let mut block = GenericArray::clone_from_slice(&sample[..HP_SAMPLE_LEN]);
+                aes.encrypt_block(&mut block);
+                mask.copy_from_slice(&block[..5]);
+            }
+            HeaderCipher::Aes256(aes) => {
+                let mut block = GenericArray::clone_from_slice(&sample[..HP_SAMPLE_LEN]);
+                aes.encrypt_block(&mut block);
+                mask.copy_from_slice(&block[..5]);
+            }
+            HeaderCipher::ChaCha20(key) => {
+                // counter is the first 4 bytes of the sample, and nonce the rest
+                let counter = u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                let mut chacha = ChaCha20::new(
+                    GenericArray::from_slice(key),
+                    GenericArray::from_slice(&sample[4..HP_SAMPLE_LEN]),
+                );
+                chacha.seek(u64::from(counter) * 64);
+                chacha.apply_keystream(&mut mask);
+            }
+        }
+        mask
     }
 
     pub fn decrypt_in_place(
         &self, sample: &[u8], first: &mut u8, packet_number: &mut [u8],
     ) -> Result<(), ()> {
-        let mut mask = GenericArray::clone_from_slice(sample);
-        self.0.encrypt_block(&mut mask);
-
+        if sample.len() < HP_SAMPLE_LEN {
+            return Err(());
+        }
+        let mask = self.mask(sample);
         let (first_mask, pn_mask) = mask.split_first().unwrap();
 
         let bits = if (*first & 0x80) != 0 {
@@ -69,53 +169,68 @@ impl HeaderProtectionKey {
     }
 }
 
+enum PacketCipher {
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+    ChaCha20(Box<ChaCha20Poly1305>),
+}
+
 pub struct PacketKey {
-    key: Aes128Gcm,
+    key: PacketCipher,
     iv: [u8; AES128_IV_LEN],
 }
 
 impl PacketKey {
-    fn new(secret: &[u8], version: u32) -> Self {
-        let hk = Hkdf::<Sha256>::from_prk(secret).unwrap();
-        let mut secret = [0u8; AES128_KEY_LEN];
-        let quickey = if version == 0x6b3343cf {
-            b"quicv2 key" as &[u8]
-        } else {
-            b"quic key" as &[u8]
+    fn new(cipher: QuicCipher, secret: &[u8], version: u32) -> Self {
+        let mut secret_key = vec![0u8; cipher.key_len()];
+        expand_label(cipher, secret, b"key", version, &mut secret_key);
+        let k = &secret_key;
+        let key = match cipher {
+            QuicCipher::Aes128Gcm => {
+                PacketCipher::Aes128(Box::new(Aes128Gcm::new(GenericArray::from_slice(k))))
+            }
+            QuicCipher::Aes256Gcm => {
+                PacketCipher::Aes256(Box::new(Aes256Gcm::new(GenericArray::from_slice(k))))
+            }
+            QuicCipher::ChaCha20Poly1305 => {
+                PacketCipher::ChaCha20(Box::new(ChaCha20Poly1305::new(GenericArray::from_slice(k))))
+            }
         };
-        hkdf_expand_label(&hk, quickey, &mut secret, AES128_KEY_LEN as u16);
-        let key = Aes128Gcm::new(GenericArray::from_slice(&secret));
 
         let mut r = PacketKey {
             key,
             iv: [0u8; AES128_IV_LEN],
         };
-        let quiciv = if version == 0x6b3343cf {
-            b"quicv2 iv" as &[u8]
-        } else {
-            b"quic iv" as &[u8]
-        };
-        hkdf_expand_label(&hk, quiciv, &mut r.iv, AES128_IV_LEN as u16);
+        expand_label(cipher, secret, b"iv", version, &mut r.iv);
         return r;
     }
 
+    fn nonce(&self, packet_number: u64) -> [u8; AES128_IV_LEN] {
+        let mut nonce = [0; AES128_IV_LEN];
+        nonce[4..].copy_from_slice(&packet_number.to_be_bytes());
+        for (nonce, inp) in nonce.iter_mut().zip(self.iv.iter()) {
+            *nonce ^= inp;
+        }
+        nonce
+    }
+
     pub fn decrypt_in_place<'a>(
         &self, packet_number: u64, header: &[u8], payload: &'a mut [u8],
     ) -> Result<&'a [u8], ()> {
         if payload.len() < AES128_TAG_LEN {
             return Err(());
         }
-        let mut nonce = [0; AES128_IV_LEN];
-        nonce[4..].copy_from_slice(&packet_number.to_be_bytes());
-        for (nonce, inp) in nonce.iter_mut().zip(self.iv.iter()) {
-            *nonce ^= inp;
-        }
+        let nonce = self.nonce(packet_number);
+        let nonce = GenericArray::from_slice(&nonce);
         let tag_pos = payload.len() - AES128_TAG_LEN;
         let (buffer, tag) = payload.split_at_mut(tag_pos);
         let taga = GenericArray::from_slice(tag);
-        self.key
-            .decrypt_in_place_detached(GenericArray::from_slice(&nonce), header, buffer, taga)
-            .map_err(|_| ())?;
+        match &self.key {
+            PacketCipher::Aes128(k) => k.decrypt_in_place_detached(nonce, header, buffer, taga),
+            PacketCipher::Aes256(k) => k.decrypt_in_place_detached(nonce, header, buffer, taga),
+            PacketCipher::ChaCha20(k) => k.decrypt_in_place_detached(nonce, header, buffer, taga),
+        }
+        .map_err(|_| ())?;
         Ok(&payload[..tag_pos])
     }
 }
@@ -123,15 +238,41 @@ impl PacketKey {
 pub struct DirectionalKeys {
     pub header: HeaderProtectionKey,
     pub packet: PacketKey,
+    cipher: QuicCipher,
+    version: u32,
+    // secret of the current key phase, to derive the next one
+    secret: Vec<u8>,
+    pub key_phase: bool,
 }
 
 impl DirectionalKeys {
-    fn new(secret: &[u8], version: u32) -> Self {
+    fn new(cipher: QuicCipher, secret: &[u8], version: u32) -> Self {
         Self {
-            header: HeaderProtectionKey::new(secret, version),
-            packet: PacketKey::new(secret, version),
+            header: HeaderProtectionKey::new(cipher, secret, version),
+            packet: PacketKey::new(cipher, secret, version),
+            cipher,
+            version,
+            secret: secret.to_vec(),
+            key_phase: false,
         }
     }
+
+    /// Secret and packet key of the next key phase, RFC 9001 section 6.
+    ///
+    /// The header protection key is not updated.
+    pub fn next_packet_key(&self) -> (Vec<u8>, PacketKey) {
+        let mut secret = vec![0u8; self.cipher.secret_len()];
+        expand_label(self.cipher, &self.secret, b"ku", self.version, &mut secret);
+        let packet = PacketKey::new(self.cipher, &secret, self.version);
+        (secret, packet)
+    }
+
+    /// Switches to the next key phase, once a packet was decrypted with it
+    pub fn update(&mut self, secret: Vec<u8>, packet: PacketKey) {
+        self.secret = secret;
+        self.packet = packet;
+        self.key_phase = !self.key_phase;
+    }
 }
 
 pub struct QuicKeys {
@@ -140,21 +281,25 @@ pub struct QuicKeys {
 }
 
 fn hkdf_expand_label(hk: &Hkdf<Sha256>, label: &[u8], okm: &mut [u8], olen: u16) {
-    const LABEL_PREFIX: &[u8] = b"tls13 ";
+    hk.expand(&hkdf_label_info(label, olen), okm).unwrap();
+}
 
-    let output_len = u16::to_be_bytes(olen);
-    let label_len = u8::to_be_bytes((LABEL_PREFIX.len() + label.len()) as u8);
-    let context_len = u8::to_be_bytes(0);
+fn hkdf_expand_label_sha384(hk: &Hkdf<Sha384>, label: &[u8], okm: &mut [u8], olen: u16) {
+    hk.expand(&hkdf_label_info(label, olen), okm).unwrap();
+}
 
-    let info = &[
-        &output_len[..],
-        &label_len[..],
-        LABEL_PREFIX,
-        label,
-        &context_len[..],
-    ];
+/// HkdfLabel of TLS 1.3, RFC 8446 section 7.1, with an empty context
+fn hkdf_label_info(label: &[u8], olen: u16) -> Vec<u8> {
+    const LABEL_PREFIX: &[u8] = b"tls13 ";
 
-    hk.expand_multi_info(info, okm).unwrap();
+    let mut info = Vec::with_capacity(4 + LABEL_PREFIX.len() + label.len());
+    info.extend_from_slice(&olen.to_be_bytes());
+    info.push((LABEL_PREFIX.len() + label.len()) as u8);
+    info.extend_from_slice(LABEL_PREFIX);
+    info.extend_from_slice(label);
+    // context length
+    info.push(0);
+    info
 }
 
 pub fn quic_keys_initial(version: u32, client_dst_connection_id: &[u8]) -> Option<QuicKeys> {
@@ -194,7 +339,96 @@ pub fn quic_keys_initial(version: u32, client_dst_connection_id: &[u8]) -> Optio
     hkdf_expand_label(&hk, b"server in", &mut server_secret, 32);
 
     return Some(QuicKeys {
-        local: DirectionalKeys::new(&server_secret, version),
-        remote: DirectionalKeys::new(&client_secret, version),
+        local: DirectionalKeys::new(QuicCipher::Aes128Gcm, &server_secret, version),
+        remote: DirectionalKeys::new(QuicCipher::Aes128Gcm, &client_secret, version),
     });
 }
+
+/// Keys for the Handshake or 1-RTT packets, from the TLS traffic secrets
+pub fn quic_keys_from_secrets(
+    cipher: QuicCipher, version: u32, client_secret: &[u8], server_secret: &[u8],
+) -> Option<QuicKeys> {
+    if client_secret.len() != cipher.secret_len() || server_secret.len() != cipher.secret_len() {
+        return None;
+    }
+    return Some(QuicKeys {
+        local: DirectionalKeys::new(cipher, server_secret, version),
+        remote: DirectionalKeys::new(cipher, client_secret, version),
+    });
+}
+
+#[cfg(test)]
+impl DirectionalKeys {
+    /// Encrypt and protect a packet, header ending with a 4 bytes packet number
+    pub fn protect(&self, header: &[u8], packet_number: u64, payload: &[u8]) -> Vec<u8> {
+        let nonce = self.packet.nonce(packet_number);
+        let nonce = GenericArray::from_slice(&nonce);
+        let mut buffer = payload.to_vec();
+        let tag = match &self.packet.key {
+            PacketCipher::Aes128(k) => k.encrypt_in_place_detached(nonce, header, &mut buffer),
+            PacketCipher::Aes256(k) => k.encrypt_in_place_detached(nonce, header, &mut buffer),
+            PacketCipher::ChaCha20(k) => k.encrypt_in_place_detached(nonce, header, &mut buffer),
+        }
+        .unwrap();
+        let mut packet = header.to_vec();
+        packet.extend_from_slice(&buffer);
+        packet.extend_from_slice(&tag);
+
+        let pn_offset = header.len() - 4;
+        let mask = self
+            .header
+            .mask(&packet[pn_offset + 4..pn_offset + 4 + HP_SAMPLE_LEN]);
+        let bits = if (packet[0] & 0x80) != 0 { 0x0f } else { 0x1f };
+        packet[0] ^= mask[0] & bits;
+        for i in 0..4 {
+            packet[pn_offset + i] ^= mask[1 + i];
+        }
+        packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quic_chacha20_short_header() {
+        // RFC 9001 appendix A.5
+        let secret =
+            hex::decode("9ac312a7f877468ebe69422748ad00a15443f18203a07d6060f688f30f21632b")
+                .unwrap();
+        let keys = DirectionalKeys::new(QuicCipher::ChaCha20Poly1305, &secret, 1);
+        assert_eq!(
+            keys.packet.iv.to_vec(),
+            hex::decode("e0459b3474bdd0e44a41c144").unwrap()
+        );
+        let sample = hex::decode("5e5cd55c41f69080575d7999c25a5bfb").unwrap();
+        assert_eq!(
+            keys.header.mask(&sample).to_vec(),
+            hex::decode("aefefe7d03").unwrap()
+        );
+
+        let packet = hex::decode("4cfe4189655e5cd55c41f69080575d7999c25a5bfb").unwrap();
+        let mut first = packet[0];
+        let mut pn = packet[1..5].to_vec();
+        keys.header
+            .decrypt_in_place(&packet[5..], &mut first, &mut pn)
+            .unwrap();
+        assert_eq!(first, 0x42);
+        assert_eq!(&pn[..3], &[0x00, 0xbf, 0xf4]);
+        let mut payload = packet[4..].to_vec();
+        let header = [first, pn[0], pn[1], pn[2]];
+        let plain = keys
+            .packet
+            .decrypt_in_place(654360564, &header, &mut payload)
+            .unwrap();
+        assert_eq!(plain, &[0x01]);
+
+        let (secret, _) = keys.next_packet_key();
+        assert_eq!(
+            secret,
+            hex::decode("1223504755036d556342ee9361d253421a826c9ecdf3c7148684b36b714881f9")
+                .unwrap()
+        );
+    }
+}
@@ -139,6 +139,8 @@ pub(crate) struct Crypto {
     pub extv: Vec<QuicTlsExtension>,
     pub ja3: Option<String>,
     pub ja4: Option<JA4>,
+    // hello random, used to find the secrets in a key log
+    pub random: Vec<u8>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -148,6 +150,15 @@ pub(crate) struct CryptoFrag {
     pub data: Vec<u8>,
 }
 
+/// IETF QUIC STREAM frame, from a decrypted Handshake or 1-RTT packet
+#[derive(Debug, PartialEq, Eq)]
+pub struct StreamData {
+    pub stream_id: u64,
+    pub offset: u64,
+    pub fin: bool,
+    pub data: Vec<u8>,
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum Frame {
     Padding,
@@ -159,6 +170,9 @@ pub(crate) enum Frame {
     // in order to parse a tls hello
     CryptoFrag(CryptoFrag),
     Stream(Stream),
+    StreamData(StreamData),
+    // other frames of decrypted packets, only their type is kept
+    Control(u8),
     Unknown(Vec<u8>),
 }
 
@@ -307,6 +321,13 @@ fn quic_get_tls_extensions(
     return extv;
 }
 
+/// 32 bytes hello random, split by tls-parser in a time and random data
+fn tls_random(rand_time: u32, rand_data: &[u8]) -> Vec<u8> {
+    let mut random = rand_time.to_be_bytes().to_vec();
+    random.extend_from_slice(rand_data);
+    random
+}
+
 fn parse_quic_handshake(msg: TlsMessage) -> Option<Frame> {
     if let Handshake(hs) = msg {
         match hs {
@@ -332,6 +353,7 @@ fn parse_quic_handshake(msg: TlsMessage) -> Option<Frame> {
                 return Some(Frame::Crypto(Crypto {
                     ciphers,
                     extv,
+                    random: tls_random(ch.rand_time, ch.rand_data),
                     ja3: if cfg!(feature = "ja3") {
                         Some(ja3)
                     } else {
@@ -355,6 +377,7 @@ fn parse_quic_handshake(msg: TlsMessage) -> Option<Frame> {
                 return Some(Frame::Crypto(Crypto {
                     ciphers,
                     extv,
+                    random: tls_random(sh.rand_time, sh.rand_data),
                     ja3: if cfg!(feature = "ja3") {
                         Some(ja3)
                     } else {
@@ -518,6 +541,108 @@ fn parse_crypto_stream_frame(input: &[u8]) -> IResult<&[u8], Frame, QuicError> {
     ))
 }
 
+fn parse_ack_ecn_frame(input: &[u8]) -> IResult<&[u8], Frame, QuicError> {
+    let (rest, ack) = parse_ack_frame(input)?;
+    //RFC9000 section 19.3.2.  ECN Counts
+    let (rest, _ect0) = quic_var_uint(rest)?;
+    let (rest, _ect1) = quic_var_uint(rest)?;
+    let (rest, _ecn_ce) = quic_var_uint(rest)?;
+    Ok((rest, ack))
+}
+
+fn parse_protected_crypto_frame(input: &[u8]) -> IResult<&[u8], Frame, QuicError> {
+    // TLS messages after the hellos are not parsed
+    let (rest, offset) = quic_var_uint(input)?;
+    let (rest, length) = quic_var_uint(rest)?;
+    let (rest, data) = take(length as usize)(rest)?;
+    Ok((
+        rest,
+        Frame::CryptoFrag(CryptoFrag {
+            offset,
+            length,
+            data: data.to_vec(),
+        }),
+    ))
+}
+
+fn parse_ietf_stream_frame(input: &[u8], frame_ty: u8) -> IResult<&[u8], Frame, QuicError> {
+    // RFC9000 section 19.8 : 0b00001_off_len_fin
+    let (rest, stream_id) = quic_var_uint(input)?;
+    let (rest, offset) = if frame_ty & 0x04 != 0 {
+        quic_var_uint(rest)?
+    } else {
+        (rest, 0)
+    };
+    let (rest, data) = if frame_ty & 0x02 != 0 {
+        let (rest, length) = quic_var_uint(rest)?;
+        take(length as usize)(rest)?
+    } else {
+        // data extends to the end of the packet
+        take(rest.len())(rest)?
+    };
+    Ok((
+        rest,
+        Frame::StreamData(StreamData {
+            stream_id,
+            offset,
+            fin: frame_ty & 0x01 != 0,
+            data: data.to_vec(),
+        }),
+    ))
+}
+
+fn parse_control_frame(input: &[u8], frame_ty: u8) -> IResult<&[u8], Frame, QuicError> {
+    // RFC9000 section 19
+    let rest = match frame_ty {
+        // RESET_STREAM : stream id, error code and final size
+        0x04 => count(quic_var_uint, 3)(input)?.0,
+        // STOP_SENDING, MAX_STREAM_DATA and STREAM_DATA_BLOCKED
+        0x05 | 0x11 | 0x15 => count(quic_var_uint, 2)(input)?.0,
+        // NEW_TOKEN
+        0x07 => {
+            let (rest, length) = quic_var_uint(input)?;
+            take(length as usize)(rest)?.0
+        }
+        // MAX_DATA, MAX_STREAMS, DATA_BLOCKED, STREAMS_BLOCKED and
+        // RETIRE_CONNECTION_ID
+        0x10 | 0x12 | 0x13 | 0x14 | 0x16 | 0x17 | 0x19 => quic_var_uint(input)?.0,
+        // NEW_CONNECTION_ID : sequence number, retire prior to, connection id
+        // and stateless reset token
+        0x18 => {
+            let (rest, _) = count(quic_var_uint, 2)(input)?;
+            let (rest, cid_len) = be_u8(rest)?;
+            let (rest, _cid) = take(cid_len as usize)(rest)?;
+            take(16_usize)(rest)?.0
+        }
+        // PATH_CHALLENGE and PATH_RESPONSE
+        0x1a | 0x1b => take(8_usize)(input)?.0,
+        // CONNECTION_CLOSE : error code, frame type for transport errors,
+        // and reason phrase
+        0x1c | 0x1d => {
+            let (rest, _error_code) = quic_var_uint(input)?;
+            let rest = if frame_ty == 0x1c {
+                quic_var_uint(rest)?.0
+            } else {
+                rest
+            };
+            let (rest, length) = quic_var_uint(rest)?;
+            take(length as usize)(rest)?.0
+        }
+        // HANDSHAKE_DONE
+        0x1e => input,
+        // DATAGRAM, RFC9221 : without length it extends to the end of the packet
+        0x30 => take(input.len())(input)?.0,
+        0x31 => {
+            let (rest, length) = quic_var_uint(input)?;
+            take(length as usize)(rest)?.0
+        }
+        _ => {
+            return Err(nom7::Err::Error(QuicError::Unhandled));
+        }
+    };
+    Ok((rest, Frame::Control(frame_ty)))
+}
+
 impl Frame {
     fn decode_frame(input: &[u8]) -> IResult<&[u8], Frame, QuicError> {
         let (rest, frame_ty) = be_u8(input)?;
@@ -540,6 +665,39 @@ impl Frame {
         Ok((rest, value))
     }
 
+    /// Decode a frame from a decrypted Handshake or 1-RTT packet
+    fn decode_protected_frame(input: &[u8]) -> IResult<&[u8], Frame, QuicError> {
+        let (rest, frame_ty) = be_u8(input)?;
+
+        let (rest, value) = match frame_ty {
+            0x00 => parse_padding_frame(rest)?,
+            0x01 => (rest, Frame::Ping),
+            0x02 => parse_ack_frame(rest)?,
+            0x03 => parse_ack_ecn_frame(rest)?,
+            0x06 => parse_protected_crypto_frame(rest)?,
+            0x08..=0x0f => parse_ietf_stream_frame(rest, frame_ty)?,
+            0x04 | 0x05 | 0x07 | 0x10..=0x1e | 0x30 | 0x31 => parse_control_frame(rest, frame_ty)?,
+            _ => ([].as_ref(), Frame::Unknown(rest.to_vec())),
+        };
+
+        Ok((rest, value))
+    }
+
+    /// Decode the frames of a decrypted Handshake or 1-RTT packet, with the
+    /// offset and length of each one in the packet payload
+    pub(crate) fn decode_protected_frames(
+        input: &[u8],
+    ) -> Result<Vec<(Frame, usize, usize)>, QuicError> {
+        let mut frames = Vec::new();
+        let mut rest = input;
+        while !rest.is_empty() {
+            let (next, frame) = Frame::decode_protected_frame(rest)?;
+            frames.push((frame, input.len() - rest.len(), rest.len() - next.len()));
+            rest = next;
+        }
+        Ok(frames)
+    }
+
     pub(crate) fn decode_frames(input: &[u8]) -> IResult<&[u8], Vec<Frame>, QuicError> {
         let (rest, mut frames) = many0(complete(Frame::decode_frame))(input)?;
 
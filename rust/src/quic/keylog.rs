@@ -0,0 +1,139 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! NSS key log file support, as written by clients honoring SSLKEYLOGFILE.
+//!
+//! Each line is `<label> <client random> <secret>`, the last two being hex
+//! encoded. Only the TLS 1.3 traffic secrets are used by QUIC.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    if input.len() % 2 != 0 || !input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&input[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// TLS 1.3 traffic secrets of one connection
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct KeyLogSecrets {
+    pub client_handshake: Option<Vec<u8>>,
+    pub server_handshake: Option<Vec<u8>>,
+    pub client_traffic: Option<Vec<u8>>,
+    pub server_traffic: Option<Vec<u8>>,
+}
+
+/// Secrets of a key log file, by client random
+#[derive(Debug, Default)]
+pub struct KeyLog {
+    secrets: HashMap<Vec<u8>, KeyLogSecrets>,
+}
+
+impl KeyLog {
+    pub fn from_file(path: &str) -> std::io::Result<KeyLog> {
+        let file = File::open(path)?;
+        return KeyLog::from_reader(file);
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> std::io::Result<KeyLog> {
+        let mut keylog = KeyLog::default();
+        for line in BufReader::new(reader).lines() {
+            keylog.add_line(&line?);
+        }
+        return Ok(keylog);
+    }
+
+    /// Add a key log line, ignoring comments, malformed lines and labels
+    /// we have no use for
+    fn add_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let mut fields = line.split_ascii_whitespace();
+        let (label, random, secret) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(label), Some(random), Some(secret)) => (label, random, secret),
+            _ => return,
+        };
+        if !matches!(
+            label,
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET"
+                | "SERVER_HANDSHAKE_TRAFFIC_SECRET"
+                | "CLIENT_TRAFFIC_SECRET_0"
+                | "SERVER_TRAFFIC_SECRET_0"
+        ) {
+            return;
+        }
+        let (random, secret) = match (decode_hex(random), decode_hex(secret)) {
+            (Some(random), Some(secret)) => (random, secret),
+            _ => return,
+        };
+        let entry = self.secrets.entry(random).or_default();
+        match label {
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => entry.client_handshake = Some(secret),
+            "SERVER_HANDSHAKE_TRAFFIC_SECRET" => entry.server_handshake = Some(secret),
+            "CLIENT_TRAFFIC_SECRET_0" => entry.client_traffic = Some(secret),
+            "SERVER_TRAFFIC_SECRET_0" => entry.server_traffic = Some(secret),
+            _ => {}
+        }
+    }
+
+    pub fn get(&self, client_random: &[u8]) -> Option<&KeyLogSecrets> {
+        self.secrets.get(client_random)
+    }
+
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keylog_parse() {
+        let buf = b"# SSL/TLS secrets log file, generated by NSS
+CLIENT_HANDSHAKE_TRAFFIC_SECRET 0102 aabb
+SERVER_HANDSHAKE_TRAFFIC_SECRET 0102 ccdd
+CLIENT_TRAFFIC_SECRET_0 0102 eeff
+SERVER_TRAFFIC_SECRET_0 0102 0011
+EXPORTER_SECRET 0102 2233
+CLIENT_TRAFFIC_SECRET_0 zz02 eeff
+CLIENT_RANDOM 0304 4455
+";
+        let keylog = KeyLog::from_reader(&buf[..]).unwrap();
+        assert_eq!(keylog.len(), 1);
+        let secrets = keylog.get(&[1, 2]).unwrap();
+        assert_eq!(
+            *secrets,
+            KeyLogSecrets {
+                client_handshake: Some(vec![0xaa, 0xbb]),
+                server_handshake: Some(vec![0xcc, 0xdd]),
+                client_traffic: Some(vec![0xee, 0xff]),
+                server_traffic: Some(vec![0x00, 0x11]),
+            }
+        );
+        assert!(keylog.get(&[3, 4]).is_none());
+    }
+}
@@ -149,6 +149,19 @@ fn log_quic(tx: &QuicTransaction, js: &mut JsonBuilder) -> Result<(), JsonError>
         js.close()?;
     }
 
+    if !tx.streams.is_empty() {
+        js.open_array("streams")?;
+        for s in &tx.streams {
+            js.start_object()?;
+            js.set_uint("stream_id", s.stream_id)?;
+            js.set_uint("offset", s.offset)?;
+            js.set_uint("length", s.data.len() as u64)?;
+            js.set_bool("fin", s.fin)?;
+            js.close()?;
+        }
+        js.close()?;
+    }
+
     js.close()?;
     Ok(())
 }
@@ -22,6 +22,7 @@ mod cyu;
 pub mod detect;
 mod error;
 mod frames;
-mod logger;
+mod keylog;
+pub mod logger;
 mod parser;
 pub mod quic;
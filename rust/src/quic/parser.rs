@@ -46,7 +46,7 @@ impl QuicVersion {
     pub const Q046: QuicVersion = QuicVersion(0x51303436);
     pub const V2: QuicVersion = QuicVersion(0x6b3343cf);
 
-    fn is_gquic(&self) -> bool {
+    pub(crate) fn is_gquic(&self) -> bool {
         *self == QuicVersion::Q043
             || *self == QuicVersion::Q044
             || *self == QuicVersion::Q045
@@ -79,7 +79,7 @@ impl From<u32> for QuicVersion {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum QuicType {
     Initial,
     Retry,
@@ -94,7 +94,7 @@ const QUIC_FLAG_DCID_LEN: u8 = 0x8;
 const QUIC_FLAG_NONCE: u8 = 0x4;
 const QUIC_FLAG_VERSION: u8 = 0x1;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct PublicFlags {
     pub is_long: bool,
     pub raw: u8,
@@ -162,7 +162,7 @@ pub fn quic_var_uint(input: &[u8]) -> IResult<&[u8], u64, QuicError> {
 }
 
 /// A QUIC packet's header.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct QuicHeader {
     pub flags: PublicFlags,
     pub ty: QuicType,
@@ -357,6 +357,10 @@ impl QuicHeader {
                         rest
                     }
                 }
+                QuicType::Handshake | QuicType::ZeroRTT if !version.is_gquic() => {
+                    has_length = true;
+                    rest
+                }
                 _ => rest,
             };
             let (rest, length) = if has_length {
@@ -396,13 +400,22 @@ impl QuicData {
         let (_, frames) = all_consuming(Frame::decode_frames)(input)?;
         Ok(QuicData { frames })
     }
+
+    /// Frames of a decrypted Handshake or 1-RTT packet
+    #[cfg(test)]
+    pub(crate) fn from_protected_bytes(input: &[u8]) -> Result<QuicData, QuicError> {
+        let frames = Frame::decode_protected_frames(input)?;
+        Ok(QuicData {
+            frames: frames.into_iter().map(|(frame, _, _)| frame).collect(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::quic::frames::{Frame, Stream, StreamTag};
+    use crate::quic::frames::{Ack, Frame, Stream, StreamData, StreamTag};
 
     #[test]
     fn public_flags_test() {
@@ -499,4 +512,89 @@ mod tests {
             data,
         );
     }
+
+    #[test]
+    fn test_parse_handshake_length() {
+        // v1 Handshake packet, followed by a coalesced short header packet
+        let test_data = hex::decode("e0000000010408090a0b04010203040401020304404142").unwrap();
+        let (rest, header) =
+            QuicHeader::from_bytes(test_data.as_ref(), TEST_DEFAULT_CID_LENGTH).unwrap();
+        assert_eq!(header.ty, QuicType::Handshake);
+        assert_eq!(header.dcid, vec![0x08, 0x09, 0x0a, 0x0b]);
+        assert_eq!(header.scid, vec![0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(header.length, 4);
+        assert_eq!(rest.len(), 7);
+    }
+
+    #[test]
+    fn test_parse_protected_frames() {
+        // ack, then a stream frame with offset, length and fin, padding,
+        // and a stream frame extending to the end of the packet
+        let test_data = hex::decode("02010000000f0405030102030000080804050607").unwrap();
+        let data = QuicData::from_protected_bytes(&test_data).unwrap();
+        assert_eq!(
+            data.frames,
+            vec![
+                Frame::Ack(Ack {
+                    largest_acknowledged: 1,
+                    ack_delay: 0,
+                    ack_range_count: 0,
+                    first_ack_range: 0,
+                }),
+                Frame::StreamData(StreamData {
+                    stream_id: 4,
+                    offset: 5,
+                    fin: true,
+                    data: vec![0x01, 0x02, 0x03],
+                }),
+                Frame::Padding,
+                Frame::StreamData(StreamData {
+                    stream_id: 8,
+                    offset: 0,
+                    fin: false,
+                    data: vec![0x04, 0x05, 0x06, 0x07],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_protected_control_frames() {
+        let mut test_data = Vec::new();
+        // NEW_CONNECTION_ID, with a 4 bytes connection id and a reset token
+        test_data.extend_from_slice(&[0x18, 0x01, 0x00, 0x04, 0xaa, 0xbb, 0xcc, 0xdd]);
+        test_data.extend_from_slice(&[0x11; 16]);
+        // MAX_DATA, MAX_STREAM_DATA and MAX_STREAMS
+        test_data.extend_from_slice(&[0x10, 0x44, 0x00, 0x11, 0x04, 0x44, 0x00, 0x12, 0x10]);
+        // RESET_STREAM, STOP_SENDING, NEW_TOKEN and HANDSHAKE_DONE
+        test_data.extend_from_slice(&[0x04, 0x04, 0x00, 0x05, 0x05, 0x04, 0x00]);
+        test_data.extend_from_slice(&[0x07, 0x02, 0xab, 0xcd, 0x1e]);
+        // stream frame with a length
+        test_data.extend_from_slice(&[0x0a, 0x04, 0x03, 0x61, 0x62, 0x63]);
+        let frames = Frame::decode_protected_frames(&test_data).unwrap();
+        let types: Vec<u8> = frames
+            .iter()
+            .filter_map(|(frame, _, _)| match frame {
+                Frame::Control(ty) => Some(*ty),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(types, vec![0x18, 0x10, 0x11, 0x12, 0x04, 0x05, 0x07, 0x1e]);
+        assert_eq!(
+            frames.last().unwrap(),
+            &(
+                Frame::StreamData(StreamData {
+                    stream_id: 4,
+                    offset: 0,
+                    fin: false,
+                    data: b"abc".to_vec(),
+                }),
+                test_data.len() - 6,
+                6
+            )
+        );
+
+        // a truncated NEW_CONNECTION_ID is an error
+        assert!(Frame::decode_protected_frames(&test_data[..10]).is_err());
+    }
 }
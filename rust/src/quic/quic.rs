@@ -16,27 +16,60 @@
  */
 
 use super::{
-    crypto::{quic_keys_initial, QuicKeys, AES128_KEY_LEN},
+    crypto::{
+        quic_keys_from_secrets, quic_keys_initial, QuicCipher, QuicKeys, AES128_TAG_LEN,
+        HP_SAMPLE_LEN,
+    },
     cyu::Cyu,
-    frames::{Frame, QuicTlsExtension, StreamTag},
+    frames::{Frame, QuicTlsExtension, StreamData, StreamTag},
+    keylog::KeyLog,
     parser::{quic_pkt_num, QuicData, QuicHeader, QuicType},
 };
 use crate::applayer::{self, *};
-use crate::core::{AppProto, Direction, Flow, ALPROTO_FAILED, ALPROTO_UNKNOWN, IPPROTO_UDP};
+use crate::conf::conf_get;
+use crate::core::{
+    AppProto, Direction, Flow, ALPROTO_FAILED, ALPROTO_UNKNOWN, IPPROTO_UDP, STREAM_TOCLIENT,
+    STREAM_TOSERVER,
+};
+use crate::frames::Frame as AppLayerFrame;
+use crate::http2::http2::{rs_http2_getfiles, HTTP2TransactionState};
+use crate::http3::http3::{Http3Error, Http3Message, Http3State, Http3Transaction, ALPROTO_HTTP3};
 use std::collections::VecDeque;
 use std::ffi::CString;
 use tls_parser::TlsExtensionType;
 
 static mut ALPROTO_QUIC: AppProto = ALPROTO_UNKNOWN;
+static mut QUIC_KEYLOG: Option<&'static KeyLog> = None;
 
 const DEFAULT_DCID_LEN: usize = 16;
 const PKT_NUM_BUF_MAX_LEN: usize = 4;
 
+#[derive(AppLayerFrameType)]
+pub enum QuicFrameType {
+    Pdu,
+    Stream,
+}
+
 #[derive(FromPrimitive, Debug, AppLayerEvent)]
 pub enum QuicEvent {
     FailedDecrypt,
     ErrorOnData,
     ErrorOnHeader,
+    Http3InvalidFrame,
+    Http3InvalidQpack,
+    Http3TooManyStreams,
+    Http3FailedDecompression,
+}
+
+impl From<Http3Error> for QuicEvent {
+    fn from(e: Http3Error) -> Self {
+        match e {
+            Http3Error::InvalidFrame => QuicEvent::Http3InvalidFrame,
+            Http3Error::InvalidQpack => QuicEvent::Http3InvalidQpack,
+            Http3Error::TooManyStreams => QuicEvent::Http3TooManyStreams,
+            Http3Error::FailedDecompression => QuicEvent::Http3FailedDecompression,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -50,6 +83,10 @@ pub struct QuicTransaction {
     pub ja3: Option<String>,
     pub ja4: Option<String>,
     pub client: bool,
+    // STREAM frames of decrypted Handshake or 1-RTT packets
+    pub streams: Vec<StreamData>,
+    // HTTP/3 request, instead of a packet
+    pub http3: Option<Http3Transaction>,
     tx_data: AppLayerTxData,
 }
 
@@ -64,6 +101,14 @@ impl QuicTransaction {
             Direction::ToClient
         };
         let cyu = Cyu::generate(&header, &data.frames);
+        let streams = data
+            .frames
+            .into_iter()
+            .filter_map(|frame| match frame {
+                Frame::StreamData(s) => Some(s),
+                _ => None,
+            })
+            .collect();
         QuicTransaction {
             tx_id: 0,
             header,
@@ -74,6 +119,8 @@ impl QuicTransaction {
             ja3,
             ja4,
             client,
+            streams,
+            http3: None,
             tx_data: AppLayerTxData::for_direction(direction),
         }
     }
@@ -94,6 +141,8 @@ impl QuicTransaction {
             ja3: None,
             ja4: None,
             client,
+            streams: Vec::new(),
+            http3: None,
             tx_data: AppLayerTxData::for_direction(direction),
         }
     }
@@ -103,8 +152,16 @@ pub struct QuicState {
     state_data: AppLayerStateData,
     max_tx_id: u64,
     keys: Option<QuicKeys>,
+    handshake_keys: Option<QuicKeys>,
+    app_keys: Option<QuicKeys>,
+    client_random: Option<Vec<u8>>,
+    server_cipher: Option<u16>,
+    // source connection id lengths, to find where short headers end
+    scid_len_ts: usize,
+    scid_len_tc: usize,
     hello_tc: bool,
     hello_ts: bool,
+    http3: Option<Http3State>,
     transactions: VecDeque<QuicTransaction>,
 }
 
@@ -114,8 +171,15 @@ impl Default for QuicState {
             state_data: AppLayerStateData::new(),
             max_tx_id: 0,
             keys: None,
+            handshake_keys: None,
+            app_keys: None,
+            client_random: None,
+            server_cipher: None,
+            scid_len_ts: DEFAULT_DCID_LEN,
+            scid_len_tc: DEFAULT_DCID_LEN,
             hello_tc: false,
             hello_ts: false,
+            http3: None,
             transactions: VecDeque::new(),
         }
     }
@@ -171,26 +235,26 @@ impl QuicState {
     }
 
     fn decrypt<'a>(
-        &mut self, to_server: bool, header: &QuicHeader, framebuf: &'a [u8], buf: &'a [u8],
-        hlen: usize, output: &'a mut Vec<u8>,
+        keys: Option<&mut QuicKeys>, to_server: bool, length: usize, framebuf: &'a [u8],
+        buf: &'a [u8], hlen: usize, output: &'a mut Vec<u8>,
     ) -> Result<usize, ()> {
-        if let Some(keys) = &self.keys {
-            let hkey = if to_server {
-                &keys.remote.header
+        if let Some(keys) = keys {
+            let dkeys = if to_server {
+                &mut keys.remote
             } else {
-                &keys.local.header
+                &mut keys.local
             };
-            if framebuf.len() < PKT_NUM_BUF_MAX_LEN + AES128_KEY_LEN {
+            if framebuf.len() < PKT_NUM_BUF_MAX_LEN + HP_SAMPLE_LEN {
                 return Err(());
             }
-            let h2len = hlen + usize::from(header.length);
+            let h2len = hlen + length;
             let mut h2 = Vec::with_capacity(h2len);
             h2.extend_from_slice(&buf[..h2len]);
             let mut h20 = h2[0];
             let mut pktnum_buf = Vec::with_capacity(PKT_NUM_BUF_MAX_LEN);
             pktnum_buf.extend_from_slice(&h2[hlen..hlen + PKT_NUM_BUF_MAX_LEN]);
-            let r1 = hkey.decrypt_in_place(
-                &h2[hlen + PKT_NUM_BUF_MAX_LEN..hlen + PKT_NUM_BUF_MAX_LEN + AES128_KEY_LEN],
+            let r1 = dkeys.header.decrypt_in_place(
+                &h2[hlen + PKT_NUM_BUF_MAX_LEN..hlen + PKT_NUM_BUF_MAX_LEN + HP_SAMPLE_LEN],
                 &mut h20,
                 &mut pktnum_buf,
             );
@@ -206,12 +270,18 @@ impl QuicState {
                 return Err(());
             }
             output.extend_from_slice(&framebuf[1 + ((h20 & 3) as usize)..]);
-            let pkey = if to_server {
-                &keys.remote.packet
-            } else {
-                &keys.local.packet
-            };
-            let r = pkey.decrypt_in_place(pkt_num, &h2[..hlen + 1 + ((h20 & 3) as usize)], output);
+            let header = &h2[..hlen + 1 + ((h20 & 3) as usize)];
+            // the key phase bit of short headers flips on key updates
+            if h20 & 0x80 == 0 && (h20 & 0x04 != 0) != dkeys.key_phase {
+                let (secret, pkey) = dkeys.next_packet_key();
+                if let Ok(r2) = pkey.decrypt_in_place(pkt_num, header, output) {
+                    let dlen = r2.len();
+                    dkeys.update(secret, pkey);
+                    return Ok(dlen);
+                }
+                return Err(());
+            }
+            let r = dkeys.packet.decrypt_in_place(pkt_num, header, output);
             if let Ok(r2) = r {
                 return Ok(r2.len());
             }
@@ -219,7 +289,9 @@ impl QuicState {
         return Err(());
     }
 
-    fn handle_frames(&mut self, data: QuicData, header: QuicHeader, to_server: bool) {
+    fn handle_frames(
+        &mut self, data: QuicData, header: QuicHeader, to_server: bool, flow: *const Flow,
+    ) {
         let mut sni: Option<Vec<u8>> = None;
         let mut ua: Option<Vec<u8>> = None;
         let mut ja3: Option<String> = None;
@@ -256,21 +328,125 @@ impl QuicState {
                     for e in &c.extv {
                         if e.etype == TlsExtensionType::ServerName && !e.values.is_empty() {
                             sni = Some(e.values[0].to_vec());
+                        } else if to_server
+                            && self.http3.is_none()
+                            && e.etype == TlsExtensionType::ApplicationLayerProtocolNegotiation
+                            && e.values.iter().any(|v| v.starts_with(b"h3"))
+                            && unsafe { ALPROTO_HTTP3 } != ALPROTO_UNKNOWN
+                        {
+                            self.http3 = Some(Http3State::new());
                         }
                     }
                     extv.extend_from_slice(&c.extv);
                     if to_server {
+                        self.client_random = Some(c.random.clone());
                         self.hello_ts = true
                     } else {
+                        self.server_cipher = c.ciphers.first().map(|cipher| cipher.0);
                         self.hello_tc = true
                     }
                 }
                 _ => {}
             }
         }
+        if self.handshake_keys.is_none() && self.app_keys.is_none() {
+            if let Some(keylog) = unsafe { QUIC_KEYLOG } {
+                self.keylog_keys(keylog, u32::from(header.version));
+            }
+        }
+        if let Some(h3) = &mut self.http3 {
+            let dir = if to_server {
+                Direction::ToServer
+            } else {
+                Direction::ToClient
+            };
+            let mut messages = Vec::new();
+            for frame in &data.frames {
+                if let Frame::StreamData(s) = frame {
+                    messages.extend(h3.parse_stream(s.stream_id, s.offset, s.fin, &s.data, dir));
+                }
+            }
+            self.handle_http3(messages, &header, flow);
+        }
         self.new_tx(header, data, sni, ua, extv, ja3, ja4, to_server);
     }
 
+    /// Applies HTTP/3 headers and bodies to the transactions of their
+    /// request streams
+    fn handle_http3(
+        &mut self, messages: Vec<Http3Message>, header: &QuicHeader, flow: *const Flow,
+    ) {
+        for message in messages {
+            let stream_id = match message {
+                Http3Message::Headers { stream_id, .. } | Http3Message::Data { stream_id, .. } => {
+                    stream_id
+                }
+                Http3Message::Error(e) => {
+                    self.set_event_notx(e.into(), header.clone(), true);
+                    continue;
+                }
+            };
+            let index = self
+                .transactions
+                .iter()
+                .position(|tx| tx.http3.as_ref().map(|h3| h3.stream_id) == Some(stream_id));
+            let tx = match index {
+                Some(index) => &mut self.transactions[index],
+                None => {
+                    if !matches!(message, Http3Message::Headers { .. }) {
+                        continue;
+                    }
+                    let mut tx = QuicTransaction::new_empty(true, header.clone());
+                    self.max_tx_id += 1;
+                    tx.tx_id = self.max_tx_id;
+                    tx.tx_data = AppLayerTxData::new();
+                    tx.tx_data.file_tx = STREAM_TOSERVER | STREAM_TOCLIENT;
+                    tx.http3 = Some(Http3Transaction::new(stream_id, tx.tx_id));
+                    self.transactions.push_back(tx);
+                    if !flow.is_null() {
+                        unsafe {
+                            AppLayerForceProtocolChange(flow, ALPROTO_HTTP3);
+                        }
+                    }
+                    self.transactions.back_mut().unwrap()
+                }
+            };
+            tx.tx_data.update_file_flags(self.state_data.file_flags);
+            if let Some(h3) = &mut tx.http3 {
+                if let Err(e) = h3.handle_message(message, &mut tx.tx_data, flow) {
+                    tx.tx_data.set_event(QuicEvent::from(e) as u8);
+                }
+            }
+        }
+    }
+
+    /// Handshake and 1-RTT keys, if the key log has the secrets of this
+    /// connection
+    fn keylog_keys(&mut self, keylog: &KeyLog, version: u32) {
+        let secrets = match self.client_random.as_ref().and_then(|r| keylog.get(r)) {
+            Some(secrets) => secrets,
+            None => return,
+        };
+        let cipher = match self.server_cipher.map(QuicCipher::from_tls_id) {
+            Some(Some(cipher)) => cipher,
+            Some(None) => {
+                SCLogDebug!(
+                    "QUIC keylog secrets found, but cipher 0x{:04x} is not supported",
+                    self.server_cipher.unwrap_or(0)
+                );
+                return;
+            }
+            None => return,
+        };
+        if let (Some(client), Some(server)) = (&secrets.client_handshake, &secrets.server_handshake)
+        {
+            self.handshake_keys = quic_keys_from_secrets(cipher, version, client, server);
+        }
+        if let (Some(client), Some(server)) = (&secrets.client_traffic, &secrets.server_traffic) {
+            self.app_keys = quic_keys_from_secrets(cipher, version, client, server);
+        }
+    }
+
     fn set_event_notx(&mut self, event: QuicEvent, header: QuicHeader, client: bool) {
         let mut tx = QuicTransaction::new_empty(client, header);
         self.max_tx_id += 1;
@@ -279,13 +455,125 @@ impl QuicState {
         self.transactions.push_back(tx);
     }
 
-    fn parse(&mut self, input: &[u8], to_server: bool) -> bool {
+    /// Keys from the key log for a Handshake or 1-RTT packet
+    fn protected_keys(&self, header: &QuicHeader) -> Option<&QuicKeys> {
+        if header.ty == QuicType::Short {
+            self.app_keys.as_ref()
+        } else if header.ty == QuicType::Handshake && !header.version.is_gquic() {
+            self.handshake_keys.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Decrypt and parse a Handshake or 1-RTT packet with the keylog keys.
+    ///
+    /// Returns what follows the packet. Packets failing to decrypt are
+    /// skipped, as after a key update we have no keys for.
+    fn parse_protected<'a>(
+        &mut self, buf: &'a [u8], rest: &'a [u8], header: QuicHeader, to_server: bool,
+        flow: *const Flow, stream_slice: &StreamSlice,
+    ) -> Result<&'a [u8], ()> {
+        let (hlen, length) = if header.ty == QuicType::Short {
+            // the destination connection id is the source one of the peer
+            let dcid_len = if to_server {
+                self.scid_len_tc
+            } else {
+                self.scid_len_ts
+            };
+            if rest.len() < dcid_len {
+                self.set_event_notx(QuicEvent::ErrorOnHeader, header, to_server);
+                return Err(());
+            }
+            // short header packets extend to the end of the datagram
+            (1 + dcid_len, rest.len() - dcid_len)
+        } else {
+            (buf.len() - rest.len(), usize::from(header.length))
+        };
+        let (framebuf, next_buf) = buf[hlen..].split_at(length);
+        let keys = if header.ty == QuicType::Short {
+            self.app_keys.as_mut()
+        } else {
+            self.handshake_keys.as_mut()
+        };
+        let mut output = Vec::with_capacity(framebuf.len() + 4);
+        if let Ok(dlen) =
+            QuicState::decrypt(keys, to_server, length, framebuf, buf, hlen, &mut output)
+        {
+            output.resize(dlen, 0);
+        } else {
+            self.set_event_notx(QuicEvent::FailedDecrypt, header, to_server);
+            return Ok(next_buf);
+        }
+        match Frame::decode_protected_frames(&output) {
+            Ok(frames) => {
+                // the payload keeps its size once decrypted, so frames are
+                // at the same offsets in the protected payload
+                let payload = &framebuf[length - AES128_TAG_LEN - output.len()..];
+                for (frame, offset, len) in &frames {
+                    if let Frame::StreamData(_) = frame {
+                        let _frame = AppLayerFrame::new(
+                            flow,
+                            stream_slice,
+                            &payload[*offset..],
+                            *len as i64,
+                            QuicFrameType::Stream as u8,
+                            None,
+                        );
+                    }
+                }
+                let data = QuicData {
+                    frames: frames.into_iter().map(|(frame, _, _)| frame).collect(),
+                };
+                self.handle_frames(data, header, to_server, flow);
+            }
+            Err(_e) => {
+                self.set_event_notx(QuicEvent::ErrorOnData, header, to_server);
+                return Err(());
+            }
+        }
+        return Ok(next_buf);
+    }
+
+    fn parse(&mut self, stream_slice: &StreamSlice, to_server: bool, flow: *const Flow) -> bool {
         // so as to loop over multiple quic headers in one packet
-        let mut buf = input;
+        let mut buf = stream_slice.as_slice();
         while !buf.is_empty() {
             match QuicHeader::from_bytes(buf, DEFAULT_DCID_LEN) {
                 Ok((rest, header)) => {
+                    // packets without a length extend to the end of the datagram
+                    let pdu_len = buf.len() - rest.len() + usize::from(header.length);
+                    let _pdu = AppLayerFrame::new(
+                        flow,
+                        stream_slice,
+                        buf,
+                        pdu_len as i64,
+                        QuicFrameType::Pdu as u8,
+                        None,
+                    );
+                    if header.ty != QuicType::Short && !header.version.is_gquic() {
+                        if to_server {
+                            self.scid_len_ts = header.scid.len();
+                        } else {
+                            self.scid_len_tc = header.scid.len();
+                        }
+                    }
+                    if self.protected_keys(&header).is_some() {
+                        match self.parse_protected(buf, rest, header, to_server, flow, stream_slice)
+                        {
+                            Ok(next_buf) => {
+                                buf = next_buf;
+                                continue;
+                            }
+                            Err(()) => return false,
+                        }
+                    }
                     if (to_server && self.hello_ts) || (!to_server && self.hello_tc) {
+                        if self.handshake_keys.is_some() {
+                            // skip to the coalesced Handshake packets
+                            buf = &rest[usize::from(header.length)..];
+                            continue;
+                        }
                         // payload is encrypted, stop parsing here
                         return true;
                     }
@@ -304,9 +592,15 @@ impl QuicState {
                     let mut output;
                     if self.keys.is_some() {
                         output = Vec::with_capacity(framebuf.len() + 4);
-                        if let Ok(dlen) =
-                            self.decrypt(to_server, &header, framebuf, buf, hlen, &mut output)
-                        {
+                        if let Ok(dlen) = QuicState::decrypt(
+                            self.keys.as_mut(),
+                            to_server,
+                            header.length.into(),
+                            framebuf,
+                            buf,
+                            hlen,
+                            &mut output,
+                        ) {
                             output.resize(dlen, 0);
                         } else {
                             self.set_event_notx(QuicEvent::FailedDecrypt, header, to_server);
@@ -333,7 +627,7 @@ impl QuicState {
 
                     match QuicData::from_bytes(framebuf) {
                         Ok(data) => {
-                            self.handle_frames(data, header, to_server);
+                            self.handle_frames(data, header, to_server, flow);
                         }
                         Err(_e) => {
                             self.set_event_notx(QuicEvent::ErrorOnData, header, to_server);
@@ -390,13 +684,12 @@ pub unsafe extern "C" fn rs_quic_probing_parser(
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_quic_parse_tc(
-    _flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, QuicState);
-    let buf = stream_slice.as_slice();
 
-    if state.parse(buf, false) {
+    if state.parse(&stream_slice, false, flow) {
         return AppLayerResult::ok();
     } else {
         return AppLayerResult::err();
@@ -405,13 +698,12 @@ pub unsafe extern "C" fn rs_quic_parse_tc(
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_quic_parse_ts(
-    _flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, QuicState);
-    let buf = stream_slice.as_slice();
 
-    if state.parse(buf, true) {
+    if state.parse(&stream_slice, true, flow) {
         return AppLayerResult::ok();
     } else {
         return AppLayerResult::err();
@@ -471,6 +763,29 @@ pub unsafe extern "C" fn rs_quic_state_get_tx_iterator(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rs_http3_tx_get_alstate_progress(
+    tx: *mut std::os::raw::c_void, _direction: u8,
+) -> std::os::raw::c_int {
+    let tx = cast_pointer!(tx, QuicTransaction);
+    match &tx.http3 {
+        Some(h3) => h3.http.state as i32,
+        // packets are complete as soon as they are parsed
+        None => HTTP2TransactionState::HTTP2StateClosed as i32,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_http3_getfiles(
+    tx: *mut std::os::raw::c_void, direction: u8,
+) -> AppLayerGetFileState {
+    let tx = cast_pointer!(tx, QuicTransaction);
+    match &mut tx.http3 {
+        Some(h3) => rs_http2_getfiles(&mut h3.http as *mut _ as *mut _, direction),
+        None => AppLayerGetFileState::err(),
+    }
+}
+
 export_tx_data_get!(rs_quic_get_tx_data, QuicTransaction);
 export_state_data_get!(rs_quic_get_state_data, QuicState);
 
@@ -480,7 +795,7 @@ const PARSER_NAME: &[u8] = b"quic\0";
 #[no_mangle]
 pub unsafe extern "C" fn rs_quic_register_parser() {
     let default_port = CString::new("[443,80]").unwrap();
-    let parser = RustParser {
+    let mut parser = RustParser {
         name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port: default_port.as_ptr(),
         ipproto: IPPROTO_UDP,
@@ -508,8 +823,8 @@ pub unsafe extern "C" fn rs_quic_register_parser() {
         get_state_data: rs_quic_get_state_data,
         apply_tx_config: None,
         flags: 0,
-        get_frame_id_by_name: None,
-        get_frame_name_by_id: None,
+        get_frame_id_by_name: Some(QuicFrameType::ffi_id_from_name),
+        get_frame_name_by_id: Some(QuicFrameType::ffi_name_from_id),
     };
 
     let ip_proto_str = CString::new("udp").unwrap();
@@ -520,9 +835,249 @@ pub unsafe extern "C" fn rs_quic_register_parser() {
         if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
             let _ = AppLayerRegisterParser(&parser, alproto);
         }
+        if let Some(path) = conf_get("app-layer.protocols.quic.keylog-file") {
+            match KeyLog::from_file(path) {
+                Ok(keylog) => {
+                    SCLogConfig!("Loaded {} QUIC keylog secrets from {}", keylog.len(), path);
+                    QUIC_KEYLOG = Some(Box::leak(Box::new(keylog)));
+                }
+                Err(e) => {
+                    SCLogError!("Failed to read QUIC keylog file {}: {}", path, e);
+                }
+            }
+        }
         SCLogDebug!("Rust quic parser registered.");
         AppLayerParserRegisterLogger(IPPROTO_UDP, ALPROTO_QUIC);
     } else {
         SCLogDebug!("Protocol detector and parser disabled for quic.");
+        return;
+    }
+
+    // http3 is quic with another name, once a request is seen
+    parser.name = b"http3\0".as_ptr() as *const std::os::raw::c_char;
+    parser.default_port = std::ptr::null();
+    parser.probe_ts = None;
+    parser.probe_tc = None;
+    parser.tx_comp_st_ts = HTTP2TransactionState::HTTP2StateClosed as i32;
+    parser.tx_comp_st_tc = HTTP2TransactionState::HTTP2StateClosed as i32;
+    parser.tx_get_progress = rs_http3_tx_get_alstate_progress;
+    parser.get_tx_files = Some(rs_http3_getfiles);
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
+        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+            let _ = AppLayerRegisterParser(&parser, alproto);
+            ALPROTO_HTTP3 = alproto;
+        } else {
+            SCLogWarning!("HTTP3 is not meant to be detection-only.");
+        }
+        AppLayerParserRegisterLogger(IPPROTO_UDP, alproto);
+        SCLogDebug!("Rust http3 parser registered.");
+    } else {
+        SCLogNotice!("Protocol detector and parser disabled for HTTP3.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_SCID: &[u8] = &[0xab; 8];
+
+    fn handshake_header(payload_len: usize) -> Vec<u8> {
+        let mut header = vec![0xe3, 0, 0, 0, 1, SERVER_SCID.len() as u8];
+        header.extend_from_slice(SERVER_SCID);
+        // empty source connection id
+        header.push(0);
+        // packet number, payload and tag
+        let length = 0x4000 | (4 + payload_len + 16) as u16;
+        header.extend_from_slice(&length.to_be_bytes());
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        header
+    }
+
+    fn hello(msg_type: u8, random: &[u8], ciphers: &[u8]) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(random);
+        // empty session id
+        body.push(0);
+        if msg_type == 1 {
+            body.extend_from_slice(&(ciphers.len() as u16).to_be_bytes());
+            body.extend_from_slice(ciphers);
+            body.extend_from_slice(&[1, 0]);
+        } else {
+            body.extend_from_slice(ciphers);
+            body.push(0);
+        }
+        // no extensions
+        body.extend_from_slice(&[0, 0]);
+        let mut msg = vec![msg_type, 0, 0, body.len() as u8];
+        msg.extend_from_slice(&body);
+        // CRYPTO frame at offset 0
+        let mut frame = vec![0x06, 0x00, 0x40, msg.len() as u8];
+        frame.extend_from_slice(&msg);
+        frame
+    }
+
+    fn decrypt_frames(state: &mut QuicState, packet: &[u8]) -> Vec<Frame> {
+        let (rest, header) = QuicHeader::from_bytes(packet, DEFAULT_DCID_LEN).unwrap();
+        let (hlen, length) = if header.ty == QuicType::Short {
+            (1 + SERVER_SCID.len(), rest.len() - SERVER_SCID.len())
+        } else {
+            (packet.len() - rest.len(), usize::from(header.length))
+        };
+        let keys = if header.ty == QuicType::Short {
+            state.app_keys.as_mut()
+        } else {
+            state.handshake_keys.as_mut()
+        };
+        let framebuf = &packet[hlen..hlen + length];
+        let mut output = Vec::new();
+        let dlen =
+            QuicState::decrypt(keys, true, length, framebuf, packet, hlen, &mut output).unwrap();
+        output.resize(dlen, 0);
+        QuicData::from_protected_bytes(&output).unwrap().frames
+    }
+
+    #[test]
+    fn test_keylog_decrypt() {
+        let random: Vec<u8> = (0..32).collect();
+        let client_hs = [0x11; 32];
+        let client_app = [0x33; 32];
+        let keylog = format!(
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET {r} {}\n\
+             SERVER_HANDSHAKE_TRAFFIC_SECRET {r} {}\n\
+             CLIENT_TRAFFIC_SECRET_0 {r} {}\n\
+             SERVER_TRAFFIC_SECRET_0 {r} {}\n",
+            hex::encode(client_hs),
+            hex::encode([0x22; 32]),
+            hex::encode(client_app),
+            hex::encode([0x44; 32]),
+            r = hex::encode(&random)
+        );
+        let keylog = KeyLog::from_reader(keylog.as_bytes()).unwrap();
+
+        // the client random is the one of the parsed ClientHello
+        let mut state = QuicState::new();
+        let data = QuicData::from_bytes(&hello(1, &random, &[0x13, 0x01, 0x13, 0x02])).unwrap();
+        for frame in data.frames {
+            if let Frame::Crypto(c) = frame {
+                state.client_random = Some(c.random);
+            }
+        }
+        assert_eq!(state.client_random.as_deref(), Some(&random[..]));
+        let data = QuicData::from_bytes(&hello(2, &[0x55; 32], &[0x13, 0x01])).unwrap();
+        for frame in data.frames {
+            if let Frame::Crypto(c) = frame {
+                state.server_cipher = c.ciphers.first().map(|cipher| cipher.0);
+            }
+        }
+        state.keylog_keys(&keylog, 1);
+        assert!(state.handshake_keys.is_some());
+        assert!(state.app_keys.is_some());
+
+        // client Handshake packet with a PING
+        let keys =
+            quic_keys_from_secrets(QuicCipher::Aes128Gcm, 1, &client_hs, &[0x22; 32]).unwrap();
+        let payload = [0x01, 0x00, 0x00, 0x00];
+        let packet = keys
+            .remote
+            .protect(&handshake_header(payload.len()), 0, &payload);
+        let frames = decrypt_frames(&mut state, &packet);
+        assert_eq!(frames[0], Frame::Ping);
+
+        // client 1-RTT packet with a STREAM frame
+        let keys =
+            quic_keys_from_secrets(QuicCipher::Aes128Gcm, 1, &client_app, &[0x44; 32]).unwrap();
+        let mut payload = vec![0x0b, 0x00, 0x05];
+        payload.extend_from_slice(b"hello");
+        let mut header = vec![0x43];
+        header.extend_from_slice(SERVER_SCID);
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        let packet = keys.remote.protect(&header, 0, &payload);
+        let frames = decrypt_frames(&mut state, &packet);
+        assert_eq!(
+            frames[0],
+            Frame::StreamData(StreamData {
+                stream_id: 0,
+                offset: 0,
+                fin: true,
+                data: b"hello".to_vec(),
+            })
+        );
+    }
+
+    fn keylog_state(random: &[u8], cipher: u16, secret_len: usize) -> QuicState {
+        let keylog = format!(
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET {r} {}\n\
+             SERVER_HANDSHAKE_TRAFFIC_SECRET {r} {}\n\
+             CLIENT_TRAFFIC_SECRET_0 {r} {}\n\
+             SERVER_TRAFFIC_SECRET_0 {r} {}\n",
+            hex::encode(vec![0x11; secret_len]),
+            hex::encode(vec![0x22; secret_len]),
+            hex::encode(vec![0x33; secret_len]),
+            hex::encode(vec![0x44; secret_len]),
+            r = hex::encode(random)
+        );
+        let keylog = KeyLog::from_reader(keylog.as_bytes()).unwrap();
+        let mut state = QuicState::new();
+        state.client_random = Some(random.to_vec());
+        state.server_cipher = Some(cipher);
+        state.keylog_keys(&keylog, 1);
+        state
+    }
+
+    fn short_header(packet_number: u32) -> Vec<u8> {
+        let mut header = vec![0x43];
+        header.extend_from_slice(SERVER_SCID);
+        header.extend_from_slice(&packet_number.to_be_bytes());
+        header
+    }
+
+    #[test]
+    fn test_keylog_ciphers() {
+        let random = [0x66; 32];
+        for (cipher, id, secret_len) in [
+            (QuicCipher::Aes256Gcm, 0x1302, 48),
+            (QuicCipher::ChaCha20Poly1305, 0x1303, 32),
+        ] {
+            let mut state = keylog_state(&random, id, secret_len);
+            assert!(state.app_keys.is_some());
+            let keys =
+                quic_keys_from_secrets(cipher, 1, &vec![0x33; secret_len], &vec![0x44; secret_len])
+                    .unwrap();
+            let packet = keys
+                .remote
+                .protect(&short_header(3), 3, &[0x01, 0x00, 0x00, 0x00]);
+            assert_eq!(decrypt_frames(&mut state, &packet)[0], Frame::Ping);
+        }
+
+        // unsupported cipher
+        let state = keylog_state(&random, 0x1304, 32);
+        assert!(state.app_keys.is_none());
+    }
+
+    #[test]
+    fn test_keylog_key_update() {
+        let mut state = keylog_state(&[0x77; 32], 0x1301, 32);
+        let mut keys =
+            quic_keys_from_secrets(QuicCipher::Aes128Gcm, 1, &[0x33; 32], &[0x44; 32]).unwrap();
+        let payload = [0x01, 0x00, 0x00, 0x00];
+        let packet = keys.remote.protect(&short_header(1), 1, &payload);
+        assert_eq!(decrypt_frames(&mut state, &packet)[0], Frame::Ping);
+
+        // the client updates its keys, and flips the key phase bit
+        let (secret, packet_key) = keys.remote.next_packet_key();
+        keys.remote.update(secret, packet_key);
+        let mut header = short_header(2);
+        header[0] |= 0x04;
+        let packet = keys.remote.protect(&header, 2, &payload);
+        assert_eq!(decrypt_frames(&mut state, &packet)[0], Frame::Ping);
+        assert!(state.app_keys.as_ref().unwrap().remote.key_phase);
+
+        // following packets use the updated keys
+        let mut header = short_header(3);
+        header[0] |= 0x04;
+        let packet = keys.remote.protect(&header, 3, &payload);
+        assert_eq!(decrypt_frames(&mut state, &packet)[0], Frame::Ping);
     }
 }
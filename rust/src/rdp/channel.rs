@@ -0,0 +1,189 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Static virtual channel parsers
+//!
+//! Only the PDUs showing a redirection are parsed: the device list
+//! announced by the client on the device redirection channel (rdpdr), and
+//! the clipboard data sent on the clipboard channel (cliprdr). Channel
+//! data is only readable without standard RDP encryption, that is when
+//! TLS is used and decrypted, or when the encryption level is none.
+//!
+//! References:
+//! * rdp-spec: <https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/>
+//! * rdpefs-spec: <https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpefs/>
+//! * rdpeclip-spec: <https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeclip/>
+
+use crate::rdp::util::utf7_slice_to_string;
+use nom7::bytes::complete::take;
+use nom7::combinator::{map_res, verify};
+use nom7::number::complete::{le_u16, le_u32};
+use nom7::IResult;
+
+/// rdp-spec, section 2.2.6.1.1
+const CHANNEL_FLAG_FIRST: u32 = 0x1;
+const CHANNEL_PACKET_COMPRESSED: u32 = 0x0020_0000;
+
+/// rdpefs-spec, section 2.2.1.1
+const RDPDR_CTYP_CORE: u16 = 0x4472;
+const PAKID_CORE_DEVICELIST_ANNOUNCE: u16 = 0x4441;
+
+/// rdpeclip-spec, section 2.2.1
+const CB_FORMAT_DATA_RESPONSE: u16 = 0x0005;
+const CB_RESPONSE_OK: u16 = 0x0001;
+
+/// rdpefs-spec, section 2.2.1.3
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq, Eq)]
+pub enum RdpdrDeviceType {
+    Serial = 0x01,
+    Parallel = 0x02,
+    Print = 0x04,
+    Filesystem = 0x08,
+    Smartcard = 0x20,
+}
+
+/// rdpefs-spec, section 2.2.1.3
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RdpdrDevice {
+    pub device_type: Option<RdpdrDeviceType>,
+    pub id: u32,
+    /// preferred DOS name, e.g. the drive letter of a filesystem
+    pub name: String,
+}
+
+impl RdpdrDevice {
+    pub fn is_drive(&self) -> bool {
+        self.device_type == Some(RdpdrDeviceType::Filesystem)
+    }
+}
+
+/// data of the first chunk of an uncompressed channel PDU, the following
+/// chunks are not reassembled
+/// rdp-spec, section 2.2.6.1
+fn parse_first_chunk(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (i, _length) = le_u32(i)?;
+    let (i, _flags) = verify(le_u32, |&x| {
+        x & CHANNEL_FLAG_FIRST != 0 && x & CHANNEL_PACKET_COMPRESSED == 0
+    })(i)?;
+    Ok((&[], i))
+}
+
+/// rdpefs-spec, section 2.2.1.3
+fn parse_device_announce(i: &[u8]) -> IResult<&[u8], RdpdrDevice> {
+    let (i, device_type) = le_u32(i)?;
+    let (i, id) = le_u32(i)?;
+    let (i, name) = map_res(take(8_usize), utf7_slice_to_string)(i)?;
+    let (i, data_length) = le_u32(i)?;
+    let (i, _data) = take(data_length)(i)?;
+    Ok((
+        i,
+        RdpdrDevice {
+            device_type: num::FromPrimitive::from_u32(device_type),
+            id,
+            name,
+        },
+    ))
+}
+
+/// parser for a client device list announce request, the devices that
+/// do not fit in the first chunk are ignored
+/// rdpefs-spec, section 2.2.2.9
+pub fn parse_rdpdr_device_list(i: &[u8]) -> IResult<&[u8], Vec<RdpdrDevice>> {
+    let (_, i) = parse_first_chunk(i)?;
+    let (i, _component) = verify(le_u16, |&x| x == RDPDR_CTYP_CORE)(i)?;
+    let (i, _packet_id) = verify(le_u16, |&x| x == PAKID_CORE_DEVICELIST_ANNOUNCE)(i)?;
+    let (mut i, count) = le_u32(i)?;
+    let mut devices = Vec::new();
+    for _ in 0..count {
+        match parse_device_announce(i) {
+            Ok((rem, device)) => {
+                devices.push(device);
+                i = rem;
+            }
+            Err(_) => {
+                break;
+            }
+        }
+    }
+    Ok((i, devices))
+}
+
+/// parser for a successful format data response, which carries the
+/// clipboard data, returns the length of the data
+/// rdpeclip-spec, section 2.2.5.2
+pub fn parse_cliprdr_data_response(i: &[u8]) -> IResult<&[u8], u32> {
+    let (_, i) = parse_first_chunk(i)?;
+    let (i, _msg_type) = verify(le_u16, |&x| x == CB_FORMAT_DATA_RESPONSE)(i)?;
+    let (i, _msg_flags) = verify(le_u16, |&x| x & CB_RESPONSE_OK != 0)(i)?;
+    let (i, length) = le_u32(i)?;
+    Ok((i, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rdpdr_device_list() {
+        // channel PDU header, first and last chunk
+        let header: &[u8] = &[0x3c, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00];
+        // core component, device list announce, 2 devices
+        let announce: &[u8] = &[0x72, 0x44, 0x41, 0x44, 0x02, 0x00, 0x00, 0x00];
+        // smartcard, id 1, "SCARD"
+        let smartcard: &[u8] = &[
+            0x20, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x53, 0x43, 0x41, 0x52, 0x44, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        // filesystem, id 2, "C:", with a full name
+        let drive: &[u8] = &[
+            0x08, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x43, 0x3a, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x43, 0x00, 0x00, 0x00,
+        ];
+        let buf = [header, announce, smartcard, drive].concat();
+        let (_, devices) = parse_rdpdr_device_list(&buf).unwrap();
+        assert_eq!(2, devices.len());
+        assert_eq!(Some(RdpdrDeviceType::Smartcard), devices[0].device_type);
+        assert_eq!("SCARD", devices[0].name);
+        assert!(!devices[0].is_drive());
+        assert_eq!(2, devices[1].id);
+        assert_eq!("C:", devices[1].name);
+        assert!(devices[1].is_drive());
+
+        // a truncated list keeps the complete devices
+        let (_, devices) = parse_rdpdr_device_list(&buf[..buf.len() - 1]).unwrap();
+        assert_eq!(1, devices.len());
+
+        // not the first chunk
+        let mut next = buf.clone();
+        next[4] = 0x02;
+        assert!(parse_rdpdr_device_list(&next).is_err());
+    }
+
+    #[test]
+    fn test_parse_cliprdr_data_response() {
+        let buf: &[u8] = &[
+            0x14, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x0c, 0x00,
+            0x00, 0x00, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x00,
+        ];
+        assert_eq!(12, parse_cliprdr_data_response(buf).unwrap().1);
+
+        // CB_RESPONSE_FAIL
+        let mut fail = buf.to_vec();
+        fail[10] = 0x02;
+        assert!(parse_cliprdr_data_response(&fail).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! CredSSP parser, used by Network Level Authentication (NLA)
+//!
+//! The TSRequest messages are normally sent inside TLS, so they are only
+//! seen in clear when the TLS layer is terminated before the sensor.
+//!
+//! References:
+//! * cssp-spec: <https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-cssp/>
+
+use crate::rdp::error::RdpError;
+use crate::smb::auth::parse_secblob;
+use der_parser::ber::{BerObject, Class};
+use der_parser::der::{
+    parse_der_integer, parse_der_octetstring, parse_der_sequence, parse_der_u32,
+};
+use nom7::{Err, IResult};
+
+/// Authentication package carried by the negotiation tokens
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CredSspAuth {
+    Ntlm,
+    Kerberos,
+}
+
+/// cssp-spec, section 2.2.1
+///
+/// Only the identity found in the negotiation tokens is kept: the user,
+/// domain and workstation of a NTLM AUTHENTICATE message, or the realm
+/// and service of a Kerberos AP-REQ. The Kerberos client name is
+/// encrypted, so the user is not known in this case.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TsRequest {
+    pub version: u32,
+    pub auth: Option<CredSspAuth>,
+    pub user: Vec<u8>,
+    pub domain: Vec<u8>,
+    pub host: Vec<u8>,
+    pub service: Vec<u8>,
+    /// whether the encrypted credentials were sent
+    pub auth_info: bool,
+    pub error_code: Option<u32>,
+}
+
+fn ber_error(e: Err<der_parser::error::BerError>) -> Err<RdpError> {
+    e.map(|_| RdpError::NotCredSsp)
+}
+
+/// error codes are NTSTATUS or HRESULT values, often encoded as negative
+/// integers, so the bits are kept as they are
+fn parse_error_code(i: &[u8]) -> Option<u32> {
+    let (_, o) = parse_der_integer(i).ok()?;
+    let mut bytes = o.as_slice().ok()?;
+    if bytes.len() == 5 && bytes[0] == 0 {
+        bytes = &bytes[1..];
+    }
+    if bytes.is_empty() || bytes.len() > 4 {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u32))
+}
+
+/// content of an explicitly tagged field
+fn tagged_content<'a>(o: &BerObject<'a>) -> Option<(u32, &'a [u8])> {
+    if o.header.class() != Class::ContextSpecific {
+        return None;
+    }
+    let data = o.content.as_slice().ok()?;
+    Some((o.header.tag().0, data))
+}
+
+/// cssp-spec, section 2.2.1.1 NegoData
+fn parse_nego_tokens(i: &[u8]) -> Vec<&[u8]> {
+    let mut tokens = Vec::new();
+    let items = match parse_der_sequence(i) {
+        Ok((_, o)) => match o.content {
+            der_parser::ber::BerObjectContent::Sequence(items) => items,
+            _ => {
+                return tokens;
+            }
+        },
+        _ => {
+            return tokens;
+        }
+    };
+    for item in items {
+        let fields = match item.as_sequence() {
+            Ok(fields) => fields,
+            _ => {
+                continue;
+            }
+        };
+        for field in fields {
+            if let Some((0, data)) = tagged_content(field) {
+                if let Ok((_, token)) = parse_der_octetstring(data) {
+                    if let Ok(token) = token.as_slice() {
+                        tokens.push(token);
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+impl TsRequest {
+    fn set_identity(&mut self, token: &[u8]) {
+        let request = match parse_secblob(token) {
+            Some(r) => r,
+            None => {
+                return;
+            }
+        };
+        if let Some(ntlmssp) = request.ntlmssp {
+            self.auth = Some(CredSspAuth::Ntlm);
+            self.user = ntlmssp.user;
+            self.domain = ntlmssp.domain;
+            self.host = ntlmssp.host;
+        } else if let Some(krb) = request.krb {
+            self.auth = Some(CredSspAuth::Kerberos);
+            self.domain = krb.realm.0.into_bytes();
+            self.service = krb.sname.to_string().into_bytes();
+        }
+    }
+}
+
+/// parser for a TSRequest message
+/// cssp-spec, section 2.2.1
+pub fn parse_ts_request(input: &[u8]) -> IResult<&[u8], TsRequest, RdpError> {
+    let (rem, o) = parse_der_sequence(input).map_err(ber_error)?;
+    let fields = o
+        .as_sequence()
+        .map_err(|_| Err::Error(RdpError::NotCredSsp))?;
+    let mut version = None;
+    let mut request = TsRequest::default();
+    for field in fields {
+        let (tag, data) = match tagged_content(field) {
+            Some(t) => t,
+            None => {
+                continue;
+            }
+        };
+        match tag {
+            0 => {
+                version = parse_der_u32(data).ok().map(|(_, v)| v);
+            }
+            1 => {
+                for token in parse_nego_tokens(data) {
+                    request.set_identity(token);
+                }
+            }
+            2 => {
+                request.auth_info = true;
+            }
+            4 => {
+                request.error_code = parse_error_code(data);
+            }
+            // pubKeyAuth and clientNonce are not used
+            _ => {}
+        }
+    }
+    request.version = version.ok_or(Err::Error(RdpError::NotCredSsp))?;
+    Ok((rem, request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut v = vec![tag];
+        let len = content.len();
+        if len < 0x80 {
+            v.push(len as u8);
+        } else {
+            v.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+        }
+        v.extend_from_slice(content);
+        v
+    }
+
+    fn ts_request(token: &[u8]) -> Vec<u8> {
+        let version = tlv(0xa0, &tlv(0x02, &[0x06]));
+        let nego_token = tlv(0x30, &tlv(0xa0, &tlv(0x04, token)));
+        let nego_data = tlv(0xa1, &tlv(0x30, &nego_token));
+        tlv(0x30, &[version, nego_data].concat())
+    }
+
+    #[test]
+    fn test_parse_ts_request_ntlm() {
+        // SPNEGO NegTokenResp with a NTLMSSP AUTHENTICATE message
+        let token = hex::decode("a18202313082022da0030a0101a28202100482020c4e544c4d5353500003000000180018009c00000048014801b40000001e001e005800000008000800760000001e001e007e00000010001000fc010000158288e20a005a290000000fc6107a73184fb65fe684f6a1641464be4400450053004b0054004f0050002d0032004100450046004d003700470075007300650072004400450053004b0054004f0050002d0032004100450046004d003700470000000000000000000000000000000000000000000000000028a0c9f4e792c408913d2878feaa9a22010100000000000078a7ed218527d2010cf876f08a0b3bfa0000000002001e004400450053004b0054004f0050002d00560031004600410030005500510001001e004400450053004b0054004f0050002d00560031004600410030005500510004001e004400450053004b0054004f0050002d00560031004600410030005500510003001e004400450053004b0054004f0050002d0056003100460041003000550051000700080078a7ed218527d20106000400020000000800300030000000000000000100000000200000ad865b6d08a95d0e76a94e2ca013ab3f69c4fd945cca01b277700fd2b305ca010a001000000000000000000000000000000000000900280063006900660073002f003100390032002e003100360038002e003100390039002e003100330033000000000000000000000000005858824ec4a47b3b42ad3132ab84a5c3a31204100100000092302d756840453f00000000").unwrap();
+        let buf = ts_request(&token);
+
+        // truncated
+        assert!(matches!(
+            parse_ts_request(&buf[..buf.len() - 1]),
+            Err(Err::Incomplete(_))
+        ));
+
+        let (rem, request) = parse_ts_request(&buf).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(request.version, 6);
+        assert_eq!(request.auth, Some(CredSspAuth::Ntlm));
+        assert_eq!(request.user, b"user");
+        assert_eq!(request.domain, b"DESKTOP-2AEFM7G");
+        assert_eq!(request.host, b"DESKTOP-2AEFM7G");
+        assert!(!request.auth_info);
+        assert_eq!(request.error_code, None);
+    }
+
+    #[test]
+    fn test_parse_ts_request_error() {
+        // server reply with STATUS_LOGON_FAILURE, as a negative integer
+        let buf: &[u8] = &[
+            0x30, 0x0d, 0xa0, 0x03, 0x02, 0x01, 0x06, 0xa4, 0x06, 0x02, 0x04, 0xc0, 0x00, 0x00,
+            0x6d,
+        ];
+        let (_, request) = parse_ts_request(buf).unwrap();
+        assert_eq!(request.auth, None);
+        assert_eq!(request.error_code, Some(0xc000006d));
+
+        // and as a positive one
+        let buf: &[u8] = &[
+            0x30, 0x0e, 0xa0, 0x03, 0x02, 0x01, 0x06, 0xa4, 0x07, 0x02, 0x05, 0x00, 0xc0, 0x00,
+            0x00, 0x6d,
+        ];
+        let (_, request) = parse_ts_request(buf).unwrap();
+        assert_eq!(request.error_code, Some(0xc000006d));
+
+        // not a TSRequest
+        let buf: &[u8] = &[0x30, 0x03, 0x02, 0x01, 0x06];
+        assert!(parse_ts_request(buf).is_err());
+    }
+}
@@ -0,0 +1,501 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::parser::{
+    CsClientCoreData, McsConnectRequestChild, NegotiationFromServer, ProtocolFlags,
+};
+use super::rdp::{RdpTransaction, RdpTransactionItem, ALPROTO_RDP};
+use crate::detect::uint::{
+    detect_parse_uint, rs_detect_u32_free, rs_detect_u32_match, rs_detect_u32_parse,
+    DetectUintData, DetectUintMode,
+};
+use crate::detect::{
+    DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
+    DetectHelperGetData, DetectHelperKeywordRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
+};
+
+use nom7::branch::alt;
+use nom7::bytes::complete::{is_a, tag};
+use nom7::combinator::{opt, value};
+use nom7::multi::many1;
+use nom7::IResult;
+
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+
+static mut G_RDP_COOKIE_BUFFER_ID: c_int = 0;
+static mut G_RDP_CLIENT_NAME_BUFFER_ID: c_int = 0;
+static mut G_RDP_CLIENT_BUILD_KW_ID: c_int = 0;
+static mut G_RDP_CLIENT_BUILD_BUFFER_ID: c_int = 0;
+static mut G_RDP_KEYBOARD_LAYOUT_KW_ID: c_int = 0;
+static mut G_RDP_KEYBOARD_LAYOUT_BUFFER_ID: c_int = 0;
+static mut G_RDP_PROTOCOLS_KW_ID: c_int = 0;
+static mut G_RDP_PROTOCOLS_BUFFER_ID: c_int = 0;
+static mut G_RDP_CHANNEL_BUFFER_ID: c_int = 0;
+
+fn get_client_core(tx: &RdpTransaction) -> Option<&CsClientCoreData> {
+    if let RdpTransactionItem::McsConnectRequest(ref mcs) = tx.item {
+        for child in &mcs.children {
+            if let McsConnectRequestChild::CsClientCore(ref client) = child {
+                return Some(client);
+            }
+        }
+    }
+    return None;
+}
+
+/// Protocols requested by the client, or selected by the server. Standard
+/// RDP security is 0, which is also used when there is no negotiation.
+fn get_protocols(tx: &RdpTransaction) -> Option<u32> {
+    match tx.item {
+        RdpTransactionItem::X224ConnectionRequest(ref x224) => Some(
+            x224.negotiation_request
+                .as_ref()
+                .map_or(0, |req| req.protocols.bits()),
+        ),
+        RdpTransactionItem::X224ConnectionConfirm(ref x224) => match x224.negotiation_from_server {
+            Some(NegotiationFromServer::Response(ref resp)) => Some(resp.protocol.clone() as u32),
+            Some(NegotiationFromServer::Failure(_)) => None,
+            None => Some(0),
+        },
+        _ => None,
+    }
+}
+
+struct RdpProtocolFlag {
+    neg: bool,
+    value: u32,
+}
+
+fn parse_protocol_list_item(s: &str) -> IResult<&str, RdpProtocolFlag> {
+    let (s, _) = opt(is_a(" "))(s)?;
+    let (s, neg) = opt(tag("!"))(s)?;
+    let neg = neg.is_some();
+    let (s, value) = alt((
+        value(ProtocolFlags::PROTOCOL_SSL.bits(), tag("ssl")),
+        value(ProtocolFlags::PROTOCOL_HYBRID_EX.bits(), tag("hybrid_ex")),
+        value(ProtocolFlags::PROTOCOL_HYBRID.bits(), tag("hybrid")),
+        value(ProtocolFlags::PROTOCOL_RDSTLS.bits(), tag("rds_tls")),
+    ))(s)?;
+    let (s, _) = opt(is_a(" ,"))(s)?;
+    Ok((s, RdpProtocolFlag { neg, value }))
+}
+
+fn parse_protocols(s: &str) -> Option<DetectUintData<u32>> {
+    // try first numerical value
+    if let Ok((_, ctx)) = detect_parse_uint::<u32>(s) {
+        return Some(ctx);
+    }
+    // otherwise, try strings for bitmask
+    if let Ok((rem, l)) = many1(parse_protocol_list_item)(s) {
+        if !rem.is_empty() {
+            return None;
+        }
+        let mut arg1 = 0;
+        let mut arg2 = 0;
+        for elem in l.iter() {
+            if elem.value & arg1 != 0 {
+                SCLogWarning!("Repeated protocol for rdp.protocols");
+                return None;
+            }
+            arg1 |= elem.value;
+            if !elem.neg {
+                arg2 |= elem.value;
+            }
+        }
+        let ctx = DetectUintData::<u32> {
+            arg1,
+            arg2,
+            mode: DetectUintMode::DetectUintModeBitmask,
+        };
+        return Some(ctx);
+    }
+    return None;
+}
+
+unsafe extern "C" fn rdp_parse_protocols(
+    ustr: *const std::os::raw::c_char,
+) -> *mut DetectUintData<u32> {
+    let ft_name: &CStr = CStr::from_ptr(ustr); //unsafe
+    if let Ok(s) = ft_name.to_str() {
+        if let Some(ctx) = parse_protocols(s) {
+            let boxed = Box::new(ctx);
+            return Box::into_raw(boxed) as *mut _;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe fn rdp_detect_u32_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+    parse: unsafe extern "C" fn(*const libc::c_char) -> *mut DetectUintData<u32>, kw_id: c_int,
+    buffer_id: c_int,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0 {
+        return -1;
+    }
+    let ctx = parse(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(de, s, kw_id, ctx, buffer_id).is_null() {
+        rdp_detect_u32_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_u32_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    rs_detect_u32_free(ctx);
+}
+
+unsafe extern "C" fn rdp_detect_client_build_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    rdp_detect_u32_setup(
+        de,
+        s,
+        raw,
+        rs_detect_u32_parse,
+        G_RDP_CLIENT_BUILD_KW_ID,
+        G_RDP_CLIENT_BUILD_BUFFER_ID,
+    )
+}
+
+unsafe extern "C" fn rdp_detect_client_build_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    if let Some(client) = get_client_core(tx) {
+        return rs_detect_u32_match(client.client_build_number, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_keyboard_layout_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    rdp_detect_u32_setup(
+        de,
+        s,
+        raw,
+        rs_detect_u32_parse,
+        G_RDP_KEYBOARD_LAYOUT_KW_ID,
+        G_RDP_KEYBOARD_LAYOUT_BUFFER_ID,
+    )
+}
+
+unsafe extern "C" fn rdp_detect_keyboard_layout_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    if let Some(client) = get_client_core(tx) {
+        return rs_detect_u32_match(client.keyboard_layout, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_protocols_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    rdp_detect_u32_setup(
+        de,
+        s,
+        raw,
+        rdp_parse_protocols,
+        G_RDP_PROTOCOLS_KW_ID,
+        G_RDP_PROTOCOLS_BUFFER_ID,
+    )
+}
+
+unsafe extern "C" fn rdp_detect_protocols_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    if let Some(protocols) = get_protocols(tx) {
+        return rs_detect_u32_match(protocols, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_cookie_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_RDP_COOKIE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_cookie_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    if let RdpTransactionItem::X224ConnectionRequest(ref x224) = tx.item {
+        if let Some(ref cookie) = x224.cookie {
+            *buffer = cookie.mstshash.as_ptr();
+            *buffer_len = cookie.mstshash.len() as u32;
+            return true;
+        }
+    }
+    return false;
+}
+
+unsafe extern "C" fn rdp_detect_cookie_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        rdp_detect_cookie_get,
+    );
+}
+
+unsafe extern "C" fn rdp_detect_client_name_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_RDP_CLIENT_NAME_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_client_name_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    if let Some(client) = get_client_core(tx) {
+        if !client.client_name.is_empty() {
+            *buffer = client.client_name.as_ptr();
+            *buffer_len = client.client_name.len() as u32;
+            return true;
+        }
+    }
+    return false;
+}
+
+unsafe extern "C" fn rdp_detect_client_name_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        rdp_detect_client_name_get,
+    );
+}
+
+unsafe extern "C" fn rdp_detect_channel_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_RDP_CHANNEL_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn rdp_detect_channel_get(
+    tx: *const c_void, _flow_flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    if let RdpTransactionItem::ChannelJoin(ref channel) = tx.item {
+        *buffer = channel.name.as_ptr();
+        *buffer_len = channel.name.len() as u32;
+        return true;
+    }
+    return false;
+}
+
+unsafe extern "C" fn rdp_detect_channel_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        rdp_detect_channel_get,
+    );
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectRdpRegister() {
+    let kw = SCSigTableElmt {
+        name: b"rdp.cookie\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the RDP cookie (mstshash)\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-cookie\0".as_ptr() as *const libc::c_char,
+        Setup: rdp_detect_cookie_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_rdp_cookie_kw_id = DetectHelperKeywordRegister(&kw);
+    G_RDP_COOKIE_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"rdp.cookie\0".as_ptr() as *const libc::c_char,
+        b"RDP cookie\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        false,
+        true,
+        rdp_detect_cookie_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"rdp.client.name\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the RDP client computer name\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-client-name\0".as_ptr() as *const libc::c_char,
+        Setup: rdp_detect_client_name_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_rdp_client_name_kw_id = DetectHelperKeywordRegister(&kw);
+    G_RDP_CLIENT_NAME_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"rdp.client.name\0".as_ptr() as *const libc::c_char,
+        b"RDP client name\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        false,
+        true,
+        rdp_detect_client_name_get_data,
+    );
+    let kw = SCSigTableElmt {
+        name: b"rdp.client.build\0".as_ptr() as *const libc::c_char,
+        desc: b"match RDP client build number\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-client-build\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(rdp_detect_client_build_match),
+        Setup: rdp_detect_client_build_setup,
+        Free: Some(rdp_detect_u32_free),
+        flags: 0,
+    };
+    G_RDP_CLIENT_BUILD_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_RDP_CLIENT_BUILD_BUFFER_ID = DetectHelperBufferRegister(
+        b"rdp.client.build\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        false,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"rdp.keyboard_layout\0".as_ptr() as *const libc::c_char,
+        desc: b"match RDP client keyboard layout\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-keyboard-layout\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(rdp_detect_keyboard_layout_match),
+        Setup: rdp_detect_keyboard_layout_setup,
+        Free: Some(rdp_detect_u32_free),
+        flags: 0,
+    };
+    G_RDP_KEYBOARD_LAYOUT_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_RDP_KEYBOARD_LAYOUT_BUFFER_ID = DetectHelperBufferRegister(
+        b"rdp.keyboard_layout\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        false,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"rdp.protocols\0".as_ptr() as *const libc::c_char,
+        desc: b"match RDP security protocols requested by the client or selected by the server\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-protocols\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(rdp_detect_protocols_match),
+        Setup: rdp_detect_protocols_setup,
+        Free: Some(rdp_detect_u32_free),
+        flags: 0,
+    };
+    G_RDP_PROTOCOLS_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_RDP_PROTOCOLS_BUFFER_ID = DetectHelperBufferRegister(
+        b"rdp.protocols\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        true,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"rdp.channel\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the name of a joined RDP virtual channel\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/rdp-keywords.html#rdp-channel\0".as_ptr() as *const libc::c_char,
+        Setup: rdp_detect_channel_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_rdp_channel_kw_id = DetectHelperKeywordRegister(&kw);
+    G_RDP_CHANNEL_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"rdp.channel\0".as_ptr() as *const libc::c_char,
+        b"RDP channel\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RDP,
+        true,
+        true,
+        rdp_detect_channel_get_data,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::uint::detect_match_uint;
+
+    #[test]
+    fn test_parse_protocols() {
+        let ctx = parse_protocols("hybrid").unwrap();
+        assert_eq!(ctx.mode, DetectUintMode::DetectUintModeBitmask);
+        assert!(detect_match_uint(&ctx, 0x3));
+        assert!(!detect_match_uint(&ctx, 0x1));
+        // hybrid_ex is not hybrid
+        assert!(!detect_match_uint(&ctx, 0x8));
+
+        let ctx = parse_protocols("ssl, !hybrid").unwrap();
+        assert!(detect_match_uint(&ctx, 0x1));
+        assert!(detect_match_uint(&ctx, 0x9));
+        assert!(!detect_match_uint(&ctx, 0x3));
+
+        let ctx = parse_protocols("0").unwrap();
+        assert!(detect_match_uint(&ctx, 0));
+        assert!(!detect_match_uint(&ctx, 1));
+
+        assert!(parse_protocols("ssl,ssl").is_none());
+        assert!(parse_protocols("tls").is_none());
+    }
+}
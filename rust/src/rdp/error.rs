@@ -24,6 +24,7 @@ use nom7::ErrorConvert;
 pub enum RdpError {
     UnimplementedLengthDeterminant,
     NotX224Class0Error,
+    NotCredSsp,
     NomError(ErrorKind),
 }
 
@@ -17,6 +17,8 @@
 
 // Author: Zach Kelly <zach.kelly@lmco.com>
 
+use super::channel::{RdpdrDevice, RdpdrDeviceType};
+use super::credssp::{CredSspAuth, TsRequest};
 use super::rdp::{RdpTransaction, RdpTransactionItem};
 use crate::jsonbuilder::{JsonBuilder, JsonError};
 use crate::rdp::parser::*;
@@ -56,12 +58,81 @@ fn log(tx: &RdpTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
             }
             js.close()?;
         }
+
+        RdpTransactionItem::TsRequest(ref req) => ts_request_to_json(req, js)?,
+
+        RdpTransactionItem::ChannelJoin(ref channel) => {
+            js.set_string("event_type", "channel_join")?;
+            js.set_string("channel", &channel.name)?;
+            js.set_uint("channel_id", channel.id as u64)?;
+        }
+
+        RdpTransactionItem::DeviceList(ref devices) => device_list_to_json(devices, js)?,
+
+        RdpTransactionItem::ClipboardData(ref data) => {
+            js.set_string("event_type", "clipboard")?;
+            if data.to_server {
+                js.set_string("direction", "to_server")?;
+            } else {
+                js.set_string("direction", "to_client")?;
+            }
+            js.set_uint("length", data.length as u64)?;
+        }
     }
 
     js.close()?;
     Ok(())
 }
 
+/// json helper for TsRequest
+fn ts_request_to_json(req: &TsRequest, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.set_string("event_type", "credssp")?;
+    js.open_object("credssp")?;
+    js.set_uint("version", req.version as u64)?;
+    match req.auth {
+        Some(CredSspAuth::Ntlm) => {
+            js.set_string("auth", "ntlm")?;
+            js.set_string_from_bytes("user", &req.user)?;
+            js.set_string_from_bytes("domain", &req.domain)?;
+            js.set_string_from_bytes("host", &req.host)?;
+        }
+        Some(CredSspAuth::Kerberos) => {
+            js.set_string("auth", "kerberos")?;
+            js.set_string_from_bytes("domain", &req.domain)?;
+            js.set_string_from_bytes("service", &req.service)?;
+        }
+        None => {}
+    }
+    if let Some(code) = req.error_code {
+        js.set_string("error_code", &format!("0x{:08x}", code))?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+/// json helper for the devices announced on the device redirection channel
+fn device_list_to_json(devices: &[RdpdrDevice], js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.set_string("event_type", "device_announce")?;
+    js.open_array("devices")?;
+    for device in devices {
+        js.start_object()?;
+        let typ = match device.device_type {
+            Some(RdpdrDeviceType::Serial) => "serial",
+            Some(RdpdrDeviceType::Parallel) => "parallel",
+            Some(RdpdrDeviceType::Print) => "printer",
+            Some(RdpdrDeviceType::Filesystem) => "filesystem",
+            Some(RdpdrDeviceType::Smartcard) => "smartcard",
+            None => "unknown",
+        };
+        js.set_string("type", typ)?;
+        js.set_uint("id", device.id as u64)?;
+        js.set_string("name", &device.name)?;
+        js.close()?;
+    }
+    js.close()?;
+    Ok(())
+}
+
 /// json helper for X224ConnectionRequest
 fn x224_req_to_json(x224: &X224ConnectionRequest, js: &mut JsonBuilder) -> Result<(), JsonError> {
     use crate::rdp::parser::NegotiationRequestFlags as Flags;
@@ -459,6 +530,7 @@ mod tests {
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
+            client_build_number: 17763,
             client_name: String::from("SERVER-XYZ"),
             keyboard_type: None,
             keyboard_subtype: 0,
@@ -495,6 +567,7 @@ mod tests {
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
+            client_build_number: 17763,
             client_name: String::from("SERVER-XYZ"),
             keyboard_type: None,
             keyboard_subtype: 0,
@@ -531,6 +604,7 @@ mod tests {
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
+            client_build_number: 17763,
             client_name: String::from("SERVER-XYZ"),
             keyboard_type: None,
             keyboard_subtype: 0,
@@ -567,6 +641,7 @@ mod tests {
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
+            client_build_number: 17763,
             client_name: String::from("SERVER-XYZ"),
             keyboard_type: None,
             keyboard_subtype: 0,
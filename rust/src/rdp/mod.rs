@@ -19,6 +19,9 @@
 //!
 //! written by Zach Kelly <zach.kelly@lmco.com>
 
+pub mod channel;
+pub mod credssp;
+pub mod detect;
 pub mod error;
 pub mod log;
 pub mod parser;
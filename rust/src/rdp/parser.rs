@@ -32,9 +32,9 @@ use crate::rdp::util::{le_slice_to_string, parse_per_length_determinant, utf7_sl
 use crate::rdp::windows;
 use nom7::bits::streaming::take as take_bits;
 use nom7::bytes::streaming::{tag, take};
-use nom7::combinator::{map, map_opt, map_res, opt, verify};
+use nom7::combinator::{complete, cond, map, map_opt, map_res, opt, verify};
 use nom7::error::{make_error, ErrorKind};
-use nom7::multi::length_data;
+use nom7::multi::{count, length_data};
 use nom7::number::streaming::{be_u16, be_u8, le_u16, le_u32, le_u8};
 use nom7::sequence::tuple;
 use nom7::{Err, IResult};
@@ -237,6 +237,8 @@ pub struct X223Data {
 pub enum X223DataChild {
     McsConnectRequest(McsConnectRequest),
     McsConnectResponse(McsConnectResponse),
+    McsChannelJoinConfirm(McsChannelJoinConfirm),
+    McsSendData(McsSendData),
     Raw(Vec<u8>),
 }
 
@@ -278,6 +280,7 @@ pub struct CsClientCoreData {
     pub sas_sequence: Option<SasSequence>,
     pub keyboard_layout: u32, // see windows::lcid_to_string
     pub client_build: windows::OperatingSystem,
+    pub client_build_number: u32,
     pub client_name: String,
     pub keyboard_type: Option<KeyboardType>,
     pub keyboard_subtype: u32,
@@ -431,8 +434,60 @@ pub struct CsUnknown {
 }
 
 /// rdp-spec, section 2.2.1.4
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct McsConnectResponse {
+    pub security: Option<ScSecurity>,
+    pub net: Option<ScNet>,
+}
+
+/// rdp-spec, section 2.2.1.4.1
+#[derive(Clone, Debug, FromPrimitive, PartialEq, Eq)]
+pub enum ScType {
+    Core = 0x0c01,
+    Security = 0x0c02,
+    Net = 0x0c03,
+}
+
+/// rdp-spec, section 2.2.1.4.3
+/// the server random and certificate are not kept
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScSecurity {
+    pub encryption_method: u32,
+    pub encryption_level: u32,
+}
+
+/// rdp-spec, section 2.2.1.4.4
+/// channel ids are in the order of the channels of the client network data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScNet {
+    pub io_channel: u16,
+    pub channel_ids: Vec<u16>,
+}
+
+/// t.125-spec, section 7, part 10, choices of DomainMCSPDU
+#[derive(Clone, Debug, FromPrimitive, PartialEq, Eq)]
+pub enum McsDomainType {
+    ChannelJoinConfirm = 15,
+    SendDataRequest = 25,
+    SendDataIndication = 26,
+}
+
+/// rdp-spec, section 2.2.1.9
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct McsConnectResponse {}
+pub struct McsChannelJoinConfirm {
+    pub result: u8,
+    pub user_id: u16,
+    pub requested: u16,
+    pub channel_id: Option<u16>,
+}
+
+/// rdp-spec, section 2.2.6.1, the data being a virtual channel chunk
+/// when sent on a static virtual channel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct McsSendData {
+    pub channel_id: u16,
+    pub data: Vec<u8>,
+}
 
 // ==================
 
@@ -715,7 +770,16 @@ fn parse_x223_data_class_0(input: &[u8]) -> IResult<&[u8], X223Data, RdpError> {
         },
     };
 
-    let child: X223DataChild = match opt2 {
+    // domain PDUs are bounded by the tpkt, so a truncated one is unknown
+    let opt3: Option<X223DataChild> = match opt2 {
+        Some(x) => Some(x),
+        None => match opt(complete(parse_mcs_domain_pdu))(i3) {
+            Ok((_remainder, opt)) => opt,
+            Err(e) => return Err(e),
+        },
+    };
+
+    let child: X223DataChild = match opt3 {
         Some(x) => x,
         None => X223DataChild::Raw(i3.to_vec()),
     };
@@ -803,7 +867,8 @@ fn parse_cs_client_core_data(input: &[u8]) -> IResult<&[u8], CsClientCoreData> {
     let (j4, color_depth) = map(le_u16, num::FromPrimitive::from_u16)(j3)?;
     let (j5, sas_sequence) = map(le_u16, num::FromPrimitive::from_u16)(j4)?;
     let (j6, keyboard_layout) = le_u32(j5)?;
-    let (j7, client_build) = map(le_u32, windows::build_number_to_os)(j6)?;
+    let (j7, client_build_number) = le_u32(j6)?;
+    let client_build = windows::build_number_to_os(client_build_number);
     let (j8, client_name) = map_res(take(32_usize), le_slice_to_string)(j7)?;
     let (j9, keyboard_type) = map(le_u32, num::FromPrimitive::from_u32)(j8)?;
     let (j10, keyboard_subtype) = le_u32(j9)?;
@@ -957,6 +1022,7 @@ fn parse_cs_client_core_data(input: &[u8]) -> IResult<&[u8], CsClientCoreData> {
             sas_sequence,
             keyboard_layout,
             client_build,
+            client_build_number,
             client_name,
             keyboard_type,
             keyboard_subtype,
@@ -1027,7 +1093,120 @@ fn parse_mcs_connect_response(i: &[u8]) -> IResult<&[u8], McsConnectResponse, Rd
         // BER: 0b01=application, 0b1=non-primitive, 0b11111
         |&x| x == 0x7f)(i)?;
     let (i, _t125_type) = verify(le_u8, |&x| x == T125Type::T125TypeMcsConnectResponse as u8)(i)?;
-    Ok((i, McsConnectResponse {}))
+    let mut response = McsConnectResponse::default();
+    // the server data is optional here, as the response is only used as a
+    // marker when it is missing
+    if let Some(data) = find_server_data(i) {
+        let mut remainder = data;
+        while let Ok((rem, (typ, block))) = parse_sc_block(remainder) {
+            match num::FromPrimitive::from_u16(typ) {
+                Some(ScType::Security) => {
+                    response.security = parse_sc_security(block).ok().map(|(_, x)| x);
+                }
+                Some(ScType::Net) => {
+                    response.net = parse_sc_net(block).ok().map(|(_, x)| x);
+                }
+                _ => {}
+            }
+            remainder = rem;
+        }
+    }
+    Ok((i, response))
+}
+
+/// server data blocks, following the H.221 server-to-client key
+/// rdp-spec, section 2.2.1.4
+fn find_server_data(i: &[u8]) -> Option<&[u8]> {
+    let key = i.windows(4).position(|w| w == b"McDn")?;
+    let (_, data) = length_data(parse_per_length_determinant)(&i[key + 4..]).ok()?;
+    Some(data)
+}
+
+/// rdp-spec, section 2.2.1.4.1
+fn parse_sc_block(i: &[u8]) -> IResult<&[u8], (u16, &[u8])> {
+    let (i, typ) = le_u16(i)?;
+    // less u16, u16
+    let (i, sz) = map_opt(le_u16, |x: u16| x.checked_sub(4))(i)?;
+    let (i, data) = take(sz)(i)?;
+    Ok((i, (typ, data)))
+}
+
+/// rdp-spec, section 2.2.1.4.3
+fn parse_sc_security(i: &[u8]) -> IResult<&[u8], ScSecurity> {
+    let (i, encryption_method) = le_u32(i)?;
+    let (i, encryption_level) = le_u32(i)?;
+    Ok((i, ScSecurity { encryption_method, encryption_level }))
+}
+
+/// rdp-spec, section 2.2.1.4.4
+fn parse_sc_net(i: &[u8]) -> IResult<&[u8], ScNet> {
+    let (i, io_channel) = le_u16(i)?;
+    let (i, channel_count) = le_u16(i)?;
+    let (i, channel_ids) = count(le_u16, channel_count as usize)(i)?;
+    Ok((i, ScNet { io_channel, channel_ids }))
+}
+
+/// t.125-spec, section 7, part 10
+/// the choice is in the upper 6 bits of the first byte, and user ids are
+/// sent less 1001 (MCS_BASE_CHANNEL_ID)
+fn parse_mcs_domain_pdu(i: &[u8]) -> IResult<&[u8], X223DataChild, RdpError> {
+    let (i, header) = be_u8(i)?;
+    let choice = match num::FromPrimitive::from_u8(header >> 2) {
+        Some(x) => x,
+        None => {
+            return Err(Err::Error(make_error(i, ErrorKind::Switch)));
+        }
+    };
+    match choice {
+        // rdp-spec, section 2.2.1.9
+        McsDomainType::ChannelJoinConfirm => {
+            // the channelId field is optional
+            let with_channel_id = header & 0x02 != 0;
+            let (i, result) = be_u8(i)?;
+            let (i, user_id) = map(be_u16, |x| x.wrapping_add(1001))(i)?;
+            let (i, requested) = be_u16(i)?;
+            let (i, channel_id) = cond(with_channel_id, be_u16)(i)?;
+            Ok((i, X223DataChild::McsChannelJoinConfirm(McsChannelJoinConfirm {
+                result,
+                user_id,
+                requested,
+                channel_id,
+            })))
+        }
+        // rdp-spec, section 2.2.6.1
+        McsDomainType::SendDataRequest | McsDomainType::SendDataIndication => {
+            let (i, _user_id) = be_u16(i)?;
+            let (i, channel_id) = be_u16(i)?;
+            // data priority and segmentation
+            let (i, _flags) = be_u8(i)?;
+            let (i, data) = length_data(parse_per_length_determinant)(i)?;
+            Ok((i, X223DataChild::McsSendData(McsSendData {
+                channel_id,
+                data: data.to_vec(),
+            })))
+        }
+    }
+}
+
+/// rdp-spec, sections 2.2.8.1.2 and 2.2.9.1.2
+const FASTPATH_ACTION: u8 = 0x0;
+
+/// parser for a fast-path PDU, which is skipped
+/// rdp-spec, sections 2.2.8.1.2 and 2.2.9.1.2
+pub fn parse_fastpath(input: &[u8]) -> IResult<&[u8], (), RdpError> {
+    let (i, _header) = verify(be_u8, |&x| x & 0x3 == FASTPATH_ACTION)(input)?;
+    let (i, length1) = be_u8(i)?;
+    // the length includes the header
+    let (i, length) = if length1 & 0x80 != 0 {
+        map(be_u8, |x| (length1 as u16 & 0x7f) << 8 | x as u16)(i)?
+    } else {
+        (i, length1 as u16)
+    };
+    if (length as usize) < input.len() - i.len() {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    let (i, _pdu) = take(length)(input)?;
+    Ok((i, ()))
 }
 
 #[cfg(test)]
@@ -1159,6 +1338,7 @@ mod tests_core_49350 {
                 build: windows::Build::Vista_6001,
                 suffix: windows::Suffix::Sp1,
             },
+            client_build_number: 6001,
             client_name: String::from("SERVER-XYZ"),
             keyboard_type: Some(KeyboardType::KbEnhanced),
             keyboard_subtype: 0,
@@ -1226,7 +1406,7 @@ mod tests_x223_response_49350 {
                 &[][..],
                 T123Tpkt {
                     child: T123TpktChild::Data(X223Data {
-                        child: X223DataChild::McsConnectResponse(McsConnectResponse {}),
+                        child: X223DataChild::McsConnectResponse(McsConnectResponse::default()),
                     })
                 }
             )),
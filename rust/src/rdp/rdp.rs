@@ -21,13 +21,21 @@
 
 use crate::applayer::{self, *};
 use crate::core::{AppProto, Flow, ALPROTO_UNKNOWN, IPPROTO_TCP};
+use crate::rdp::channel::{parse_cliprdr_data_response, parse_rdpdr_device_list, RdpdrDevice};
+use crate::rdp::credssp::{parse_ts_request, TsRequest};
 use crate::rdp::parser::*;
 use nom7::Err;
 use std;
 use std::collections::VecDeque;
 use tls_parser::{parse_tls_plaintext, TlsMessage, TlsMessageHandshake, TlsRecordType};
 
-static mut ALPROTO_RDP: AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_RDP: AppProto = ALPROTO_UNKNOWN;
+
+#[derive(AppLayerEvent)]
+pub enum RdpEvent {
+    DriveRedirection,
+    ClipboardRedirection,
+}
 
 //
 // transactions
@@ -38,6 +46,20 @@ pub struct CertificateBlob {
     pub data: Vec<u8>,
 }
 
+/// static virtual channel, with the id given by the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RdpChannel {
+    pub name: String,
+    pub id: u16,
+}
+
+/// clipboard data sent over the clipboard channel
+#[derive(Debug, PartialEq, Eq)]
+pub struct ClipboardData {
+    pub to_server: bool,
+    pub length: u32,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum RdpTransactionItem {
     X224ConnectionRequest(X224ConnectionRequest),
@@ -45,6 +67,10 @@ pub enum RdpTransactionItem {
     McsConnectRequest(McsConnectRequest),
     McsConnectResponse(McsConnectResponse),
     TlsCertificateChain(Vec<CertificateBlob>),
+    TsRequest(TsRequest),
+    ChannelJoin(RdpChannel),
+    DeviceList(Vec<RdpdrDevice>),
+    ClipboardData(ClipboardData),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -112,6 +138,13 @@ pub struct RdpState {
     transactions: VecDeque<RdpTransaction>,
     tls_parsing: bool,
     bypass_parsing: bool,
+    /// channels requested in the client network data
+    requested_channels: Vec<String>,
+    channels: Vec<RdpChannel>,
+    /// set after the MCS connect response, fast-path PDUs may follow
+    connected: bool,
+    /// whether channel data is sent without standard RDP encryption
+    channel_data_in_clear: bool,
 }
 
 impl State<RdpTransaction> for RdpState {
@@ -132,6 +165,10 @@ impl RdpState {
             transactions: VecDeque::new(),
             tls_parsing: false,
             bypass_parsing: false,
+            requested_channels: Vec::new(),
+            channels: Vec::new(),
+            connected: false,
+            channel_data_in_clear: false,
         }
     }
 
@@ -162,6 +199,106 @@ impl RdpState {
         return tx;
     }
 
+    /// CredSSP messages are only logged when they carry an identity or
+    /// an error
+    fn add_ts_request(&mut self, request: TsRequest) {
+        if request.auth.is_some() || request.error_code.is_some() {
+            let tx = self.new_tx(RdpTransactionItem::TsRequest(request));
+            self.transactions.push_back(tx);
+        }
+    }
+
+    fn add_mcs_connect_request(&mut self, mcs: McsConnectRequest) {
+        for child in &mcs.children {
+            if let McsConnectRequestChild::CsNet(net) = child {
+                self.requested_channels = net.channels.clone();
+            }
+        }
+        let tx = self.new_tx(RdpTransactionItem::McsConnectRequest(mcs));
+        self.transactions.push_back(tx);
+    }
+
+    /// the server gives the channel ids in the order of the request
+    fn add_mcs_connect_response(&mut self, mcs: McsConnectResponse) {
+        if let Some(ref net) = mcs.net {
+            self.channels = self
+                .requested_channels
+                .iter()
+                .zip(net.channel_ids.iter())
+                .map(|(name, &id)| RdpChannel {
+                    name: name.clone(),
+                    id,
+                })
+                .collect();
+        }
+        // ENCRYPTION_LEVEL_NONE, also used with TLS and CredSSP
+        self.channel_data_in_clear = matches!(
+            mcs.security,
+            Some(ScSecurity {
+                encryption_level: 0,
+                ..
+            })
+        );
+        self.connected = true;
+        let tx = self.new_tx(RdpTransactionItem::McsConnectResponse(mcs));
+        self.transactions.push_back(tx);
+    }
+
+    fn get_channel(&self, id: u16) -> Option<&RdpChannel> {
+        self.channels.iter().find(|c| c.id == id)
+    }
+
+    /// only successful joins of the static virtual channels are logged,
+    /// not the ones of the I/O and user channels
+    fn add_channel_join(&mut self, confirm: McsChannelJoinConfirm) {
+        // rt-successful
+        if confirm.result != 0 {
+            return;
+        }
+        let id = confirm.channel_id.unwrap_or(confirm.requested);
+        if let Some(channel) = self.get_channel(id).cloned() {
+            let tx = self.new_tx(RdpTransactionItem::ChannelJoin(channel));
+            self.transactions.push_back(tx);
+        }
+    }
+
+    /// look for redirections in the data sent on the device redirection
+    /// and clipboard channels
+    fn add_send_data(&mut self, data: McsSendData, to_server: bool) {
+        let name = match self.get_channel(data.channel_id) {
+            Some(channel) => channel.name.to_ascii_lowercase(),
+            None => {
+                return;
+            }
+        };
+        match name.as_str() {
+            "rdpdr" if to_server => {
+                if let Ok((_, devices)) = parse_rdpdr_device_list(&data.data) {
+                    if devices.is_empty() {
+                        return;
+                    }
+                    let drive = devices.iter().any(|d| d.is_drive());
+                    let mut tx = self.new_tx(RdpTransactionItem::DeviceList(devices));
+                    if drive {
+                        tx.tx_data.set_event(RdpEvent::DriveRedirection as u8);
+                    }
+                    self.transactions.push_back(tx);
+                }
+            }
+            "cliprdr" => {
+                if let Ok((_, length)) = parse_cliprdr_data_response(&data.data) {
+                    let mut tx = self.new_tx(RdpTransactionItem::ClipboardData(ClipboardData {
+                        to_server,
+                        length,
+                    }));
+                    tx.tx_data.set_event(RdpEvent::ClipboardRedirection as u8);
+                    self.transactions.push_back(tx);
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// parse buffer captures from client to server
     fn parse_ts(&mut self, input: &[u8]) -> AppLayerResult {
         // no need to process input buffer
@@ -211,12 +348,14 @@ impl RdpState {
 
                             // X.223 data packet, evaluate what it encapsulates
                             T123TpktChild::Data(x223) => {
-                                #[allow(clippy::single_match)]
                                 match x223.child {
                                     X223DataChild::McsConnectRequest(mcs) => {
-                                        let tx =
-                                            self.new_tx(RdpTransactionItem::McsConnectRequest(mcs));
-                                        self.transactions.push_back(tx);
+                                        self.add_mcs_connect_request(mcs);
+                                    }
+                                    X223DataChild::McsSendData(data)
+                                        if self.channel_data_in_clear =>
+                                    {
+                                        self.add_send_data(data, true);
                                     }
                                     // unknown message in X.223, skip
                                     _ => (),
@@ -247,6 +386,41 @@ impl RdpState {
                             } else {
                                 return r;
                             }
+                        } else if self.connected && probe_fastpath(available) {
+                            match parse_fastpath(available) {
+                                Ok((remainder, _)) => {
+                                    available = remainder;
+                                }
+
+                                Err(Err::Incomplete(_)) => {
+                                    return AppLayerResult::incomplete(
+                                        (input.len() - available.len()) as u32,
+                                        (available.len() + 1) as u32,
+                                    );
+                                }
+
+                                Err(Err::Failure(_)) | Err(Err::Error(_)) => {
+                                    return AppLayerResult::err();
+                                }
+                            }
+                        } else if probe_credssp(available) {
+                            match parse_ts_request(available) {
+                                Ok((remainder, request)) => {
+                                    available = remainder;
+                                    self.add_ts_request(request);
+                                }
+
+                                Err(Err::Incomplete(_)) => {
+                                    return AppLayerResult::incomplete(
+                                        (input.len() - available.len()) as u32,
+                                        (available.len() + 1) as u32,
+                                    );
+                                }
+
+                                Err(Err::Failure(_)) | Err(Err::Error(_)) => {
+                                    return AppLayerResult::err();
+                                }
+                            }
                         } else {
                             return AppLayerResult::err();
                         }
@@ -325,14 +499,25 @@ impl RdpState {
 
                             // X.223 data packet, evaluate what it encapsulates
                             T123TpktChild::Data(x223) => {
-                                #[allow(clippy::single_match)]
                                 match x223.child {
                                     X223DataChild::McsConnectResponse(mcs) => {
-                                        let tx = self
-                                            .new_tx(RdpTransactionItem::McsConnectResponse(mcs));
-                                        self.transactions.push_back(tx);
-                                        self.bypass_parsing = true;
-                                        return AppLayerResult::ok();
+                                        self.add_mcs_connect_response(mcs);
+                                        // without channels, there is nothing more to see
+                                        if self.channels.is_empty() {
+                                            self.bypass_parsing = true;
+                                            return AppLayerResult::ok();
+                                        }
+                                    }
+                                    X223DataChild::McsChannelJoinConfirm(confirm) => {
+                                        self.add_channel_join(confirm);
+                                    }
+                                    X223DataChild::McsSendData(data) => {
+                                        if !self.channel_data_in_clear {
+                                            // the joins are over, and the rest is encrypted
+                                            self.bypass_parsing = true;
+                                            return AppLayerResult::ok();
+                                        }
+                                        self.add_send_data(data, false);
                                     }
 
                                     // unknown message in X.223, skip
@@ -364,6 +549,41 @@ impl RdpState {
                             } else {
                                 return r;
                             }
+                        } else if self.connected && probe_fastpath(available) {
+                            match parse_fastpath(available) {
+                                Ok((remainder, _)) => {
+                                    available = remainder;
+                                }
+
+                                Err(Err::Incomplete(_)) => {
+                                    return AppLayerResult::incomplete(
+                                        (input.len() - available.len()) as u32,
+                                        (available.len() + 1) as u32,
+                                    );
+                                }
+
+                                Err(Err::Failure(_)) | Err(Err::Error(_)) => {
+                                    return AppLayerResult::err();
+                                }
+                            }
+                        } else if probe_credssp(available) {
+                            match parse_ts_request(available) {
+                                Ok((remainder, request)) => {
+                                    available = remainder;
+                                    self.add_ts_request(request);
+                                }
+
+                                Err(Err::Incomplete(_)) => {
+                                    return AppLayerResult::incomplete(
+                                        (input.len() - available.len()) as u32,
+                                        (available.len() + 1) as u32,
+                                    );
+                                }
+
+                                Err(Err::Failure(_)) | Err(Err::Error(_)) => {
+                                    return AppLayerResult::err();
+                                }
+                            }
                         } else {
                             return AppLayerResult::err();
                         }
@@ -425,6 +645,16 @@ fn probe_tls_handshake(input: &[u8]) -> bool {
     !input.is_empty() && input[0] == u8::from(TlsRecordType::Handshake)
 }
 
+/// probe for a fast-path PDU, which has no tpkt header
+fn probe_fastpath(input: &[u8]) -> bool {
+    !input.is_empty() && input[0] & 0x3 == 0
+}
+
+/// probe for a CredSSP TSRequest, a DER sequence
+fn probe_credssp(input: &[u8]) -> bool {
+    !input.is_empty() && input[0] == 0x30
+}
+
 //
 // parse
 //
@@ -483,8 +713,8 @@ pub unsafe extern "C" fn rs_rdp_register_parser() {
         tx_comp_st_ts: 1,
         tx_comp_st_tc: 1,
         tx_get_progress: rs_rdp_tx_get_progress,
-        get_eventinfo: None,
-        get_eventinfo_byid: None,
+        get_eventinfo: Some(RdpEvent::get_event_info),
+        get_eventinfo_byid: Some(RdpEvent::get_event_info_by_id),
         localstorage_new: None,
         localstorage_free: None,
         get_tx_files: None,
@@ -586,10 +816,85 @@ mod tests {
         // exactly aligns with transaction
         assert_eq!(AppLayerResult::ok(), state.parse_tc(buf_2));
         assert_eq!(1, state.transactions.len());
-        let item = RdpTransactionItem::McsConnectResponse(McsConnectResponse {});
+        let item = RdpTransactionItem::McsConnectResponse(McsConnectResponse::default());
         assert_eq!(item, state.transactions[0].item);
     }
 
+    #[test]
+    fn test_parse_tc_credssp() {
+        // X.224 connection confirm selecting hybrid, then a TSRequest in clear
+        // with STATUS_LOGON_FAILURE
+        let buf: &[u8] = &[
+            0x03, 0x00, 0x00, 0x13, 0x0e, 0xd0, 0x00, 0x00, 0x12, 0x34, 0x00, 0x02, 0x00, 0x08,
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x30, 0x0d, 0xa0, 0x03, 0x02, 0x01, 0x06, 0xa4, 0x06,
+            0x02, 0x04, 0xc0, 0x00, 0x00, 0x6d,
+        ];
+        let mut state = RdpState::new();
+        // will consume the confirm, request length + 1
+        assert_eq!(
+            AppLayerResult::incomplete(19, 15),
+            state.parse_tc(&buf[..buf.len() - 1])
+        );
+        assert_eq!(1, state.transactions.len());
+        assert_eq!(AppLayerResult::ok(), state.parse_tc(&buf[19..]));
+        assert_eq!(2, state.transactions.len());
+        match &state.transactions[1].item {
+            RdpTransactionItem::TsRequest(req) => {
+                assert_eq!(6, req.version);
+                assert_eq!(Some(0xc000006d), req.error_code);
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+        assert!(!state.tls_parsing);
+    }
+
+    #[test]
+    fn test_parse_channels() {
+        let mut state = RdpState::new();
+        state.requested_channels = vec![String::from("rdpdr"), String::from("cliprdr")];
+        // MCS connect response, with ENCRYPTION_LEVEL_NONE and the channel ids
+        let response: &[u8] = &[
+            0x03, 0x00, 0x00, 0x26, 0x02, 0xf0, 0x80, 0x7f, 0x66, 0x4d, 0x63, 0x44, 0x6e, 0x18,
+            0x02, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x0c,
+            0x0c, 0x00, 0xeb, 0x03, 0x02, 0x00, 0xec, 0x03, 0xed, 0x03,
+        ];
+        // channel join confirm of rdpdr
+        let confirm: &[u8] = &[
+            0x03, 0x00, 0x00, 0x0f, 0x02, 0xf0, 0x80, 0x3e, 0x00, 0x00, 0x06, 0x03, 0xec, 0x03,
+            0xec,
+        ];
+        assert_eq!(AppLayerResult::ok(), state.parse_tc(&[response, confirm].concat()));
+        assert!(state.channel_data_in_clear);
+        assert_eq!(2, state.transactions.len());
+        let channel = RdpChannel {
+            name: String::from("rdpdr"),
+            id: 1004,
+        };
+        assert_eq!(
+            RdpTransactionItem::ChannelJoin(channel),
+            state.transactions[1].item
+        );
+
+        // send data request on rdpdr with a drive, then a fast-path input PDU
+        let request: &[u8] = &[
+            0x03, 0x00, 0x00, 0x36, 0x02, 0xf0, 0x80, 0x64, 0x00, 0x06, 0x03, 0xec, 0x70, 0x28,
+            0x28, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x72, 0x44, 0x41, 0x44, 0x01, 0x00,
+            0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x43, 0x3a, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x43, 0x00, 0x00, 0x00, 0x04, 0x03,
+            0x00,
+        ];
+        assert_eq!(AppLayerResult::ok(), state.parse_ts(request));
+        assert_eq!(3, state.transactions.len());
+        match &state.transactions[2].item {
+            RdpTransactionItem::DeviceList(devices) => {
+                assert_eq!(1, devices.len());
+                assert!(devices[0].is_drive());
+            }
+            item => panic!("unexpected item {:?}", item),
+        }
+        assert!(!state.bypass_parsing);
+    }
+
     #[test]
     fn test_parse_tc_other() {
         let buf: &[u8] = &[0x03, 0x00, 0x00, 0x01, 0x00];
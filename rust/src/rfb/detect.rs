@@ -65,7 +65,41 @@ unsafe extern "C" fn rfb_name_get(
     );
 }
 
+unsafe extern "C" fn rfb_clipboard_get_data(
+    tx: *const c_void, _flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, RFBTransaction);
+    if let Some(ref c) = tx.clipboard {
+        let p = &c.text;
+        if !p.is_empty() {
+            *buffer = p.as_ptr();
+            *buffer_len = p.len() as u32;
+            return true;
+        }
+    }
+
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn rfb_clipboard_get(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        rfb_clipboard_get_data,
+    );
+}
+
 static mut G_RFB_NAME_BUFFER_ID: c_int = 0;
+static mut G_RFB_CLIPBOARD_BUFFER_ID: c_int = 0;
 static mut G_RFB_SEC_TYPE_KW_ID: c_int = 0;
 static mut G_RFB_SEC_TYPE_BUFFER_ID: c_int = 0;
 static mut G_RFB_SEC_RESULT_KW_ID: c_int = 0;
@@ -83,6 +117,18 @@ unsafe extern "C" fn rfb_name_setup(
     return 0;
 }
 
+unsafe extern "C" fn rfb_clipboard_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_RFB) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_RFB_CLIPBOARD_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
 unsafe extern "C" fn rfb_sec_type_setup(
     de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
 ) -> c_int {
@@ -206,6 +252,24 @@ pub unsafe extern "C" fn ScDetectRfbRegister() {
         false,
         rfb_name_get,
     );
+    let kw = SCSigTableElmt {
+        name: b"rfb.clipboard\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the RFB cut text\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/rfb-keywords.html#rfb-clipboard\0".as_ptr() as *const libc::c_char,
+        Setup: rfb_clipboard_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_rfb_clipboard_kw_id = DetectHelperKeywordRegister(&kw);
+    G_RFB_CLIPBOARD_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"rfb.clipboard\0".as_ptr() as *const libc::c_char,
+        b"rfb clipboard\0".as_ptr() as *const libc::c_char,
+        ALPROTO_RFB,
+        true,
+        true,
+        rfb_clipboard_get,
+    );
     let kw = SCSigTableElmt {
         name: b"rfb.sectype\0".as_ptr() as *const libc::c_char,
         desc: b"match RFB security type\0".as_ptr() as *const libc::c_char,
@@ -17,16 +17,80 @@
 
 // Author: Frank Honza <frank.honza@dcso.de>
 
-use super::parser::RFBSecurityResultStatus;
-use super::rfb::RFBTransaction;
+use super::parser::{encoding_name, RFBSecurityResultStatus};
+use super::rfb::{RFBClipboard, RFBSession, RFBTransaction};
+use crate::core::Direction;
 use crate::detect::EnumString;
 use crate::jsonbuilder::{JsonBuilder, JsonError};
 use std;
 use std::fmt::Write;
 
+fn log_encoding(js: &mut JsonBuilder, key: &str, encoding: i32) -> Result<(), JsonError> {
+    match encoding_name(encoding) {
+        Some(name) => js.set_string(key, name)?,
+        None => js.set_string(key, &encoding.to_string())?,
+    };
+    Ok(())
+}
+
+fn log_session(session: &RFBSession, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("session")?;
+    if !session.encodings.is_empty() {
+        js.open_array("encodings")?;
+        for &encoding in &session.encodings {
+            match encoding_name(encoding) {
+                Some(name) => js.append_string(name)?,
+                None => js.append_string(&encoding.to_string())?,
+            };
+        }
+        js.close()?;
+    }
+    js.set_uint("key_events", session.key_events)?;
+    js.set_uint("pointer_events", session.pointer_events)?;
+    js.set_uint("client_cut_text", session.client_cut_text)?;
+    js.set_uint("server_cut_text", session.server_cut_text)?;
+    js.set_uint("framebuffer_updates", session.framebuffer_updates)?;
+    js.set_uint("rectangles", session.rectangles)?;
+    js.set_uint("pixels", session.pixels)?;
+    if !session.rectangle_encodings.is_empty() {
+        js.open_array("rectangle_encodings")?;
+        for &(encoding, count) in &session.rectangle_encodings {
+            js.start_object()?;
+            log_encoding(js, "encoding", encoding)?;
+            js.set_uint("count", count)?;
+            js.close()?;
+        }
+        js.close()?;
+    }
+    if let Some(encoding) = session.unknown_encoding {
+        log_encoding(js, "unknown_encoding", encoding)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+fn log_clipboard(clipboard: &RFBClipboard, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("clipboard")?;
+    if clipboard.direction == Direction::ToServer {
+        js.set_string("direction", "to_server")?;
+    } else {
+        js.set_string("direction", "to_client")?;
+    }
+    js.set_uint("length", clipboard.text.len() as u64)?;
+    js.close()?;
+    Ok(())
+}
+
 fn log_rfb(tx: &RFBTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
     js.open_object("rfb")?;
 
+    // Cut text transactions only have the clipboard
+    if let Some(clipboard) = &tx.clipboard {
+        log_clipboard(clipboard, js)?;
+        js.close()?;
+        return Ok(());
+    }
+
     // Protocol version
     if let Some(tx_spv) = &tx.tc_server_protocol_version {
         js.open_object("server_protocol_version")?;
@@ -122,6 +186,10 @@ fn log_rfb(tx: &RFBTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
         js.close()?;
     }
 
+    if let Some(session) = &tx.session {
+        log_session(session, js)?;
+    }
+
     js.close()?;
 
     return Ok(());
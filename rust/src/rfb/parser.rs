@@ -20,6 +20,8 @@
 use nom7::bytes::streaming::tag;
 use nom7::bytes::streaming::take;
 use nom7::combinator::map_res;
+use nom7::error::{make_error, ErrorKind};
+use nom7::multi::count;
 use nom7::number::streaming::*;
 use nom7::*;
 use std::fmt;
@@ -38,6 +40,7 @@ pub enum RFBGlobalState {
     TSVncResponse,
     TCSecurityResult,
     TSClientInit,
+    Message,
     Skip,
 }
 
@@ -55,6 +58,7 @@ impl fmt::Display for RFBGlobalState {
             RFBGlobalState::TCSecurityResult => write!(f, "TCSecurityResult"),
             RFBGlobalState::TCServerSecurityType => write!(f, "TCServerSecurityType"),
             RFBGlobalState::TSClientInit => write!(f, "TSClientInit"),
+            RFBGlobalState::Message => write!(f, "Message"),
             RFBGlobalState::Skip => write!(f, "Skip"),
         }
     }
@@ -117,6 +121,32 @@ pub struct PixelFormat {
     pub blue_shift: u8,
 }
 
+/// Size of a pixel on the wire
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PixelSize {
+    pub bytes: u64,
+    /// size of a TPIXEL, used by the tight encoding
+    pub tight: u64,
+}
+
+impl PixelFormat {
+    pub fn pixel_size(&self) -> PixelSize {
+        let bytes = (self.bits_per_pixel as u64 + 7) / 8;
+        let tight = if self.true_colour_flag != 0
+            && self.bits_per_pixel == 32
+            && self.depth == 24
+            && self.red_max == 255
+            && self.green_max == 255
+            && self.blue_max == 255
+        {
+            3
+        } else {
+            bytes
+        };
+        PixelSize { bytes, tight }
+    }
+}
+
 pub struct ServerInit {
     pub width: u16,
     pub height: u16,
@@ -236,6 +266,425 @@ pub fn parse_server_init(i: &[u8]) -> IResult<&[u8], ServerInit> {
     Ok((i, init))
 }
 
+/// Text of a ClientCutText or ServerCutText message
+pub struct CutText<'a> {
+    /// ISO 8859-1 text, or with the extended clipboard pseudo-encoding,
+    /// flags followed by zlib compressed data
+    pub text: &'a [u8],
+    pub extended: bool,
+}
+
+/// Messages sent by the client after the handshake
+pub enum ClientMessage<'a> {
+    SetPixelFormat(PixelFormat),
+    SetEncodings(Vec<i32>),
+    FramebufferUpdateRequest,
+    KeyEvent { down: bool, key: u32 },
+    PointerEvent { button_mask: u8 },
+    ClientCutText(CutText<'a>),
+    EnableContinuousUpdates,
+    Fence,
+}
+
+/// Messages sent by the server after the handshake
+pub enum ServerMessage<'a> {
+    FramebufferUpdate { rectangles: u16 },
+    SetColourMapEntries,
+    Bell,
+    ServerCutText(CutText<'a>),
+    EndOfContinuousUpdates,
+    Fence,
+}
+
+pub struct Rectangle {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub encoding: i32,
+}
+
+/// How the pixel data of a rectangle continues after its header
+#[derive(Debug, PartialEq, Eq)]
+pub enum RectangleData {
+    /// bytes of data to skip
+    Skip(u64),
+    /// hextile tiles, to be parsed one by one
+    Hextile,
+    /// end of the rectangles of the update
+    LastRect,
+}
+
+/// Name of an encoding or pseudo-encoding
+pub fn encoding_name(encoding: i32) -> Option<&'static str> {
+    let name = match encoding {
+        0 => "raw",
+        1 => "copy_rect",
+        2 => "rre",
+        4 => "corre",
+        5 => "hextile",
+        6 => "zlib",
+        7 => "tight",
+        8 => "zlibhex",
+        15 => "trle",
+        16 => "zrle",
+        21 => "jpeg",
+        50 => "h264",
+        -32..=-23 => "jpeg_quality_level",
+        -223 => "desktop_size",
+        -224 => "last_rect",
+        -232 => "pointer_pos",
+        -239 => "cursor",
+        -240 => "x_cursor",
+        -256..=-247 => "compress_level",
+        -258 => "qemu_extended_key_event",
+        -259 => "qemu_audio",
+        -260 => "tight_png",
+        -261 => "led_state",
+        -307 => "desktop_name",
+        -308 => "extended_desktop_size",
+        -309 => "xvp",
+        -312 => "fence",
+        -313 => "continuous_updates",
+        -314 => "cursor_with_alpha",
+        -1063131698 => "extended_clipboard",
+        _ => {
+            return None;
+        }
+    };
+    Some(name)
+}
+
+/// Maximum length of the text of a cut text message, as it is buffered
+/// until complete
+pub const RFB_MAX_CUT_TEXT_LEN: u32 = 1024 * 1024;
+
+/// Parse a cut text message, failing with `ErrorKind::TooLarge` if its
+/// text is longer than `RFB_MAX_CUT_TEXT_LEN`
+fn parse_cut_text(i: &[u8]) -> IResult<&[u8], CutText<'_>> {
+    let (i, _) = take(3_usize)(i)?;
+    // a negative length is used by the extended clipboard pseudo-encoding
+    let (i, length) = be_i32(i)?;
+    if length.unsigned_abs() > RFB_MAX_CUT_TEXT_LEN {
+        return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+    }
+    let (i, text) = take(length.unsigned_abs() as usize)(i)?;
+    Ok((
+        i,
+        CutText {
+            text,
+            extended: length < 0,
+        },
+    ))
+}
+
+fn parse_fence(i: &[u8]) -> IResult<&[u8], ()> {
+    let (i, _) = take(3_usize)(i)?;
+    let (i, _flags) = be_u32(i)?;
+    let (i, length) = be_u8(i)?;
+    let (i, _payload) = take(length as usize)(i)?;
+    Ok((i, ()))
+}
+
+pub fn parse_client_message(i: &[u8]) -> IResult<&[u8], ClientMessage<'_>> {
+    let (i, message_type) = be_u8(i)?;
+    match message_type {
+        0 => {
+            let (i, _) = take(3_usize)(i)?;
+            let (i, pixel_format) = parse_pixel_format(i)?;
+            Ok((i, ClientMessage::SetPixelFormat(pixel_format)))
+        }
+        2 => {
+            let (i, _) = take(1_usize)(i)?;
+            let (i, number) = be_u16(i)?;
+            let (i, encodings) = count(be_i32, number as usize)(i)?;
+            Ok((i, ClientMessage::SetEncodings(encodings)))
+        }
+        3 => {
+            let (i, _) = take(9_usize)(i)?;
+            Ok((i, ClientMessage::FramebufferUpdateRequest))
+        }
+        4 => {
+            let (i, down) = be_u8(i)?;
+            let (i, _) = take(2_usize)(i)?;
+            let (i, key) = be_u32(i)?;
+            Ok((
+                i,
+                ClientMessage::KeyEvent {
+                    down: down != 0,
+                    key,
+                },
+            ))
+        }
+        5 => {
+            let (i, button_mask) = be_u8(i)?;
+            let (i, _) = take(4_usize)(i)?;
+            Ok((i, ClientMessage::PointerEvent { button_mask }))
+        }
+        6 => {
+            let (i, cut_text) = parse_cut_text(i)?;
+            Ok((i, ClientMessage::ClientCutText(cut_text)))
+        }
+        150 => {
+            let (i, _) = take(9_usize)(i)?;
+            Ok((i, ClientMessage::EnableContinuousUpdates))
+        }
+        248 => {
+            let (i, _) = parse_fence(i)?;
+            Ok((i, ClientMessage::Fence))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+pub fn parse_server_message(i: &[u8]) -> IResult<&[u8], ServerMessage<'_>> {
+    let (i, message_type) = be_u8(i)?;
+    match message_type {
+        0 => {
+            let (i, _) = take(1_usize)(i)?;
+            let (i, rectangles) = be_u16(i)?;
+            Ok((i, ServerMessage::FramebufferUpdate { rectangles }))
+        }
+        1 => {
+            let (i, _) = take(3_usize)(i)?;
+            let (i, colours) = be_u16(i)?;
+            let (i, _) = take(colours as usize * 6)(i)?;
+            Ok((i, ServerMessage::SetColourMapEntries))
+        }
+        2 => Ok((i, ServerMessage::Bell)),
+        3 => {
+            let (i, cut_text) = parse_cut_text(i)?;
+            Ok((i, ServerMessage::ServerCutText(cut_text)))
+        }
+        150 => Ok((i, ServerMessage::EndOfContinuousUpdates)),
+        248 => {
+            let (i, _) = parse_fence(i)?;
+            Ok((i, ServerMessage::Fence))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Header of a rectangle of a FramebufferUpdate
+pub fn parse_rectangle_header(i: &[u8]) -> IResult<&[u8], Rectangle> {
+    let (i, x) = be_u16(i)?;
+    let (i, y) = be_u16(i)?;
+    let (i, width) = be_u16(i)?;
+    let (i, height) = be_u16(i)?;
+    let (i, encoding) = be_i32(i)?;
+    Ok((
+        i,
+        Rectangle {
+            x,
+            y,
+            width,
+            height,
+            encoding,
+        },
+    ))
+}
+
+/// Length of tight compressed data, on 1 to 3 bytes
+fn parse_tight_length(i: &[u8]) -> IResult<&[u8], u64> {
+    let (i, b) = be_u8(i)?;
+    let mut length = (b & 0x7f) as u64;
+    if b & 0x80 == 0 {
+        return Ok((i, length));
+    }
+    let (i, b) = be_u8(i)?;
+    length |= ((b & 0x7f) as u64) << 7;
+    if b & 0x80 == 0 {
+        return Ok((i, length));
+    }
+    let (i, b) = be_u8(i)?;
+    length |= (b as u64) << 14;
+    Ok((i, length))
+}
+
+/// Tight rectangle, returns the length of the compressed data
+fn parse_tight<'a>(i: &'a [u8], rect: &Rectangle, pixel: PixelSize) -> IResult<&'a [u8], u64> {
+    let (i, control) = be_u8(i)?;
+    match control >> 4 {
+        // fill
+        0x08 => {
+            let (i, _) = take(pixel.tight as usize)(i)?;
+            Ok((i, 0))
+        }
+        // jpeg
+        0x09 => parse_tight_length(i),
+        // basic, with an optional filter
+        compression @ 0x00..=0x07 => {
+            let (i, filter) = if compression & 0x04 != 0 {
+                be_u8(i)?
+            } else {
+                (i, 0)
+            };
+            let width = rect.width as u64;
+            let (i, row) = match filter {
+                // copy and gradient
+                0 | 2 => (i, width * pixel.tight),
+                // palette
+                1 => {
+                    let (i, colours) = be_u8(i)?;
+                    let colours = colours as u64 + 1;
+                    let (i, _) = take((colours * pixel.tight) as usize)(i)?;
+                    if colours <= 2 {
+                        (i, (width + 7) / 8)
+                    } else {
+                        (i, width)
+                    }
+                }
+                _ => {
+                    return Err(Err::Error(make_error(i, ErrorKind::Switch)));
+                }
+            };
+            // small data is not compressed
+            let size = row * rect.height as u64;
+            if size < 12 {
+                let (i, _) = take(size as usize)(i)?;
+                Ok((i, 0))
+            } else {
+                parse_tight_length(i)
+            }
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Beginning of the data of a rectangle, up to the pixel data that can be
+/// skipped. Rectangles using an unknown encoding are an error, as the
+/// length of their data is not known.
+pub fn parse_rectangle_data<'a>(
+    i: &'a [u8], rect: &Rectangle, pixel: PixelSize,
+) -> IResult<&'a [u8], RectangleData> {
+    let width = rect.width as u64;
+    let height = rect.height as u64;
+    let mask = (width + 7) / 8 * height;
+    match rect.encoding {
+        // raw
+        0 => Ok((i, RectangleData::Skip(width * height * pixel.bytes))),
+        // copy rect
+        1 => {
+            let (i, _) = take(4_usize)(i)?;
+            Ok((i, RectangleData::Skip(0)))
+        }
+        // rre and corre
+        2 | 4 => {
+            let (i, subrects) = be_u32(i)?;
+            let (i, _background) = take(pixel.bytes as usize)(i)?;
+            let size = if rect.encoding == 2 { 8 } else { 4 };
+            Ok((
+                i,
+                RectangleData::Skip(subrects as u64 * (pixel.bytes + size)),
+            ))
+        }
+        5 => Ok((i, RectangleData::Hextile)),
+        // zlib and zrle
+        6 | 16 => {
+            let (i, length) = be_u32(i)?;
+            Ok((i, RectangleData::Skip(length as u64)))
+        }
+        7 => {
+            let (i, length) = parse_tight(i, rect, pixel)?;
+            Ok((i, RectangleData::Skip(length)))
+        }
+        // desktop size, pointer pos, qemu extended key event and audio,
+        // fence and continuous updates
+        -223 | -232 | -258 | -259 | -312 | -313 => Ok((i, RectangleData::Skip(0))),
+        -224 => Ok((i, RectangleData::LastRect)),
+        // cursor: pixels and bitmask
+        -239 => Ok((i, RectangleData::Skip(width * height * pixel.bytes + mask))),
+        // x cursor: colours, bitmap and bitmask
+        -240 => {
+            if width * height == 0 {
+                Ok((i, RectangleData::Skip(0)))
+            } else {
+                Ok((i, RectangleData::Skip(6 + 2 * mask)))
+            }
+        }
+        // led state
+        -261 => Ok((i, RectangleData::Skip(1))),
+        -307 => {
+            let (i, length) = be_u32(i)?;
+            Ok((i, RectangleData::Skip(length as u64)))
+        }
+        -308 => {
+            let (i, screens) = be_u8(i)?;
+            let (i, _) = take(3_usize)(i)?;
+            Ok((i, RectangleData::Skip(screens as u64 * 16)))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Progress in the 16x16 tiles of a hextile rectangle
+#[derive(Debug)]
+pub struct HextileRect {
+    width: u64,
+    height: u64,
+    next: u64,
+}
+
+impl HextileRect {
+    pub fn new(rect: &Rectangle) -> Self {
+        Self {
+            width: rect.width as u64,
+            height: rect.height as u64,
+            next: 0,
+        }
+    }
+
+    /// Size of the next tile, if any
+    pub fn tile(&self) -> Option<(u64, u64)> {
+        let columns = (self.width + 15) / 16;
+        if columns == 0 {
+            return None;
+        }
+        let x = self.next % columns * 16;
+        let y = self.next / columns * 16;
+        if y >= self.height {
+            return None;
+        }
+        Some((
+            std::cmp::min(16, self.width - x),
+            std::cmp::min(16, self.height - y),
+        ))
+    }
+
+    pub fn advance(&mut self) {
+        self.next += 1;
+    }
+}
+
+/// Hextile tile, returns the length of its raw pixel data
+pub fn parse_hextile_tile(
+    i: &[u8], width: u64, height: u64, pixel: PixelSize,
+) -> IResult<&[u8], u64> {
+    let (i, subencoding) = be_u8(i)?;
+    if subencoding & 0x01 != 0 {
+        return Ok((i, width * height * pixel.bytes));
+    }
+    let mut colours = 0;
+    if subencoding & 0x02 != 0 {
+        colours += pixel.bytes;
+    }
+    if subencoding & 0x04 != 0 {
+        colours += pixel.bytes;
+    }
+    let (i, _) = take(colours as usize)(i)?;
+    if subencoding & 0x08 == 0 {
+        return Ok((i, 0));
+    }
+    let (i, subrects) = be_u8(i)?;
+    let size = if subencoding & 0x10 != 0 {
+        pixel.bytes + 2
+    } else {
+        2
+    };
+    let (i, _) = take(subrects as usize * size as usize)(i)?;
+    Ok((i, 0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -405,4 +854,79 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_rectangle_data() {
+        let pixel = PixelSize { bytes: 4, tight: 3 };
+        let rect = |width, height, encoding| Rectangle {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            encoding,
+        };
+
+        // rre with 3 subrects
+        let buf = [0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00];
+        let (rem, data) = parse_rectangle_data(&buf, &rect(8, 8, 2), pixel).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(data, RectangleData::Skip(36));
+
+        // tight, palette of 2 colours, 64x2 pixels so 16 bytes of data
+        // with a 3 bytes length
+        let buf = [0x40, 0x01, 0x01, 0, 0, 0, 1, 1, 1, 0x90, 0x80, 0x01];
+        let (rem, data) = parse_rectangle_data(&buf, &rect(64, 2, 7), pixel).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(data, RectangleData::Skip(0x4010));
+
+        // tight, fill
+        let buf = [0x80, 0xff, 0xff, 0xff];
+        let (rem, data) = parse_rectangle_data(&buf, &rect(64, 64, 7), pixel).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(data, RectangleData::Skip(0));
+
+        // cursor, 8x2 pixels and a 2 bytes bitmask
+        let (_, data) = parse_rectangle_data(&[], &rect(8, 2, -239), pixel).unwrap();
+        assert_eq!(data, RectangleData::Skip(66));
+
+        // trle
+        assert!(matches!(
+            parse_rectangle_data(&[], &rect(8, 8, 15), pixel),
+            Err(Err::Error(_))
+        ));
+        assert_eq!(encoding_name(15), Some("trle"));
+        assert_eq!(encoding_name(-250), Some("compress_level"));
+        assert_eq!(encoding_name(1000), None);
+    }
+
+    #[test]
+    fn test_hextile_tiles() {
+        let mut hextile = HextileRect::new(&Rectangle {
+            x: 0,
+            y: 0,
+            width: 20,
+            height: 17,
+            encoding: 5,
+        });
+        let mut tiles = Vec::new();
+        while let Some(tile) = hextile.tile() {
+            tiles.push(tile);
+            hextile.advance();
+        }
+        assert_eq!(tiles, vec![(16, 16), (4, 16), (16, 1), (4, 1)]);
+
+        let pixel = PixelSize { bytes: 2, tight: 2 };
+        // raw
+        let (_, len) = parse_hextile_tile(&[0x01], 4, 16, pixel).unwrap();
+        assert_eq!(len, 128);
+        // background, foreground and 2 coloured subrects
+        let buf = [0x1e, 0, 0, 0, 0, 0x02, 0, 0, 0, 0, 0, 0, 0, 0];
+        let (rem, len) = parse_hextile_tile(&buf, 16, 16, pixel).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(len, 0);
+        assert!(matches!(
+            parse_hextile_tile(&buf[..13], 16, 16, pixel),
+            Err(Err::Incomplete(_))
+        ));
+    }
 }
@@ -21,8 +21,14 @@
 use super::parser;
 use crate::applayer;
 use crate::applayer::*;
-use crate::core::{AppProto, Flow, ALPROTO_UNKNOWN, IPPROTO_TCP};
+use crate::core::{
+    AppProto, Direction, Flow, SuricataFileContext, ALPROTO_UNKNOWN, IPPROTO_TCP, STREAM_TOCLIENT,
+    STREAM_TOSERVER,
+};
+use crate::filecontainer::FileFlowFlagsToFlags;
+use crate::filetracker::*;
 use crate::frames::*;
+use nom7::error::ErrorKind;
 use nom7::Err;
 use std;
 use std::ffi::CString;
@@ -30,18 +36,95 @@ use std::os::raw::c_char;
 
 pub(super) static mut ALPROTO_RFB: AppProto = ALPROTO_UNKNOWN;
 
+pub static mut SURICATA_RFB_FILE_CONFIG: Option<&'static SuricataFileContext> = None;
+
+#[no_mangle]
+pub unsafe extern "C" fn SCRfbInit(context: &'static mut SuricataFileContext) {
+    SURICATA_RFB_FILE_CONFIG = Some(context);
+}
+
 #[derive(FromPrimitive, Debug, AppLayerEvent)]
 pub enum RFBEvent {
     UnimplementedSecurityType,
     UnknownSecurityResult,
     MalformedMessage,
     ConfusedState,
+    UnknownMessageType,
+    ClipboardTooLarge,
 }
 
 #[derive(AppLayerFrameType)]
 pub enum RFBFrameType {
     Pdu,
 }
+
+/// Summary of the messages exchanged after the handshake
+#[derive(Debug, Default)]
+pub struct RFBSession {
+    /// encodings of the last SetEncodings, in order of preference
+    pub encodings: Vec<i32>,
+    /// key presses, releases are not counted
+    pub key_events: u64,
+    pub pointer_events: u64,
+    pub client_cut_text: u64,
+    pub server_cut_text: u64,
+    pub framebuffer_updates: u64,
+    pub rectangles: u64,
+    /// updated area, pseudo-encodings excluded
+    pub pixels: u64,
+    /// number of rectangles per encoding
+    pub rectangle_encodings: Vec<(i32, u64)>,
+    /// encoding of a rectangle whose length is not known, the server
+    /// messages are not followed after it
+    pub unknown_encoding: Option<i32>,
+}
+
+impl RFBSession {
+    fn add_client_message(&mut self, message: &parser::ClientMessage) {
+        match message {
+            parser::ClientMessage::SetEncodings(encodings) => {
+                self.encodings = encodings.clone();
+            }
+            parser::ClientMessage::KeyEvent { down: true, .. } => {
+                self.key_events += 1;
+            }
+            parser::ClientMessage::PointerEvent { .. } => {
+                self.pointer_events += 1;
+            }
+            parser::ClientMessage::ClientCutText(_) => {
+                self.client_cut_text += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn add_rectangle(&mut self, rect: &parser::Rectangle) {
+        self.rectangles += 1;
+        if rect.encoding >= 0 {
+            self.pixels += rect.width as u64 * rect.height as u64;
+        }
+        match self
+            .rectangle_encodings
+            .iter_mut()
+            .find(|(e, _)| *e == rect.encoding)
+        {
+            Some((_, count)) => {
+                *count += 1;
+            }
+            None => {
+                self.rectangle_encodings.push((rect.encoding, 1));
+            }
+        }
+    }
+}
+
+/// Text of a ClientCutText or ServerCutText message
+#[derive(Debug)]
+pub struct RFBClipboard {
+    pub direction: Direction,
+    pub text: Vec<u8>,
+}
+
 pub struct RFBTransaction {
     tx_id: u64,
     pub complete: bool,
@@ -59,6 +142,13 @@ pub struct RFBTransaction {
     pub tc_failure_reason: Option<parser::FailureReason>,
     pub tc_server_init: Option<parser::ServerInit>,
 
+    /// set on the handshake transaction once the handshake is done, and
+    /// completed at the end of the flow
+    pub session: Option<RFBSession>,
+    /// each cut text message is a transaction of its own
+    pub clipboard: Option<RFBClipboard>,
+    file_tracker: FileTransferTracker,
+
     tx_data: applayer::AppLayerTxData,
 }
 
@@ -93,6 +183,10 @@ impl RFBTransaction {
             tc_failure_reason: None,
             tc_server_init: None,
 
+            session: None,
+            clipboard: None,
+            file_tracker: FileTransferTracker::new(),
+
             tx_data: applayer::AppLayerTxData::new(),
         }
     }
@@ -100,6 +194,53 @@ impl RFBTransaction {
     fn set_event(&mut self, event: RFBEvent) {
         self.tx_data.set_event(event as u8);
     }
+
+    /// Pass the clipboard text on as a file
+    fn clipboard_file(&mut self, flow_file_flags: u16) {
+        let sfcm = match unsafe { SURICATA_RFB_FILE_CONFIG } {
+            Some(sfcm) => sfcm,
+            None => {
+                return;
+            }
+        };
+        let clipboard = match &self.clipboard {
+            Some(c) if !c.text.is_empty() => c,
+            _ => {
+                return;
+            }
+        };
+        let dir_flag = if clipboard.direction == Direction::ToServer {
+            STREAM_TOSERVER
+        } else {
+            STREAM_TOCLIENT
+        };
+        self.tx_data.update_file_flags(flow_file_flags);
+        self.tx_data.file_tx = dir_flag;
+        self.tx_data.incr_files_opened();
+        let ft = &mut self.file_tracker;
+        ft.file_flags = unsafe { FileFlowFlagsToFlags(self.tx_data.file_flags, dir_flag) };
+        ft.tx_id = self.tx_id - 1;
+        let xid = self.tx_id as u32;
+        ft.new_chunk(
+            sfcm,
+            b"clipboard",
+            &clipboard.text,
+            0,
+            clipboard.text.len() as u32,
+            0,
+            false,
+            &xid,
+        );
+        ft.close(sfcm);
+    }
+}
+
+impl Drop for RFBTransaction {
+    fn drop(&mut self) {
+        if let Some(sfcm) = unsafe { SURICATA_RFB_FILE_CONFIG } {
+            self.file_tracker.file.free(sfcm);
+        }
+    }
 }
 
 pub struct RFBState {
@@ -107,6 +248,17 @@ pub struct RFBState {
     tx_id: u64,
     transactions: Vec<RFBTransaction>,
     state: parser::RFBGlobalState,
+
+    /// pixel size set by ServerInit or SetPixelFormat
+    pixel_size: parser::PixelSize,
+    /// rectangles left in the current FramebufferUpdate
+    tc_rectangles: u16,
+    tc_hextile: Option<parser::HextileRect>,
+    /// pixel data left to skip
+    tc_gap: u64,
+    /// set once the messages of a direction can no longer be followed
+    ts_lost: bool,
+    tc_lost: bool,
 }
 
 impl State<RFBTransaction> for RFBState {
@@ -132,6 +284,13 @@ impl RFBState {
             tx_id: 0,
             transactions: Vec::new(),
             state: parser::RFBGlobalState::TCServerProtocolVersion,
+
+            pixel_size: parser::PixelSize::default(),
+            tc_rectangles: 0,
+            tc_hextile: None,
+            tc_gap: 0,
+            ts_lost: false,
+            tc_lost: false,
         }
     }
 
@@ -169,6 +328,72 @@ impl RFBState {
         self.transactions.iter_mut().find(|tx| tx.tx_id == tx_id)
     }
 
+    fn get_session(&mut self) -> Option<&mut RFBSession> {
+        self.transactions
+            .iter_mut()
+            .find_map(|tx| tx.session.as_mut())
+    }
+
+    fn set_session_event(&mut self, event: RFBEvent) {
+        if let Some(tx) = self.transactions.iter_mut().find(|tx| tx.session.is_some()) {
+            tx.set_event(event);
+        }
+    }
+
+    /// Complete the handshake transaction, which holds the session summary
+    fn close_session(&mut self) {
+        if let Some(tx) = self.transactions.iter_mut().find(|tx| tx.session.is_some()) {
+            tx.complete = true;
+        }
+    }
+
+    fn new_clipboard_tx(&mut self, cut_text: &parser::CutText, direction: Direction) {
+        let mut tx = self.new_tx();
+        tx.clipboard = Some(RFBClipboard {
+            direction,
+            text: cut_text.text.to_vec(),
+        });
+        tx.clipboard_file(self.state_data.file_flags);
+        tx.complete = true;
+        self.transactions.push(tx);
+    }
+
+    fn handle_client_message(&mut self, message: parser::ClientMessage) {
+        if let Some(session) = self.get_session() {
+            session.add_client_message(&message);
+        }
+        match message {
+            parser::ClientMessage::SetPixelFormat(pixel_format) => {
+                self.pixel_size = pixel_format.pixel_size();
+            }
+            // the extended clipboard data is compressed
+            parser::ClientMessage::ClientCutText(cut_text) if !cut_text.extended => {
+                self.new_clipboard_tx(&cut_text, Direction::ToServer);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_server_message(&mut self, message: parser::ServerMessage) {
+        match message {
+            parser::ServerMessage::FramebufferUpdate { rectangles } => {
+                self.tc_rectangles = rectangles;
+                if let Some(session) = self.get_session() {
+                    session.framebuffer_updates += 1;
+                }
+            }
+            parser::ServerMessage::ServerCutText(cut_text) => {
+                if let Some(session) = self.get_session() {
+                    session.server_cut_text += 1;
+                }
+                if !cut_text.extended {
+                    self.new_clipboard_tx(&cut_text, Direction::ToClient);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn parse_request(&mut self, flow: *const Flow, stream_slice: StreamSlice) -> AppLayerResult {
         let input = stream_slice.as_slice();
 
@@ -364,6 +589,47 @@ impl RFBState {
                         return AppLayerResult::ok();
                     }
                 },
+                parser::RFBGlobalState::Message => {
+                    if self.ts_lost {
+                        return AppLayerResult::ok();
+                    }
+                    match parser::parse_client_message(current) {
+                        Ok((rem, message)) => {
+                            let len = current.len() - rem.len();
+                            let _pdu = Frame::new(
+                                flow,
+                                &stream_slice,
+                                current,
+                                len as i64,
+                                RFBFrameType::Pdu as u8,
+                                None,
+                            );
+                            consumed += len;
+                            current = rem;
+                            self.handle_client_message(message);
+                        }
+                        Err(Err::Incomplete(_)) => {
+                            return AppLayerResult::incomplete(
+                                consumed as u32,
+                                (current.len() + 1) as u32,
+                            );
+                        }
+                        Err(Err::Error(e)) if e.code == ErrorKind::TooLarge => {
+                            // Not buffered, and the messages after it can't be
+                            // found without going through it.
+                            self.set_session_event(RFBEvent::ClipboardTooLarge);
+                            self.ts_lost = true;
+                            return AppLayerResult::ok();
+                        }
+                        Err(_) => {
+                            // The length of unknown messages is not known, so
+                            // the following ones can't be found.
+                            self.set_session_event(RFBEvent::UnknownMessageType);
+                            self.ts_lost = true;
+                            return AppLayerResult::ok();
+                        }
+                    }
+                }
                 parser::RFBGlobalState::Skip => {
                     // End of parseable handshake reached, skip rest of traffic
                     return AppLayerResult::ok();
@@ -699,12 +965,14 @@ impl RFBState {
 
                             current = rem;
 
-                            self.state = parser::RFBGlobalState::Skip;
+                            self.state = parser::RFBGlobalState::Message;
+                            self.pixel_size = request.pixel_format.pixel_size();
 
                             if let Some(current_transaction) = self.get_current_tx() {
                                 current_transaction.tc_server_init = Some(request);
-                                // connection initialization is complete and parsed
-                                current_transaction.complete = true;
+                                // connection initialization is complete and parsed,
+                                // the transaction now follows the session
+                                current_transaction.session = Some(RFBSession::default());
                             } else {
                                 debug_validate_fail!("no transaction set at server init stage");
                             }
@@ -726,8 +994,127 @@ impl RFBState {
                         }
                     }
                 }
+                parser::RFBGlobalState::Message => {
+                    if self.tc_lost {
+                        return AppLayerResult::ok();
+                    }
+                    if self.tc_gap > 0 {
+                        let len = std::cmp::min(self.tc_gap, current.len() as u64);
+                        self.tc_gap -= len;
+                        consumed += len as usize;
+                        current = &current[len as usize..];
+                        continue;
+                    }
+                    if let Some(hextile) = &mut self.tc_hextile {
+                        if let Some((width, height)) = hextile.tile() {
+                            match parser::parse_hextile_tile(
+                                current,
+                                width,
+                                height,
+                                self.pixel_size,
+                            ) {
+                                Ok((rem, gap)) => {
+                                    consumed += current.len() - rem.len();
+                                    current = rem;
+                                    self.tc_gap = gap;
+                                    hextile.advance();
+                                }
+                                Err(_) => {
+                                    return AppLayerResult::incomplete(
+                                        consumed as u32,
+                                        (current.len() + 1) as u32,
+                                    );
+                                }
+                            }
+                            continue;
+                        }
+                        self.tc_hextile = None;
+                    }
+                    if self.tc_rectangles > 0 {
+                        let (rem, rect) = match parser::parse_rectangle_header(current) {
+                            Ok(r) => r,
+                            Err(_) => {
+                                return AppLayerResult::incomplete(
+                                    consumed as u32,
+                                    (current.len() + 1) as u32,
+                                );
+                            }
+                        };
+                        match parser::parse_rectangle_data(rem, &rect, self.pixel_size) {
+                            Ok((rem, data)) => {
+                                consumed += current.len() - rem.len();
+                                current = rem;
+                                self.tc_rectangles -= 1;
+                                match data {
+                                    parser::RectangleData::Skip(len) => {
+                                        self.tc_gap = len;
+                                    }
+                                    parser::RectangleData::Hextile => {
+                                        self.tc_hextile = Some(parser::HextileRect::new(&rect));
+                                    }
+                                    parser::RectangleData::LastRect => {
+                                        self.tc_rectangles = 0;
+                                        continue;
+                                    }
+                                }
+                                if let Some(session) = self.get_session() {
+                                    session.add_rectangle(&rect);
+                                }
+                            }
+                            Err(Err::Incomplete(_)) => {
+                                return AppLayerResult::incomplete(
+                                    consumed as u32,
+                                    (current.len() + 1) as u32,
+                                );
+                            }
+                            Err(_) => {
+                                // The length of the data of this encoding is
+                                // not known, stop following the server.
+                                if let Some(session) = self.get_session() {
+                                    session.add_rectangle(&rect);
+                                    session.unknown_encoding = Some(rect.encoding);
+                                }
+                                self.tc_lost = true;
+                                return AppLayerResult::ok();
+                            }
+                        }
+                        continue;
+                    }
+                    match parser::parse_server_message(current) {
+                        Ok((rem, message)) => {
+                            let len = current.len() - rem.len();
+                            let _pdu = Frame::new(
+                                flow,
+                                &stream_slice,
+                                current,
+                                len as i64,
+                                RFBFrameType::Pdu as u8,
+                                None,
+                            );
+                            consumed += len;
+                            current = rem;
+                            self.handle_server_message(message);
+                        }
+                        Err(Err::Incomplete(_)) => {
+                            return AppLayerResult::incomplete(
+                                consumed as u32,
+                                (current.len() + 1) as u32,
+                            );
+                        }
+                        Err(Err::Error(e)) if e.code == ErrorKind::TooLarge => {
+                            self.set_session_event(RFBEvent::ClipboardTooLarge);
+                            self.tc_lost = true;
+                            return AppLayerResult::ok();
+                        }
+                        Err(_) => {
+                            self.set_session_event(RFBEvent::UnknownMessageType);
+                            self.tc_lost = true;
+                            return AppLayerResult::ok();
+                        }
+                    }
+                }
                 parser::RFBGlobalState::Skip => {
-                    //todo implement RFB messages, for now we stop here
+                    // Parsing was stopped, skip rest of traffic
                     return AppLayerResult::ok();
                 }
                 _ => {
@@ -777,20 +1164,28 @@ pub unsafe extern "C" fn rs_rfb_state_tx_free(state: *mut std::os::raw::c_void,
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_rfb_parse_request(
-    flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    flow: *const Flow, state: *mut std::os::raw::c_void, pstate: *mut std::os::raw::c_void,
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, RFBState);
-    return state.parse_request(flow, stream_slice);
+    let r = state.parse_request(flow, stream_slice);
+    if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TS) > 0 {
+        state.close_session();
+    }
+    return r;
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_rfb_parse_response(
-    flow: *const Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    flow: *const Flow, state: *mut std::os::raw::c_void, pstate: *mut std::os::raw::c_void,
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, RFBState);
-    return state.parse_response(flow, stream_slice);
+    let r = state.parse_response(flow, stream_slice);
+    if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TC) > 0 {
+        state.close_session();
+    }
+    return r;
 }
 
 #[no_mangle]
@@ -825,6 +1220,25 @@ pub unsafe extern "C" fn rs_rfb_tx_get_alstate_progress(
     return 0;
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn SCRfbGetTxFiles(
+    tx: *mut std::os::raw::c_void, direction: u8,
+) -> AppLayerGetFileState {
+    let tx = cast_pointer!(tx, RFBTransaction);
+    if let Some(clipboard) = &tx.clipboard {
+        let tx_dir: u8 = clipboard.direction.into();
+        if direction & tx_dir != 0 {
+            if let Some(sfcm) = { SURICATA_RFB_FILE_CONFIG } {
+                return AppLayerGetFileState {
+                    fc: &mut tx.file_tracker.file,
+                    cfg: sfcm.files_sbcfg,
+                };
+            }
+        }
+    }
+    AppLayerGetFileState::err()
+}
+
 // Parser name as a C style string.
 const PARSER_NAME: &[u8] = b"rfb\0";
 
@@ -855,7 +1269,7 @@ pub unsafe extern "C" fn SCRfbRegisterParser() {
         get_eventinfo_byid: Some(RFBEvent::get_event_info_by_id),
         localstorage_new: None,
         localstorage_free: None,
-        get_tx_files: None,
+        get_tx_files: Some(SCRfbGetTxFiles),
         get_tx_iterator: Some(applayer::state_get_tx_iterator::<RFBState, RFBTransaction>),
         get_tx_data: rs_rfb_get_tx_data,
         get_state_data: rs_rfb_get_state_data,
@@ -1022,7 +1436,176 @@ mod test {
             std::ptr::null(),
             StreamSlice::from_slice(&buf[36..90], STREAM_START, 0),
         );
-        ok_state = parser::RFBGlobalState::Skip;
+        ok_state = parser::RFBGlobalState::Message;
         assert_eq!(init_state.state, ok_state);
     }
+
+    /// State after a ServerInit with a 1280x800 framebuffer, 32 bits per pixel
+    fn session_state() -> RFBState {
+        let mut state = RFBState::new();
+        let tx = state.new_tx();
+        state.transactions.push(tx);
+        state.state = parser::RFBGlobalState::TCServerInit;
+        let buf: &[u8] = &[
+            0x05, 0x00, 0x03, 0x20, 0x20, 0x18, 0x00, 0x01, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff,
+            0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x74, 0x65, 0x73, 0x74,
+        ];
+        state.parse_response(
+            std::ptr::null(),
+            StreamSlice::from_slice(buf, STREAM_START, 0),
+        );
+        assert_eq!(state.state, parser::RFBGlobalState::Message);
+        state
+    }
+
+    #[test]
+    fn test_rfb_client_messages() {
+        let mut state = session_state();
+        let buf: &[u8] = &[
+            0x02, 0x00, 0x00, 0x03, /* SetEncodings, 3 encodings */
+            0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x05, 0xff, 0xff, 0xff, 0x21, 0x04, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x61, /* KeyEvent, a pressed */
+            0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x61, /* KeyEvent, a released */
+            0x05, 0x01, 0x00, 0x10, 0x00, 0x20, /* PointerEvent */
+            0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, /* ClientCutText */
+            0x73, 0x65, 0x63, 0x72, 0x65, 0x74,
+        ];
+        // the cut text is buffered until complete
+        let r = state.parse_request(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf[..buf.len() - 1], STREAM_START, 0),
+        );
+        assert_eq!(r.consumed, 38);
+        let r = state.parse_request(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf[38..], STREAM_START, 0),
+        );
+        assert_eq!(r, AppLayerResult::ok());
+
+        let session = state.get_session().unwrap();
+        assert_eq!(session.encodings, vec![7, 5, -223]);
+        assert_eq!(session.key_events, 1);
+        assert_eq!(session.pointer_events, 1);
+        assert_eq!(session.client_cut_text, 1);
+
+        assert_eq!(state.transactions.len(), 2);
+        let tx = &state.transactions[1];
+        assert!(tx.complete);
+        let clipboard = tx.clipboard.as_ref().unwrap();
+        assert_eq!(clipboard.direction, Direction::ToServer);
+        assert_eq!(clipboard.text, b"secret");
+        // the session is complete at the end of the flow
+        assert!(!state.transactions[0].complete);
+        state.close_session();
+        assert!(state.transactions[0].complete);
+
+        // an unknown message stops the parsing of the client messages
+        let buf: &[u8] = &[0x07, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x61];
+        state.parse_request(
+            std::ptr::null(),
+            StreamSlice::from_slice(buf, STREAM_START, 0),
+        );
+        assert!(state.ts_lost);
+        assert_eq!(state.get_session().unwrap().key_events, 1);
+    }
+
+    #[test]
+    fn test_rfb_cut_text_too_large() {
+        let mut state = session_state();
+        let mut buf: Vec<u8> = vec![0x06, 0x00, 0x00, 0x00]; /* ClientCutText */
+        buf.extend_from_slice(&(parser::RFB_MAX_CUT_TEXT_LEN + 1).to_be_bytes());
+        buf.extend_from_slice(b"secret");
+        // not buffered until complete
+        let r = state.parse_request(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf, STREAM_START, 0),
+        );
+        assert_eq!(r, AppLayerResult::ok());
+        assert!(state.ts_lost);
+        assert_eq!(state.transactions.len(), 1);
+
+        // extended clipboard, with a negative length
+        let mut buf: Vec<u8> = vec![0x03, 0x00, 0x00, 0x00]; /* ServerCutText */
+        buf.extend_from_slice(&(-(parser::RFB_MAX_CUT_TEXT_LEN as i32) - 1).to_be_bytes());
+        let r = state.parse_response(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf, STREAM_START, 0),
+        );
+        assert_eq!(r, AppLayerResult::ok());
+        assert!(state.tc_lost);
+        assert_eq!(state.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_rfb_server_messages() {
+        let mut state = session_state();
+        let mut buf: Vec<u8> = vec![0x00, 0x00, 0x00, 0x04]; /* FramebufferUpdate, 4 rectangles */
+        // raw 2x2
+        buf.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        buf.extend_from_slice(&[0xaa; 16]);
+        // copy rect 10x10
+        buf.extend_from_slice(&[
+            0x00, 0x10, 0x00, 0x10, 0x00, 0x0a, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00,
+        ]);
+        // hextile 20x10: a raw tile, and a tile with a background and one subrect
+        buf.extend_from_slice(&[
+            0x00, 0x20, 0x00, 0x20, 0x00, 0x14, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x05, 0x01,
+        ]);
+        buf.extend_from_slice(&[0xbb; 640]);
+        buf.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x33]);
+        // tight 100x100, basic compression with 200 bytes of data
+        buf.extend_from_slice(&[
+            0x00, 0x40, 0x00, 0x40, 0x00, 0x64, 0x00, 0x64, 0x00, 0x00, 0x00, 0x07, 0x00, 0xc8,
+            0x01,
+        ]);
+        buf.extend_from_slice(&[0xcc; 200]);
+        // ServerCutText
+        buf.extend_from_slice(&[
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c, 0x6f,
+        ]);
+        // Bell
+        buf.push(0x02);
+
+        // the pixel data is skipped without being buffered
+        let r = state.parse_response(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf[..300], STREAM_START, 0),
+        );
+        assert_eq!(r, AppLayerResult::ok());
+        assert_eq!(state.tc_gap, 640 - (300 - 61));
+        let r = state.parse_response(
+            std::ptr::null(),
+            StreamSlice::from_slice(&buf[300..], STREAM_START, 0),
+        );
+        assert_eq!(r, AppLayerResult::ok());
+
+        let session = state.get_session().unwrap();
+        assert_eq!(session.framebuffer_updates, 1);
+        assert_eq!(session.rectangles, 4);
+        assert_eq!(session.pixels, 4 + 100 + 200 + 10000);
+        assert_eq!(
+            session.rectangle_encodings,
+            vec![(0, 1), (1, 1), (5, 1), (7, 1)]
+        );
+        assert_eq!(session.server_cut_text, 1);
+        assert_eq!(session.unknown_encoding, None);
+        let clipboard = state.transactions[1].clipboard.as_ref().unwrap();
+        assert_eq!(clipboard.direction, Direction::ToClient);
+        assert_eq!(clipboard.text, b"hello");
+
+        // the length of the data of a trle rectangle is not known
+        let buf: &[u8] = &[
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x0f, 0x00,
+        ];
+        state.parse_response(
+            std::ptr::null(),
+            StreamSlice::from_slice(buf, STREAM_START, 0),
+        );
+        assert!(state.tc_lost);
+        assert_eq!(state.get_session().unwrap().unknown_encoding, Some(15));
+    }
 }
@@ -0,0 +1,121 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::parser::{RtcpPacket, RtcpReportBlock};
+use super::rtp::{RTPSource, RTPTransaction};
+use crate::jsonbuilder::{JsonBuilder, JsonError};
+
+fn log_source(
+    tx: &RTPTransaction, source: &RTPSource, js: &mut JsonBuilder,
+) -> Result<(), JsonError> {
+    js.set_uint("ssrc", source.ssrc as u64)?;
+    js.set_uint("payload_type", source.payload_type as u64)?;
+    let encoding = tx
+        .media
+        .as_ref()
+        .and_then(|m| m.get_format(source.payload_type))
+        .and_then(|f| f.encoding.as_ref());
+    if let Some(encoding) = encoding {
+        js.set_string("codec", encoding)?;
+    }
+    js.set_uint("packets", source.packets)?;
+    js.set_uint("bytes", source.bytes)?;
+    js.set_uint("expected", source.expected())?;
+    js.set_uint("lost", source.lost())?;
+    if let Some(jitter) = source.jitter_ms() {
+        js.set_float("jitter", jitter)?;
+    }
+    Ok(())
+}
+
+fn log_report_blocks(reports: &[RtcpReportBlock], js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_array("reports")?;
+    for report in reports {
+        js.start_object()?;
+        js.set_uint("ssrc", report.ssrc as u64)?;
+        js.set_uint("fraction_lost", report.fraction_lost as u64)?;
+        js.set_int("cumulative_lost", report.cumulative_lost as i64)?;
+        js.set_uint("highest_sequence", report.highest_sequence as u64)?;
+        js.set_uint("jitter", report.jitter as u64)?;
+        js.close()?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+fn log_rtcp(packets: &[RtcpPacket], js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_array("rtcp")?;
+    for packet in packets {
+        js.start_object()?;
+        js.set_string("type", packet.type_str())?;
+        match packet {
+            RtcpPacket::SenderReport {
+                ssrc,
+                packet_count,
+                octet_count,
+                reports,
+            } => {
+                js.set_uint("ssrc", *ssrc as u64)?;
+                js.set_uint("packets", *packet_count as u64)?;
+                js.set_uint("bytes", *octet_count as u64)?;
+                log_report_blocks(reports, js)?;
+            }
+            RtcpPacket::ReceiverReport { ssrc, reports } => {
+                js.set_uint("ssrc", *ssrc as u64)?;
+                log_report_blocks(reports, js)?;
+            }
+            RtcpPacket::Bye { sources } => {
+                js.open_array("sources")?;
+                for ssrc in sources {
+                    js.append_uint(*ssrc as u64)?;
+                }
+                js.close()?;
+            }
+            RtcpPacket::Other { .. } => {}
+        }
+        js.close()?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+fn log_rtp(tx: &RTPTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("rtp")?;
+    if let Some(media) = &tx.media {
+        js.set_string("call_id", &media.call_id)?;
+        js.set_string("media", &media.media)?;
+        js.set_bool("negotiated", tx.negotiated)?;
+    }
+    if let Some(source) = &tx.source {
+        log_source(tx, source, js)?;
+    } else if !tx.rtcp.is_empty() {
+        if let Some(codec) = tx.media.as_ref().and_then(|m| m.codec()) {
+            js.set_string("codec", codec)?;
+        }
+        log_rtcp(&tx.rtcp, js)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_log_json(
+    tx: *mut std::os::raw::c_void, js: &mut JsonBuilder,
+) -> bool {
+    let tx = cast_pointer!(tx, RTPTransaction);
+    log_rtp(tx, js).is_ok()
+}
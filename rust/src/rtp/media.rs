@@ -0,0 +1,331 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Media streams negotiated by SDP offer/answer exchanges (RFC 3264).
+//!
+//! The negotiated RTP and RTCP ports are registered as app-layer
+//! expectations so the media flows are parsed as RTP and tagged with the
+//! Call-ID and codec of the call.
+
+use crate::applayer::{AppLayerExpectationCreate, AppLayerExpectationCreateAddr};
+use crate::core::{Flow, ALPROTO_UNKNOWN, STREAM_TOCLIENT, STREAM_TOSERVER};
+use crate::rtp::rtp::ALPROTO_RTP;
+use crate::sdp::parser::{MediaDescription, SdpMessage};
+use std::net::IpAddr;
+use std::os::raw::{c_int, c_void};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RTPFormat {
+    pub payload_type: u8,
+    /// Encoding name and clock rate, e.g. "PCMU/8000".
+    pub encoding: Option<String>,
+    pub clock_rate: Option<u32>,
+}
+
+/// One media stream of a call.
+#[derive(Debug, Clone)]
+pub struct RTPMedia {
+    pub call_id: String,
+    pub media: String,
+    /// Formats of the answer first, the first one being the codec in use.
+    pub formats: Vec<RTPFormat>,
+    pub offer_addr: IpAddr,
+    pub offer_port: u16,
+    pub offer_rtcp_port: u16,
+    pub answer_addr: IpAddr,
+    pub answer_port: u16,
+    pub answer_rtcp_port: u16,
+}
+
+impl RTPMedia {
+    pub fn codec(&self) -> Option<&str> {
+        self.formats.first().and_then(|f| f.encoding.as_deref())
+    }
+
+    pub fn get_format(&self, payload_type: u8) -> Option<&RTPFormat> {
+        self.formats.iter().find(|f| f.payload_type == payload_type)
+    }
+
+    fn ports(&self) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for port in [
+            self.offer_port,
+            self.offer_rtcp_port,
+            self.answer_port,
+            self.answer_rtcp_port,
+        ] {
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+        ports
+    }
+}
+
+/// Encoding and clock rate of the static payload types (RFC 3551).
+fn static_format(payload_type: u8) -> Option<(&'static str, u32)> {
+    let format = match payload_type {
+        0 => ("PCMU/8000", 8000),
+        3 => ("GSM/8000", 8000),
+        4 => ("G723/8000", 8000),
+        8 => ("PCMA/8000", 8000),
+        9 => ("G722/8000", 8000),
+        13 => ("CN/8000", 8000),
+        18 => ("G729/8000", 8000),
+        26 => ("JPEG/90000", 90000),
+        31 => ("H261/90000", 90000),
+        34 => ("H263/90000", 90000),
+        _ => {
+            return None;
+        }
+    };
+    Some(format)
+}
+
+/// Values of the media and session level attributes with this name.
+fn get_attribute<'a>(
+    sdp: &'a SdpMessage, media: &'a MediaDescription, name: &str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    let name = format!("{}:", name);
+    media
+        .attributes
+        .iter()
+        .chain(sdp.attributes.iter())
+        .flatten()
+        .filter_map(move |a| a.strip_prefix(name.as_str()))
+}
+
+fn get_format(sdp: &SdpMessage, media: &MediaDescription, payload_type: u8) -> RTPFormat {
+    let rtpmap = get_attribute(sdp, media, "rtpmap").find_map(|v| {
+        let (pt, encoding) = v.split_once(' ')?;
+        if pt.parse::<u8>().ok()? == payload_type {
+            Some(encoding.trim())
+        } else {
+            None
+        }
+    });
+    if let Some(encoding) = rtpmap {
+        // encoding name / clock rate [/ channels]
+        let clock_rate = encoding.split('/').nth(1).and_then(|r| r.parse().ok());
+        return RTPFormat {
+            payload_type,
+            encoding: Some(encoding.to_string()),
+            clock_rate,
+        };
+    }
+    match static_format(payload_type) {
+        Some((encoding, clock_rate)) => RTPFormat {
+            payload_type,
+            encoding: Some(encoding.to_string()),
+            clock_rate: Some(clock_rate),
+        },
+        None => RTPFormat {
+            payload_type,
+            encoding: None,
+            clock_rate: None,
+        },
+    }
+}
+
+fn get_address(sdp: &SdpMessage, media: &MediaDescription) -> Option<IpAddr> {
+    media
+        .connection_data
+        .as_ref()
+        .or(sdp.connection_data.as_ref())
+        .map(|c| c.connection_address)
+}
+
+/// RTCP port, from the rtcp attribute (RFC 3605) or the next port.
+fn get_rtcp_port(sdp: &SdpMessage, media: &MediaDescription) -> u16 {
+    get_attribute(sdp, media, "rtcp")
+        .find_map(|v| v.split_whitespace().next()?.parse().ok())
+        .unwrap_or_else(|| media.port.wrapping_add(1))
+}
+
+/// Media streams negotiated by an offer and its answer. Media lines of
+/// the answer match the ones of the offer by position, and streams
+/// rejected with port 0 or not using RTP are skipped.
+pub fn rtp_get_negotiated_media(
+    call_id: &str, offer: &SdpMessage, answer: &SdpMessage,
+) -> Vec<RTPMedia> {
+    let mut negotiated = Vec::new();
+    let (offer_media, answer_media) = match (&offer.media_description, &answer.media_description) {
+        (Some(o), Some(a)) => (o, a),
+        _ => {
+            return negotiated;
+        }
+    };
+    for (om, am) in offer_media.iter().zip(answer_media.iter()) {
+        if om.port == 0 || am.port == 0 || om.media != am.media || !am.proto.contains("RTP") {
+            continue;
+        }
+        let (offer_addr, answer_addr) = match (get_address(offer, om), get_address(answer, am)) {
+            (Some(o), Some(a)) => (o, a),
+            _ => {
+                continue;
+            }
+        };
+        let mut formats: Vec<RTPFormat> = Vec::new();
+        for (sdp, m) in [(answer, am), (offer, om)] {
+            for pt in m.fmt.iter().filter_map(|f| f.parse::<u8>().ok()) {
+                if pt < 128 && !formats.iter().any(|f| f.payload_type == pt) {
+                    formats.push(get_format(sdp, m, pt));
+                }
+            }
+        }
+        negotiated.push(RTPMedia {
+            call_id: call_id.to_string(),
+            media: am.media.clone(),
+            formats,
+            offer_addr,
+            offer_port: om.port,
+            offer_rtcp_port: get_rtcp_port(offer, om),
+            answer_addr,
+            answer_port: am.port,
+            answer_rtcp_port: get_rtcp_port(answer, am),
+        });
+    }
+    negotiated
+}
+
+/// Data attached to the expectations, and so to the RTP flows.
+#[repr(C)]
+pub struct RTPExpectation {
+    /// Free function used by the expectation API, must be first.
+    free: unsafe extern "C" fn(*mut c_void),
+    pub media: RTPMedia,
+    /// Whether the flow is between the negotiated addresses.
+    pub negotiated: bool,
+}
+
+unsafe extern "C" fn rtp_expectation_free(data: *mut c_void) {
+    std::mem::drop(Box::from_raw(data as *mut RTPExpectation));
+}
+
+fn rtp_expectation_new(media: &RTPMedia, negotiated: bool) -> *mut c_void {
+    let data = RTPExpectation {
+        free: rtp_expectation_free,
+        media: media.clone(),
+        negotiated,
+    };
+    Box::into_raw(Box::new(data)) as *mut c_void
+}
+
+/// Register expectations for the media streams of a call.
+///
+/// The same ports are also expected between the addresses of the
+/// signalling flow, so media sent to or from a peer that was not
+/// negotiated in the SDP is still parsed and flagged. Expectations are
+/// matched newest first, so when both address pairs are the same the
+/// negotiated ones, created last, are used.
+pub unsafe fn rtp_expect_media(flow: *const Flow, media: &[RTPMedia]) {
+    let alproto = ALPROTO_RTP;
+    if alproto == ALPROTO_UNKNOWN {
+        return;
+    }
+    let direction = (STREAM_TOSERVER | STREAM_TOCLIENT) as c_int;
+    for m in media {
+        for port in m.ports() {
+            let data = rtp_expectation_new(m, false);
+            if AppLayerExpectationCreate(flow, direction, 0, port, alproto, data) != 0 {
+                rtp_expectation_free(data);
+            }
+        }
+    }
+    for m in media {
+        let (src, dst) = match (m.offer_addr, m.answer_addr) {
+            (IpAddr::V4(o), IpAddr::V4(a)) => (o.octets().to_vec(), a.octets().to_vec()),
+            (IpAddr::V6(o), IpAddr::V6(a)) => (o.octets().to_vec(), a.octets().to_vec()),
+            _ => {
+                continue;
+            }
+        };
+        for port in m.ports() {
+            let data = rtp_expectation_new(m, true);
+            if AppLayerExpectationCreateAddr(
+                flow,
+                direction,
+                src.as_ptr(),
+                dst.as_ptr(),
+                src.len() as u8,
+                0,
+                port,
+                alproto,
+                data,
+            ) != 0
+            {
+                rtp_expectation_free(data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdp::parser::sdp_parse_message;
+
+    const OFFER: &[u8] = b"v=0\r\n\
+                           o=alice 2890844526 2890844526 IN IP4 10.0.0.1\r\n\
+                           s=Talk\r\n\
+                           c=IN IP4 10.0.0.1\r\n\
+                           t=0 0\r\n\
+                           m=audio 49170 RTP/AVP 0 8 97 101\r\n\
+                           a=rtpmap:97 iLBC/8000\r\n\
+                           a=rtpmap:101 telephone-event/8000\r\n\
+                           m=video 51372 RTP/AVP 31\r\n";
+
+    const ANSWER: &[u8] = b"v=0\r\n\
+                            o=bob 2808844564 2808844564 IN IP4 10.0.0.2\r\n\
+                            s=Talk\r\n\
+                            c=IN IP4 10.0.0.2\r\n\
+                            t=0 0\r\n\
+                            m=audio 5004 RTP/AVP 8 101\r\n\
+                            a=rtcp:5010\r\n\
+                            a=rtpmap:101 telephone-event/8000\r\n\
+                            m=video 0 RTP/AVP 31\r\n";
+
+    #[test]
+    fn test_negotiated_media() {
+        let (_, offer) = sdp_parse_message(OFFER).unwrap();
+        let (_, answer) = sdp_parse_message(ANSWER).unwrap();
+        let media = rtp_get_negotiated_media("a84b4c76e66710", &offer, &answer);
+        // the video stream was rejected
+        assert_eq!(media.len(), 1);
+        let m = &media[0];
+        assert_eq!(m.call_id, "a84b4c76e66710");
+        assert_eq!(m.media, "audio");
+        assert_eq!(m.codec(), Some("PCMA/8000"));
+        assert_eq!(m.offer_addr, "10.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(m.offer_port, 49170);
+        assert_eq!(m.offer_rtcp_port, 49171);
+        assert_eq!(m.answer_addr, "10.0.0.2".parse::<IpAddr>().unwrap());
+        assert_eq!(m.answer_port, 5004);
+        assert_eq!(m.answer_rtcp_port, 5010);
+        let pts: Vec<u8> = m.formats.iter().map(|f| f.payload_type).collect();
+        assert_eq!(pts, vec![8, 101, 0, 97]);
+        assert_eq!(
+            m.get_format(97),
+            Some(&RTPFormat {
+                payload_type: 97,
+                encoding: Some("iLBC/8000".to_string()),
+                clock_rate: Some(8000),
+            })
+        );
+        assert_eq!(m.ports(), vec![49170, 49171, 5004, 5010]);
+    }
+}
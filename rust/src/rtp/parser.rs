@@ -0,0 +1,251 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+// References:
+//   https://www.rfc-editor.org/rfc/rfc3550 (RTP and RTCP)
+//   https://www.rfc-editor.org/rfc/rfc5761 (RTP and RTCP on a single port)
+
+use nom7::bytes::complete::take;
+use nom7::combinator::{complete, verify};
+use nom7::error::{make_error, ErrorKind};
+use nom7::multi::{count, many1};
+use nom7::number::complete::{be_i24, be_u16, be_u32, be_u8};
+use nom7::{Err, IResult};
+
+pub const RTP_VERSION: u8 = 2;
+
+// RTCP packet types.
+pub const RTCP_SR: u8 = 200;
+pub const RTCP_RR: u8 = 201;
+pub const RTCP_SDES: u8 = 202;
+pub const RTCP_BYE: u8 = 203;
+pub const RTCP_APP: u8 = 204;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RtpHeader {
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub csrc: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtcpReportBlock {
+    pub ssrc: u32,
+    pub fraction_lost: u8,
+    pub cumulative_lost: i32,
+    pub highest_sequence: u32,
+    pub jitter: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpPacket {
+    SenderReport {
+        ssrc: u32,
+        packet_count: u32,
+        octet_count: u32,
+        reports: Vec<RtcpReportBlock>,
+    },
+    ReceiverReport {
+        ssrc: u32,
+        reports: Vec<RtcpReportBlock>,
+    },
+    Bye {
+        sources: Vec<u32>,
+    },
+    Other {
+        packet_type: u8,
+    },
+}
+
+impl RtcpPacket {
+    pub fn type_str(&self) -> &'static str {
+        match self {
+            RtcpPacket::SenderReport { .. } => "sr",
+            RtcpPacket::ReceiverReport { .. } => "rr",
+            RtcpPacket::Bye { .. } => "bye",
+            RtcpPacket::Other { packet_type } => match *packet_type {
+                RTCP_SDES => "sdes",
+                RTCP_APP => "app",
+                _ => "unknown",
+            },
+        }
+    }
+}
+
+/// RTCP packet types all fall in 200-204 in the second byte, which
+/// corresponds to the marker bit and the RTP payload types 72-76 that are
+/// never used for RTP (RFC 5761 section 4).
+pub fn is_rtcp(i: &[u8]) -> bool {
+    i.len() >= 2 && (RTCP_SR..=RTCP_APP).contains(&i[1])
+}
+
+pub fn parse_rtp_header(i: &[u8]) -> IResult<&[u8], RtpHeader> {
+    let (i, first) = verify(be_u8, |b| b >> 6 == RTP_VERSION)(i)?;
+    let (i, second) = be_u8(i)?;
+    let (i, sequence) = be_u16(i)?;
+    let (i, timestamp) = be_u32(i)?;
+    let (i, ssrc) = be_u32(i)?;
+    let (i, csrc) = count(be_u32, (first & 0x0f) as usize)(i)?;
+    let i = if first & 0x10 != 0 {
+        let (i, _profile) = be_u16(i)?;
+        let (i, length) = be_u16(i)?;
+        let (i, _) = take(length as usize * 4)(i)?;
+        i
+    } else {
+        i
+    };
+    let header = RtpHeader {
+        marker: second & 0x80 != 0,
+        payload_type: second & 0x7f,
+        sequence,
+        timestamp,
+        ssrc,
+        csrc,
+    };
+    Ok((i, header))
+}
+
+fn parse_report_block(i: &[u8]) -> IResult<&[u8], RtcpReportBlock> {
+    let (i, ssrc) = be_u32(i)?;
+    let (i, fraction_lost) = be_u8(i)?;
+    let (i, cumulative_lost) = be_i24(i)?;
+    let (i, highest_sequence) = be_u32(i)?;
+    let (i, jitter) = be_u32(i)?;
+    // last SR timestamp and delay since last SR
+    let (i, _) = take(8_usize)(i)?;
+    let block = RtcpReportBlock {
+        ssrc,
+        fraction_lost,
+        cumulative_lost,
+        highest_sequence,
+        jitter,
+    };
+    Ok((i, block))
+}
+
+fn parse_rtcp_packet(i: &[u8]) -> IResult<&[u8], RtcpPacket> {
+    let (i, first) = verify(be_u8, |b| b >> 6 == RTP_VERSION)(i)?;
+    let (i, packet_type) = verify(be_u8, |t| (RTCP_SR..=RTCP_APP).contains(t))(i)?;
+    let (i, length) = be_u16(i)?;
+    let (rem, body) = take(length as usize * 4)(i)?;
+    let rc = (first & 0x1f) as usize;
+    let packet = match packet_type {
+        RTCP_SR => {
+            let (b, ssrc) = be_u32(body)?;
+            // NTP and RTP timestamps
+            let (b, _) = take(12_usize)(b)?;
+            let (b, packet_count) = be_u32(b)?;
+            let (b, octet_count) = be_u32(b)?;
+            let (_, reports) = count(parse_report_block, rc)(b)?;
+            RtcpPacket::SenderReport {
+                ssrc,
+                packet_count,
+                octet_count,
+                reports,
+            }
+        }
+        RTCP_RR => {
+            let (b, ssrc) = be_u32(body)?;
+            let (_, reports) = count(parse_report_block, rc)(b)?;
+            RtcpPacket::ReceiverReport { ssrc, reports }
+        }
+        RTCP_BYE => {
+            let (_, sources) = count(be_u32, rc)(body)?;
+            RtcpPacket::Bye { sources }
+        }
+        _ => RtcpPacket::Other { packet_type },
+    };
+    Ok((rem, packet))
+}
+
+/// Parse a compound RTCP packet.
+pub fn parse_rtcp(i: &[u8]) -> IResult<&[u8], Vec<RtcpPacket>> {
+    let (i, packets) = many1(complete(parse_rtcp_packet))(i)?;
+    // the last packet may be followed by padding only
+    if i.iter().any(|&b| b != 0) {
+        return Err(Err::Error(make_error(i, ErrorKind::Eof)));
+    }
+    Ok((i, packets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rtp_header() {
+        let buf: &[u8] = &[
+            0x80, 0x80, 0x1a, 0x2b, 0x00, 0x00, 0x00, 0xa0, 0xde, 0xad, 0xbe, 0xef, 0xff, 0xff,
+        ];
+        let (rem, header) = parse_rtp_header(buf).unwrap();
+        assert_eq!(rem, &[0xff, 0xff]);
+        assert!(header.marker);
+        assert_eq!(header.payload_type, 0);
+        assert_eq!(header.sequence, 0x1a2b);
+        assert_eq!(header.timestamp, 160);
+        assert_eq!(header.ssrc, 0xdeadbeef);
+        assert!(header.csrc.is_empty());
+        assert!(!is_rtcp(buf));
+
+        // one CSRC and a one word header extension
+        let buf: &[u8] = &[
+            0x91, 0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x02, 0xbe, 0xde, 0x00, 0x01, 0x10, 0x20, 0x30, 0x40, 0xd5,
+        ];
+        let (rem, header) = parse_rtp_header(buf).unwrap();
+        assert_eq!(rem, &[0xd5]);
+        assert_eq!(header.payload_type, 8);
+        assert_eq!(header.csrc, vec![2]);
+
+        // wrong version
+        assert!(parse_rtp_header(&[0x40, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rtcp() {
+        // receiver report with one report block, followed by a BYE
+        let buf: &[u8] = &[
+            0x81, 0xc9, 0x00, 0x07, 0x00, 0x00, 0x00, 0x01, 0xde, 0xad, 0xbe, 0xef, 0x19, 0x00,
+            0x00, 0x05, 0x00, 0x01, 0x00, 0x64, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x81, 0xcb, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+        ];
+        assert!(is_rtcp(buf));
+        let (_, packets) = parse_rtcp(buf).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            packets[0],
+            RtcpPacket::ReceiverReport {
+                ssrc: 1,
+                reports: vec![RtcpReportBlock {
+                    ssrc: 0xdeadbeef,
+                    fraction_lost: 25,
+                    cumulative_lost: 5,
+                    highest_sequence: 0x10064,
+                    jitter: 32,
+                }],
+            }
+        );
+        assert_eq!(packets[1], RtcpPacket::Bye { sources: vec![1] });
+        assert_eq!(packets[1].type_str(), "bye");
+
+        // truncated report block
+        assert!(parse_rtcp(&buf[..20]).is_err());
+    }
+}
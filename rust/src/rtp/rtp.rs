@@ -0,0 +1,571 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::applayer::{self, *};
+use crate::conf::conf_get;
+use crate::core::{self, AppProto, Direction, Flow, ALPROTO_UNKNOWN, IPPROTO_UDP};
+use crate::rtp::media::{RTPExpectation, RTPMedia};
+use crate::rtp::parser::*;
+use std;
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::time::Duration;
+
+pub static mut ALPROTO_RTP: AppProto = ALPROTO_UNKNOWN;
+
+/// Number of packets of a source between two statistics transactions.
+static mut RTP_STATS_INTERVAL: u64 = 1000;
+
+/// Maximum number of sources tracked per flow.
+const RTP_MAX_SOURCES: usize = 16;
+
+// Sequence number jumps considered as losses or as misordered packets
+// (RFC 3550 appendix A.1).
+const RTP_MAX_DROPOUT: u16 = 3000;
+const RTP_MAX_MISORDER: u16 = 100;
+
+#[derive(AppLayerEvent)]
+pub enum RTPEvent {
+    InvalidData,
+    /// Media flow between addresses that were not negotiated in the SDP
+    MediaNotNegotiated,
+    /// Payload type that was not negotiated in the SDP
+    UnexpectedPayloadType,
+}
+
+/// Statistics of a synchronization source.
+#[derive(Debug, Clone)]
+pub struct RTPSource {
+    pub ssrc: u32,
+    pub payload_type: u8,
+    pub packets: u64,
+    pub bytes: u64,
+    /// Sequence number of the first packet.
+    base_seq: u32,
+    /// Highest sequence number and number of times it wrapped.
+    max_seq: u16,
+    cycles: u32,
+    /// Packets received since the first one, for the loss.
+    received: u64,
+    clock_rate: Option<u32>,
+    /// Interarrival jitter, in timestamp units.
+    jitter: f64,
+    transit: Option<u32>,
+    unexpected_payload_type: bool,
+}
+
+impl RTPSource {
+    fn new(header: &RtpHeader) -> RTPSource {
+        RTPSource {
+            ssrc: header.ssrc,
+            payload_type: header.payload_type,
+            packets: 0,
+            bytes: 0,
+            base_seq: header.sequence as u32,
+            max_seq: header.sequence,
+            cycles: 0,
+            received: 0,
+            clock_rate: None,
+            jitter: 0.0,
+            transit: None,
+            unexpected_payload_type: false,
+        }
+    }
+
+    fn update(&mut self, header: &RtpHeader, len: usize, clock_rate: Option<u32>, ts: Duration) {
+        self.packets += 1;
+        self.bytes += len as u64;
+        if self.payload_type != header.payload_type || self.packets == 1 {
+            self.payload_type = header.payload_type;
+            self.clock_rate = clock_rate;
+            self.transit = None;
+        }
+        self.update_seq(header.sequence);
+        self.update_jitter(header.timestamp, ts);
+    }
+
+    /// RFC 3550 appendix A.1, without the probation of new sources.
+    fn update_seq(&mut self, seq: u16) {
+        if self.received > 0 {
+            let delta = seq.wrapping_sub(self.max_seq);
+            if delta < RTP_MAX_DROPOUT {
+                if seq < self.max_seq {
+                    self.cycles += 1 << 16;
+                }
+                self.max_seq = seq;
+            } else if delta <= u16::MAX - RTP_MAX_MISORDER {
+                // large jump, the source restarted
+                self.base_seq = seq as u32;
+                self.max_seq = seq;
+                self.cycles = 0;
+                self.received = 0;
+            }
+        }
+        self.received += 1;
+    }
+
+    /// RFC 3550 appendix A.8.
+    fn update_jitter(&mut self, timestamp: u32, ts: Duration) {
+        let clock_rate = match self.clock_rate {
+            Some(rate) if rate > 0 => rate,
+            _ => {
+                return;
+            }
+        };
+        // arrival time in timestamp units, wrapping as the RTP timestamp
+        let arrival = (ts.as_micros() * clock_rate as u128 / 1_000_000) as u32;
+        let transit = arrival.wrapping_sub(timestamp);
+        if let Some(last) = self.transit {
+            let d = (transit.wrapping_sub(last) as i32).unsigned_abs() as f64;
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.transit = Some(transit);
+    }
+
+    pub fn expected(&self) -> u64 {
+        (self.cycles as u64 + self.max_seq as u64 + 1).saturating_sub(self.base_seq as u64)
+    }
+
+    pub fn lost(&self) -> u64 {
+        self.expected().saturating_sub(self.received)
+    }
+
+    /// Interarrival jitter in milliseconds, if the clock rate is known.
+    pub fn jitter_ms(&self) -> Option<f64> {
+        let clock_rate = self.clock_rate.filter(|&rate| rate > 0)?;
+        Some(self.jitter * 1000.0 / clock_rate as f64)
+    }
+}
+
+pub struct RTPTransaction {
+    tx_id: u64,
+    pub media: Option<RTPMedia>,
+    pub negotiated: bool,
+    /// Statistics of a source, so far.
+    pub source: Option<RTPSource>,
+    pub rtcp: Vec<RtcpPacket>,
+    tx_data: applayer::AppLayerTxData,
+}
+
+impl Transaction for RTPTransaction {
+    fn id(&self) -> u64 {
+        self.tx_id
+    }
+}
+
+#[derive(Default)]
+pub struct RTPState {
+    state_data: AppLayerStateData,
+    tx_id: u64,
+    transactions: VecDeque<RTPTransaction>,
+    /// Media stream from the expectation of the flow.
+    media: Option<RTPMedia>,
+    negotiated: bool,
+    initialized: bool,
+    sources: Vec<RTPSource>,
+}
+
+impl State<RTPTransaction> for RTPState {
+    fn get_transaction_count(&self) -> usize {
+        self.transactions.len()
+    }
+
+    fn get_transaction_by_index(&self, index: usize) -> Option<&RTPTransaction> {
+        self.transactions.get(index)
+    }
+}
+
+impl RTPState {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the media stream the flow was expected for.
+    fn init(&mut self, flow: *const Flow) {
+        self.initialized = true;
+        let data = unsafe { AppLayerExpectationGetData(flow) as *const RTPExpectation };
+        if let Some(expectation) = unsafe { data.as_ref() } {
+            self.set_media(&expectation.media, expectation.negotiated);
+        }
+    }
+
+    fn set_media(&mut self, media: &RTPMedia, negotiated: bool) {
+        self.media = Some(media.clone());
+        self.negotiated = negotiated;
+    }
+
+    fn new_tx(&mut self, direction: Direction) -> RTPTransaction {
+        self.tx_id += 1;
+        let mut tx = RTPTransaction {
+            tx_id: self.tx_id,
+            media: self.media.clone(),
+            negotiated: self.negotiated,
+            source: None,
+            rtcp: Vec::new(),
+            tx_data: applayer::AppLayerTxData::for_direction(direction),
+        };
+        // flag the first transaction of the flow
+        if self.tx_id == 1 && self.media.is_some() && !self.negotiated {
+            tx.tx_data.set_event(RTPEvent::MediaNotNegotiated as u8);
+        }
+        tx
+    }
+
+    fn add_source_tx(&mut self, source: RTPSource, direction: Direction) -> &mut RTPTransaction {
+        let mut tx = self.new_tx(direction);
+        tx.source = Some(source);
+        self.transactions.push_back(tx);
+        self.transactions.back_mut().unwrap()
+    }
+
+    fn set_event(&mut self, event: RTPEvent) {
+        if let Some(tx) = self.transactions.back_mut() {
+            tx.tx_data.set_event(event as u8);
+        }
+    }
+
+    fn handle_rtp(&mut self, header: &RtpHeader, len: usize, direction: Direction, ts: Duration) {
+        let (idx, new_source) = match self.sources.iter().position(|s| s.ssrc == header.ssrc) {
+            Some(idx) => (idx, false),
+            None => {
+                if self.sources.len() >= RTP_MAX_SOURCES {
+                    return;
+                }
+                self.sources.push(RTPSource::new(header));
+                (self.sources.len() - 1, true)
+            }
+        };
+        let (clock_rate, unexpected) = match &self.media {
+            Some(media) => match media.get_format(header.payload_type) {
+                Some(format) => (format.clock_rate, false),
+                None => (None, true),
+            },
+            None => (None, false),
+        };
+        let source = &mut self.sources[idx];
+        source.update(header, len, clock_rate, ts);
+        // only flag the first unexpected payload type of a source
+        let flag = unexpected && !source.unexpected_payload_type;
+        if flag {
+            source.unexpected_payload_type = true;
+        }
+        if new_source || flag || source.packets % unsafe { RTP_STATS_INTERVAL } == 0 {
+            let source = source.clone();
+            let tx = self.add_source_tx(source, direction);
+            if flag {
+                tx.tx_data.set_event(RTPEvent::UnexpectedPayloadType as u8);
+            }
+        }
+    }
+
+    fn handle_rtcp(&mut self, packets: Vec<RtcpPacket>, direction: Direction) {
+        let mut ended = Vec::new();
+        for packet in &packets {
+            if let RtcpPacket::Bye { sources } = packet {
+                for ssrc in sources {
+                    if let Some(idx) = self.sources.iter().position(|s| s.ssrc == *ssrc) {
+                        ended.push(self.sources.remove(idx));
+                    }
+                }
+            }
+        }
+        let mut tx = self.new_tx(direction);
+        tx.rtcp = packets;
+        self.transactions.push_back(tx);
+        // final statistics of the sources that left
+        for source in ended {
+            self.add_source_tx(source, direction);
+        }
+    }
+
+    fn parse(&mut self, input: &[u8], direction: Direction, ts: Duration) -> bool {
+        // STUN and DTLS can be multiplexed on the same port (RFC 7983)
+        if input.is_empty() || input[0] >> 6 != RTP_VERSION {
+            return true;
+        }
+        if is_rtcp(input) {
+            match parse_rtcp(input) {
+                Ok((_, packets)) => {
+                    self.handle_rtcp(packets, direction);
+                    return true;
+                }
+                Err(_) => {
+                    self.set_event(RTPEvent::InvalidData);
+                    return false;
+                }
+            }
+        }
+        match parse_rtp_header(input) {
+            Ok((_, header)) => {
+                self.handle_rtp(&header, input.len(), direction, ts);
+                return true;
+            }
+            Err(_) => {
+                self.set_event(RTPEvent::InvalidData);
+                return false;
+            }
+        }
+    }
+
+    fn get_tx(&mut self, tx_id: u64) -> Option<&RTPTransaction> {
+        self.transactions.iter().find(|tx| tx.tx_id == tx_id + 1)
+    }
+
+    fn free_tx(&mut self, tx_id: u64) {
+        if let Some(idx) = self
+            .transactions
+            .iter()
+            .position(|tx| tx.tx_id == tx_id + 1)
+        {
+            self.transactions.remove(idx);
+        }
+    }
+}
+
+unsafe fn rtp_parse(
+    flow: *const Flow, state: *mut std::os::raw::c_void, stream_slice: StreamSlice,
+    direction: Direction,
+) -> AppLayerResult {
+    let state = cast_pointer!(state, RTPState);
+    if !state.initialized {
+        state.init(flow);
+    }
+    let ts = match (flow as *mut Flow).as_mut() {
+        Some(flow) => flow.get_last_time(),
+        None => Duration::ZERO,
+    };
+    if state.parse(stream_slice.as_slice(), direction, ts) {
+        return AppLayerResult::ok();
+    }
+    return AppLayerResult::err();
+}
+
+unsafe extern "C" fn rs_rtp_parse_ts(
+    flow: *const core::Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
+) -> AppLayerResult {
+    rtp_parse(flow, state, stream_slice, Direction::ToServer)
+}
+
+unsafe extern "C" fn rs_rtp_parse_tc(
+    flow: *const core::Flow, state: *mut std::os::raw::c_void, _pstate: *mut std::os::raw::c_void,
+    stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
+) -> AppLayerResult {
+    rtp_parse(flow, state, stream_slice, Direction::ToClient)
+}
+
+#[no_mangle]
+pub extern "C" fn rs_rtp_tx_get_alstate_progress(
+    _tx: *mut std::os::raw::c_void, _direction: u8,
+) -> std::os::raw::c_int {
+    // transactions are complete when created
+    return 1;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_state_get_tx(
+    state: *mut std::os::raw::c_void, tx_id: u64,
+) -> *mut std::os::raw::c_void {
+    let state = cast_pointer!(state, RTPState);
+    match state.get_tx(tx_id) {
+        Some(tx) => {
+            return tx as *const _ as *mut _;
+        }
+        None => {
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_state_get_tx_count(state: *mut std::os::raw::c_void) -> u64 {
+    let state = cast_pointer!(state, RTPState);
+    return state.tx_id;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_state_tx_free(state: *mut std::os::raw::c_void, tx_id: u64) {
+    let state = cast_pointer!(state, RTPState);
+    state.free_tx(tx_id);
+}
+
+#[no_mangle]
+pub extern "C" fn rs_rtp_state_new(
+    _orig_state: *mut std::os::raw::c_void, _orig_proto: AppProto,
+) -> *mut std::os::raw::c_void {
+    let state = RTPState::new();
+    let boxed = Box::new(state);
+    return Box::into_raw(boxed) as *mut _;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_state_free(state: *mut std::os::raw::c_void) {
+    std::mem::drop(Box::from_raw(state as *mut RTPState));
+}
+
+export_tx_data_get!(rs_rtp_get_tx_data, RTPTransaction);
+export_state_data_get!(rs_rtp_get_state_data, RTPState);
+
+const PARSER_NAME: &[u8] = b"rtp\0";
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rtp_register_parser() {
+    SCLogDebug!("Registering RTP parser.");
+    // RTP has no well known port nor reliable pattern, the flows are only
+    // detected through the expectations created by SIP.
+    let parser = RustParser {
+        name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
+        default_port: std::ptr::null(),
+        ipproto: IPPROTO_UDP,
+        probe_ts: None,
+        probe_tc: None,
+        min_depth: 0,
+        max_depth: 16,
+        state_new: rs_rtp_state_new,
+        state_free: rs_rtp_state_free,
+        tx_free: rs_rtp_state_tx_free,
+        parse_ts: rs_rtp_parse_ts,
+        parse_tc: rs_rtp_parse_tc,
+        get_tx_count: rs_rtp_state_get_tx_count,
+        get_tx: rs_rtp_state_get_tx,
+        tx_comp_st_ts: 1,
+        tx_comp_st_tc: 1,
+        tx_get_progress: rs_rtp_tx_get_alstate_progress,
+        get_eventinfo: Some(RTPEvent::get_event_info),
+        get_eventinfo_byid: Some(RTPEvent::get_event_info_by_id),
+        localstorage_new: None,
+        localstorage_free: None,
+        get_tx_files: None,
+        get_tx_iterator: Some(applayer::state_get_tx_iterator::<RTPState, RTPTransaction>),
+        get_tx_data: rs_rtp_get_tx_data,
+        get_state_data: rs_rtp_get_state_data,
+        apply_tx_config: None,
+        flags: 0,
+        get_frame_id_by_name: None,
+        get_frame_name_by_id: None,
+    };
+
+    let ip_proto_str = CString::new("udp").unwrap();
+
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
+        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+            let _ = AppLayerRegisterParser(&parser, alproto);
+            AppLayerRegisterExpectationProto(IPPROTO_UDP, alproto);
+            // expectations are only created once the parser is enabled
+            ALPROTO_RTP = alproto;
+        }
+        if let Some(val) = conf_get("app-layer.protocols.rtp.stats-interval") {
+            match val.parse::<u64>() {
+                Ok(v) if v > 0 => {
+                    RTP_STATS_INTERVAL = v;
+                }
+                _ => {
+                    SCLogError!("Invalid value for rtp.stats-interval");
+                }
+            }
+        }
+    } else {
+        SCLogDebug!("Protocol detector and parser disabled for RTP.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::media::RTPFormat;
+
+    fn rtp_packet(pt: u8, seq: u16, timestamp: u32, ssrc: u32) -> Vec<u8> {
+        let mut buf = vec![0x80, pt];
+        buf.extend_from_slice(&seq.to_be_bytes());
+        buf.extend_from_slice(&timestamp.to_be_bytes());
+        buf.extend_from_slice(&ssrc.to_be_bytes());
+        buf.extend_from_slice(&[0xd5; 160]);
+        buf
+    }
+
+    fn media() -> RTPMedia {
+        RTPMedia {
+            call_id: "a84b4c76e66710".to_string(),
+            media: "audio".to_string(),
+            formats: vec![RTPFormat {
+                payload_type: 8,
+                encoding: Some("PCMA/8000".to_string()),
+                clock_rate: Some(8000),
+            }],
+            offer_addr: "10.0.0.1".parse().unwrap(),
+            offer_port: 49170,
+            offer_rtcp_port: 49171,
+            answer_addr: "10.0.0.2".parse().unwrap(),
+            answer_port: 5004,
+            answer_rtcp_port: 5005,
+        }
+    }
+
+    #[test]
+    fn test_rtp_source_loss() {
+        let mut state = RTPState::new();
+        state.set_media(&media(), true);
+        // 20ms packets, 65534 to 5 with 2 and 3 lost
+        let mut ts = Duration::from_secs(100);
+        for (i, seq) in [65534_u16, 65535, 0, 1, 4, 5].iter().enumerate() {
+            let buf = rtp_packet(8, *seq, i as u32 * 160, 1);
+            assert!(state.parse(&buf, Direction::ToServer, ts));
+            ts += Duration::from_millis(20);
+        }
+        let source = &state.sources[0];
+        assert_eq!(source.packets, 6);
+        assert_eq!(source.expected(), 8);
+        assert_eq!(source.lost(), 2);
+        assert_eq!(source.jitter_ms(), Some(0.0));
+        // first packet of the source
+        assert_eq!(state.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_rtp_source_jitter() {
+        let mut state = RTPState::new();
+        state.set_media(&media(), true);
+        let ts = Duration::from_secs(100);
+        state.parse(&rtp_packet(8, 1, 0, 1), Direction::ToServer, ts);
+        // 10ms late, so a transit difference of 80 timestamp units
+        let ts = ts + Duration::from_millis(30);
+        state.parse(&rtp_packet(8, 2, 160, 1), Direction::ToServer, ts);
+        assert_eq!(state.sources[0].jitter_ms(), Some(10.0 / 16.0));
+    }
+
+    #[test]
+    fn test_rtp_unexpected_payload_type() {
+        let mut state = RTPState::new();
+        state.set_media(&media(), false);
+        let ts = Duration::from_secs(100);
+        state.parse(&rtp_packet(8, 1, 0, 1), Direction::ToServer, ts);
+        state.parse(&rtp_packet(0, 2, 160, 1), Direction::ToServer, ts);
+        state.parse(&rtp_packet(0, 3, 320, 1), Direction::ToServer, ts);
+        // new source, then the first unexpected payload type only
+        assert_eq!(state.transactions.len(), 2);
+        assert!(state.sources[0].unexpected_payload_type);
+        assert_eq!(state.sources[0].payload_type, 0);
+
+        // RTCP BYE ends the source
+        let bye: &[u8] = &[0x81, 0xcb, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01];
+        assert!(state.parse(bye, Direction::ToServer, ts));
+        assert!(state.sources.is_empty());
+        assert_eq!(state.transactions.len(), 4);
+        assert_eq!(state.transactions[3].source.as_ref().unwrap().packets, 3);
+    }
+}
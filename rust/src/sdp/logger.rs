@@ -22,7 +22,12 @@ use crate::jsonbuilder::{JsonBuilder, JsonError};
 use super::parser::{ConnectionData, MediaDescription, SdpMessage};
 
 pub fn sdp_log(msg: &SdpMessage, js: &mut JsonBuilder) -> Result<(), JsonError> {
-    js.open_object("sdp")?;
+    sdp_log_object("sdp", msg, js)
+}
+
+/// Log a SDP message as an object with the given name.
+pub fn sdp_log_object(name: &str, msg: &SdpMessage, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object(name)?;
 
     let origin = format!(
         "{} {} {} {} {} {}",
@@ -37,7 +37,7 @@ use nom7::{
 use std::net::IpAddr;
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SdpMessage {
     pub version: u32,
     pub origin: OriginField,
@@ -56,7 +56,7 @@ pub struct SdpMessage {
     pub media_description: Option<Vec<MediaDescription>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OriginField {
     pub username: String,
     pub sess_id: String,
@@ -66,7 +66,7 @@ pub struct OriginField {
     pub unicast_address: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConnectionData {
     pub nettype: String,
     pub addrtype: String,
@@ -75,7 +75,7 @@ pub struct ConnectionData {
     pub number_of_addresses: Option<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MediaDescription {
     pub media: String,
     pub port: u16,
@@ -0,0 +1,630 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! SIP dialog tracking.
+//!
+//! Requests and responses are grouped into dialogs by Call-ID and the
+//! From and To tags (RFC 3261 section 12). Only INVITE dialogs are
+//! tracked.
+
+use crate::rtp::media::{rtp_get_negotiated_media, RTPMedia};
+use crate::sdp::parser::SdpMessage;
+use crate::sip::parser::{Request, Response};
+use crate::sip::sip::SIPEvent;
+use std::collections::{HashMap, VecDeque};
+
+/// Maximum number of dialogs tracked at the same time per flow.
+const SIP_MAX_DIALOGS: usize = 256;
+
+/// Maximum number of users REGISTER failures are counted for per flow.
+const SIP_MAX_REGISTER_USERS: usize = 1024;
+
+/// Number of failed REGISTER attempts for a user before an event is set.
+pub static mut SIP_REGISTER_FAILURE_THRESHOLD: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SIPDialogState {
+    /// INVITE sent, no response yet.
+    Calling,
+    /// Provisional response seen (180 Ringing, 183 Session Progress).
+    Early,
+    /// 2xx response to the INVITE seen.
+    Confirmed,
+    /// BYE seen.
+    Terminated,
+    /// Final non-2xx response to the INVITE.
+    Failed,
+    /// INVITE was cancelled before it was answered.
+    Cancelled,
+}
+
+impl SIPDialogState {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            SIPDialogState::Calling => "calling",
+            SIPDialogState::Early => "early",
+            SIPDialogState::Confirmed => "confirmed",
+            SIPDialogState::Terminated => "terminated",
+            SIPDialogState::Failed => "failed",
+            SIPDialogState::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SIPDialog {
+    pub call_id: String,
+    pub from: String,
+    pub to: String,
+    /// From tag of the caller.
+    pub from_tag: String,
+    /// To tag set by the callee.
+    pub to_tag: Option<String>,
+    pub state: SIPDialogState,
+    /// Final response code of the INVITE.
+    pub final_status: Option<u16>,
+    /// The 2xx response was acknowledged.
+    pub acked: bool,
+    /// Whether the BYE came from the caller.
+    pub ended_by_caller: Option<bool>,
+    pub invite_ts: u64,
+    pub answer_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+    /// SDP offer and answer of the INVITE transaction.
+    pub offer: Option<SdpMessage>,
+    pub answer: Option<SdpMessage>,
+    /// The media streams of the offer and answer were queued.
+    media_negotiated: bool,
+}
+
+impl SIPDialog {
+    fn new(call_id: &str, from_tag: &str, request: &Request, ts: u64) -> SIPDialog {
+        SIPDialog {
+            call_id: call_id.to_string(),
+            from: sip_get_header(&request.headers, "From")
+                .unwrap_or_default()
+                .to_string(),
+            to: sip_get_header(&request.headers, "To")
+                .unwrap_or_default()
+                .to_string(),
+            from_tag: from_tag.to_string(),
+            to_tag: None,
+            state: SIPDialogState::Calling,
+            final_status: None,
+            acked: false,
+            ended_by_caller: None,
+            invite_ts: ts,
+            answer_ts: None,
+            end_ts: None,
+            offer: request.body.clone(),
+            answer: None,
+            media_negotiated: false,
+        }
+    }
+
+    /// Call duration in seconds, from the answer to the BYE.
+    pub fn duration(&self) -> Option<u64> {
+        match (self.answer_ts, self.end_ts) {
+            (Some(start), Some(end)) => Some(end.saturating_sub(start)),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, call_id: &str, from_tag: &str, to_tag: Option<&str>) -> bool {
+        if self.call_id != call_id {
+            return false;
+        }
+        // request from the caller
+        if self.from_tag == from_tag {
+            return match (self.to_tag.as_deref(), to_tag) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            };
+        }
+        // request from the callee, the tags are swapped
+        return to_tag == Some(self.from_tag.as_str()) && self.to_tag.as_deref() == Some(from_tag);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SIPDialogTracker {
+    dialogs: VecDeque<SIPDialog>,
+    register_failures: HashMap<String, u32>,
+    /// Media streams negotiated and not taken yet.
+    media: Vec<RTPMedia>,
+}
+
+impl SIPDialogTracker {
+    fn find(&self, call_id: &str, from_tag: &str, to_tag: Option<&str>) -> Option<usize> {
+        self.dialogs
+            .iter()
+            .position(|d| d.matches(call_id, from_tag, to_tag))
+    }
+
+    /// Queue the media streams of a dialog once its offer and answer are
+    /// both known.
+    fn check_media(&mut self, idx: usize) {
+        let dialog = &mut self.dialogs[idx];
+        if dialog.media_negotiated {
+            return;
+        }
+        if let (Some(offer), Some(answer)) = (&dialog.offer, &dialog.answer) {
+            dialog.media_negotiated = true;
+            self.media
+                .extend(rtp_get_negotiated_media(&dialog.call_id, offer, answer));
+        }
+    }
+
+    /// Media streams negotiated since the last call.
+    pub fn take_media(&mut self) -> Vec<RTPMedia> {
+        std::mem::take(&mut self.media)
+    }
+
+    /// Take the dialogs that have not ended, to be logged when the flow
+    /// ends.
+    pub fn flush(&mut self) -> Vec<SIPDialog> {
+        self.dialogs.drain(..).collect()
+    }
+
+    /// Update the dialogs with a request. Returns an event to set on the
+    /// request and the dialog if it ended, or if it was evicted to make
+    /// room for a new one.
+    pub fn handle_request(
+        &mut self, request: &Request, ts: u64,
+    ) -> (Option<SIPEvent>, Option<SIPDialog>) {
+        let (call_id, from_tag, to_tag) = match dialog_id(&request.headers) {
+            Some(id) => id,
+            None => {
+                return (None, None);
+            }
+        };
+        let idx = self.find(call_id, from_tag, to_tag);
+        match request.method.as_str() {
+            "INVITE" if idx.is_none() && to_tag.is_none() => {
+                let evicted = if self.dialogs.len() >= SIP_MAX_DIALOGS {
+                    self.dialogs.pop_front()
+                } else {
+                    None
+                };
+                self.dialogs
+                    .push_back(SIPDialog::new(call_id, from_tag, request, ts));
+                return (None, evicted);
+            }
+            "ACK" => {
+                // ACKs of error responses, e.g. of an authentication
+                // challenge, are not the ACK of the answer
+                if let Some(idx) =
+                    idx.filter(|&i| self.dialogs[i].state == SIPDialogState::Confirmed)
+                {
+                    let dialog = &mut self.dialogs[idx];
+                    dialog.acked = true;
+                    // offer in the 2xx, answer in the ACK
+                    if dialog.answer.is_none() && request.body.is_some() {
+                        dialog.answer = request.body.clone();
+                    }
+                    self.check_media(idx);
+                }
+            }
+            "CANCEL" => {
+                if let Some(idx) = idx {
+                    let dialog = &mut self.dialogs[idx];
+                    if dialog.state == SIPDialogState::Calling
+                        || dialog.state == SIPDialogState::Early
+                    {
+                        dialog.state = SIPDialogState::Cancelled;
+                    }
+                }
+            }
+            "BYE" => {
+                let idx = match idx {
+                    Some(idx) if self.dialogs[idx].state == SIPDialogState::Confirmed => idx,
+                    _ => {
+                        return (Some(SIPEvent::ByeWithoutDialog), None);
+                    }
+                };
+                let mut dialog = self.dialogs.remove(idx).unwrap();
+                dialog.state = SIPDialogState::Terminated;
+                dialog.end_ts = Some(ts);
+                dialog.ended_by_caller = Some(dialog.from_tag == from_tag);
+                return (None, Some(dialog));
+            }
+            _ => {}
+        }
+        (None, None)
+    }
+
+    /// Update the dialogs with a response. Returns an event to set on the
+    /// response and the dialog if it ended.
+    pub fn handle_response(
+        &mut self, response: &Response, ts: u64,
+    ) -> (Option<SIPEvent>, Option<SIPDialog>) {
+        let code = match response.code.parse::<u16>() {
+            Ok(code) => code,
+            Err(_) => {
+                return (None, None);
+            }
+        };
+        let method = match sip_get_header(&response.headers, "CSeq")
+            .and_then(|cseq| cseq.split_whitespace().nth(1))
+        {
+            Some(method) => method,
+            None => {
+                return (None, None);
+            }
+        };
+        match method {
+            "REGISTER" if code == 401 || code == 407 => {
+                return (self.register_failure(response), None);
+            }
+            // authentication challenge, the INVITE is sent again with
+            // the credentials in the same dialog
+            "INVITE" if code == 401 || code == 407 => {}
+            "INVITE" => {
+                let (call_id, from_tag, to_tag) = match dialog_id(&response.headers) {
+                    Some(id) => id,
+                    None => {
+                        return (None, None);
+                    }
+                };
+                let idx = match self.find(call_id, from_tag, to_tag) {
+                    Some(idx) => idx,
+                    None => {
+                        return (None, None);
+                    }
+                };
+                let dialog = &mut self.dialogs[idx];
+                if code >= 300 {
+                    let mut dialog = self.dialogs.remove(idx).unwrap();
+                    if dialog.state != SIPDialogState::Cancelled {
+                        dialog.state = SIPDialogState::Failed;
+                    }
+                    dialog.final_status = Some(code);
+                    dialog.end_ts = Some(ts);
+                    return (None, Some(dialog));
+                }
+                if code > 100 {
+                    if dialog.to_tag.is_none() {
+                        dialog.to_tag = to_tag.map(|t| t.to_string());
+                    }
+                    if response.body.is_some() {
+                        // offer in the 2xx when the INVITE had none
+                        if dialog.offer.is_none() {
+                            dialog.offer = response.body.clone();
+                        } else if dialog.answer.is_none() {
+                            dialog.answer = response.body.clone();
+                        }
+                    }
+                }
+                if code >= 200 {
+                    if dialog.answer_ts.is_none() {
+                        dialog.answer_ts = Some(ts);
+                        dialog.final_status = Some(code);
+                    }
+                    dialog.state = SIPDialogState::Confirmed;
+                } else if code > 100 && dialog.state == SIPDialogState::Calling {
+                    dialog.state = SIPDialogState::Early;
+                }
+                self.check_media(idx);
+            }
+            _ => {}
+        }
+        (None, None)
+    }
+
+    fn register_failure(&mut self, response: &Response) -> Option<SIPEvent> {
+        let user = sip_get_header(&response.headers, "From").and_then(header_uri_user)?;
+        if !self.register_failures.contains_key(user)
+            && self.register_failures.len() >= SIP_MAX_REGISTER_USERS
+        {
+            return None;
+        }
+        let count = self.register_failures.entry(user.to_string()).or_insert(0);
+        *count += 1;
+        if *count == unsafe { SIP_REGISTER_FAILURE_THRESHOLD } {
+            return Some(SIPEvent::RegisterBruteForce);
+        }
+        None
+    }
+}
+
+/// Get the first value of a header, the name being case insensitive.
+pub fn sip_get_header<'a>(
+    headers: &'a HashMap<String, Vec<String>>, name: &str,
+) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .and_then(|(_, v)| v.first())
+        .map(|v| v.as_str())
+}
+
+/// Call-ID, From tag and To tag of a message.
+fn dialog_id(headers: &HashMap<String, Vec<String>>) -> Option<(&str, &str, Option<&str>)> {
+    let call_id = sip_get_header(headers, "Call-ID")?.trim();
+    let from_tag = sip_get_header(headers, "From").and_then(header_tag)?;
+    let to_tag = sip_get_header(headers, "To").and_then(header_tag);
+    Some((call_id, from_tag, to_tag))
+}
+
+/// Value of the tag parameter of a From or To header.
+fn header_tag(value: &str) -> Option<&str> {
+    // parameters of the URI are inside the angle brackets
+    let params = match value.rfind('>') {
+        Some(idx) => &value[idx + 1..],
+        None => value,
+    };
+    params.split(';').skip(1).find_map(|p| {
+        let p = p.trim();
+        if p.len() > 4 && p[..4].eq_ignore_ascii_case("tag=") {
+            Some(&p[4..])
+        } else {
+            None
+        }
+    })
+}
+
+/// User part of the URI of a From or To header.
+fn header_uri_user(value: &str) -> Option<&str> {
+    let uri = match (value.find('<'), value.find('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value.split(';').next()?,
+    };
+    let uri = uri.trim();
+    let uri = uri
+        .strip_prefix("sips:")
+        .or_else(|| uri.strip_prefix("sip:"))
+        .unwrap_or(uri);
+    let user = uri.split('@').next()?;
+    if user.is_empty() || user.len() == uri.len() {
+        return None;
+    }
+    Some(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sip::parser::{sip_parse_request, sip_parse_response};
+
+    fn request(buf: &str) -> Request {
+        sip_parse_request(buf.as_bytes()).unwrap().1
+    }
+
+    fn response(buf: &str) -> Response {
+        sip_parse_response(buf.as_bytes()).unwrap().1
+    }
+
+    const INVITE: &str = "INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+                          From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                          To: Bob <sip:bob@biloxi.com>\r\n\
+                          Call-ID: a84b4c76e66710\r\n\
+                          CSeq: 314159 INVITE\r\n\
+                          \r\n";
+
+    #[test]
+    fn test_header_tag() {
+        assert_eq!(
+            header_tag("Alice <sip:alice@atlanta.com;transport=udp>;tag=1928301774"),
+            Some("1928301774")
+        );
+        assert_eq!(header_tag("sip:alice@atlanta.com;TAG=abc"), Some("abc"));
+        assert_eq!(header_tag("Bob <sip:bob@biloxi.com>"), None);
+        assert_eq!(
+            header_uri_user("Bob <sip:bob@biloxi.com>;tag=1"),
+            Some("bob")
+        );
+        assert_eq!(header_uri_user("sip:biloxi.com"), None);
+    }
+
+    #[test]
+    fn test_dialog_call() {
+        let mut tracker = SIPDialogTracker::default();
+        assert!(matches!(
+            tracker.handle_request(&request(INVITE), 10),
+            (None, None)
+        ));
+        assert_eq!(tracker.dialogs[0].state, SIPDialogState::Calling);
+
+        let ringing = "SIP/2.0 180 Ringing\r\n\
+                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                       To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                       Call-ID: a84b4c76e66710\r\n\
+                       CSeq: 314159 INVITE\r\n\
+                       \r\n";
+        tracker.handle_response(&response(ringing), 11);
+        assert_eq!(tracker.dialogs[0].state, SIPDialogState::Early);
+        assert_eq!(tracker.dialogs[0].to_tag.as_deref(), Some("a6c85cf"));
+
+        let ok = ringing.replace("180 Ringing", "200 OK");
+        tracker.handle_response(&response(&ok), 15);
+        assert_eq!(tracker.dialogs[0].state, SIPDialogState::Confirmed);
+
+        let ack = INVITE
+            .replace("INVITE sip", "ACK sip")
+            .replace("CSeq: 314159 INVITE", "CSeq: 314159 ACK")
+            .replace(
+                "<sip:bob@biloxi.com>\r\n",
+                "<sip:bob@biloxi.com>;tag=a6c85cf\r\n",
+            );
+        tracker.handle_request(&request(&ack), 15);
+        assert!(tracker.dialogs[0].acked);
+
+        // BYE from the callee
+        let bye = "BYE sip:alice@pc33.atlanta.com SIP/2.0\r\n\
+                   From: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                   To: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                   Call-ID: a84b4c76e66710\r\n\
+                   CSeq: 231 BYE\r\n\
+                   \r\n";
+        let (event, dialog) = tracker.handle_request(&request(bye), 75);
+        assert!(event.is_none());
+        let dialog = dialog.unwrap();
+        assert_eq!(dialog.state, SIPDialogState::Terminated);
+        assert_eq!(dialog.final_status, Some(200));
+        assert_eq!(dialog.ended_by_caller, Some(false));
+        assert_eq!(dialog.duration(), Some(60));
+        assert!(tracker.dialogs.is_empty());
+
+        // BYE again, the dialog is gone
+        let (event, dialog) = tracker.handle_request(&request(bye), 76);
+        assert!(matches!(event, Some(SIPEvent::ByeWithoutDialog)));
+        assert!(dialog.is_none());
+    }
+
+    #[test]
+    fn test_dialog_failed() {
+        let mut tracker = SIPDialogTracker::default();
+        tracker.handle_request(&request(INVITE), 10);
+        let busy = "SIP/2.0 486 Busy Here\r\n\
+                    From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                    To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                    Call-ID: a84b4c76e66710\r\n\
+                    CSeq: 314159 INVITE\r\n\
+                    \r\n";
+        let (_, dialog) = tracker.handle_response(&response(busy), 12);
+        let dialog = dialog.unwrap();
+        assert_eq!(dialog.state, SIPDialogState::Failed);
+        assert_eq!(dialog.final_status, Some(486));
+        assert_eq!(dialog.duration(), None);
+    }
+
+    #[test]
+    fn test_dialog_auth_challenge() {
+        let mut tracker = SIPDialogTracker::default();
+        tracker.handle_request(&request(INVITE), 10);
+        let challenge = "SIP/2.0 407 Proxy Authentication Required\r\n\
+                         From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                         To: Bob <sip:bob@biloxi.com>;tag=3flal12sf\r\n\
+                         Call-ID: a84b4c76e66710\r\n\
+                         CSeq: 314159 INVITE\r\n\
+                         \r\n";
+        let (_, dialog) = tracker.handle_response(&response(challenge), 11);
+        assert!(dialog.is_none());
+        let ack = INVITE
+            .replace("INVITE sip", "ACK sip")
+            .replace("CSeq: 314159 INVITE", "CSeq: 314159 ACK")
+            .replace(
+                "<sip:bob@biloxi.com>\r\n",
+                "<sip:bob@biloxi.com>;tag=3flal12sf\r\n",
+            );
+        tracker.handle_request(&request(&ack), 11);
+        assert!(!tracker.dialogs[0].acked);
+
+        // the INVITE with the credentials is answered in the same dialog
+        let invite = INVITE.replace("CSeq: 314159", "CSeq: 314160");
+        assert!(matches!(
+            tracker.handle_request(&request(&invite), 12),
+            (None, None)
+        ));
+        let ok = "SIP/2.0 200 OK\r\n\
+                  From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                  To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                  Call-ID: a84b4c76e66710\r\n\
+                  CSeq: 314160 INVITE\r\n\
+                  \r\n";
+        tracker.handle_response(&response(ok), 15);
+        assert_eq!(tracker.dialogs.len(), 1);
+        assert_eq!(tracker.dialogs[0].state, SIPDialogState::Confirmed);
+        assert_eq!(tracker.dialogs[0].to_tag.as_deref(), Some("a6c85cf"));
+        assert_eq!(tracker.dialogs[0].final_status, Some(200));
+    }
+
+    #[test]
+    fn test_dialog_flush() {
+        let mut tracker = SIPDialogTracker::default();
+        for i in 0..SIP_MAX_DIALOGS {
+            let invite = INVITE.replace("a84b4c76e66710", &i.to_string());
+            assert!(matches!(
+                tracker.handle_request(&request(&invite), 10),
+                (None, None)
+            ));
+        }
+        // the oldest dialog is evicted, and returned to be logged
+        let (_, dialog) = tracker.handle_request(&request(INVITE), 11);
+        let dialog = dialog.unwrap();
+        assert_eq!(dialog.call_id, "0");
+        assert_eq!(dialog.state, SIPDialogState::Calling);
+        assert_eq!(tracker.dialogs.len(), SIP_MAX_DIALOGS);
+
+        // the dialogs still open at the end of the flow
+        let dialogs = tracker.flush();
+        assert_eq!(dialogs.len(), SIP_MAX_DIALOGS);
+        assert_eq!(dialogs[SIP_MAX_DIALOGS - 1].call_id, "a84b4c76e66710");
+        assert!(tracker.dialogs.is_empty());
+        assert!(tracker.flush().is_empty());
+    }
+
+    #[test]
+    fn test_dialog_media() {
+        let mut tracker = SIPDialogTracker::default();
+        let invite = format!(
+            "{}v=0\r\n\
+             o=alice 2890844526 2890844526 IN IP4 10.0.0.1\r\n\
+             s=Talk\r\n\
+             c=IN IP4 10.0.0.1\r\n\
+             t=0 0\r\n\
+             m=audio 49170 RTP/AVP 0 8\r\n",
+            INVITE
+        );
+        tracker.handle_request(&request(&invite), 10);
+        assert!(tracker.take_media().is_empty());
+
+        let ok = "SIP/2.0 200 OK\r\n\
+                  From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                  To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                  Call-ID: a84b4c76e66710\r\n\
+                  CSeq: 314159 INVITE\r\n\
+                  \r\n\
+                  v=0\r\n\
+                  o=bob 2808844564 2808844564 IN IP4 10.0.0.2\r\n\
+                  s=Talk\r\n\
+                  c=IN IP4 10.0.0.2\r\n\
+                  t=0 0\r\n\
+                  m=audio 5004 RTP/AVP 0\r\n";
+        tracker.handle_response(&response(ok), 15);
+        let media = tracker.take_media();
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].call_id, "a84b4c76e66710");
+        assert_eq!(media[0].codec(), Some("PCMU/8000"));
+        assert_eq!(media[0].answer_port, 5004);
+
+        // retransmission of the 200 OK
+        tracker.handle_response(&response(ok), 16);
+        assert!(tracker.take_media().is_empty());
+    }
+
+    #[test]
+    fn test_register_brute_force() {
+        let mut tracker = SIPDialogTracker::default();
+        let unauthorized = "SIP/2.0 401 Unauthorized\r\n\
+                            From: <sip:100@pbx.example.com>;tag=1\r\n\
+                            To: <sip:100@pbx.example.com>;tag=2\r\n\
+                            Call-ID: 1\r\n\
+                            CSeq: 1 REGISTER\r\n\
+                            \r\n";
+        let threshold = unsafe { SIP_REGISTER_FAILURE_THRESHOLD };
+        for _ in 1..threshold {
+            let (event, _) = tracker.handle_response(&response(unauthorized), 1);
+            assert!(event.is_none());
+        }
+        let (event, _) = tracker.handle_response(&response(unauthorized), 1);
+        assert!(matches!(event, Some(SIPEvent::RegisterBruteForce)));
+        // only once
+        let (event, _) = tracker.handle_response(&response(unauthorized), 1);
+        assert!(event.is_none());
+    }
+}
@@ -18,12 +18,50 @@
 // written by Giuseppe Longo <giuseppe@glongo.it>
 
 use crate::jsonbuilder::{JsonBuilder, JsonError};
-use crate::sdp::logger::sdp_log;
+use crate::sdp::logger::{sdp_log, sdp_log_object};
+use crate::sip::dialog::SIPDialog;
 use crate::sip::sip::SIPTransaction;
 
+fn log_dialog(dialog: &SIPDialog, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("dialog")?;
+    js.set_string("call_id", &dialog.call_id)?;
+    js.set_string("from", &dialog.from)?;
+    js.set_string("to", &dialog.to)?;
+    js.set_string("from_tag", &dialog.from_tag)?;
+    if let Some(to_tag) = &dialog.to_tag {
+        js.set_string("to_tag", to_tag)?;
+    }
+    js.set_string("state", dialog.state.to_str())?;
+    if let Some(status) = dialog.final_status {
+        js.set_uint("final_status", status as u64)?;
+    }
+    js.set_bool("acked", dialog.acked)?;
+    if let Some(by_caller) = dialog.ended_by_caller {
+        js.set_string("ended_by", if by_caller { "caller" } else { "callee" })?;
+    }
+    if let Some(answer_ts) = dialog.answer_ts {
+        js.set_uint("setup_time", answer_ts.saturating_sub(dialog.invite_ts))?;
+    }
+    if let Some(duration) = dialog.duration() {
+        js.set_uint("duration", duration)?;
+    }
+    if let Some(offer) = &dialog.offer {
+        sdp_log_object("offer", offer, js)?;
+    }
+    if let Some(answer) = &dialog.answer {
+        sdp_log_object("answer", answer, js)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
 fn log(tx: &SIPTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
     js.open_object("sip")?;
 
+    if let Some(dialog) = &tx.dialog {
+        log_dialog(dialog, js)?;
+    }
+
     if let Some(req) = &tx.request {
         js.set_string("method", &req.method)?
             .set_string("uri", &req.path)?
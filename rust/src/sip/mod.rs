@@ -20,6 +20,7 @@
 // written by Giuseppe Longo <giuseppe@glongo.it>
 
 pub mod detect;
+pub mod dialog;
 pub mod log;
 pub mod parser;
 pub mod sip;
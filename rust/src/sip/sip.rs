@@ -18,9 +18,12 @@
 // written by Giuseppe Longo <giuseppe@glongo.it>
 
 use crate::applayer::{self, *};
+use crate::conf::conf_get;
 use crate::core;
-use crate::core::{AppProto, ALPROTO_UNKNOWN, IPPROTO_TCP, IPPROTO_UDP};
+use crate::core::{AppProto, Direction, ALPROTO_UNKNOWN, IPPROTO_TCP, IPPROTO_UDP};
 use crate::frames::*;
+use crate::rtp::media::rtp_expect_media;
+use crate::sip::dialog::*;
 use crate::sip::parser::*;
 use nom7::Err;
 use std;
@@ -44,6 +47,8 @@ pub enum SIPFrameType {
 pub enum SIPEvent {
     IncompleteData,
     InvalidData,
+    ByeWithoutDialog,
+    RegisterBruteForce,
 }
 
 #[derive(Default)]
@@ -53,6 +58,9 @@ pub struct SIPState {
     tx_id: u64,
     request_frame: Option<Frame>,
     response_frame: Option<Frame>,
+    dialogs: SIPDialogTracker,
+    /// time of the last packet, in seconds
+    ts: u64,
 }
 
 impl State<SIPTransaction> for SIPState {
@@ -71,6 +79,8 @@ pub struct SIPTransaction {
     pub response: Option<Response>,
     pub request_line: Option<String>,
     pub response_line: Option<String>,
+    /// set on the transaction logging a dialog that ended
+    pub dialog: Option<Box<SIPDialog>>,
     tx_data: applayer::AppLayerTxData,
 }
 
@@ -112,6 +122,83 @@ impl SIPState {
         }
     }
 
+    fn update_ts(&mut self, flow: *const core::Flow) {
+        if let Some(flow) = unsafe { (flow as *mut core::Flow).as_mut() } {
+            self.ts = flow.get_last_time().as_secs();
+        }
+    }
+
+    /// Add a transaction for a request, in either direction as the callee
+    /// sends requests too, e.g. a BYE.
+    fn add_request_tx(
+        &mut self, flow: *const core::Flow, stream_slice: &StreamSlice, input: &[u8],
+        request: Request, direction: Direction,
+    ) -> u64 {
+        let mut tx = self.new_tx(direction);
+        let tx_id = tx.id;
+        sip_frames_ts(flow, stream_slice, &request, tx_id);
+        let (event, dialog) = self.dialogs.handle_request(&request, self.ts);
+        tx.request = Some(request);
+        if let Ok((_, req_line)) = sip_take_line(input) {
+            tx.request_line = req_line;
+        }
+        if let Some(event) = event {
+            tx.tx_data.set_event(event as u8);
+        }
+        self.transactions.push_back(tx);
+        if let Some(dialog) = dialog {
+            self.add_dialog_tx(dialog);
+        }
+        self.expect_media(flow);
+        tx_id
+    }
+
+    fn add_response_tx(
+        &mut self, flow: *const core::Flow, stream_slice: &StreamSlice, input: &[u8],
+        response: Response, direction: Direction,
+    ) -> u64 {
+        let mut tx = self.new_tx(direction);
+        let tx_id = tx.id;
+        sip_frames_tc(flow, stream_slice, &response, tx_id);
+        let (event, dialog) = self.dialogs.handle_response(&response, self.ts);
+        tx.response = Some(response);
+        if let Ok((_, resp_line)) = sip_take_line(input) {
+            tx.response_line = resp_line;
+        }
+        if let Some(event) = event {
+            tx.tx_data.set_event(event as u8);
+        }
+        self.transactions.push_back(tx);
+        if let Some(dialog) = dialog {
+            self.add_dialog_tx(dialog);
+        }
+        self.expect_media(flow);
+        tx_id
+    }
+
+    fn add_dialog_tx(&mut self, dialog: SIPDialog) {
+        self.tx_id += 1;
+        let mut tx = SIPTransaction::new(self.tx_id, Direction::ToServer);
+        tx.tx_data = applayer::AppLayerTxData::new();
+        tx.dialog = Some(Box::new(dialog));
+        self.transactions.push_back(tx);
+    }
+
+    /// Log the dialogs that did not end before the end of the flow.
+    fn flush_dialogs(&mut self) {
+        for dialog in self.dialogs.flush() {
+            self.add_dialog_tx(dialog);
+        }
+    }
+
+    /// Expect the RTP flows of the calls negotiated by the last message.
+    fn expect_media(&mut self, flow: *const core::Flow) {
+        let media = self.dialogs.take_media();
+        if !media.is_empty() {
+            unsafe { rtp_expect_media(flow, &media) };
+        }
+    }
+
     // app-layer-frame-documentation tag start: parse_request
     fn parse_request(&mut self, flow: *const core::Flow, stream_slice: StreamSlice) -> bool {
         let input = stream_slice.as_slice();
@@ -127,13 +214,7 @@ impl SIPState {
 
         match sip_parse_request(input) {
             Ok((_, request)) => {
-                let mut tx = self.new_tx(crate::core::Direction::ToServer);
-                sip_frames_ts(flow, &stream_slice, &request, tx.id);
-                tx.request = Some(request);
-                if let Ok((_, req_line)) = sip_take_line(input) {
-                    tx.request_line = req_line;
-                }
-                self.transactions.push_back(tx);
+                self.add_request_tx(flow, &stream_slice, input, request, Direction::ToServer);
                 return true;
             }
             // app-layer-frame-documentation tag end: parse_request
@@ -142,6 +223,11 @@ impl SIPState {
                 return false;
             }
             Err(_) => {
+                // response to a request sent by the callee
+                if let Ok((_, response)) = sip_parse_response(input) {
+                    self.add_response_tx(flow, &stream_slice, input, response, Direction::ToServer);
+                    return true;
+                }
                 self.set_event(SIPEvent::InvalidData);
                 return false;
             }
@@ -169,16 +255,29 @@ impl SIPState {
                 );
                 SCLogDebug!("ts: pdu {:?}", self.request_frame);
             }
-            match sip_parse_request(start) {
-                Ok((rem, request)) => {
-                    let mut tx = self.new_tx(crate::core::Direction::ToServer);
-                    let tx_id = tx.id;
-                    sip_frames_ts(flow, &stream_slice, &request, tx_id);
-                    tx.request = Some(request);
-                    if let Ok((_, req_line)) = sip_take_line(start) {
-                        tx.request_line = req_line;
-                    }
-                    self.transactions.push_back(tx);
+            let res = match sip_parse_request(start) {
+                Ok((rem, request)) => Ok((
+                    rem,
+                    self.add_request_tx(flow, &stream_slice, start, request, Direction::ToServer),
+                )),
+                Err(Err::Incomplete(needed)) => Err(Err::Incomplete(needed)),
+                // response to a request sent by the callee
+                Err(e) => match sip_parse_response(start) {
+                    Ok((rem, response)) => Ok((
+                        rem,
+                        self.add_response_tx(
+                            flow,
+                            &stream_slice,
+                            start,
+                            response,
+                            Direction::ToServer,
+                        ),
+                    )),
+                    Err(_) => Err(e),
+                },
+            };
+            match res {
+                Ok((rem, tx_id)) => {
                     let consumed = start.len() - rem.len();
                     start = rem;
 
@@ -223,13 +322,7 @@ impl SIPState {
 
         match sip_parse_response(input) {
             Ok((_, response)) => {
-                let mut tx = self.new_tx(crate::core::Direction::ToClient);
-                sip_frames_tc(flow, &stream_slice, &response, tx.id);
-                tx.response = Some(response);
-                if let Ok((_, resp_line)) = sip_take_line(input) {
-                    tx.response_line = resp_line;
-                }
-                self.transactions.push_back(tx);
+                self.add_response_tx(flow, &stream_slice, input, response, Direction::ToClient);
                 return true;
             }
             Err(Err::Incomplete(_)) => {
@@ -237,6 +330,11 @@ impl SIPState {
                 return false;
             }
             Err(_) => {
+                // request sent by the callee
+                if let Ok((_, request)) = sip_parse_request(input) {
+                    self.add_request_tx(flow, &stream_slice, input, request, Direction::ToClient);
+                    return true;
+                }
                 self.set_event(SIPEvent::InvalidData);
                 return false;
             }
@@ -264,16 +362,29 @@ impl SIPState {
                 );
                 SCLogDebug!("tc: pdu {:?}", self.request_frame);
             }
-            match sip_parse_response(start) {
-                Ok((rem, response)) => {
-                    let mut tx = self.new_tx(crate::core::Direction::ToClient);
-                    let tx_id = tx.id;
-                    sip_frames_tc(flow, &stream_slice, &response, tx_id);
-                    tx.response = Some(response);
-                    if let Ok((_, resp_line)) = sip_take_line(start) {
-                        tx.response_line = resp_line;
-                    }
-                    self.transactions.push_back(tx);
+            let res = match sip_parse_response(start) {
+                Ok((rem, response)) => Ok((
+                    rem,
+                    self.add_response_tx(flow, &stream_slice, start, response, Direction::ToClient),
+                )),
+                Err(Err::Incomplete(needed)) => Err(Err::Incomplete(needed)),
+                // request sent by the callee
+                Err(e) => match sip_parse_request(start) {
+                    Ok((rem, request)) => Ok((
+                        rem,
+                        self.add_request_tx(
+                            flow,
+                            &stream_slice,
+                            start,
+                            request,
+                            Direction::ToClient,
+                        ),
+                    )),
+                    Err(_) => Err(e),
+                },
+            };
+            match res {
+                Ok((rem, tx_id)) => {
                     let consumed = start.len() - rem.len();
                     start = rem;
 
@@ -313,6 +424,7 @@ impl SIPTransaction {
             response: None,
             request_line: None,
             response_line: None,
+            dialog: None,
             tx_data: applayer::AppLayerTxData::for_direction(direction),
         }
     }
@@ -443,6 +555,7 @@ pub unsafe extern "C" fn rs_sip_parse_request(
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, SIPState);
+    state.update_ts(flow);
     state.parse_request(flow, stream_slice).into()
 }
 
@@ -453,6 +566,8 @@ pub unsafe extern "C" fn rs_sip_parse_request_tcp(
 ) -> AppLayerResult {
     if stream_slice.is_empty() {
         if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TS) > 0 {
+            let state = cast_pointer!(state, SIPState);
+            state.flush_dialogs();
             return AppLayerResult::ok();
         } else {
             return AppLayerResult::err();
@@ -460,6 +575,7 @@ pub unsafe extern "C" fn rs_sip_parse_request_tcp(
     }
 
     let state = cast_pointer!(state, SIPState);
+    state.update_ts(flow);
     state.parse_request_tcp(flow, stream_slice)
 }
 
@@ -469,6 +585,7 @@ pub unsafe extern "C" fn rs_sip_parse_response(
     stream_slice: StreamSlice, _data: *const std::os::raw::c_void,
 ) -> AppLayerResult {
     let state = cast_pointer!(state, SIPState);
+    state.update_ts(flow);
     state.parse_response(flow, stream_slice).into()
 }
 
@@ -479,6 +596,8 @@ pub unsafe extern "C" fn rs_sip_parse_response_tcp(
 ) -> AppLayerResult {
     if stream_slice.is_empty() {
         if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TC) > 0 {
+            let state = cast_pointer!(state, SIPState);
+            state.flush_dialogs();
             return AppLayerResult::ok();
         } else {
             return AppLayerResult::err();
@@ -486,6 +605,7 @@ pub unsafe extern "C" fn rs_sip_parse_response_tcp(
     }
 
     let state = cast_pointer!(state, SIPState);
+    state.update_ts(flow);
     state.parse_response_tcp(flow, stream_slice)
 }
 
@@ -542,6 +662,14 @@ const PARSER_NAME: &[u8] = b"sip\0";
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_sip_register_parser() {
+    if let Some(val) = conf_get("app-layer.protocols.sip.register-failure-threshold") {
+        if let Ok(v) = val.parse::<u32>() {
+            SIP_REGISTER_FAILURE_THRESHOLD = v;
+        } else {
+            SCLogError!("Invalid value for sip.register-failure-threshold");
+        }
+    }
+
     let mut parser = RustParser {
         name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port: std::ptr::null(),
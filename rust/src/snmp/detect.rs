@@ -17,16 +17,18 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
-use super::snmp::{SNMPTransaction, ALPROTO_SNMP};
+use super::snmp::{SNMPTransaction, SNMPValue, ALPROTO_SNMP};
 use crate::detect::uint::{
     rs_detect_u32_free, rs_detect_u32_match, rs_detect_u32_parse, DetectUintData,
 };
 use crate::detect::{
     DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
-    DetectHelperGetData, DetectHelperKeywordRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    DetectHelperGetData, DetectHelperGetMultiData, DetectHelperKeywordRegister,
+    DetectHelperMultiBufferMpmRegister, DetectSignatureSetAppProto, SCSigTableElmt,
     SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
 };
 use std::os::raw::{c_int, c_void};
+use std::ptr;
 
 static mut G_SNMP_VERSION_KW_ID: c_int = 0;
 static mut G_SNMP_VERSION_BUFFER_ID: c_int = 0;
@@ -34,6 +36,8 @@ static mut G_SNMP_PDUTYPE_KW_ID: c_int = 0;
 static mut G_SNMP_PDUTYPE_BUFFER_ID: c_int = 0;
 static mut G_SNMP_USM_BUFFER_ID: c_int = 0;
 static mut G_SNMP_COMMUNITY_BUFFER_ID: c_int = 0;
+static mut G_SNMP_OID_BUFFER_ID: c_int = 0;
+static mut G_SNMP_VALUE_BUFFER_ID: c_int = 0;
 
 unsafe extern "C" fn snmp_detect_version_setup(
     de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
@@ -182,6 +186,104 @@ pub unsafe extern "C" fn snmp_detect_community_get_data(
         snmp_detect_community_get,
     );
 }
+
+unsafe extern "C" fn snmp_detect_oid_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_SNMP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_SNMP_OID_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn snmp_detect_oid_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, SNMPTransaction);
+    if let Some(ref info) = tx.info {
+        if let Some(var) = info.vars.get(local_id as usize) {
+            *buffer = var.oid.as_ptr();
+            *buffer_len = var.oid.len() as u32;
+            return true;
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn snmp_detect_oid_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        snmp_detect_oid_get,
+    );
+}
+
+unsafe extern "C" fn snmp_detect_value_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_SNMP) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_SNMP_VALUE_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn snmp_detect_value_get(
+    tx: *const c_void, _flow_flags: u8, local_id: u32, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, SNMPTransaction);
+    if let Some(ref info) = tx.info {
+        // only the OctetString values are inspected
+        let value = info
+            .vars
+            .iter()
+            .filter_map(|var| match var.value {
+                SNMPValue::OctetString(ref s) => Some(s),
+                _ => None,
+            })
+            .nth(local_id as usize);
+        if let Some(value) = value {
+            *buffer = value.as_ptr();
+            *buffer_len = value.len() as u32;
+            return true;
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn snmp_detect_value_get_data(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int, local_id: u32,
+) -> *mut c_void {
+    return DetectHelperGetMultiData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        local_id,
+        snmp_detect_value_get,
+    );
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ScDetectSNMPRegister() {
     let kw = SCSigTableElmt {
@@ -256,4 +358,115 @@ pub unsafe extern "C" fn ScDetectSNMPRegister() {
         true,
         snmp_detect_community_get_data,
     );
+
+    let kw = SCSigTableElmt {
+        name: b"snmp.oid\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the OIDs of the SNMP variable bindings\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/snmp-keywords.html#snmp-oid\0".as_ptr() as *const libc::c_char,
+        Setup: snmp_detect_oid_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_snmp_oid_kw_id = DetectHelperKeywordRegister(&kw);
+    G_SNMP_OID_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"snmp.oid\0".as_ptr() as *const libc::c_char,
+        b"SNMP variable binding OIDs\0".as_ptr() as *const libc::c_char,
+        ALPROTO_SNMP,
+        true,
+        true,
+        snmp_detect_oid_get_data,
+    );
+
+    let kw = SCSigTableElmt {
+        name: b"snmp.value\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the OctetString values of the SNMP variable bindings\0"
+            .as_ptr() as *const libc::c_char,
+        url: b"/rules/snmp-keywords.html#snmp-value\0".as_ptr() as *const libc::c_char,
+        Setup: snmp_detect_value_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_snmp_value_kw_id = DetectHelperKeywordRegister(&kw);
+    G_SNMP_VALUE_BUFFER_ID = DetectHelperMultiBufferMpmRegister(
+        b"snmp.value\0".as_ptr() as *const libc::c_char,
+        b"SNMP variable binding values\0".as_ptr() as *const libc::c_char,
+        ALPROTO_SNMP,
+        true,
+        true,
+        snmp_detect_value_get_data,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Direction;
+    use crate::snmp::snmp::{SNMPPduInfo, SNMPVariable};
+
+    fn set_request_tx<'a>() -> SNMPTransaction<'a> {
+        let mut tx = SNMPTransaction::new(Direction::ToServer, 2, 1);
+        tx.info = Some(SNMPPduInfo {
+            vars: vec![
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.40.0".to_string(),
+                    value: SNMPValue::Integer(5),
+                },
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.4.0".to_string(),
+                    value: SNMPValue::OctetString(b"admin".to_vec()),
+                },
+            ],
+            ..Default::default()
+        });
+        tx
+    }
+
+    fn get_buffers(
+        tx: &SNMPTransaction,
+        get: unsafe extern "C" fn(*const c_void, u8, u32, *mut *const u8, *mut u32) -> bool,
+    ) -> Vec<Vec<u8>> {
+        let mut buffers = Vec::new();
+        let mut buffer: *const u8 = ptr::null();
+        let mut buffer_len: u32 = 0;
+        let tx = tx as *const SNMPTransaction as *const c_void;
+        while unsafe { get(tx, 0, buffers.len() as u32, &mut buffer, &mut buffer_len) } {
+            buffers
+                .push(unsafe { std::slice::from_raw_parts(buffer, buffer_len as usize) }.to_vec());
+        }
+        buffers
+    }
+
+    #[test]
+    fn test_snmp_detect_oid() {
+        let tx = set_request_tx();
+        let oids = get_buffers(&tx, snmp_detect_oid_get);
+        assert_eq!(
+            oids,
+            vec![
+                b"1.3.6.1.2.1.1.40.0".to_vec(),
+                b"1.3.6.1.2.1.1.4.0".to_vec()
+            ]
+        );
+        // a subtree prefix with a trailing dot does not match its siblings
+        let matches: Vec<bool> = oids
+            .iter()
+            .map(|oid| oid.starts_with(b"1.3.6.1.2.1.1.4."))
+            .collect();
+        assert_eq!(matches, vec![false, true]);
+    }
+
+    #[test]
+    fn test_snmp_detect_value() {
+        let tx = set_request_tx();
+        // only the OctetString value is a buffer
+        assert_eq!(
+            get_buffers(&tx, snmp_detect_value_get),
+            vec![b"admin".to_vec()]
+        );
+        let tx = SNMPTransaction::new(Direction::ToServer, 3, 1);
+        assert!(get_buffers(&tx, snmp_detect_value_get).is_empty());
+    }
 }
@@ -18,10 +18,13 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use crate::jsonbuilder::{JsonBuilder, JsonError};
-use crate::snmp::snmp::SNMPTransaction;
+use crate::snmp::snmp::{SNMPTransaction, SNMPValue, SNMPVariable};
 use crate::snmp::snmp_parser::{NetworkAddress,PduType};
 use std::borrow::Cow;
 
+/// Maximum length of a logged OctetString value
+const SNMP_LOG_MAX_VALUE_LEN: usize = 256;
+
 fn str_of_pdu_type(t:&PduType) -> Cow<str> {
     match t {
         &PduType::GetRequest => Cow::Borrowed("get_request"),
@@ -37,10 +40,52 @@ fn str_of_pdu_type(t:&PduType) -> Cow<str> {
     }
 }
 
+fn snmp_log_variable(jsb: &mut JsonBuilder, var: &SNMPVariable) -> Result<(), JsonError>
+{
+    jsb.start_object()?;
+    jsb.set_string("oid", &var.oid)?;
+    match var.value {
+        SNMPValue::Integer(n) => {
+            jsb.set_string("type", "integer")?;
+            jsb.set_string("value", &n.to_string())?;
+        },
+        SNMPValue::Unsigned(n) => {
+            jsb.set_string("type", "unsigned")?;
+            jsb.set_string("value", &n.to_string())?;
+        },
+        SNMPValue::OctetString(ref s) => {
+            jsb.set_string("type", "octet_string")?;
+            jsb.set_string_from_bytes_limited("value", s, SNMP_LOG_MAX_VALUE_LEN)?;
+        },
+        SNMPValue::Oid(ref oid) => {
+            jsb.set_string("type", "oid")?;
+            jsb.set_string("value", oid)?;
+        },
+        SNMPValue::IpAddress(ip) => {
+            jsb.set_string("type", "ip_address")?;
+            jsb.set_string("value", &ip.to_string())?;
+        },
+        SNMPValue::Null => {
+            jsb.set_string("type", "null")?;
+        },
+        SNMPValue::Other => {
+            jsb.set_string("type", "other")?;
+        },
+    }
+    jsb.close()?;
+    Ok(())
+}
+
 fn snmp_log_response(jsb: &mut JsonBuilder, tx: &mut SNMPTransaction) -> Result<(), JsonError>
 {
     jsb.open_object("snmp")?;
     jsb.set_uint("version", tx.version as u64)?;
+    if let Some(authenticated) = tx.authenticated {
+        jsb.set_bool("authenticated", authenticated)?;
+    }
+    if tx.decrypted {
+        jsb.set_bool("decrypted", true)?;
+    }
     if tx.encrypted {
         jsb.set_string("pdu_type", "encrypted")?;
     } else {
@@ -59,9 +104,17 @@ fn snmp_log_response(jsb: &mut JsonBuilder, tx: &mut SNMPTransaction) -> Result<
             if !info.vars.is_empty() {
                 jsb.open_array("vars")?;
                 for var in info.vars.iter() {
-                    jsb.append_string(&var.to_string())?;
+                    jsb.append_string(&var.oid)?;
                 }
                 jsb.close()?;
+                // values written by a SetRequest
+                if info.pdu_type == PduType::SetRequest {
+                    jsb.open_array("set_values")?;
+                    for var in info.vars.iter() {
+                        snmp_log_variable(jsb, var)?;
+                    }
+                    jsb.close()?;
+                }
             }
         }
         if let Some(community) = &tx.community {
@@ -81,3 +134,55 @@ pub extern "C" fn rs_snmp_log_json_response(tx: &mut SNMPTransaction, jsb: &mut
 {
     snmp_log_response(jsb, tx).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Direction;
+    use crate::jsonbuilder::{jb_len, jb_ptr};
+    use crate::snmp::snmp::SNMPPduInfo;
+
+    fn log_tx(tx: &mut SNMPTransaction) -> String {
+        let mut jsb = JsonBuilder::try_new_object().unwrap();
+        snmp_log_response(&mut jsb, tx).unwrap();
+        jsb.close().unwrap();
+        let buf = unsafe { std::slice::from_raw_parts(jb_ptr(&mut jsb), jb_len(&jsb)) };
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_snmp_log_set_values() {
+        let mut tx = SNMPTransaction::new(Direction::ToServer, 2, 1);
+        tx.info = Some(SNMPPduInfo {
+            pdu_type: PduType::SetRequest,
+            vars: vec![
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.4.0".to_string(),
+                    value: SNMPValue::OctetString(vec![b'a'; SNMP_LOG_MAX_VALUE_LEN + 1]),
+                },
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.40.0".to_string(),
+                    value: SNMPValue::Integer(-5),
+                },
+            ],
+            ..Default::default()
+        });
+        let truncated = format!("{}[truncated 1 additional byte]", "a".repeat(SNMP_LOG_MAX_VALUE_LEN));
+        assert_eq!(
+            log_tx(&mut tx),
+            format!(
+                "{{\"snmp\":{{\"version\":2,\"pdu_type\":\"set_request\",\
+                 \"vars\":[\"1.3.6.1.2.1.1.4.0\",\"1.3.6.1.2.1.1.40.0\"],\
+                 \"set_values\":[{{\"oid\":\"1.3.6.1.2.1.1.4.0\",\"type\":\"octet_string\",\"value\":\"{}\"}},\
+                 {{\"oid\":\"1.3.6.1.2.1.1.40.0\",\"type\":\"integer\",\"value\":\"-5\"}}]}}}}",
+                truncated
+            )
+        );
+
+        // values of other requests are not logged
+        if let Some(ref mut info) = tx.info {
+            info.pdu_type = PduType::GetRequest;
+        }
+        assert!(!log_tx(&mut tx).contains("set_values"));
+    }
+}
@@ -24,3 +24,4 @@ extern crate snmp_parser;
 pub mod snmp;
 pub mod log;
 pub mod detect;
+pub mod usm;
@@ -20,12 +20,14 @@
 use crate::snmp::snmp_parser::*;
 use crate::core::{self, *};
 use crate::applayer::{self, *};
+use crate::snmp::usm::*;
 use std;
 use std::ffi::CString;
 
 use asn1_rs::Oid;
-use der_parser::ber::BerObjectContent;
+use der_parser::ber::{ber_read_element_header, parse_ber_octetstring, BerObjectContent, Tag};
 use der_parser::der::parse_der_sequence;
+use der_parser::error::BerError;
 use nom7::{Err, IResult};
 use nom7::error::{ErrorKind, make_error};
 
@@ -34,6 +36,7 @@ pub enum SNMPEvent {
     MalformedData,
     UnknownSecurityModel,
     VersionMismatch,
+    AuthenticationFailed,
 }
 
 #[derive(Default)]
@@ -57,7 +60,53 @@ pub struct SNMPPduInfo<'a> {
 
     pub trap_type: Option<(TrapType,Oid<'a>,NetworkAddress)>,
 
-    pub vars: Vec<Oid<'a>>,
+    pub vars: Vec<SNMPVariable>,
+}
+
+/// Value of a variable binding
+#[derive(Debug, PartialEq, Eq)]
+pub enum SNMPValue {
+    Integer(i64),
+    Unsigned(u64),
+    OctetString(Vec<u8>),
+    Oid(String),
+    IpAddress(std::net::Ipv4Addr),
+    Null,
+    /// Exceptions and other types, which are not kept
+    Other,
+}
+
+/// Variable binding, with the OID in dotted notation
+#[derive(Debug, PartialEq, Eq)]
+pub struct SNMPVariable {
+    pub oid: String,
+    pub value: SNMPValue,
+}
+
+impl SNMPVariable {
+    fn new(var: &SnmpVariable) -> SNMPVariable {
+        let value = match var.val {
+            VarBindValue::Value(ref v) => match v {
+                ObjectSyntax::Number(n) => SNMPValue::Integer(*n as i64),
+                ObjectSyntax::String(s) => SNMPValue::OctetString(s.to_vec()),
+                ObjectSyntax::Object(ref oid) => SNMPValue::Oid(oid.to_id_string()),
+                ObjectSyntax::IpAddress(NetworkAddress::IPv4(ip)) => SNMPValue::IpAddress(*ip),
+                ObjectSyntax::Counter32(n) |
+                ObjectSyntax::Gauge32(n) |
+                ObjectSyntax::TimeTicks(n) |
+                ObjectSyntax::UInteger32(n) => SNMPValue::Unsigned(*n as u64),
+                ObjectSyntax::Counter64(n) => SNMPValue::Unsigned(*n),
+                ObjectSyntax::Empty => SNMPValue::Null,
+                _ => SNMPValue::Other,
+            },
+            VarBindValue::Unspecified => SNMPValue::Null,
+            _ => SNMPValue::Other,
+        };
+        SNMPVariable {
+            oid: var.oid.to_id_string(),
+            value,
+        }
+    }
 }
 
 pub struct SNMPTransaction<'a> {
@@ -76,6 +125,12 @@ pub struct SNMPTransaction<'a> {
     /// True if transaction was encrypted
     pub encrypted: bool,
 
+    /// Result of the USM authentication, if the user is configured (SNMPv3)
+    pub authenticated: Option<bool>,
+
+    /// True if transaction was decrypted with a configured user (SNMPv3)
+    pub decrypted: bool,
+
     /// The internal transaction id
     id: u64,
 
@@ -116,7 +171,7 @@ impl<'a> State<SNMPTransaction<'a>> for SNMPState<'a> {
 }
 
 impl<'a> SNMPState<'a> {
-    fn add_pdu_info(&mut self, pdu: &SnmpPdu<'a>, tx: &mut SNMPTransaction<'a>) {
+    fn add_pdu_info(&mut self, pdu: &SnmpPdu, tx: &mut SNMPTransaction<'a>) {
         let mut pdu_info = SNMPPduInfo {
             pdu_type: pdu.pdu_type(),
             ..Default::default()
@@ -128,12 +183,12 @@ impl<'a> SNMPState<'a> {
             SnmpPdu::Bulk(_) => {
             },
             SnmpPdu::TrapV1(ref t)    => {
-                pdu_info.trap_type = Some((t.generic_trap,t.enterprise.clone(),t.agent_addr));
+                pdu_info.trap_type = Some((t.generic_trap,t.enterprise.to_owned(),t.agent_addr));
             }
         }
 
         for var in pdu.vars_iter() {
-            pdu_info.vars.push(var.oid.to_owned());
+            pdu_info.vars.push(SNMPVariable::new(var));
         }
         tx.info = Some(pdu_info);
     }
@@ -151,32 +206,82 @@ impl<'a> SNMPState<'a> {
         0
     }
 
-    fn handle_snmp_v3(&mut self, msg: SnmpV3Message<'a>, _direction: Direction) -> i32 {
+    fn handle_snmp_v3(&mut self, i: &[u8], msg: SnmpV3Message<'a>, _direction: Direction) -> i32 {
         let mut tx = self.new_tx(_direction);
         if self.version != msg.version {
             SCLogDebug!("SNMP version mismatch: expected {}, received {}", self.version, msg.version);
             self.set_event_tx(&mut tx, SNMPEvent::VersionMismatch);
         }
+        let usm = match msg.security_params {
+            SecurityParameters::USM(usm) => Some(usm),
+            _                            => {
+                self.set_event_tx(&mut tx, SNMPEvent::UnknownSecurityModel);
+                None
+            }
+        };
+        let user = usm.as_ref().and_then(|usm| snmp_usm_get_user(&usm.msg_user_name));
+        if let (Some(usm), Some(user)) = (&usm, user) {
+            if msg.header_data.is_authenticated() {
+                let verified = snmp_verify_auth(i, usm, user);
+                if !verified {
+                    self.set_event_tx(&mut tx, SNMPEvent::AuthenticationFailed);
+                }
+                tx.authenticated = Some(verified);
+            }
+        }
         match msg.data {
             ScopedPduData::Plaintext(pdu) => {
                 self.add_pdu_info(&pdu.data, &mut tx);
             },
-            _                             => {
+            ScopedPduData::Encrypted(data) => {
                 tx.encrypted = true;
+                // only decrypt if the message was authenticated with the key
+                // the privacy key is derived from
+                if let (Some(usm), Some(user), Some(true)) = (&usm, user, tx.authenticated) {
+                    if let Some(scoped_pdu) = snmp_decrypt_scoped_pdu(data, usm, user) {
+                        if self.add_decrypted_pdu_info(&scoped_pdu, &mut tx) {
+                            tx.encrypted = false;
+                            tx.decrypted = true;
+                        }
+                    }
+                }
             }
         }
-        match msg.security_params {
-            SecurityParameters::USM(usm) => {
-                tx.usm = Some(usm.msg_user_name);
-            },
-            _                            => {
-                self.set_event_tx(&mut tx, SNMPEvent::UnknownSecurityModel);
-            }
+        if let Some(usm) = usm {
+            tx.usm = Some(usm.msg_user_name);
         }
         self.transactions.push(tx);
         0
     }
 
+    /// Parse the PDU of a decrypted scopedPDU and add it to the transaction.
+    ///
+    /// snmp-parser does not expose its PDU parser, so the PDU is wrapped in
+    /// a SNMPv2c message with an empty community.
+    ///
+    /// Returns true if the PDU could be parsed
+    fn add_decrypted_pdu_info(&mut self, scoped_pdu: &[u8], tx: &mut SNMPTransaction<'a>) -> bool {
+        let pdu = match parse_scoped_pdu_contents(scoped_pdu) {
+            Ok((_, pdu)) => pdu,
+            Err(_) => {
+                SCLogDebug!("Invalid decrypted scopedPDU");
+                return false;
+            }
+        };
+        let mut content = vec![0x02, 0x01, 0x01, 0x04, 0x00];
+        content.extend_from_slice(pdu);
+        let mut wrapped = vec![0x30];
+        wrapped.extend(der_encode_length(content.len()));
+        wrapped.extend(content);
+        match parse_snmp_generic_message(&wrapped) {
+            Ok((_,SnmpGenericMessage::V2(msg))) => {
+                self.add_pdu_info(&msg.pdu, tx);
+                true
+            },
+            _ => false,
+        }
+    }
+
     /// Parse an SNMP request message
     ///
     /// Returns 0 if successful, or -1 on error
@@ -189,7 +294,7 @@ impl<'a> SNMPState<'a> {
         match parse_snmp_generic_message(i) {
             Ok((_rem,SnmpGenericMessage::V1(msg))) |
             Ok((_rem,SnmpGenericMessage::V2(msg))) => self.handle_snmp_v12(msg, direction),
-            Ok((_rem,SnmpGenericMessage::V3(msg))) => self.handle_snmp_v3(msg, direction),
+            Ok((rem,SnmpGenericMessage::V3(msg))) => self.handle_snmp_v3(&i[..i.len()-rem.len()], msg, direction),
             Err(_e) => {
                 SCLogDebug!("parse_snmp failed: {:?}", _e);
                 self.set_event(SNMPEvent::MalformedData);
@@ -242,6 +347,8 @@ impl<'a> SNMPTransaction<'a> {
             community: None,
             usm: None,
             encrypted: false,
+            authenticated: None,
+            decrypted: false,
             id,
             tx_data: applayer::AppLayerTxData::for_direction(direction),
         }
@@ -373,10 +480,65 @@ pub unsafe extern "C" fn rs_snmp_probing_parser(_flow: *const Flow,
 export_tx_data_get!(rs_snmp_get_tx_data, SNMPTransaction);
 export_state_data_get!(rs_snmp_get_state_data, SNMPState);
 
+/// Verify the authentication parameters of a whole SNMPv3 message
+fn snmp_verify_auth(msg: &[u8], usm: &UsmSecurityParameters, user: &SNMPUsmUser) -> bool {
+    let auth = usm.msg_authentication_parameters;
+    // the HMAC is computed with the authentication parameters zeroed
+    let offset = (auth.as_ptr() as usize).wrapping_sub(msg.as_ptr() as usize);
+    if offset > msg.len() || msg.len() - offset < auth.len() {
+        return false;
+    }
+    let mut buf = msg.to_vec();
+    buf[offset..offset + auth.len()].fill(0);
+    let key = snmp_usm_localize_key(user.auth_protocol, &user.auth_key, usm.msg_authoritative_engine_id);
+    snmp_usm_verify_auth(user.auth_protocol, &key, &buf, auth)
+}
+
+/// Decrypt the scopedPDU of a SNMPv3 message, if the user has privacy
+fn snmp_decrypt_scoped_pdu(data: &[u8], usm: &UsmSecurityParameters, user: &SNMPUsmUser) -> Option<Vec<u8>> {
+    let (proto, ref priv_key) = user.privacy.as_ref()?;
+    let key = snmp_usm_localize_key(user.auth_protocol, priv_key, usm.msg_authoritative_engine_id);
+    match proto {
+        SNMPPrivProtocol::Des => snmp_usm_decrypt_des(&key, usm.msg_privacy_parameters, data),
+        SNMPPrivProtocol::Aes => snmp_usm_decrypt_aes(&key,
+                                                      usm.msg_authoritative_engine_boots,
+                                                      usm.msg_authoritative_engine_time,
+                                                      usm.msg_privacy_parameters,
+                                                      data),
+    }
+}
+
+/// Parse the contextEngineID and contextName of a scopedPDU, and return
+/// the PDU. Trailing bytes, like the DES padding, are ignored.
+fn parse_scoped_pdu_contents(i: &[u8]) -> IResult<&[u8], &[u8], BerError> {
+    let (rem, hdr) = ber_read_element_header(i)?;
+    if hdr.tag() != Tag::Sequence {
+        return Err(Err::Error(BerError::InvalidTag));
+    }
+    let len = hdr.length().definite().map_err(Err::Error)?;
+    if rem.len() < len {
+        return Err(Err::Error(BerError::InvalidLength));
+    }
+    let (content, _ctx_engine_id) = parse_ber_octetstring(&rem[..len])?;
+    let (pdu, _ctx_name) = parse_ber_octetstring(content)?;
+    Ok((&rem[len..], pdu))
+}
+
+fn der_encode_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+    let mut out = vec![0x80 | bytes.len() as u8];
+    out.extend(bytes);
+    out
+}
+
 const PARSER_NAME : &[u8] = b"snmp\0";
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_register_snmp_parser() {
+    snmp_usm_load_config();
     let default_port = CString::new("161").unwrap();
     let mut parser = RustParser {
         name               : PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
@@ -430,3 +592,112 @@ pub unsafe extern "C" fn rs_register_snmp_parser() {
         SCLogDebug!("Protocol detector and parser disabled for SNMP.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snmp_set_request_vars() {
+        // v2c SetRequest of sysContact.0 to "admin" and of a sibling OID to 5
+        let buf = [
+            0x30, 0x3b, 0x02, 0x01, 0x01, 0x04, 0x07, b'p', b'r', b'i', b'v', b'a', b't', b'e',
+            0xa3, 0x2d, 0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x22,
+            0x30, 0x11, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x04, 0x00, 0x04, 0x05,
+            b'a', b'd', b'm', b'i', b'n', 0x30, 0x0d, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01,
+            0x01, 0x28, 0x00, 0x02, 0x01, 0x05,
+        ];
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(&buf, Direction::ToServer), 0);
+        let info = state.transactions[0].info.as_ref().unwrap();
+        assert_eq!(info.pdu_type, PduType::SetRequest);
+        assert_eq!(
+            info.vars,
+            vec![
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.4.0".to_string(),
+                    value: SNMPValue::OctetString(b"admin".to_vec()),
+                },
+                SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.40.0".to_string(),
+                    value: SNMPValue::Integer(5),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snmp_v3_decrypt() {
+        // authenticated with HMAC-MD5-96 and password "maplesyrup", the
+        // privacy password is "privsyrup", engine id 00..02 as in RFC 3414
+        let users = ["des-user", "aes-user"]
+            .iter()
+            .zip([SNMPPrivProtocol::Des, SNMPPrivProtocol::Aes])
+            .map(|(name, proto)| SNMPUsmUser {
+                name: name.to_string(),
+                auth_protocol: SNMPAuthProtocol::Md5,
+                auth_key: snmp_usm_password_to_key(SNMPAuthProtocol::Md5, b"maplesyrup"),
+                privacy: Some((
+                    proto,
+                    snmp_usm_password_to_key(SNMPAuthProtocol::Md5, b"privsyrup"),
+                )),
+            })
+            .collect();
+        snmp_usm_set_users(users);
+
+        // GetRequest of sysDescr.0, CBC-DES encrypted
+        let des = [
+            0x30, 0x81, 0x80, 0x02, 0x01, 0x03, 0x30, 0x0e, 0x02, 0x01, 0x42, 0x02, 0x03, 0x00,
+            0xff, 0xe3, 0x04, 0x01, 0x07, 0x02, 0x01, 0x03, 0x04, 0x39, 0x30, 0x37, 0x04, 0x0c,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x01,
+            0x01, 0x02, 0x02, 0x04, 0xd2, 0x04, 0x08, 0x64, 0x65, 0x73, 0x2d, 0x75, 0x73, 0x65,
+            0x72, 0x04, 0x0c, 0x09, 0xf4, 0xe2, 0xe0, 0x3d, 0x47, 0x87, 0x7b, 0xe2, 0xb5, 0x74,
+            0xd7, 0x04, 0x08, 0x00, 0x00, 0x00, 0x01, 0x0a, 0x0b, 0x0c, 0x0d, 0x04, 0x30, 0x99,
+            0x65, 0x32, 0xd3, 0x81, 0x36, 0xdb, 0x04, 0x95, 0x78, 0xb1, 0x0a, 0x31, 0xbc, 0xb9,
+            0xc6, 0xa9, 0x09, 0x75, 0x71, 0xbc, 0x7e, 0x47, 0x0e, 0xef, 0xae, 0xb8, 0xc0, 0x44,
+            0x1c, 0x18, 0x36, 0x64, 0x2b, 0x31, 0xc9, 0x1a, 0x81, 0x37, 0xaa, 0x25, 0x48, 0x4c,
+            0x45, 0xe4, 0x09, 0xed, 0xc6,
+        ];
+        // the same GetRequest, CFB128-AES-128 encrypted
+        let aes = [
+            0x30, 0x7e, 0x02, 0x01, 0x03, 0x30, 0x0e, 0x02, 0x01, 0x42, 0x02, 0x03, 0x00, 0xff,
+            0xe3, 0x04, 0x01, 0x07, 0x02, 0x01, 0x03, 0x04, 0x39, 0x30, 0x37, 0x04, 0x0c, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x01, 0x01,
+            0x02, 0x02, 0x04, 0xd2, 0x04, 0x08, 0x61, 0x65, 0x73, 0x2d, 0x75, 0x73, 0x65, 0x72,
+            0x04, 0x0c, 0xd3, 0x8a, 0xb9, 0xad, 0xda, 0x0f, 0xe3, 0xcd, 0x2d, 0x61, 0xfa, 0x46,
+            0x04, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x04, 0x2e, 0x75, 0x9e,
+            0x77, 0xc5, 0x14, 0xa1, 0x80, 0x95, 0xd3, 0xa3, 0xcf, 0x86, 0xf7, 0xc9, 0x87, 0x53,
+            0x03, 0x83, 0x51, 0x7d, 0xb8, 0x65, 0x85, 0x58, 0x72, 0xc3, 0x28, 0x8f, 0xf8, 0x43,
+            0xe0, 0xdd, 0xd4, 0x84, 0xdd, 0x32, 0x70, 0x4e, 0x54, 0x00, 0x29, 0x1a, 0xc6, 0xa8,
+            0x80, 0x55,
+        ];
+        for buf in [&des[..], &aes[..]] {
+            let mut state = SNMPState::new();
+            assert_eq!(state.parse(buf, Direction::ToServer), 0);
+            let tx = &state.transactions[0];
+            assert_eq!(tx.authenticated, Some(true));
+            assert!(tx.decrypted);
+            assert!(!tx.encrypted);
+            let info = tx.info.as_ref().unwrap();
+            assert_eq!(info.pdu_type, PduType::GetRequest);
+            assert_eq!(
+                info.vars,
+                vec![SNMPVariable {
+                    oid: "1.3.6.1.2.1.1.1.0".to_string(),
+                    value: SNMPValue::Null,
+                }]
+            );
+        }
+
+        // a flipped bit of the ciphertext fails the authentication, the
+        // scopedPDU is then left encrypted
+        let mut tampered = aes;
+        tampered[100] ^= 1;
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(&tampered, Direction::ToServer), 0);
+        let tx = &state.transactions[0];
+        assert_eq!(tx.authenticated, Some(false));
+        assert!(!tx.decrypted);
+        assert!(tx.encrypted);
+    }
+}
@@ -0,0 +1,384 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! SNMPv3 User-based Security Model: key localization, authentication
+//! and privacy.
+//!
+//! References:
+//!   - RFC 3414: User-based Security Model (USM) for SNMPv3
+//!   - RFC 3826: The AES Cipher Algorithm in the SNMP USM
+
+use crate::conf::conf_get_node;
+
+use aes::cipher::generic_array::GenericArray;
+use aes::{Aes128, BlockEncrypt, NewBlockCipher};
+use des::cipher::BlockDecrypt;
+use des::Des;
+use digest::Digest;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+
+/// Length of the truncated HMAC in msgAuthenticationParameters
+pub const SNMP_USM_AUTH_LEN: usize = 12;
+/// Length of the salt in msgPrivacyParameters
+pub const SNMP_USM_PRIV_PARAMS_LEN: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SNMPAuthProtocol {
+    Md5,
+    Sha,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SNMPPrivProtocol {
+    Des,
+    Aes,
+}
+
+/// Credentials of a SNMPv3 user, with the keys not yet localized
+#[derive(Debug)]
+pub struct SNMPUsmUser {
+    pub name: String,
+    pub auth_protocol: SNMPAuthProtocol,
+    pub auth_key: Vec<u8>,
+    pub privacy: Option<(SNMPPrivProtocol, Vec<u8>)>,
+}
+
+/// Users from the configuration, set once when the parser is registered
+static mut SNMP_USM_USERS: &[SNMPUsmUser] = &[];
+
+pub fn snmp_usm_get_user(name: &str) -> Option<&'static SNMPUsmUser> {
+    let users = unsafe { SNMP_USM_USERS };
+    users.iter().find(|u| u.name == name)
+}
+
+impl SNMPAuthProtocol {
+    fn hash(&self, data: &[&[u8]]) -> Vec<u8> {
+        match self {
+            SNMPAuthProtocol::Md5 => {
+                let mut h = Md5::new();
+                data.iter().for_each(|d| h.update(d));
+                h.finalize().to_vec()
+            }
+            SNMPAuthProtocol::Sha => {
+                let mut h = Sha1::new();
+                data.iter().for_each(|d| h.update(d));
+                h.finalize().to_vec()
+            }
+        }
+    }
+}
+
+/// Password to key algorithm, RFC 3414 appendix A.2
+pub fn snmp_usm_password_to_key(proto: SNMPAuthProtocol, password: &[u8]) -> Vec<u8> {
+    // hash 1MB made of the repeated password
+    let mut buf = Vec::with_capacity(1_048_576);
+    while buf.len() < 1_048_576 {
+        let left = std::cmp::min(password.len(), 1_048_576 - buf.len());
+        buf.extend_from_slice(&password[..left]);
+    }
+    proto.hash(&[&buf])
+}
+
+/// Localize a key for an authoritative engine, RFC 3414 section 2.6
+pub fn snmp_usm_localize_key(proto: SNMPAuthProtocol, key: &[u8], engine_id: &[u8]) -> Vec<u8> {
+    proto.hash(&[key, engine_id, key])
+}
+
+/// Verify the HMAC-MD5-96 or HMAC-SHA-96 of a whole message, which has
+/// its msgAuthenticationParameters zeroed
+pub fn snmp_usm_verify_auth(
+    proto: SNMPAuthProtocol, key: &[u8], msg: &[u8], auth_params: &[u8],
+) -> bool {
+    if auth_params.len() != SNMP_USM_AUTH_LEN {
+        return false;
+    }
+    match proto {
+        SNMPAuthProtocol::Md5 => {
+            if let Ok(mut mac) = Hmac::<Md5>::new_from_slice(key) {
+                mac.update(msg);
+                return mac.verify_truncated_left(auth_params).is_ok();
+            }
+        }
+        SNMPAuthProtocol::Sha => {
+            if let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(key) {
+                mac.update(msg);
+                return mac.verify_truncated_left(auth_params).is_ok();
+            }
+        }
+    }
+    return false;
+}
+
+/// Decrypt a CBC-DES encrypted scopedPDU, RFC 3414 section 8.1.1
+pub fn snmp_usm_decrypt_des(key: &[u8], salt: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if key.len() < 16 || salt.len() != SNMP_USM_PRIV_PARAMS_LEN || data.len() % 8 != 0 {
+        return None;
+    }
+    let cipher = Des::new_from_slice(&key[..8]).ok()?;
+    // the last 8 octets of the key are the pre-IV
+    let mut iv: Vec<u8> = key[8..16].iter().zip(salt).map(|(k, s)| k ^ s).collect();
+    let mut out = Vec::with_capacity(data.len());
+    for chunk in data.chunks(8) {
+        let mut block = GenericArray::clone_from_slice(chunk);
+        cipher.decrypt_block(&mut block);
+        out.extend(block.iter().zip(&iv).map(|(b, v)| b ^ v));
+        iv = chunk.to_vec();
+    }
+    Some(out)
+}
+
+/// Decrypt a CFB128-AES-128 encrypted scopedPDU, RFC 3826 section 3.1
+pub fn snmp_usm_decrypt_aes(
+    key: &[u8], engine_boots: u32, engine_time: u32, salt: &[u8], data: &[u8],
+) -> Option<Vec<u8>> {
+    if key.len() < 16 || salt.len() != SNMP_USM_PRIV_PARAMS_LEN {
+        return None;
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(&key[..16]));
+    let mut iv = Vec::with_capacity(16);
+    iv.extend_from_slice(&engine_boots.to_be_bytes());
+    iv.extend_from_slice(&engine_time.to_be_bytes());
+    iv.extend_from_slice(salt);
+    let mut block = GenericArray::clone_from_slice(&iv);
+    let mut out = Vec::with_capacity(data.len());
+    for chunk in data.chunks(16) {
+        cipher.encrypt_block(&mut block);
+        out.extend(chunk.iter().zip(block.iter()).map(|(c, k)| c ^ k));
+        if chunk.len() == 16 {
+            block = GenericArray::clone_from_slice(chunk);
+        }
+    }
+    Some(out)
+}
+
+/// Get a key from the configuration of a user: either a password, or the
+/// non-localized key as a hex string
+fn snmp_usm_conf_key(
+    proto: SNMPAuthProtocol, user: &str, key: &str, password: Option<&str>, hexkey: Option<&str>,
+) -> Option<Vec<u8>> {
+    if let Some(password) = password {
+        if password.is_empty() {
+            SCLogError!("Empty {}-password for SNMP user {}", key, user);
+            return None;
+        }
+        return Some(snmp_usm_password_to_key(proto, password.as_bytes()));
+    }
+    if let Some(hexkey) = hexkey {
+        let hexkey = hexkey.trim_start_matches("0x");
+        if hexkey.len() % 2 != 0 || !hexkey.is_ascii() {
+            SCLogError!("Invalid {}-key for SNMP user {}", key, user);
+            return None;
+        }
+        let bytes: Result<Vec<u8>, _> = (0..hexkey.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hexkey[i..i + 2], 16))
+            .collect();
+        if let Ok(bytes) = bytes {
+            return Some(bytes);
+        }
+        SCLogError!("Invalid {}-key for SNMP user {}", key, user);
+    }
+    return None;
+}
+
+/// Load the SNMPv3 users from the app-layer.protocols.snmp.usm list
+pub fn snmp_usm_load_config() {
+    let mut users = Vec::new();
+    for i in 0.. {
+        let prefix = format!("app-layer.protocols.snmp.usm.{}", i);
+        let node = match conf_get_node(&prefix) {
+            Some(node) => node,
+            None => break,
+        };
+        let name = match node.get_child_value("user") {
+            Some(name) => name.to_string(),
+            None => {
+                SCLogError!("Missing user name in {}", prefix);
+                continue;
+            }
+        };
+        let auth_protocol = match node.get_child_value("auth-protocol") {
+            Some("md5") => SNMPAuthProtocol::Md5,
+            Some("sha") | None => SNMPAuthProtocol::Sha,
+            Some(x) => {
+                SCLogError!("Invalid auth-protocol {} for SNMP user {}", x, name);
+                continue;
+            }
+        };
+        let auth_key = match snmp_usm_conf_key(
+            auth_protocol,
+            &name,
+            "auth",
+            node.get_child_value("auth-password"),
+            node.get_child_value("auth-key"),
+        ) {
+            Some(key) => key,
+            None => {
+                SCLogError!("Missing authentication key for SNMP user {}", name);
+                continue;
+            }
+        };
+        let priv_protocol = match node.get_child_value("priv-protocol") {
+            Some("des") => Some(SNMPPrivProtocol::Des),
+            Some("aes") => Some(SNMPPrivProtocol::Aes),
+            None => None,
+            Some(x) => {
+                SCLogError!("Invalid priv-protocol {} for SNMP user {}", x, name);
+                continue;
+            }
+        };
+        let privacy = if let Some(priv_protocol) = priv_protocol {
+            match snmp_usm_conf_key(
+                auth_protocol,
+                &name,
+                "priv",
+                node.get_child_value("priv-password"),
+                node.get_child_value("priv-key"),
+            ) {
+                Some(key) => Some((priv_protocol, key)),
+                None => {
+                    SCLogError!("Missing privacy key for SNMP user {}", name);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        SCLogDebug!("Loaded SNMPv3 user {}", name);
+        users.push(SNMPUsmUser {
+            name,
+            auth_protocol,
+            auth_key,
+            privacy,
+        });
+    }
+    snmp_usm_set_users(users);
+}
+
+/// Set the SNMPv3 users, which are then kept for the lifetime of the program
+pub fn snmp_usm_set_users(users: Vec<SNMPUsmUser>) {
+    unsafe {
+        SNMP_USM_USERS = Box::leak(users.into_boxed_slice());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snmp_usm_localize_key() {
+        // RFC 3414 appendix A.3
+        let engine_id = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+        let ku = snmp_usm_password_to_key(SNMPAuthProtocol::Md5, b"maplesyrup");
+        assert_eq!(
+            ku,
+            [
+                0x9f, 0xaf, 0x32, 0x83, 0x88, 0x4e, 0x92, 0x83, 0x4e, 0xbc, 0x98, 0x47, 0xd8, 0xed,
+                0xd9, 0x63
+            ]
+        );
+        assert_eq!(
+            snmp_usm_localize_key(SNMPAuthProtocol::Md5, &ku, &engine_id),
+            [
+                0x52, 0x6f, 0x5e, 0xed, 0x9f, 0xcc, 0xe2, 0x6f, 0x89, 0x64, 0xc2, 0x93, 0x07, 0x87,
+                0xd8, 0x2b
+            ]
+        );
+        let ku = snmp_usm_password_to_key(SNMPAuthProtocol::Sha, b"maplesyrup");
+        assert_eq!(
+            ku,
+            [
+                0x9f, 0xb5, 0xcc, 0x03, 0x81, 0x49, 0x7b, 0x37, 0x93, 0x52, 0x89, 0x39, 0xff, 0x78,
+                0x8d, 0x5d, 0x79, 0x14, 0x52, 0x11
+            ]
+        );
+        assert_eq!(
+            snmp_usm_localize_key(SNMPAuthProtocol::Sha, &ku, &engine_id),
+            [
+                0x66, 0x95, 0xfe, 0xbc, 0x92, 0x88, 0xe3, 0x62, 0x82, 0x23, 0x5f, 0xc7, 0x15, 0x1f,
+                0x12, 0x84, 0x97, 0xb3, 0x8f, 0x3f
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snmp_usm_verify_auth() {
+        // RFC 2202 test case 2, truncated to 96 bits
+        let mac = [
+            0x75, 0x0c, 0x78, 0x3e, 0x6a, 0xb0, 0xb5, 0x03, 0xea, 0xa8, 0x6e, 0x31,
+        ];
+        assert!(snmp_usm_verify_auth(
+            SNMPAuthProtocol::Md5,
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &mac
+        ));
+        assert!(!snmp_usm_verify_auth(
+            SNMPAuthProtocol::Md5,
+            b"Jefe",
+            b"what do ya want for something?",
+            &mac
+        ));
+        let mac = [
+            0xef, 0xfc, 0xdf, 0x6a, 0xe5, 0xeb, 0x2f, 0xa2, 0xd2, 0x74, 0x16, 0xd5,
+        ];
+        assert!(snmp_usm_verify_auth(
+            SNMPAuthProtocol::Sha,
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &mac
+        ));
+        assert!(!snmp_usm_verify_auth(
+            SNMPAuthProtocol::Sha,
+            b"Jefe",
+            b"what do ya want for nothing?",
+            &mac[..8]
+        ));
+    }
+
+    #[test]
+    fn test_snmp_usm_decrypt_aes() {
+        // NIST SP 800-38A F.3.13, CFB128-AES128.Decrypt, with the IV split
+        // in engine boots, engine time and salt
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let salt = [0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f];
+        let data = [
+            0x3b, 0x3f, 0xd9, 0x2e, 0xb7, 0x2d, 0xad, 0x20, 0x33, 0x34, 0x49, 0xf8, 0xe8, 0x3c,
+            0xfb, 0x4a, 0xc8, 0xa6, 0x45, 0x37, 0xa0, 0xb3, 0xa9, 0x3f, 0xcd, 0xe3, 0xcd, 0xad,
+            0x9f, 0x1c, 0xe5, 0x8b,
+        ];
+        let out = snmp_usm_decrypt_aes(&key, 0x00010203, 0x04050607, &salt, &data).unwrap();
+        assert_eq!(
+            out,
+            [
+                0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+                0x17, 0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac,
+                0x45, 0xaf, 0x8e, 0x51
+            ]
+        );
+        // CFB does not need padding
+        let out = snmp_usm_decrypt_aes(&key, 0x00010203, 0x04050607, &salt, &data[..20]).unwrap();
+        assert_eq!(out.len(), 20);
+        assert_eq!(out[16..], [0xae, 0x2d, 0x8a, 0x57]);
+    }
+}
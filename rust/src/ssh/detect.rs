@@ -15,8 +15,19 @@
  * 02110-1301, USA.
  */
 
-use super::ssh::SSHTransaction;
+use super::session::{SshAuthOutcome, SshSessionType};
+use super::ssh::{rs_ssh_enable_encrypted_analysis, SSHTransaction, ALPROTO_SSH};
 use crate::core::Direction;
+use crate::detect::uint::{
+    detect_parse_uint_enum, rs_detect_u32_free, rs_detect_u32_match, rs_detect_u32_parse,
+    rs_detect_u8_free, rs_detect_u8_match, DetectUintData,
+};
+use crate::detect::{
+    DetectHelperBufferRegister, DetectHelperKeywordRegister, DetectSignatureSetAppProto,
+    SCSigTableElmt, SigMatchAppendSMToList,
+};
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
 use std::ptr;
 
 #[no_mangle]
@@ -140,3 +151,227 @@ pub unsafe extern "C" fn rs_ssh_tx_get_hassh_string(
 
     return 0;
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_ssh_tx_get_hostkey_fingerprint(
+    tx: *mut std::os::raw::c_void, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> u8 {
+    let tx = cast_pointer!(tx, SSHTransaction);
+    let m = &tx.hostkey_fingerprint;
+    if !m.is_empty() {
+        *buffer = m.as_ptr();
+        *buffer_len = m.len() as u32;
+        return 1;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+
+    return 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_ssh_tx_get_kex_algorithm(
+    tx: *mut std::os::raw::c_void, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> u8 {
+    let tx = cast_pointer!(tx, SSHTransaction);
+    if let Some(negotiated) = &tx.negotiated {
+        let m = &negotiated.kex;
+        if !m.is_empty() {
+            *buffer = m.as_ptr();
+            *buffer_len = m.len() as u32;
+            return 1;
+        }
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+
+    return 0;
+}
+
+static mut G_SSH_AUTH_OUTCOME_KW_ID: c_int = 0;
+static mut G_SSH_AUTH_OUTCOME_BUFFER_ID: c_int = 0;
+static mut G_SSH_AUTH_FAILURES_KW_ID: c_int = 0;
+static mut G_SSH_AUTH_FAILURES_BUFFER_ID: c_int = 0;
+static mut G_SSH_SESSION_TYPE_KW_ID: c_int = 0;
+static mut G_SSH_SESSION_TYPE_BUFFER_ID: c_int = 0;
+
+unsafe fn ssh_detect_parse_enum<T: crate::detect::EnumString<u8>>(
+    raw: *const libc::c_char,
+) -> *mut c_void {
+    if let Ok(s) = CStr::from_ptr(raw).to_str() {
+        if let Some(ctx) = detect_parse_uint_enum::<u8, T>(s) {
+            return Box::into_raw(Box::new(ctx)) as *mut c_void;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe fn ssh_detect_session_setup(
+    de: *mut c_void, s: *mut c_void, ctx: *mut c_void, kw_id: c_int, buffer_id: c_int,
+    free: unsafe extern "C" fn(*mut c_void, *mut c_void),
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_SSH) != 0 {
+        free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    if SigMatchAppendSMToList(de, s, kw_id, ctx, buffer_id).is_null() {
+        free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    // the keywords need the encrypted session analysis
+    rs_ssh_enable_encrypted_analysis();
+    return 0;
+}
+
+unsafe extern "C" fn ssh_detect_u8_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    rs_detect_u8_free(ctx);
+}
+
+unsafe extern "C" fn ssh_detect_u32_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    rs_detect_u32_free(ctx);
+}
+
+unsafe extern "C" fn ssh_detect_auth_outcome_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    let ctx = ssh_detect_parse_enum::<SshAuthOutcome>(raw);
+    if ctx.is_null() {
+        return -1;
+    }
+    ssh_detect_session_setup(
+        de,
+        s,
+        ctx,
+        G_SSH_AUTH_OUTCOME_KW_ID,
+        G_SSH_AUTH_OUTCOME_BUFFER_ID,
+        ssh_detect_u8_free,
+    )
+}
+
+unsafe extern "C" fn ssh_detect_auth_outcome_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, SSHTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    if let Some(session) = &tx.session {
+        return rs_detect_u8_match(session.auth_outcome as u8, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ssh_detect_auth_failures_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    let ctx = rs_detect_u32_parse(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    ssh_detect_session_setup(
+        de,
+        s,
+        ctx,
+        G_SSH_AUTH_FAILURES_KW_ID,
+        G_SSH_AUTH_FAILURES_BUFFER_ID,
+        ssh_detect_u32_free,
+    )
+}
+
+unsafe extern "C" fn ssh_detect_auth_failures_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, SSHTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u32>);
+    if let Some(session) = &tx.session {
+        return rs_detect_u32_match(session.auth_failures, ctx);
+    }
+    return 0;
+}
+
+unsafe extern "C" fn ssh_detect_session_type_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    let ctx = ssh_detect_parse_enum::<SshSessionType>(raw);
+    if ctx.is_null() {
+        return -1;
+    }
+    ssh_detect_session_setup(
+        de,
+        s,
+        ctx,
+        G_SSH_SESSION_TYPE_KW_ID,
+        G_SSH_SESSION_TYPE_BUFFER_ID,
+        ssh_detect_u8_free,
+    )
+}
+
+unsafe extern "C" fn ssh_detect_session_type_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, SSHTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    if let Some(session) = &tx.session {
+        return rs_detect_u8_match(session.session_type as u8, ctx);
+    }
+    return 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectSshRegister() {
+    let kw = SCSigTableElmt {
+        name: b"ssh.auth_outcome\0".as_ptr() as *const libc::c_char,
+        desc: b"match estimated outcome of the SSH authentication\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ssh-keywords.html#ssh-auth-outcome\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ssh_detect_auth_outcome_match),
+        Setup: ssh_detect_auth_outcome_setup,
+        Free: Some(ssh_detect_u8_free),
+        flags: 0,
+    };
+    G_SSH_AUTH_OUTCOME_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_SSH_AUTH_OUTCOME_BUFFER_ID = DetectHelperBufferRegister(
+        b"ssh.auth_outcome\0".as_ptr() as *const libc::c_char,
+        ALPROTO_SSH,
+        true,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ssh.auth_failures\0".as_ptr() as *const libc::c_char,
+        desc: b"match estimated number of failed SSH authentication attempts\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/ssh-keywords.html#ssh-auth-failures\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ssh_detect_auth_failures_match),
+        Setup: ssh_detect_auth_failures_setup,
+        Free: Some(ssh_detect_u32_free),
+        flags: 0,
+    };
+    G_SSH_AUTH_FAILURES_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_SSH_AUTH_FAILURES_BUFFER_ID = DetectHelperBufferRegister(
+        b"ssh.auth_failures\0".as_ptr() as *const libc::c_char,
+        ALPROTO_SSH,
+        true,
+        true,
+    );
+    let kw = SCSigTableElmt {
+        name: b"ssh.session_type\0".as_ptr() as *const libc::c_char,
+        desc: b"match estimated type of SSH session\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/ssh-keywords.html#ssh-session-type\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(ssh_detect_session_type_match),
+        Setup: ssh_detect_session_type_setup,
+        Free: Some(ssh_detect_u8_free),
+        flags: 0,
+    };
+    G_SSH_SESSION_TYPE_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_SSH_SESSION_TYPE_BUFFER_ID = DetectHelperBufferRegister(
+        b"ssh.session_type\0".as_ptr() as *const libc::c_char,
+        ALPROTO_SSH,
+        true,
+        true,
+    );
+}
@@ -15,9 +15,71 @@
  * 02110-1301, USA.
  */
 
+use super::parser::{ssh_split_name_list, SshKexInit, SshNegotiated};
+use super::session::SshSession;
 use super::ssh::{SSHTransaction, SSH_MAX_BANNER_LEN};
+use crate::detect::EnumString;
 use crate::jsonbuilder::{JsonBuilder, JsonError};
 
+fn log_name_list(js: &mut JsonBuilder, key: &str, list: &[u8]) -> Result<(), JsonError> {
+    js.open_array(key)?;
+    for name in ssh_split_name_list(list) {
+        js.append_string_from_bytes(name)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+fn log_kexinit(kexinit: &SshKexInit, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("kexinit")?;
+    log_name_list(js, "kex_algorithms", &kexinit.kex_algs)?;
+    log_name_list(js, "server_host_key_algorithms", &kexinit.server_host_key_algs)?;
+    log_name_list(js, "encryption_client_to_server", &kexinit.encr_algs_client_to_server)?;
+    log_name_list(js, "encryption_server_to_client", &kexinit.encr_algs_server_to_client)?;
+    log_name_list(js, "mac_client_to_server", &kexinit.mac_algs_client_to_server)?;
+    log_name_list(js, "mac_server_to_client", &kexinit.mac_algs_server_to_client)?;
+    log_name_list(js, "compression_client_to_server", &kexinit.comp_algs_client_to_server)?;
+    log_name_list(js, "compression_server_to_client", &kexinit.comp_algs_server_to_client)?;
+    js.close()?;
+    Ok(())
+}
+
+fn log_negotiated(negotiated: &SshNegotiated, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("negotiated")?;
+    for (key, value) in [
+        ("kex", &negotiated.kex),
+        ("server_host_key", &negotiated.server_host_key),
+        ("encryption_client_to_server", &negotiated.encr_client_to_server),
+        ("encryption_server_to_client", &negotiated.encr_server_to_client),
+        ("mac_client_to_server", &negotiated.mac_client_to_server),
+        ("mac_server_to_client", &negotiated.mac_server_to_client),
+        ("compression_client_to_server", &negotiated.comp_client_to_server),
+        ("compression_server_to_client", &negotiated.comp_server_to_client),
+    ] {
+        if !value.is_empty() {
+            js.set_string_from_bytes(key, value)?;
+        }
+    }
+    js.close()?;
+    Ok(())
+}
+
+fn log_session(session: &SshSession, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("session")?;
+    js.set_string("auth", session.auth_outcome.to_str())?;
+    js.set_uint("auth_failures", session.auth_failures as u64)?;
+    js.set_string("type", session.session_type.to_str())?;
+    js.set_uint("keystrokes", session.keystrokes)?;
+    for (key, counters) in [("client", &session.client), ("server", &session.server)] {
+        js.open_object(key)?;
+        js.set_uint("messages", counters.messages)?;
+        js.set_uint("bytes", counters.bytes)?;
+        js.close()?;
+    }
+    js.close()?;
+    Ok(())
+}
+
 fn log_ssh(tx: &SSHTransaction, js: &mut JsonBuilder) -> Result<bool, JsonError> {
     js.open_object("ssh")?;
     if tx.cli_hdr.protover.is_empty() && tx.srv_hdr.protover.is_empty() {
@@ -39,6 +101,9 @@ fn log_ssh(tx: &SSHTransaction, js: &mut JsonBuilder) -> Result<bool, JsonError>
             }
             js.close()?;
         }
+        if let Some(kexinit) = &tx.cli_hdr.kexinit {
+            log_kexinit(kexinit, js)?;
+        }
         js.close()?;
     }
     if !tx.srv_hdr.protover.is_empty() {
@@ -57,8 +122,23 @@ fn log_ssh(tx: &SSHTransaction, js: &mut JsonBuilder) -> Result<bool, JsonError>
             }
             js.close()?;
         }
+        if let Some(kexinit) = &tx.srv_hdr.kexinit {
+            log_kexinit(kexinit, js)?;
+        }
+        if !tx.hostkey_type.is_empty() {
+            js.open_object("hostkey")?;
+            js.set_string_from_bytes("type", &tx.hostkey_type)?;
+            js.set_string_from_bytes("fingerprint", &tx.hostkey_fingerprint)?;
+            js.close()?;
+        }
         js.close()?;
     }
+    if let Some(negotiated) = &tx.negotiated {
+        log_negotiated(negotiated, js)?;
+    }
+    if let Some(session) = &tx.session {
+        log_session(session, js)?;
+    }
     js.close()?;
     return Ok(true);
 }
@@ -20,4 +20,5 @@
 pub mod detect;
 pub mod logger;
 mod parser;
+pub mod session;
 pub mod ssh;
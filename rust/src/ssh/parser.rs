@@ -15,6 +15,7 @@
  * 02110-1301, USA.
  */
 
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
 use digest::Digest;
 use digest::Update;
 use md5::Md5;
@@ -26,6 +27,7 @@ use nom7::multi::length_data;
 use nom7::number::streaming::{be_u32, be_u8};
 use nom7::sequence::terminated;
 use nom7::IResult;
+use sha2::Sha256;
 use std::fmt;
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
@@ -40,6 +42,7 @@ pub enum MessageCode {
 	NewKeys,
 	KexdhInit,
 	KexdhReply,
+	KexDhGexReply,
 	
 	Undefined(u8),
 }
@@ -57,6 +60,7 @@ impl MessageCode {
             21 => MessageCode::NewKeys,
             30 => MessageCode::KexdhInit,
             31 => MessageCode::KexdhReply,
+            33 => MessageCode::KexDhGexReply,
             _ => MessageCode::Undefined(value),
         }
     }
@@ -241,6 +245,168 @@ pub fn ssh_parse_key_exchange(i: &[u8]) -> IResult<&[u8], SshPacketKeyExchange>
     ))
 }
 
+/// Name-lists of a KEXINIT message.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SshKexInit {
+    pub kex_algs: Vec<u8>,
+    pub server_host_key_algs: Vec<u8>,
+    pub encr_algs_client_to_server: Vec<u8>,
+    pub encr_algs_server_to_client: Vec<u8>,
+    pub mac_algs_client_to_server: Vec<u8>,
+    pub mac_algs_server_to_client: Vec<u8>,
+    pub comp_algs_client_to_server: Vec<u8>,
+    pub comp_algs_server_to_client: Vec<u8>,
+}
+
+impl<'a> From<&SshPacketKeyExchange<'a>> for SshKexInit {
+    fn from(kex: &SshPacketKeyExchange<'a>) -> Self {
+        Self {
+            kex_algs: kex.kex_algs.to_vec(),
+            server_host_key_algs: kex.server_host_key_algs.to_vec(),
+            encr_algs_client_to_server: kex.encr_algs_client_to_server.to_vec(),
+            encr_algs_server_to_client: kex.encr_algs_server_to_client.to_vec(),
+            mac_algs_client_to_server: kex.mac_algs_client_to_server.to_vec(),
+            mac_algs_server_to_client: kex.mac_algs_server_to_client.to_vec(),
+            comp_algs_client_to_server: kex.comp_algs_client_to_server.to_vec(),
+            comp_algs_server_to_client: kex.comp_algs_server_to_client.to_vec(),
+        }
+    }
+}
+
+/// Algorithms chosen from the client and server KEXINIT messages.
+/// Empty values mean that no common algorithm was found, or, for the
+/// MACs, that the cipher is an AEAD one which needs no MAC.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SshNegotiated {
+    pub kex: Vec<u8>,
+    pub server_host_key: Vec<u8>,
+    pub encr_client_to_server: Vec<u8>,
+    pub encr_server_to_client: Vec<u8>,
+    pub mac_client_to_server: Vec<u8>,
+    pub mac_server_to_client: Vec<u8>,
+    pub comp_client_to_server: Vec<u8>,
+    pub comp_server_to_client: Vec<u8>,
+}
+
+impl SshNegotiated {
+    /// Group exchange methods send the host key in KEX_DH_GEX_REPLY
+    /// instead of KEXDH_REPLY (RFC 4419).
+    pub fn is_group_exchange(&self) -> bool {
+        self.kex.starts_with(b"diffie-hellman-group-exchange-")
+    }
+
+    /// Length of the MAC or authentication tag of the encrypted records
+    /// sent in a direction, when their packet length is not encrypted.
+    /// That is the case with AES-GCM and the encrypt-then-MAC modes.
+    pub fn clear_length_mac_len(&self, to_server: bool) -> Option<u32> {
+        let (encr, mac) = if to_server {
+            (&self.encr_client_to_server, &self.mac_client_to_server)
+        } else {
+            (&self.encr_server_to_client, &self.mac_server_to_client)
+        };
+        if is_aead_cipher(encr) {
+            if encr.ends_with(b"-gcm@openssh.com") {
+                return Some(16);
+            }
+            return None;
+        }
+        match mac.as_slice() {
+            b"hmac-sha2-512-etm@openssh.com" => Some(64),
+            b"hmac-sha2-256-etm@openssh.com" => Some(32),
+            b"hmac-sha1-etm@openssh.com" | b"hmac-ripemd160-etm@openssh.com" => Some(20),
+            b"hmac-md5-etm@openssh.com" | b"umac-128-etm@openssh.com" => Some(16),
+            b"hmac-sha1-96-etm@openssh.com" | b"hmac-md5-96-etm@openssh.com" => Some(12),
+            b"umac-64-etm@openssh.com" => Some(8),
+            _ => None,
+        }
+    }
+}
+
+pub fn ssh_split_name_list(list: &[u8]) -> impl Iterator<Item = &[u8]> {
+    list.split(|&c| c == b',').filter(|n| !n.is_empty())
+}
+
+/// The chosen algorithm is the first one of the client list that the
+/// server also supports (RFC 4253 section 7.1).
+fn negotiate_algorithm(client: &[u8], server: &[u8]) -> Vec<u8> {
+    ssh_split_name_list(client)
+        .find(|&c| ssh_split_name_list(server).any(|s| s == c))
+        .map(|c| c.to_vec())
+        .unwrap_or_default()
+}
+
+fn is_aead_cipher(cipher: &[u8]) -> bool {
+    cipher == b"chacha20-poly1305@openssh.com"
+        || (cipher.starts_with(b"aes") && cipher.ends_with(b"-gcm@openssh.com"))
+}
+
+fn negotiate_mac(cipher: &[u8], client: &[u8], server: &[u8]) -> Vec<u8> {
+    if is_aead_cipher(cipher) {
+        return Vec::new();
+    }
+    negotiate_algorithm(client, server)
+}
+
+pub fn ssh_negotiate(client: &SshKexInit, server: &SshKexInit) -> SshNegotiated {
+    let encr_client_to_server = negotiate_algorithm(
+        &client.encr_algs_client_to_server,
+        &server.encr_algs_client_to_server,
+    );
+    let encr_server_to_client = negotiate_algorithm(
+        &client.encr_algs_server_to_client,
+        &server.encr_algs_server_to_client,
+    );
+    SshNegotiated {
+        kex: negotiate_algorithm(&client.kex_algs, &server.kex_algs),
+        server_host_key: negotiate_algorithm(
+            &client.server_host_key_algs,
+            &server.server_host_key_algs,
+        ),
+        mac_client_to_server: negotiate_mac(
+            &encr_client_to_server,
+            &client.mac_algs_client_to_server,
+            &server.mac_algs_client_to_server,
+        ),
+        mac_server_to_client: negotiate_mac(
+            &encr_server_to_client,
+            &client.mac_algs_server_to_client,
+            &server.mac_algs_server_to_client,
+        ),
+        comp_client_to_server: negotiate_algorithm(
+            &client.comp_algs_client_to_server,
+            &server.comp_algs_client_to_server,
+        ),
+        comp_server_to_client: negotiate_algorithm(
+            &client.comp_algs_server_to_client,
+            &server.comp_algs_server_to_client,
+        ),
+        encr_client_to_server,
+        encr_server_to_client,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SshHostKey<'a> {
+    pub key_type: &'a [u8],
+    pub blob: &'a [u8],
+}
+
+impl<'a> SshHostKey<'a> {
+    /// SHA256 fingerprint, in the format used by OpenSSH.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let hash = Sha256::digest(self.blob);
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)).into_bytes()
+    }
+}
+
+/// Parse the server host key out of a KEXDH_REPLY, KEX_ECDH_REPLY or
+/// KEX_DH_GEX_REPLY message, which all start with it.
+pub fn ssh_parse_kex_reply(i: &[u8]) -> IResult<&[u8], SshHostKey> {
+    let (i, blob) = parse_string(i)?;
+    let (_, key_type) = parse_string(blob)?;
+    Ok((i, SshHostKey { key_type, blob }))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -724,4 +890,80 @@ mod tests {
             panic!("ssh_parse_key_exchange() parsed malicious key_exchange");
         }
 }
+
+    #[test]
+    fn test_negotiate() {
+        let client = SshKexInit {
+            kex_algs: b"curve25519-sha256,diffie-hellman-group14-sha256,ext-info-c".to_vec(),
+            server_host_key_algs: b"ssh-ed25519,rsa-sha2-512".to_vec(),
+            encr_algs_client_to_server: b"chacha20-poly1305@openssh.com,aes128-ctr".to_vec(),
+            encr_algs_server_to_client: b"chacha20-poly1305@openssh.com,aes128-ctr".to_vec(),
+            mac_algs_client_to_server: b"hmac-sha2-256,hmac-sha1".to_vec(),
+            mac_algs_server_to_client: b"hmac-sha2-256,hmac-sha1".to_vec(),
+            comp_algs_client_to_server: b"none,zlib@openssh.com".to_vec(),
+            comp_algs_server_to_client: b"none,zlib@openssh.com".to_vec(),
+        };
+        let server = SshKexInit {
+            kex_algs: b"diffie-hellman-group14-sha256,diffie-hellman-group1-sha1".to_vec(),
+            server_host_key_algs: b"rsa-sha2-512,ssh-rsa".to_vec(),
+            encr_algs_client_to_server: b"aes128-ctr".to_vec(),
+            encr_algs_server_to_client: b"aes256-gcm@openssh.com,chacha20-poly1305@openssh.com"
+                .to_vec(),
+            mac_algs_client_to_server: b"hmac-sha1,hmac-sha2-256".to_vec(),
+            mac_algs_server_to_client: b"hmac-sha1".to_vec(),
+            comp_algs_client_to_server: b"zlib@openssh.com".to_vec(),
+            comp_algs_server_to_client: b"none".to_vec(),
+        };
+        let negotiated = ssh_negotiate(&client, &server);
+        assert_eq!(negotiated.kex, b"diffie-hellman-group14-sha256");
+        assert!(!negotiated.is_group_exchange());
+        assert_eq!(negotiated.server_host_key, b"rsa-sha2-512");
+        assert_eq!(negotiated.encr_client_to_server, b"aes128-ctr");
+        // the client order wins
+        assert_eq!(negotiated.mac_client_to_server, b"hmac-sha2-256");
+        assert_eq!(negotiated.encr_server_to_client, b"chacha20-poly1305@openssh.com");
+        // no MAC with an AEAD cipher
+        assert!(negotiated.mac_server_to_client.is_empty());
+        assert_eq!(negotiated.comp_client_to_server, b"zlib@openssh.com");
+        assert_eq!(negotiated.comp_server_to_client, b"none");
+        assert_eq!(negotiated.clear_length_mac_len(true), None);
+        assert_eq!(negotiated.clear_length_mac_len(false), None);
+        let etm = SshNegotiated {
+            encr_client_to_server: b"aes256-gcm@openssh.com".to_vec(),
+            encr_server_to_client: b"aes128-ctr".to_vec(),
+            mac_server_to_client: b"hmac-sha2-256-etm@openssh.com".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(etm.clear_length_mac_len(true), Some(16));
+        assert_eq!(etm.clear_length_mac_len(false), Some(32));
+
+        let server = SshKexInit {
+            kex_algs: b"diffie-hellman-group-exchange-sha256".to_vec(),
+            ..Default::default()
+        };
+        let negotiated = ssh_negotiate(&client, &server);
+        assert!(negotiated.kex.is_empty());
+        assert!(negotiated.server_host_key.is_empty());
+    }
+
+    #[test]
+    fn test_parse_kex_reply() {
+        // ssh-ed25519 host key, followed by the start of the ephemeral key
+        let mut reply = vec![0x00, 0x00, 0x00, 0x33, 0x00, 0x00, 0x00, 0x0b];
+        reply.extend_from_slice(b"ssh-ed25519");
+        reply.extend_from_slice(&[0x00, 0x00, 0x00, 0x20]);
+        reply.extend(0..32_u8);
+        reply.extend_from_slice(&[0x00, 0x00, 0x00, 0x20, 0xaa]);
+        let (rem, hostkey) = ssh_parse_kex_reply(&reply).unwrap();
+        assert_eq!(rem, &[0x00, 0x00, 0x00, 0x20, 0xaa]);
+        assert_eq!(hostkey.key_type, b"ssh-ed25519");
+        assert_eq!(hostkey.blob.len(), 51);
+        assert_eq!(
+            hostkey.fingerprint(),
+            b"SHA256:ZkAslGjFiUHdGf/WUL8rQvkib4PTvQatUV0OUQSncCA"
+        );
+
+        // truncated host key
+        assert!(ssh_parse_kex_reply(&reply[..40]).is_err());
+    }
 }
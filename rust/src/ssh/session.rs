@@ -0,0 +1,271 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Analysis of the encrypted part of SSH sessions.
+//!
+//! Nothing is decrypted, only the sizes of the messages and the time
+//! between them are used, so the results are estimates.
+//!
+//! After NEWKEYS the client asks for the ssh-userauth service, and the
+//! size of the SERVICE_ACCEPT reply is used as a reference for the rest of
+//! the session. USERAUTH_SUCCESS has a one byte payload and is at least
+//! one cipher block smaller, while USERAUTH_FAILURE, which lists the
+//! methods that can continue, is about the same size. Once authenticated,
+//! the session is classified from the number of keystroke sized client
+//! messages and from how the bytes are spread between the directions.
+
+use std::time::Duration;
+use suricata_derive::EnumStringU8;
+
+/// Server replies to an authentication request at least this much
+/// smaller than the reference are USERAUTH_SUCCESS.
+const SSH_AUTH_SUCCESS_DELTA: u64 = 16;
+/// Server replies up to this much larger than the reference are
+/// USERAUTH_FAILURE, larger ones are USERAUTH_PK_OK, INFO_REQUEST or
+/// banners.
+const SSH_AUTH_FAILURE_DELTA: u64 = 32;
+/// Keystrokes are client messages up to this much larger than the
+/// reference, sent some time after the previous one.
+const SSH_KEYSTROKE_DELTA: u64 = 16;
+const SSH_KEYSTROKE_MIN_GAP: Duration = Duration::from_millis(10);
+const SSH_INTERACTIVE_MIN_KEYSTROKES: u64 = 8;
+/// File transfers carry this many bytes, nearly all in one direction.
+const SSH_TRANSFER_MIN_BYTES: u64 = 65536;
+const SSH_TRANSFER_MIN_PERCENT: u64 = 90;
+/// Forwarded connections carry this many bytes in each direction.
+const SSH_FORWARDING_MIN_BYTES: u64 = 8192;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumStringU8)]
+#[repr(u8)]
+pub enum SshAuthOutcome {
+    #[default]
+    Unknown = 0,
+    Success = 1,
+    Failure = 2,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumStringU8)]
+#[repr(u8)]
+pub enum SshSessionType {
+    #[default]
+    Unknown = 0,
+    Interactive = 1,
+    FileTransfer = 2,
+    PortForwarding = 3,
+}
+
+/// Messages and bytes sent in one direction after authentication.
+#[derive(Debug, Default)]
+pub struct SshSessionCounters {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SshSession {
+    /// Size of the SERVICE_ACCEPT message.
+    reference: Option<u64>,
+    /// Client messages since NEWKEYS, the first one being SERVICE_REQUEST.
+    client_messages: u64,
+    /// Whether an authentication request is waiting for its reply.
+    auth_pending: bool,
+    last_client_ts: Option<Duration>,
+
+    pub auth_outcome: SshAuthOutcome,
+    pub auth_failures: u32,
+    pub session_type: SshSessionType,
+    pub keystrokes: u64,
+    pub client: SshSessionCounters,
+    pub server: SshSessionCounters,
+    /// Set once the flow is over, the results are final.
+    pub done: bool,
+}
+
+impl SshSession {
+    /// Account for an encrypted message of `size` bytes, record header and
+    /// MAC included.
+    pub fn update(&mut self, size: u64, to_server: bool, ts: Duration) {
+        if self.done {
+            return;
+        }
+        if to_server {
+            self.client_messages += 1;
+            if self.auth_outcome == SshAuthOutcome::Success {
+                self.client.messages += 1;
+                self.client.bytes += size;
+                if self.is_keystroke(size, ts) {
+                    self.keystrokes += 1;
+                }
+                self.session_type = self.classify();
+            } else if self.client_messages > 1 {
+                self.auth_pending = true;
+            }
+            self.last_client_ts = Some(ts);
+            return;
+        }
+        if self.auth_outcome == SshAuthOutcome::Success {
+            self.server.messages += 1;
+            self.server.bytes += size;
+            self.session_type = self.classify();
+            return;
+        }
+        if self.client_messages < 2 {
+            // SERVICE_ACCEPT, or the EXT_INFO sent before it
+            self.reference = Some(self.reference.map_or(size, |r| r.min(size)));
+            return;
+        }
+        let reference = match self.reference {
+            Some(r) => r,
+            None => {
+                return;
+            }
+        };
+        if !self.auth_pending {
+            return;
+        }
+        if size + SSH_AUTH_SUCCESS_DELTA <= reference {
+            self.auth_outcome = SshAuthOutcome::Success;
+            self.auth_pending = false;
+        } else if size <= reference + SSH_AUTH_FAILURE_DELTA {
+            self.auth_failures += 1;
+            self.auth_pending = false;
+        }
+    }
+
+    /// Called at the end of the flow: without a successful
+    /// authentication, failed attempts mean that the client gave up.
+    pub fn finalize(&mut self) {
+        if self.auth_outcome == SshAuthOutcome::Unknown && self.auth_failures > 0 {
+            self.auth_outcome = SshAuthOutcome::Failure;
+        }
+        self.done = true;
+    }
+
+    fn is_keystroke(&self, size: u64, ts: Duration) -> bool {
+        let small = match self.reference {
+            Some(r) => size <= r + SSH_KEYSTROKE_DELTA,
+            None => false,
+        };
+        let gap = match self.last_client_ts {
+            Some(last) => ts.saturating_sub(last),
+            None => Duration::MAX,
+        };
+        small && gap >= SSH_KEYSTROKE_MIN_GAP
+    }
+
+    fn classify(&self) -> SshSessionType {
+        let total = self.client.bytes + self.server.bytes;
+        let max = std::cmp::max(self.client.bytes, self.server.bytes);
+        if max >= SSH_TRANSFER_MIN_BYTES && max * 100 >= total * SSH_TRANSFER_MIN_PERCENT {
+            SshSessionType::FileTransfer
+        } else if self.keystrokes >= SSH_INTERACTIVE_MIN_KEYSTROKES
+            && self.keystrokes * 2 >= self.client.messages
+        {
+            SshSessionType::Interactive
+        } else if self.client.bytes >= SSH_FORWARDING_MIN_BYTES
+            && self.server.bytes >= SSH_FORWARDING_MIN_BYTES
+        {
+            SshSessionType::PortForwarding
+        } else {
+            SshSessionType::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Authentication with chacha20-poly1305: SERVICE_ACCEPT is 48 bytes,
+    /// USERAUTH_FAILURE 56 and USERAUTH_SUCCESS 32.
+    fn authenticate(session: &mut SshSession, failures: u32, success: bool) {
+        session.update(68, true, ms(0));
+        session.update(200, false, ms(1));
+        session.update(48, false, ms(2));
+        for _ in 0..failures {
+            session.update(84, true, ms(3));
+            session.update(56, false, ms(4));
+        }
+        if success {
+            // publickey query, USERAUTH_PK_OK and signed request
+            session.update(116, true, ms(5));
+            session.update(100, false, ms(6));
+            session.update(420, true, ms(7));
+            session.update(32, false, ms(8));
+        }
+    }
+
+    #[test]
+    fn test_auth_success() {
+        let mut session = SshSession::default();
+        authenticate(&mut session, 1, true);
+        assert_eq!(session.auth_outcome, SshAuthOutcome::Success);
+        assert_eq!(session.auth_failures, 1);
+        // not counted as a failure once authenticated
+        session.update(56, false, ms(9));
+        session.finalize();
+        assert_eq!(session.auth_outcome, SshAuthOutcome::Success);
+        assert_eq!(session.auth_failures, 1);
+        assert_eq!(session.server.messages, 1);
+    }
+
+    #[test]
+    fn test_auth_failure() {
+        let mut session = SshSession::default();
+        authenticate(&mut session, 4, false);
+        assert_eq!(session.auth_outcome, SshAuthOutcome::Unknown);
+        assert_eq!(session.auth_failures, 4);
+        session.finalize();
+        assert_eq!(session.auth_outcome, SshAuthOutcome::Failure);
+        assert!(session.done);
+    }
+
+    #[test]
+    fn test_session_type() {
+        let mut session = SshSession::default();
+        authenticate(&mut session, 0, true);
+        for i in 0..20 {
+            // keystroke and its echo
+            session.update(36, true, ms(1000 + i * 150));
+            session.update(36, false, ms(1001 + i * 150));
+        }
+        assert_eq!(session.keystrokes, 20);
+        assert_eq!(session.session_type, SshSessionType::Interactive);
+
+        let mut session = SshSession::default();
+        authenticate(&mut session, 0, true);
+        for i in 0..100 {
+            session.update(32768, false, ms(1000 + i));
+            if i % 10 == 0 {
+                session.update(52, true, ms(1000 + i));
+            }
+        }
+        assert_eq!(session.session_type, SshSessionType::FileTransfer);
+
+        let mut session = SshSession::default();
+        authenticate(&mut session, 0, true);
+        for i in 0..10 {
+            session.update(1500, true, ms(1000 + i));
+            session.update(4000, false, ms(1000 + i));
+        }
+        assert_eq!(session.session_type, SshSessionType::PortForwarding);
+    }
+}
@@ -16,20 +16,28 @@
  */
 
 use super::parser;
+use super::session::SshSession;
 use crate::applayer::*;
+use crate::conf::conf_get_bool;
 use crate::core::*;
 use nom7::Err;
 use std::ffi::CString;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use crate::frames::Frame;
 
-static mut ALPROTO_SSH: AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_SSH: AppProto = ALPROTO_UNKNOWN;
 static HASSH_ENABLED: AtomicBool = AtomicBool::new(false);
+static ENCRYPTED_ANALYSIS_ENABLED: AtomicBool = AtomicBool::new(false);
 
 fn hassh_is_enabled() -> bool {
     HASSH_ENABLED.load(Ordering::Relaxed)
 }
 
+fn encrypted_analysis_is_enabled() -> bool {
+    ENCRYPTED_ANALYSIS_ENABLED.load(Ordering::Relaxed)
+}
+
 #[derive(AppLayerFrameType)]
 pub enum SshFrameType {
     RecordHdr,
@@ -43,6 +51,10 @@ pub enum SSHEvent {
     LongBanner,
     InvalidRecord,
     LongKexRecord,
+    WeakKexAlgorithm,
+    WeakHostKeyAlgorithm,
+    WeakCipher,
+    WeakMac,
 }
 
 #[repr(u8)]
@@ -57,6 +69,34 @@ pub enum SSHConnectionState {
 pub const SSH_MAX_BANNER_LEN: usize = 256;
 const SSH_RECORD_HEADER_LEN: usize = 6;
 const SSH_MAX_REASSEMBLED_RECORD_LEN: usize = 65535;
+// maximum packet size accepted by OpenSSH
+const SSH_MAX_ENCRYPTED_RECORD_LEN: u32 = 256 * 1024;
+
+const SSH_WEAK_KEX_ALGORITHMS: &[&[u8]] = &[
+    b"diffie-hellman-group1-sha1",
+    b"diffie-hellman-group-exchange-sha1",
+    b"rsa1024-sha1",
+];
+const SSH_WEAK_HOST_KEY_ALGORITHMS: &[&[u8]] = &[b"ssh-dss"];
+const SSH_WEAK_CIPHERS: &[&[u8]] = &[
+    b"none",
+    b"des-cbc",
+    b"3des-cbc",
+    b"blowfish-cbc",
+    b"cast128-cbc",
+    b"arcfour",
+    b"arcfour128",
+    b"arcfour256",
+];
+const SSH_WEAK_MACS: &[&[u8]] = &[
+    b"none",
+    b"hmac-md5",
+    b"hmac-md5-96",
+    b"hmac-md5-etm@openssh.com",
+    b"hmac-md5-96-etm@openssh.com",
+    b"hmac-sha1-96",
+    b"hmac-sha1-96-etm@openssh.com",
+];
 
 pub struct SshHeader {
     record_left: u32,
@@ -68,6 +108,8 @@ pub struct SshHeader {
 
     pub hassh: Vec<u8>,
     pub hassh_string: Vec<u8>,
+
+    pub kexinit: Option<parser::SshKexInit>,
 }
 
 impl Default for SshHeader {
@@ -88,6 +130,8 @@ impl SshHeader {
 
             hassh: Vec::new(),
             hassh_string: Vec::new(),
+
+            kexinit: None,
         }
     }
 }
@@ -97,9 +141,105 @@ pub struct SSHTransaction {
     pub srv_hdr: SshHeader,
     pub cli_hdr: SshHeader,
 
+    pub negotiated: Option<parser::SshNegotiated>,
+    pub hostkey_type: Vec<u8>,
+    pub hostkey_fingerprint: Vec<u8>,
+
+    /// Analysis of the encrypted part of the session, when enabled.
+    pub session: Option<SshSession>,
+
     tx_data: AppLayerTxData,
 }
 
+impl SSHTransaction {
+    fn get_hdr(&mut self, resp: bool) -> &mut SshHeader {
+        if !resp {
+            &mut self.cli_hdr
+        } else {
+            &mut self.srv_hdr
+        }
+    }
+
+    fn set_event(&mut self, event: SSHEvent) {
+        self.tx_data.set_event(event as u8);
+    }
+
+    /// Handles a complete key exchange message, without its record header.
+    fn parse_kex_message(&mut self, msg: parser::MessageCode, input: &[u8], resp: bool) {
+        match msg {
+            parser::MessageCode::Kexinit => {
+                let hdr = self.get_hdr(resp);
+                if hdr.kexinit.is_some() {
+                    return;
+                }
+                if let Ok((_, key_exchange)) = parser::ssh_parse_key_exchange(input) {
+                    if hassh_is_enabled() {
+                        key_exchange.generate_hassh(&mut hdr.hassh_string, &mut hdr.hassh, &resp);
+                    }
+                    hdr.kexinit = Some((&key_exchange).into());
+                    self.negotiate();
+                }
+            }
+            parser::MessageCode::KexdhReply | parser::MessageCode::KexDhGexReply if resp => {
+                if !self.hostkey_type.is_empty() {
+                    return;
+                }
+                // the message code of the reply depends on the key exchange method
+                let reply = match &self.negotiated {
+                    Some(n) if n.is_group_exchange() => parser::MessageCode::KexDhGexReply,
+                    Some(_) => parser::MessageCode::KexdhReply,
+                    None => {
+                        return;
+                    }
+                };
+                if msg != reply {
+                    return;
+                }
+                if let Ok((_, hostkey)) = parser::ssh_parse_kex_reply(input) {
+                    self.hostkey_type = hostkey.key_type.to_vec();
+                    self.hostkey_fingerprint = hostkey.fingerprint();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn negotiate(&mut self) {
+        let negotiated = match (&self.cli_hdr.kexinit, &self.srv_hdr.kexinit) {
+            (Some(client), Some(server)) => parser::ssh_negotiate(client, server),
+            _ => {
+                return;
+            }
+        };
+        if SSH_WEAK_KEX_ALGORITHMS.contains(&negotiated.kex.as_slice()) {
+            self.set_event(SSHEvent::WeakKexAlgorithm);
+        }
+        if SSH_WEAK_HOST_KEY_ALGORITHMS.contains(&negotiated.server_host_key.as_slice()) {
+            self.set_event(SSHEvent::WeakHostKeyAlgorithm);
+        }
+        if SSH_WEAK_CIPHERS.contains(&negotiated.encr_client_to_server.as_slice())
+            || SSH_WEAK_CIPHERS.contains(&negotiated.encr_server_to_client.as_slice())
+        {
+            self.set_event(SSHEvent::WeakCipher);
+        }
+        if SSH_WEAK_MACS.contains(&negotiated.mac_client_to_server.as_slice())
+            || SSH_WEAK_MACS.contains(&negotiated.mac_server_to_client.as_slice())
+        {
+            self.set_event(SSHEvent::WeakMac);
+        }
+        self.negotiated = Some(negotiated);
+    }
+}
+
+fn is_kex_message(msg: parser::MessageCode) -> bool {
+    matches!(
+        msg,
+        parser::MessageCode::Kexinit
+            | parser::MessageCode::KexdhReply
+            | parser::MessageCode::KexDhGexReply
+    )
+}
+
 #[derive(Default)]
 pub struct SSHState {
     state_data: AppLayerStateData,
@@ -112,20 +252,16 @@ impl SSHState {
     }
 
     fn set_event(&mut self, event: SSHEvent) {
-        self.transaction.tx_data.set_event(event as u8);
+        self.transaction.set_event(event);
     }
 
     fn parse_record(
         &mut self, mut input: &[u8], resp: bool, pstate: *mut std::os::raw::c_void,
         flow: *const Flow, stream_slice: &StreamSlice,
     ) -> AppLayerResult {
-        let (hdr, ohdr) = if !resp {
-            (&mut self.transaction.cli_hdr, &self.transaction.srv_hdr)
-        } else {
-            (&mut self.transaction.srv_hdr, &self.transaction.cli_hdr)
-        };
         let il = input.len();
         //first skip record left bytes
+        let hdr = self.transaction.get_hdr(resp);
         if hdr.record_left > 0 {
             //should we check for overflow ?
             let ilen = input.len() as u32;
@@ -134,24 +270,14 @@ impl SSHState {
                 return AppLayerResult::ok();
             } else {
                 let start = hdr.record_left as usize;
-                match hdr.record_left_msg {
-                    // parse reassembled tcp segments
-                    parser::MessageCode::Kexinit if hassh_is_enabled() => {
-                        if let Ok((_rem, key_exchange)) =
-                            parser::ssh_parse_key_exchange(&input[..start])
-                        {
-                            key_exchange.generate_hassh(
-                                &mut hdr.hassh_string,
-                                &mut hdr.hassh,
-                                &resp,
-                            );
-                        }
-                        hdr.record_left_msg = parser::MessageCode::Undefined(0);
-                    }
-                    _ => {}
+                let msg = hdr.record_left_msg;
+                hdr.record_left_msg = parser::MessageCode::Undefined(0);
+                hdr.record_left = 0;
+                // parse reassembled tcp segments
+                if is_kex_message(msg) {
+                    self.transaction.parse_kex_message(msg, &input[..start], resp);
                 }
                 input = &input[start..];
-                hdr.record_left = 0;
             }
         }
         //parse records out of input
@@ -184,16 +310,23 @@ impl SSHState {
                     );
                     SCLogDebug!("SSH valid record {}", head);
                     match head.msg_code {
-                        parser::MessageCode::Kexinit if hassh_is_enabled() => {
+                        msg if is_kex_message(msg) => {
                             //let endkex = SSH_RECORD_HEADER_LEN + head.pkt_len - 2;
                             let endkex = input.len() - rem.len();
-                            if let Ok((_, key_exchange)) = parser::ssh_parse_key_exchange(&input[SSH_RECORD_HEADER_LEN..endkex]) {
-                                key_exchange.generate_hassh(&mut hdr.hassh_string, &mut hdr.hassh, &resp);
-                            }
+                            self.transaction.parse_kex_message(msg, &input[SSH_RECORD_HEADER_LEN..endkex], resp);
                         }
                         parser::MessageCode::NewKeys => {
-                            hdr.flags = SSHConnectionState::SshStateFinished;
-                            if ohdr.flags >= SSHConnectionState::SshStateFinished {
+                            self.transaction.get_hdr(resp).flags = SSHConnectionState::SshStateFinished;
+                            if encrypted_analysis_is_enabled() {
+                                // the rest of the input is encrypted
+                                self.transaction.session.get_or_insert_with(SshSession::default);
+                                let mut r = self.parse_encrypted(rem, resp, pstate, flow);
+                                if r.is_incomplete() {
+                                    r.consumed += (il - rem.len()) as u32;
+                                }
+                                return r;
+                            }
+                            if self.transaction.get_hdr(!resp).flags >= SSHConnectionState::SshStateFinished {
                                 unsafe {
                                     AppLayerParserStateSetFlag(
                                         pstate,
@@ -240,18 +373,22 @@ impl SSHState {
                             );
                             SCLogDebug!("SSH valid record header {}", head);
                             let remlen = rem.len() as u32;
+                            let hdr = self.transaction.get_hdr(resp);
                             hdr.record_left = head.pkt_len - 2 - remlen;
                             //header with rem as incomplete data
                             match head.msg_code { 
                                 parser::MessageCode::NewKeys => {
                                     hdr.flags = SSHConnectionState::SshStateFinished;
+                                    if encrypted_analysis_is_enabled() {
+                                        self.transaction.session.get_or_insert_with(SshSession::default);
+                                    }
                                 }
-                                parser::MessageCode::Kexinit if hassh_is_enabled() => {
+                                msg if is_kex_message(msg) => {
                                     // check if buffer is bigger than maximum reassembled packet size
                                     hdr.record_left = head.pkt_len - 2;
                                     if hdr.record_left < SSH_MAX_REASSEMBLED_RECORD_LEN as u32 {
                                         // saving type of incomplete kex message
-                                        hdr.record_left_msg = parser::MessageCode::Kexinit;
+                                        hdr.record_left_msg = msg;
                                         return AppLayerResult::incomplete(
                                             (il - rem.len()) as u32,
                                             head.pkt_len - 2
@@ -292,6 +429,84 @@ impl SSHState {
         return AppLayerResult::ok();
     }
 
+    /// Keeps track of the sizes of the encrypted records. Their boundaries
+    /// are only known when the packet length is not encrypted, otherwise
+    /// each chunk of data is taken as one message.
+    fn parse_encrypted(
+        &mut self, mut input: &[u8], resp: bool, pstate: *mut std::os::raw::c_void,
+        flow: *const Flow,
+    ) -> AppLayerResult {
+        let ts = match unsafe { (flow as *mut Flow).as_mut() } {
+            Some(flow) => flow.get_last_time(),
+            None => Duration::ZERO,
+        };
+        let mac_len = self
+            .transaction
+            .negotiated
+            .as_ref()
+            .and_then(|n| n.clear_length_mac_len(!resp));
+        let SSHTransaction { cli_hdr, srv_hdr, session, .. } = &mut self.transaction;
+        let (hdr, ohdr) = if !resp { (cli_hdr, srv_hdr) } else { (srv_hdr, cli_hdr) };
+        let session = match session {
+            Some(s) => s,
+            None => {
+                return AppLayerResult::ok();
+            }
+        };
+        if ohdr.flags >= SSHConnectionState::SshStateFinished {
+            // keep parsing, but stop inspecting the encrypted payload
+            unsafe {
+                AppLayerParserStateSetFlag(pstate, APP_LAYER_PARSER_NO_INSPECTION_PAYLOAD);
+            }
+        }
+        let il = input.len();
+        //first skip record left bytes
+        if hdr.record_left > 0 {
+            let ilen = input.len() as u32;
+            if hdr.record_left >= ilen {
+                hdr.record_left -= ilen;
+                return AppLayerResult::ok();
+            }
+            input = &input[hdr.record_left as usize..];
+            hdr.record_left = 0;
+        }
+        let mac_len = match mac_len {
+            Some(l) => l,
+            None => {
+                if !input.is_empty() {
+                    session.update(input.len() as u64, !resp, ts);
+                }
+                return AppLayerResult::ok();
+            }
+        };
+        while !input.is_empty() {
+            match parser::ssh_parse_record_header(input) {
+                Ok((_, head)) if head.pkt_len <= SSH_MAX_ENCRYPTED_RECORD_LEN => {
+                    // only the packet length is readable
+                    let len = 4 + head.pkt_len + mac_len;
+                    session.update(len as u64, !resp, ts);
+                    if len as usize > input.len() {
+                        hdr.record_left = len - input.len() as u32;
+                        return AppLayerResult::ok();
+                    }
+                    input = &input[len as usize..];
+                }
+                Err(Err::Incomplete(_)) => {
+                    return AppLayerResult::incomplete(
+                        (il - input.len()) as u32,
+                        SSH_RECORD_HEADER_LEN as u32,
+                    );
+                }
+                _ => {
+                    SCLogDebug!("SSH invalid encrypted record header");
+                    self.set_event(SSHEvent::InvalidRecord);
+                    return AppLayerResult::err();
+                }
+            }
+        }
+        return AppLayerResult::ok();
+    }
+
     fn parse_banner(
         &mut self, input: &[u8], resp: bool, pstate: *mut std::os::raw::c_void,
         flow: *const Flow, stream_slice: &StreamSlice,
@@ -415,11 +630,21 @@ pub unsafe extern "C" fn rs_ssh_parse_request(
     let state = &mut cast_pointer!(state, SSHState);
     let buf = stream_slice.as_slice();
     let hdr = &mut state.transaction.cli_hdr;
-    if hdr.flags < SSHConnectionState::SshStateBannerDone {
-        return state.parse_banner(buf, false, pstate, flow, &stream_slice);
+    let r = if hdr.flags < SSHConnectionState::SshStateBannerDone {
+        state.parse_banner(buf, false, pstate, flow, &stream_slice)
+    } else if hdr.flags >= SSHConnectionState::SshStateFinished
+        && state.transaction.session.is_some()
+    {
+        state.parse_encrypted(buf, false, pstate, flow)
     } else {
-        return state.parse_record(buf, false, pstate, flow, &stream_slice);
+        state.parse_record(buf, false, pstate, flow, &stream_slice)
+    };
+    if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TS) > 0 {
+        if let Some(session) = &mut state.transaction.session {
+            session.finalize();
+        }
     }
+    return r;
 }
 
 #[no_mangle]
@@ -431,11 +656,21 @@ pub unsafe extern "C" fn rs_ssh_parse_response(
     let state = &mut cast_pointer!(state, SSHState);
     let buf = stream_slice.as_slice();
     let hdr = &mut state.transaction.srv_hdr;
-    if hdr.flags < SSHConnectionState::SshStateBannerDone {
-        return state.parse_banner(buf, true, pstate, flow, &stream_slice);
+    let r = if hdr.flags < SSHConnectionState::SshStateBannerDone {
+        state.parse_banner(buf, true, pstate, flow, &stream_slice)
+    } else if hdr.flags >= SSHConnectionState::SshStateFinished
+        && state.transaction.session.is_some()
+    {
+        state.parse_encrypted(buf, true, pstate, flow)
     } else {
-        return state.parse_record(buf, true, pstate, flow, &stream_slice);
+        state.parse_record(buf, true, pstate, flow, &stream_slice)
+    };
+    if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF_TC) > 0 {
+        if let Some(session) = &mut state.transaction.session {
+            session.finalize();
+        }
     }
+    return r;
 }
 
 #[no_mangle]
@@ -472,7 +707,11 @@ pub unsafe extern "C" fn rs_ssh_tx_get_alstate_progress(
     if tx.cli_hdr.flags >= SSHConnectionState::SshStateFinished
         && tx.srv_hdr.flags >= SSHConnectionState::SshStateFinished
     {
-        return SSHConnectionState::SshStateFinished as i32;
+        // the encrypted session is analysed until the end of the flow
+        if tx.session.as_ref().map_or(true, |s| s.done) {
+            return SSHConnectionState::SshStateFinished as i32;
+        }
+        return SSHConnectionState::SshStateBannerDone as i32;
     }
 
     if direction == Direction::ToServer.into() {
@@ -533,6 +772,9 @@ pub unsafe extern "C" fn rs_ssh_register_parser() {
             let _ = AppLayerRegisterParser(&parser, alproto);
         }
         AppLayerParserRegisterLogger(IPPROTO_TCP, ALPROTO_SSH);
+        if conf_get_bool("app-layer.protocols.ssh.encrypted-analysis") {
+            rs_ssh_enable_encrypted_analysis();
+        }
         SCLogDebug!("Rust ssh parser registered.");
     } else {
         SCLogNotice!("Protocol detector and parser disabled for SSH.");
@@ -549,19 +791,21 @@ pub extern "C" fn rs_ssh_hassh_is_enabled() -> bool {
     hassh_is_enabled()
 }
 
+#[no_mangle]
+pub extern "C" fn rs_ssh_enable_encrypted_analysis() {
+    ENCRYPTED_ANALYSIS_ENABLED.store(true, Ordering::Relaxed)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rs_ssh_tx_get_log_condition( tx: *mut std::os::raw::c_void) -> bool {
     let tx = cast_pointer!(tx, SSHTransaction);
-    
-    if rs_ssh_hassh_is_enabled() {
-        if  tx.cli_hdr.flags == SSHConnectionState::SshStateFinished &&
-            tx.srv_hdr.flags == SSHConnectionState::SshStateFinished {
-            return true; 
-        }
-    }
-    else if  tx.cli_hdr.flags == SSHConnectionState::SshStateBannerDone && 
-        tx.srv_hdr.flags == SSHConnectionState::SshStateBannerDone {
-        return true;
+
+    // log once the key exchange is done, so that the negotiated
+    // algorithms and the host key are known, or at the end of the flow
+    // when the encrypted session is analysed
+    if  tx.cli_hdr.flags == SSHConnectionState::SshStateFinished &&
+        tx.srv_hdr.flags == SSHConnectionState::SshStateFinished {
+        return tx.session.as_ref().map_or(true, |s| s.done);
     }
     return false;
 }
@@ -0,0 +1,230 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::parser::TelnetOptionCode;
+use super::telnet::{TelnetTransaction, ALPROTO_TELNET};
+use crate::detect::uint::{
+    detect_match_uint, detect_parse_uint_enum, rs_detect_u8_free, DetectUintData,
+};
+use crate::detect::{
+    DetectBufferSetActiveList, DetectHelperBufferMpmRegister, DetectHelperBufferRegister,
+    DetectHelperGetData, DetectHelperKeywordRegister, DetectSignatureSetAppProto, SCSigTableElmt,
+    SigMatchAppendSMToList, SIGMATCH_INFO_STICKY_BUFFER, SIGMATCH_NOOPT,
+};
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+static mut G_TELNET_USERNAME_BUFFER_ID: c_int = 0;
+static mut G_TELNET_COMMAND_BUFFER_ID: c_int = 0;
+static mut G_TELNET_OPTION_KW_ID: c_int = 0;
+static mut G_TELNET_OPTION_BUFFER_ID: c_int = 0;
+
+unsafe extern "C" fn telnet_username_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_TELNET) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_TELNET_USERNAME_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn telnet_username_get_data(
+    tx: *const c_void, _flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, TelnetTransaction);
+    if let Some(ref username) = tx.username {
+        *buffer = username.as_ptr();
+        *buffer_len = username.len() as u32;
+        return true;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn telnet_username_get(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        telnet_username_get_data,
+    );
+}
+
+unsafe extern "C" fn telnet_command_setup(
+    de: *mut c_void, s: *mut c_void, _raw: *const std::os::raw::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_TELNET) != 0 {
+        return -1;
+    }
+    if DetectBufferSetActiveList(de, s, G_TELNET_COMMAND_BUFFER_ID) < 0 {
+        return -1;
+    }
+    return 0;
+}
+
+unsafe extern "C" fn telnet_command_get_data(
+    tx: *const c_void, _flags: u8, buffer: *mut *const u8, buffer_len: *mut u32,
+) -> bool {
+    let tx = cast_pointer!(tx, TelnetTransaction);
+    if let Some(ref command) = tx.command {
+        *buffer = command.as_ptr();
+        *buffer_len = command.len() as u32;
+        return true;
+    }
+    *buffer = ptr::null();
+    *buffer_len = 0;
+    return false;
+}
+
+unsafe extern "C" fn telnet_command_get(
+    de: *mut c_void, transforms: *const c_void, flow: *const c_void, flow_flags: u8,
+    tx: *const c_void, list_id: c_int,
+) -> *mut c_void {
+    return DetectHelperGetData(
+        de,
+        transforms,
+        flow,
+        flow_flags,
+        tx,
+        list_id,
+        telnet_command_get_data,
+    );
+}
+
+unsafe extern "C" fn telnet_parse_option(
+    ustr: *const std::os::raw::c_char,
+) -> *mut DetectUintData<u8> {
+    let ft_name: &CStr = CStr::from_ptr(ustr); //unsafe
+    if let Ok(s) = ft_name.to_str() {
+        if let Some(ctx) = detect_parse_uint_enum::<u8, TelnetOptionCode>(s) {
+            let boxed = Box::new(ctx);
+            return Box::into_raw(boxed) as *mut _;
+        }
+    }
+    return std::ptr::null_mut();
+}
+
+unsafe extern "C" fn telnet_option_setup(
+    de: *mut c_void, s: *mut c_void, raw: *const libc::c_char,
+) -> c_int {
+    if DetectSignatureSetAppProto(s, ALPROTO_TELNET) != 0 {
+        return -1;
+    }
+    let ctx = telnet_parse_option(raw) as *mut c_void;
+    if ctx.is_null() {
+        return -1;
+    }
+    if SigMatchAppendSMToList(de, s, G_TELNET_OPTION_KW_ID, ctx, G_TELNET_OPTION_BUFFER_ID)
+        .is_null()
+    {
+        telnet_option_free(std::ptr::null_mut(), ctx);
+        return -1;
+    }
+    return 0;
+}
+
+fn telnet_option_match_aux(tx: &TelnetTransaction, ctx: &DetectUintData<u8>) -> c_int {
+    for n in tx.negotiations.iter() {
+        if detect_match_uint(ctx, n.option) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+unsafe extern "C" fn telnet_option_match(
+    _de: *mut c_void, _f: *mut c_void, _flags: u8, _state: *mut c_void, tx: *mut c_void,
+    _sig: *const c_void, ctx: *const c_void,
+) -> c_int {
+    let tx = cast_pointer!(tx, TelnetTransaction);
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    return telnet_option_match_aux(tx, ctx);
+}
+
+unsafe extern "C" fn telnet_option_free(_de: *mut c_void, ctx: *mut c_void) {
+    // Just unbox...
+    let ctx = cast_pointer!(ctx, DetectUintData<u8>);
+    rs_detect_u8_free(ctx);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ScDetectTelnetRegister() {
+    let kw = SCSigTableElmt {
+        name: b"telnet.username\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on the Telnet login username\0".as_ptr()
+            as *const libc::c_char,
+        url: b"/rules/telnet-keywords.html#telnet-username\0".as_ptr() as *const libc::c_char,
+        Setup: telnet_username_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_telnet_username_kw_id = DetectHelperKeywordRegister(&kw);
+    G_TELNET_USERNAME_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"telnet.username\0".as_ptr() as *const libc::c_char,
+        b"Telnet login username\0".as_ptr() as *const libc::c_char,
+        ALPROTO_TELNET,
+        false,
+        true,
+        telnet_username_get,
+    );
+    let kw = SCSigTableElmt {
+        name: b"telnet.command\0".as_ptr() as *const libc::c_char,
+        desc: b"sticky buffer to match on a Telnet command line\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/telnet-keywords.html#telnet-command\0".as_ptr() as *const libc::c_char,
+        Setup: telnet_command_setup,
+        flags: SIGMATCH_NOOPT | SIGMATCH_INFO_STICKY_BUFFER,
+        AppLayerTxMatch: None,
+        Free: None,
+    };
+    let _g_telnet_command_kw_id = DetectHelperKeywordRegister(&kw);
+    G_TELNET_COMMAND_BUFFER_ID = DetectHelperBufferMpmRegister(
+        b"telnet.command\0".as_ptr() as *const libc::c_char,
+        b"Telnet command line\0".as_ptr() as *const libc::c_char,
+        ALPROTO_TELNET,
+        false,
+        true,
+        telnet_command_get,
+    );
+    let kw = SCSigTableElmt {
+        name: b"telnet.option\0".as_ptr() as *const libc::c_char,
+        desc: b"match on negotiated Telnet options\0".as_ptr() as *const libc::c_char,
+        url: b"/rules/telnet-keywords.html#telnet-option\0".as_ptr() as *const libc::c_char,
+        AppLayerTxMatch: Some(telnet_option_match),
+        Setup: telnet_option_setup,
+        Free: Some(telnet_option_free),
+        flags: 0,
+    };
+    G_TELNET_OPTION_KW_ID = DetectHelperKeywordRegister(&kw);
+    G_TELNET_OPTION_BUFFER_ID = DetectHelperBufferRegister(
+        b"telnet.option\0".as_ptr() as *const libc::c_char,
+        ALPROTO_TELNET,
+        true,
+        true,
+    );
+}
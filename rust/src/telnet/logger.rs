@@ -0,0 +1,105 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use super::parser::{
+    TelnetOptionCode, TELNET_CMD_DO, TELNET_CMD_DONT, TELNET_CMD_WILL, TELNET_CMD_WONT,
+};
+use super::telnet::TelnetTransaction;
+use crate::detect::EnumString;
+use crate::jsonbuilder::{JsonBuilder, JsonError};
+use std;
+
+fn command_string(cmd: u8) -> String {
+    match cmd {
+        TELNET_CMD_WILL => "will".to_string(),
+        TELNET_CMD_WONT => "wont".to_string(),
+        TELNET_CMD_DO => "do".to_string(),
+        TELNET_CMD_DONT => "dont".to_string(),
+        _ => format!("unknown-{}", cmd),
+    }
+}
+
+fn option_string(option: u8) -> String {
+    if let Some(o) = TelnetOptionCode::from_u(option) {
+        o.to_str().to_string()
+    } else {
+        format!("unknown-{}", option)
+    }
+}
+
+fn log_telnet(tx: &TelnetTransaction, js: &mut JsonBuilder) -> Result<(), JsonError> {
+    js.open_object("telnet")?;
+    if !tx.negotiations.is_empty() {
+        js.open_array("options")?;
+        for n in &tx.negotiations {
+            js.start_object()?;
+            js.set_string(
+                "direction",
+                if n.direction.is_to_server() {
+                    "toserver"
+                } else {
+                    "toclient"
+                },
+            )?;
+            js.set_string("command", &command_string(n.command))?;
+            js.set_string("option", &option_string(n.option))?;
+            js.close()?;
+        }
+        js.close()?;
+    }
+    if let Some(ref ttype) = tx.terminal_type {
+        js.set_string_from_bytes("terminal_type", ttype)?;
+    }
+    if let Some((width, height)) = tx.window_size {
+        js.open_object("window_size")?;
+        js.set_uint("width", width as u64)?;
+        js.set_uint("height", height as u64)?;
+        js.close()?;
+    }
+    if !tx.environ.is_empty() {
+        js.open_array("environ")?;
+        for var in &tx.environ {
+            js.start_object()?;
+            js.set_string_from_bytes("name", &var.name)?;
+            if let Some(ref value) = var.value {
+                js.set_string_from_bytes("value", value)?;
+            }
+            js.set_bool("user", var.user)?;
+            js.close()?;
+        }
+        js.close()?;
+    }
+    if let Some(ref username) = tx.username {
+        js.set_string_from_bytes("username", username)?;
+    }
+    if let Some(success) = tx.auth_success {
+        js.set_string("auth", if success { "success" } else { "failure" })?;
+    }
+    if let Some(ref command) = tx.command {
+        js.set_string_from_bytes("command", command)?;
+    }
+    js.close()?;
+    Ok(())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_telnet_logger_log(
+    tx: *mut std::os::raw::c_void, js: &mut JsonBuilder,
+) -> bool {
+    let tx = cast_pointer!(tx, TelnetTransaction);
+    log_telnet(tx, js).is_ok()
+}
@@ -17,5 +17,7 @@
 
 //! Telnet application layer and parser module.
 
-pub mod telnet;
+pub mod detect;
+pub mod logger;
 mod parser;
+pub mod telnet;
@@ -18,10 +18,50 @@
 use crate::common::nom7::take_until_and_consume;
 use nom7::combinator::peek;
 use nom7::bytes::complete::take;
-use nom7::{IResult};
+use nom7::{Err, IResult, Needed};
 use nom7::number::streaming::le_u8;
 use nom7::bytes::streaming::tag;
-use nom7::bytes::streaming::{take_until};
+use suricata_derive::EnumStringU8;
+
+pub const TELNET_CMD_SE: u8 = 240;
+pub const TELNET_CMD_SB: u8 = 250;
+pub const TELNET_CMD_WILL: u8 = 251;
+pub const TELNET_CMD_WONT: u8 = 252;
+pub const TELNET_CMD_DO: u8 = 253;
+pub const TELNET_CMD_DONT: u8 = 254;
+pub const TELNET_CMD_IAC: u8 = 255;
+
+/// Telnet options as registered by IANA. Only the ones we decode or
+/// commonly see in the wild are listed here.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumStringU8)]
+pub enum TelnetOptionCode {
+    BinaryTransmission = 0,
+    Echo = 1,
+    SuppressGoAhead = 3,
+    Status = 5,
+    TimingMark = 6,
+    TerminalType = 24,
+    Naws = 31,
+    TerminalSpeed = 32,
+    RemoteFlowControl = 33,
+    Linemode = 34,
+    XDisplayLocation = 35,
+    Environ = 36,
+    Authentication = 37,
+    Encryption = 38,
+    NewEnviron = 39,
+}
+
+// TERMINAL-TYPE and NEW-ENVIRON subnegotiation commands
+const TELNET_SB_IS: u8 = 0;
+const TELNET_SB_INFO: u8 = 2;
+
+// NEW-ENVIRON type codes, RFC 1572
+const TELNET_ENV_VAR: u8 = 0;
+const TELNET_ENV_VALUE: u8 = 1;
+const TELNET_ENV_ESC: u8 = 2;
+const TELNET_ENV_USERVAR: u8 = 3;
 
 pub fn peek_message_is_ctl(i: &[u8]) -> IResult<&[u8], bool> {
     let (i, v) = peek(le_u8)(i)?;
@@ -33,12 +73,236 @@ pub enum TelnetMessageType<'a> {
     Data(&'a [u8]),
 }
 
+/// Decoded form of a control message as returned by `parse_ctl_message`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TelnetControl<'a> {
+    /// WILL/WONT/DO/DONT followed by the option code
+    Negotiation(u8, u8),
+    /// Subnegotiation with the option code and the raw parameters.
+    /// `terminated` is false if no `IAC SE` was seen.
+    Suboption {
+        option: u8,
+        data: &'a [u8],
+        terminated: bool,
+    },
+    Command(u8),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TelnetEnvironVar {
+    pub user: bool,
+    pub name: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+pub fn decode_ctl_message(c: &[u8]) -> Option<TelnetControl> {
+    if c.len() < 2 || c[0] != TELNET_CMD_IAC {
+        return None;
+    }
+    match c[1] {
+        TELNET_CMD_WILL..=TELNET_CMD_DONT if c.len() == 3 => {
+            Some(TelnetControl::Negotiation(c[1], c[2]))
+        }
+        TELNET_CMD_SB if c.len() >= 3 => {
+            let data = &c[3..];
+            let terminated = data.ends_with(&[TELNET_CMD_IAC, TELNET_CMD_SE]);
+            let data = if terminated {
+                &data[..data.len() - 2]
+            } else {
+                data
+            };
+            Some(TelnetControl::Suboption {
+                option: c[2],
+                data,
+                terminated,
+            })
+        }
+        cmd => Some(TelnetControl::Command(cmd)),
+    }
+}
+
+/// Get the terminal type from a TERMINAL-TYPE IS subnegotiation.
+pub fn decode_terminal_type(d: &[u8]) -> Option<&[u8]> {
+    match d.split_first() {
+        Some((&TELNET_SB_IS, ttype)) => Some(ttype),
+        _ => None,
+    }
+}
+
+/// Get width and height from a NAWS subnegotiation.
+pub fn decode_naws(d: &[u8]) -> Option<(u16, u16)> {
+    // a 255 in the values is doubled on the wire
+    let d: Vec<u8> = unescape_iac(d);
+    if d.len() != 4 {
+        return None;
+    }
+    let width = u16::from_be_bytes([d[0], d[1]]);
+    let height = u16::from_be_bytes([d[2], d[3]]);
+    Some((width, height))
+}
+
+/// Get the variables from a NEW-ENVIRON IS or INFO subnegotiation.
+pub fn decode_new_environ(d: &[u8]) -> Vec<TelnetEnvironVar> {
+    let mut vars = Vec::new();
+    let d = match d.split_first() {
+        Some((&TELNET_SB_IS, rest)) | Some((&TELNET_SB_INFO, rest)) => rest,
+        _ => return vars,
+    };
+    let mut cur: Option<TelnetEnvironVar> = None;
+    let mut in_value = false;
+    let mut i = 0;
+    while i < d.len() {
+        match d[i] {
+            TELNET_ENV_VAR | TELNET_ENV_USERVAR => {
+                if let Some(v) = cur.take() {
+                    vars.push(v);
+                }
+                cur = Some(TelnetEnvironVar {
+                    user: d[i] == TELNET_ENV_USERVAR,
+                    ..Default::default()
+                });
+                in_value = false;
+            }
+            TELNET_ENV_VALUE => {
+                if let Some(ref mut v) = cur {
+                    v.value = Some(Vec::new());
+                }
+                in_value = true;
+            }
+            b => {
+                let b = if b == TELNET_ENV_ESC && i + 1 < d.len() {
+                    i += 1;
+                    d[i]
+                } else {
+                    b
+                };
+                if let Some(ref mut v) = cur {
+                    if in_value {
+                        if let Some(ref mut value) = v.value {
+                            value.push(b);
+                        }
+                    } else {
+                        v.name.push(b);
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    if let Some(v) = cur.take() {
+        vars.push(v);
+    }
+    vars
+}
+
+/// Check a data line for an `IAC` that is neither doubled nor followed
+/// by a valid command.
+pub fn data_has_invalid_iac(d: &[u8]) -> bool {
+    let mut i = 0;
+    while i < d.len() {
+        if d[i] == TELNET_CMD_IAC {
+            match d.get(i + 1) {
+                Some(&TELNET_CMD_IAC) => {
+                    i += 1;
+                }
+                Some(&c) if c >= TELNET_CMD_SE => {}
+                _ => {
+                    return true;
+                }
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+fn unescape_iac(d: &[u8]) -> Vec<u8> {
+    let mut r = Vec::with_capacity(d.len());
+    let mut i = 0;
+    while i < d.len() {
+        r.push(d[i]);
+        if d[i] == TELNET_CMD_IAC && i + 1 < d.len() && d[i + 1] == TELNET_CMD_IAC {
+            i += 1;
+        }
+        i += 1;
+    }
+    r
+}
+
+/// Maximum number of parameter bytes accepted in a subnegotiation before
+/// it is cut off and flagged as oversized.
+pub const TELNET_MAX_SUBOPTION_LEN: usize = 1024;
+
+/// Parse a subnegotiation up to and including the terminating `IAC SE`.
+///
+/// If another command starts before the `IAC SE`, or the parameters
+/// exceed `TELNET_MAX_SUBOPTION_LEN`, the subnegotiation is returned
+/// without terminator. In the latter case the rest of it is left to
+/// `skip_suboption`.
 pub fn parse_ctl_suboption<'a>(i: &'a[u8], full: &'a[u8]) -> IResult<&'a[u8], &'a[u8]> {
     let (i, _sc) = le_u8(i)?;
-    let tag: &[u8] = b"\xff\xf0";
-    let (i, x) = take_until(tag)(i)?;
-    let o = &full[..(x.len()+3)];
-    Ok((i, o))
+    let mut idx = 0;
+    while idx < TELNET_MAX_SUBOPTION_LEN {
+        if idx >= i.len() {
+            return Err(Err::Incomplete(Needed::new(1)));
+        }
+        if i[idx] != TELNET_CMD_IAC {
+            idx += 1;
+            continue;
+        }
+        if idx + 1 >= i.len() {
+            return Err(Err::Incomplete(Needed::new(1)));
+        }
+        match i[idx + 1] {
+            // escaped 255 data byte
+            TELNET_CMD_IAC => {
+                idx += 2;
+            }
+            TELNET_CMD_SE => {
+                idx += 2;
+                break;
+            }
+            // a new command starts, so the subnegotiation was never terminated
+            _ => {
+                break;
+            }
+        }
+    }
+    let o = &full[..(idx + 3)];
+    Ok((&i[idx..], o))
+}
+
+/// Skip the rest of a subnegotiation that was cut off at
+/// `TELNET_MAX_SUBOPTION_LEN`, up to and including the `IAC SE`.
+///
+/// Returns the remaining input and whether the end of the subnegotiation
+/// was found. Another command also ends it, but is left in the remainder.
+/// A trailing `IAC` is left in the remainder as well, as it may be the
+/// start of the terminator.
+pub fn skip_suboption(i: &[u8]) -> (&[u8], bool) {
+    let mut idx = 0;
+    while idx < i.len() {
+        if i[idx] != TELNET_CMD_IAC {
+            idx += 1;
+            continue;
+        }
+        match i.get(idx + 1) {
+            None => {
+                return (&i[idx..], false);
+            }
+            // escaped 255 data byte
+            Some(&TELNET_CMD_IAC) => {
+                idx += 2;
+            }
+            Some(&TELNET_CMD_SE) => {
+                return (&i[idx + 2..], true);
+            }
+            Some(_) => {
+                return (&i[idx..], true);
+            }
+        }
+    }
+    (&i[idx..], false)
 }
 
 pub fn parse_ctl_message(oi: &[u8]) -> IResult<&[u8], &[u8]> {
@@ -48,7 +312,8 @@ pub fn parse_ctl_message(oi: &[u8]) -> IResult<&[u8], &[u8]> {
         251..=254 => take(3_usize)(oi)?,
         240..=249 => take(2_usize)(oi)?,
         250 => parse_ctl_suboption(i, oi)?,
-        _ => take(2_usize)(oi)?, // TODO maybe an error or some other special handling
+        // unknown commands are reported by the caller through decode_ctl_message
+        _ => take(2_usize)(oi)?,
     };
     Ok((i, d))
 }
@@ -64,6 +329,26 @@ pub fn parse_message(i: &[u8]) -> IResult<&[u8], TelnetMessageType> {
     }
 }
 
+/// Parse client data after login. In character mode every keystroke may
+/// arrive on its own, so this takes the data up to and including the end
+/// of the line, up to the next command, or else all of the input.
+pub fn parse_command_data(i: &[u8]) -> IResult<&[u8], TelnetMessageType> {
+    let (i, v) = peek(le_u8)(i)?;
+    if v == TELNET_CMD_IAC {
+        let (i, c) = parse_ctl_message(i)?;
+        return Ok((i, TelnetMessageType::Control(c)));
+    }
+    let end = match i
+        .iter()
+        .position(|&b| matches!(b, b'\r' | b'\n' | TELNET_CMD_IAC))
+    {
+        Some(p) if i[p] == TELNET_CMD_IAC => p,
+        Some(p) => p + 1,
+        None => i.len(),
+    };
+    Ok((&i[end..], TelnetMessageType::Data(&i[..end])))
+}
+
 // 'login: ', 'Password: ', possibly with leading ctls
 pub fn parse_welcome_message(i: &[u8]) -> IResult<&[u8], TelnetMessageType> {
     let (i, v) = peek(le_u8)(i)?;
@@ -75,3 +360,138 @@ pub fn parse_welcome_message(i: &[u8]) -> IResult<&[u8], TelnetMessageType> {
         Ok((i, TelnetMessageType::Data(t)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_negotiation() {
+        let buf: &[u8] = &[0xff, 0xfb, 0x18];
+        let (rem, ctl) = parse_ctl_message(buf).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(
+            decode_ctl_message(ctl),
+            Some(TelnetControl::Negotiation(TELNET_CMD_WILL, TelnetOptionCode::TerminalType as u8))
+        );
+    }
+
+    #[test]
+    fn test_decode_suboptions() {
+        let buf: &[u8] = b"\xff\xfa\x18\x00xterm\xff\xf0";
+        let (rem, ctl) = parse_ctl_message(buf).unwrap();
+        assert!(rem.is_empty());
+        match decode_ctl_message(ctl) {
+            Some(TelnetControl::Suboption {
+                option: 24,
+                data,
+                terminated: true,
+            }) => {
+                assert_eq!(decode_terminal_type(data), Some(&b"xterm"[..]));
+            }
+            _ => panic!("unexpected control message"),
+        }
+
+        assert_eq!(decode_naws(&[0x00, 0x50, 0x00, 0x18]), Some((80, 24)));
+        assert_eq!(decode_naws(&[0x00, 0xff, 0xff, 0x00, 0x18]), Some((255, 24)));
+        assert_eq!(decode_naws(&[0x00, 0x50]), None);
+
+        let vars = decode_new_environ(b"\x00\x00USER\x01root\x03DISPLAY\x01:0");
+        assert_eq!(vars.len(), 2);
+        assert!(!vars[0].user);
+        assert_eq!(vars[0].name, b"USER");
+        assert_eq!(vars[0].value, Some(b"root".to_vec()));
+        assert!(vars[1].user);
+        assert_eq!(vars[1].name, b"DISPLAY");
+        assert_eq!(vars[1].value, Some(b":0".to_vec()));
+    }
+
+    #[test]
+    fn test_suboption_malformed() {
+        // another negotiation starts inside the subnegotiation
+        let buf: &[u8] = b"\xff\xfa\x18\x00xt\xff\xfb\x01";
+        let (rem, ctl) = parse_ctl_message(buf).unwrap();
+        assert_eq!(rem, b"\xff\xfb\x01");
+        assert_eq!(
+            decode_ctl_message(ctl),
+            Some(TelnetControl::Suboption {
+                option: 24,
+                data: b"\x00xt",
+                terminated: false
+            })
+        );
+
+        // escaped IAC does not terminate
+        let buf: &[u8] = b"\xff\xfa\x1f\x00\xff\xff\x00\x18\xff\xf0";
+        let (rem, _) = parse_ctl_message(buf).unwrap();
+        assert!(rem.is_empty());
+
+        // no terminator yet
+        let buf: &[u8] = b"\xff\xfa\x18\x00xterm";
+        assert!(matches!(parse_ctl_message(buf), Err(Err::Incomplete(_))));
+
+        // no terminator within the limit
+        let mut buf = b"\xff\xfa\x18".to_vec();
+        buf.extend_from_slice(&[b'A'; TELNET_MAX_SUBOPTION_LEN + 16]);
+        let (rem, ctl) = parse_ctl_message(&buf).unwrap();
+        // left for skip_suboption
+        assert_eq!(rem.len(), 16);
+        match decode_ctl_message(ctl) {
+            Some(TelnetControl::Suboption {
+                data,
+                terminated: false,
+                ..
+            }) => {
+                assert_eq!(data.len(), TELNET_MAX_SUBOPTION_LEN);
+            }
+            _ => panic!("unexpected control message"),
+        }
+    }
+
+    #[test]
+    fn test_skip_suboption() {
+        assert_eq!(skip_suboption(b"AAAA\xff\xf0login"), (&b"login"[..], true));
+        assert_eq!(
+            skip_suboption(b"AA\xff\xff\xf0AA\xff\xf0"),
+            (&b""[..], true)
+        );
+        assert_eq!(
+            skip_suboption(b"AA\xff\xfb\x01"),
+            (&b"\xff\xfb\x01"[..], true)
+        );
+        assert_eq!(skip_suboption(b"AAAA"), (&b""[..], false));
+        assert_eq!(skip_suboption(b"AAAA\xff"), (&b"\xff"[..], false));
+    }
+
+    #[test]
+    fn test_parse_command_data() {
+        let (rem, msg) = parse_command_data(b"l").unwrap();
+        assert!(rem.is_empty());
+        assert!(matches!(msg, TelnetMessageType::Data(b"l")));
+
+        let (rem, msg) = parse_command_data(b"ls\r\0pwd").unwrap();
+        assert_eq!(rem, b"\0pwd");
+        assert!(matches!(msg, TelnetMessageType::Data(b"ls\r")));
+
+        let (rem, msg) = parse_command_data(b"ls\xff\xf1").unwrap();
+        assert_eq!(rem, b"\xff\xf1");
+        assert!(matches!(msg, TelnetMessageType::Data(b"ls")));
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let buf: &[u8] = &[0xff, 0x10, b'a'];
+        let (rem, ctl) = parse_ctl_message(buf).unwrap();
+        assert_eq!(rem, b"a");
+        assert_eq!(decode_ctl_message(ctl), Some(TelnetControl::Command(0x10)));
+    }
+
+    #[test]
+    fn test_data_invalid_iac() {
+        assert!(!data_has_invalid_iac(b"ls -la"));
+        assert!(!data_has_invalid_iac(b"a\xff\xffb"));
+        assert!(!data_has_invalid_iac(b"a\xff\xf1b"));
+        assert!(data_has_invalid_iac(b"a\xff\x10b"));
+        assert!(data_has_invalid_iac(b"ab\xff"));
+    }
+}
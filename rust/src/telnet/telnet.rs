@@ -16,17 +16,36 @@
  */
 
 use std;
-use crate::core::{ALPROTO_UNKNOWN, AppProto, Flow, IPPROTO_TCP};
+use crate::core::{ALPROTO_UNKNOWN, AppProto, Direction, Flow, IPPROTO_TCP};
 use crate::applayer::{self, *};
 use crate::frames::*;
+use std::collections::HashMap;
 use std::ffi::CString;
 use nom7::IResult;
 use super::parser;
 
-static mut ALPROTO_TELNET: AppProto = ALPROTO_UNKNOWN;
+pub(super) static mut ALPROTO_TELNET: AppProto = ALPROTO_UNKNOWN;
+
+/// Number of negotiations for a single option after which the peers are
+/// considered to be stuck in (or deliberately causing) a negotiation loop.
+const TELNET_MAX_OPTION_NEGOTIATIONS: u32 = 32;
+
+/// Maximum number of negotiations and environment variables kept in a
+/// transaction.
+const TELNET_MAX_TX_NEGOTIATIONS: usize = 64;
+const TELNET_MAX_TX_ENVIRON: usize = 32;
+
+/// Maximum length of a command line, the rest of a longer line is dropped.
+const TELNET_MAX_COMMAND_LEN: usize = 4096;
 
 #[derive(AppLayerEvent)]
-enum TelnetEvent {}
+enum TelnetEvent {
+    UnknownCommand,
+    UnterminatedSuboption,
+    OversizedSuboption,
+    InvalidIacEscape,
+    OptionNegotiationFlood,
+}
 
 #[derive(AppLayerFrameType)]
 pub enum TelnetFrameType {
@@ -35,12 +54,35 @@ pub enum TelnetFrameType {
     Data,
 }
 
+/// A WILL/WONT/DO/DONT option negotiation.
+#[derive(Debug)]
+pub struct TelnetNegotiation {
+    pub direction: Direction,
+    pub command: u8,
+    pub option: u8,
+}
+
 #[derive(Default)]
 pub struct TelnetTransaction {
     tx_id: u64,
+    pub negotiations: Vec<TelnetNegotiation>,
+    pub terminal_type: Option<Vec<u8>>,
+    pub window_size: Option<(u16, u16)>,
+    pub environ: Vec<parser::TelnetEnvironVar>,
+    pub username: Option<Vec<u8>>,
+    /// Set once the server accepted or rejected the login.
+    pub auth_success: Option<bool>,
+    pub command: Option<Vec<u8>>,
+    complete: bool,
     tx_data: AppLayerTxData,
 }
 
+impl TelnetTransaction {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
 impl Transaction for TelnetTransaction {
     fn id(&self) -> u64 {
         self.tx_id
@@ -72,6 +114,17 @@ pub struct TelnetState {
     /// either control or data frame
     response_specific_frame: Option<Frame>,
     state: TelnetProtocolState,
+    /// number of negotiations seen per option code
+    negotiation_count: HashMap<u8, u32>,
+    /// set once an option was negotiated too often, after which
+    /// negotiations are no longer recorded
+    negotiation_flood: bool,
+    /// skipping the rest of an oversized subnegotiation
+    request_skip_suboption: bool,
+    response_skip_suboption: bool,
+    /// command line being typed after login, which in character mode
+    /// arrives a keystroke at a time
+    command_line: Vec<u8>,
 }
 
 impl State<TelnetTransaction> for TelnetState {
@@ -103,6 +156,11 @@ impl TelnetState {
             response_frame: None,
             response_specific_frame: None,
             state: TelnetProtocolState::Idle,
+            negotiation_count: HashMap::new(),
+            negotiation_flood: false,
+            request_skip_suboption: false,
+            response_skip_suboption: false,
+            command_line: Vec::new(),
         }
     }
 
@@ -128,9 +186,116 @@ impl TelnetState {
         self.transactions.iter().find(|tx| tx.tx_id == tx_id + 1)
     }
 
-    fn _find_request(&mut self) -> Option<&mut TelnetTransaction> {
-        // TODO
-        None
+    fn new_tx(&mut self) -> &mut TelnetTransaction {
+        let mut tx = TelnetTransaction::new();
+        self.tx_id += 1;
+        tx.tx_id = self.tx_id;
+        self.transactions.push(tx);
+        // we just pushed it, so unwrap is safe
+        self.transactions.last_mut().unwrap()
+    }
+
+    /// Get the transaction that is still being built, creating one if needed.
+    fn get_current_tx(&mut self) -> &mut TelnetTransaction {
+        let open = matches!(self.transactions.last(), Some(tx) if !tx.complete);
+        if !open {
+            return self.new_tx();
+        }
+        // checked above
+        self.transactions.last_mut().unwrap()
+    }
+
+    fn complete_current_tx(&mut self) {
+        if let Some(tx) = self.transactions.last_mut() {
+            tx.complete = true;
+        }
+    }
+
+    /// Set an event on the most recent transaction.
+    fn set_event(&mut self, event: TelnetEvent) {
+        if self.transactions.is_empty() {
+            self.new_tx();
+        }
+        if let Some(tx) = self.transactions.last_mut() {
+            tx.tx_data.set_event(event as u8);
+        }
+    }
+
+    fn handle_control(&mut self, ctl: &[u8], direction: Direction) {
+        let ctl = match parser::decode_ctl_message(ctl) {
+            Some(parser::TelnetControl::Command(cmd)) => {
+                if cmd < parser::TELNET_CMD_SE {
+                    self.set_event(TelnetEvent::UnknownCommand);
+                }
+                return;
+            }
+            None => return,
+            Some(ctl) => ctl,
+        };
+        if let parser::TelnetControl::Negotiation(_, option) = ctl {
+            if self.negotiation_flood {
+                return;
+            }
+            let count = self.negotiation_count.entry(option).or_insert(0);
+            *count += 1;
+            if *count > TELNET_MAX_OPTION_NEGOTIATIONS {
+                self.set_event(TelnetEvent::OptionNegotiationFlood);
+                self.negotiation_flood = true;
+                return;
+            }
+        }
+        let tx = self.get_current_tx();
+        match ctl {
+            parser::TelnetControl::Negotiation(command, option) => {
+                if tx.negotiations.len() >= TELNET_MAX_TX_NEGOTIATIONS {
+                    return;
+                }
+                tx.negotiations.push(TelnetNegotiation {
+                    direction,
+                    command,
+                    option,
+                });
+            }
+            parser::TelnetControl::Suboption {
+                option,
+                data,
+                terminated,
+            } => {
+                if !terminated {
+                    if data.len() >= parser::TELNET_MAX_SUBOPTION_LEN {
+                        tx.tx_data.set_event(TelnetEvent::OversizedSuboption as u8);
+                        // the rest of it is not parsed as data
+                        if direction == Direction::ToServer {
+                            self.request_skip_suboption = true;
+                        } else {
+                            self.response_skip_suboption = true;
+                        }
+                    } else {
+                        tx.tx_data.set_event(TelnetEvent::UnterminatedSuboption as u8);
+                    }
+                    return;
+                }
+                if option == parser::TelnetOptionCode::TerminalType as u8 {
+                    if let Some(ttype) = parser::decode_terminal_type(data) {
+                        tx.terminal_type = Some(ttype.to_vec());
+                    }
+                } else if option == parser::TelnetOptionCode::Naws as u8 {
+                    if let Some(size) = parser::decode_naws(data) {
+                        tx.window_size = Some(size);
+                    }
+                } else if option == parser::TelnetOptionCode::NewEnviron as u8 {
+                    for var in parser::decode_new_environ(data) {
+                        if tx.username.is_none() && !var.user && var.name == b"USER" {
+                            tx.username = var.value.clone();
+                        }
+                        if tx.environ.len() < TELNET_MAX_TX_ENVIRON {
+                            tx.environ.push(var);
+                        }
+                    }
+                }
+            }
+            parser::TelnetControl::Command(_) => {}
+        }
     }
 
     // app-layer-frame-documentation tag start: parse_request
@@ -157,6 +322,21 @@ impl TelnetState {
 
         let mut start = input;
         while !start.is_empty() {
+            if self.request_skip_suboption {
+                let (rem, done) = parser::skip_suboption(start);
+                start = rem;
+                if !done {
+                    if start.is_empty() {
+                        break;
+                    }
+                    // a trailing IAC may be the start of the terminator
+                    let consumed = input.len() - start.len();
+                    let needed = start.len() + 1;
+                    return AppLayerResult::incomplete(consumed as u32, needed as u32);
+                }
+                self.request_skip_suboption = false;
+                continue;
+            }
             if self.request_frame.is_none() {
                 self.request_frame = Frame::new(
                     flow,
@@ -193,7 +373,11 @@ impl TelnetState {
                 }
             }
             // app-layer-frame-documentation tag start: update frame_len
-            match parser::parse_message(start) {
+            let r = match self.state {
+                TelnetProtocolState::AuthOk => parser::parse_command_data(start),
+                _ => parser::parse_message(start),
+            };
+            match r {
                 Ok((rem, request)) => {
                     let consumed = start.len() - rem.len();
                     if rem.len() == start.len() {
@@ -214,21 +398,37 @@ impl TelnetState {
                     if let parser::TelnetMessageType::Data(d) = request {
                         match self.state {
                             TelnetProtocolState::LoginSent => {
+                                let tx = self.get_current_tx();
+                                tx.username = Some(trim_line(d).to_vec());
                                 self.state = TelnetProtocolState::LoginRecv;
                             }
                             TelnetProtocolState::PasswdSent => {
                                 self.state = TelnetProtocolState::PasswdRecv;
                             }
                             TelnetProtocolState::AuthOk => {
-                                let _message = std::str::from_utf8(d);
-                                if let Ok(_message) = _message {
-                                    SCLogDebug!("=> {}", _message);
+                                let room = TELNET_MAX_COMMAND_LEN - self.command_line.len();
+                                self.command_line
+                                    .extend_from_slice(&d[..std::cmp::min(d.len(), room)]);
+                                if d.ends_with(b"\r") || d.ends_with(b"\n") {
+                                    let line = std::mem::take(&mut self.command_line);
+                                    let line = trim_line(&line);
+                                    if !line.is_empty() {
+                                        SCLogDebug!("=> {:?}", line);
+                                        self.complete_current_tx();
+                                        let tx = self.new_tx();
+                                        tx.command = Some(line.to_vec());
+                                        tx.complete = true;
+                                    }
                                 }
                             }
                             _ => {}
                         }
-                    } else if let parser::TelnetMessageType::Control(_c) = request {
-                        SCLogDebug!("request {:?}", _c);
+                        if parser::data_has_invalid_iac(d) {
+                            self.set_event(TelnetEvent::InvalidIacEscape);
+                        }
+                    } else if let parser::TelnetMessageType::Control(c) = request {
+                        SCLogDebug!("request {:?}", c);
+                        self.handle_control(c, Direction::ToServer);
                     }
                 }
                 Err(nom7::Err::Incomplete(_)) => {
@@ -268,6 +468,21 @@ impl TelnetState {
         }
         let mut start = input;
         while !start.is_empty() {
+            if self.response_skip_suboption {
+                let (rem, done) = parser::skip_suboption(start);
+                start = rem;
+                if !done {
+                    if start.is_empty() {
+                        break;
+                    }
+                    // a trailing IAC may be the start of the terminator
+                    let consumed = input.len() - start.len();
+                    let needed = start.len() + 1;
+                    return AppLayerResult::incomplete(consumed as u32, needed as u32);
+                }
+                self.response_skip_suboption = false;
+                continue;
+            }
             if self.response_frame.is_none() {
                 self.response_frame = Frame::new(flow, stream_slice, start, -1_i64, TelnetFrameType::Pdu as u8, None);
             }
@@ -311,11 +526,14 @@ impl TelnetState {
                                 self.state = TelnetProtocolState::PasswdSent;
                             },
                             TelnetProtocolState::PasswdRecv => {
-                                if let Ok(message) = std::str::from_utf8(d) {
+                                if let Ok(message) = std::str::from_utf8(trim_line(d)) {
                                     match message {
                                         "Login incorrect" => {
                                             SCLogDebug!("LOGIN FAILED");
                                             self.state = TelnetProtocolState::AuthFail;
+                                            let tx = self.get_current_tx();
+                                            tx.auth_success = Some(false);
+                                            tx.complete = true;
                                         },
                                         "" => {
 
@@ -323,6 +541,9 @@ impl TelnetState {
                                         &_ => {
                                             SCLogDebug!("LOGIN OK");
                                             self.state = TelnetProtocolState::AuthOk;
+                                            let tx = self.get_current_tx();
+                                            tx.auth_success = Some(true);
+                                            tx.complete = true;
                                         },
                                     }
                                 }
@@ -335,8 +556,12 @@ impl TelnetState {
                             },
                             _ => {},
                         }
-                    } else if let parser::TelnetMessageType::Control(_c) = response {
-                        SCLogDebug!("response {:?}", _c);
+                        if parser::data_has_invalid_iac(d) {
+                            self.set_event(TelnetEvent::InvalidIacEscape);
+                        }
+                    } else if let parser::TelnetMessageType::Control(c) = response {
+                        SCLogDebug!("response {:?}", c);
+                        self.handle_control(c, Direction::ToClient);
                     }
                 }
                 Err(nom7::Err::Incomplete(_)) => {
@@ -364,6 +589,20 @@ impl TelnetState {
     }
 }
 
+/// Strip the line terminators and any NUL padding from a data line. A
+/// `CR NUL` or `CR LF` split from the previous line leaves them in front.
+fn trim_line(d: &[u8]) -> &[u8] {
+    let mut start = 0;
+    while start < d.len() && matches!(d[start], b'\n' | b'\0') {
+        start += 1;
+    }
+    let mut end = d.len();
+    while end > start && matches!(d[end - 1], b'\r' | b'\n' | b'\0') {
+        end -= 1;
+    }
+    &d[start..end]
+}
+
 /// Probe for a valid header.
 ///
 fn probe(input: &[u8]) -> IResult<&[u8], ()> {
@@ -495,8 +734,10 @@ pub unsafe extern "C" fn rs_telnet_tx_get_alstate_progress(
     tx: *mut std::os::raw::c_void,
     _direction: u8,
 ) -> std::os::raw::c_int {
-    let _tx = cast_pointer!(tx, TelnetTransaction);
-    // TODO
+    let tx = cast_pointer!(tx, TelnetTransaction);
+    if tx.complete {
+        return 1;
+    }
     return 0;
 }
 
@@ -563,3 +804,104 @@ pub unsafe extern "C" fn rs_telnet_register_parser() {
         SCLogDebug!("Protocol detector and parser disabled for TELNET.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::STREAM_START;
+
+    fn parse_request(state: &mut TelnetState, buf: &[u8]) -> AppLayerResult {
+        let stream_slice = StreamSlice::from_slice(buf, STREAM_START, 0);
+        state.parse_request(std::ptr::null(), &stream_slice, buf)
+    }
+
+    #[test]
+    fn test_oversized_suboption_skipped() {
+        let mut state = TelnetState::new();
+        state.state = TelnetProtocolState::LoginSent;
+
+        let mut buf = b"\xff\xfa\x18".to_vec();
+        buf.extend_from_slice(&[b'A'; parser::TELNET_MAX_SUBOPTION_LEN + 16]);
+        buf.extend_from_slice(b"\r\n");
+        assert_eq!(parse_request(&mut state, &buf), AppLayerResult::ok());
+        assert!(state.request_skip_suboption);
+
+        // a trailing IAC is kept until we know what follows it
+        assert_eq!(
+            parse_request(&mut state, b"AA\xff"),
+            AppLayerResult::incomplete(2, 2)
+        );
+        assert_eq!(
+            parse_request(&mut state, b"\xff\xf0root\r\n"),
+            AppLayerResult::ok()
+        );
+        assert!(!state.request_skip_suboption);
+        assert_eq!(state.transactions.len(), 1);
+        assert_eq!(state.transactions[0].username, Some(b"root".to_vec()));
+    }
+
+    #[test]
+    fn test_command_character_mode() {
+        let mut state = TelnetState::new();
+        state.state = TelnetProtocolState::AuthOk;
+        for k in [&b"l"[..], b"s", b"\r\0", b"p", b"w", b"d", b"\r\n"] {
+            assert_eq!(parse_request(&mut state, k), AppLayerResult::ok());
+        }
+        assert_eq!(state.transactions.len(), 2);
+        assert_eq!(state.transactions[0].command, Some(b"ls".to_vec()));
+        assert_eq!(state.transactions[1].command, Some(b"pwd".to_vec()));
+
+        // line mode
+        assert_eq!(
+            parse_request(&mut state, b"id\r\nuname -a\r\n"),
+            AppLayerResult::ok()
+        );
+        assert_eq!(state.transactions.len(), 4);
+        assert_eq!(state.transactions[3].command, Some(b"uname -a".to_vec()));
+
+        let buf = vec![b'A'; TELNET_MAX_COMMAND_LEN + 1];
+        assert_eq!(parse_request(&mut state, &buf), AppLayerResult::ok());
+        assert_eq!(parse_request(&mut state, b"\r\n"), AppLayerResult::ok());
+        assert_eq!(
+            state.transactions[4].command.as_ref().map(|c| c.len()),
+            Some(TELNET_MAX_COMMAND_LEN)
+        );
+    }
+
+    #[test]
+    fn test_negotiations_capped() {
+        let mut state = TelnetState::new();
+        let mut buf = Vec::new();
+        for option in 0..TELNET_MAX_TX_NEGOTIATIONS as u8 + 8 {
+            buf.extend_from_slice(&[0xff, parser::TELNET_CMD_DO, option]);
+        }
+        buf.extend_from_slice(b"\xff\xfa\x27\x00");
+        for _ in 0..TELNET_MAX_TX_ENVIRON + 8 {
+            buf.extend_from_slice(b"\x03VAR\x01value");
+        }
+        buf.extend_from_slice(b"\xff\xf0");
+        assert_eq!(parse_request(&mut state, &buf), AppLayerResult::ok());
+        assert_eq!(state.transactions.len(), 1);
+        assert_eq!(
+            state.transactions[0].negotiations.len(),
+            TELNET_MAX_TX_NEGOTIATIONS
+        );
+        assert_eq!(state.transactions[0].environ.len(), TELNET_MAX_TX_ENVIRON);
+    }
+
+    #[test]
+    fn test_negotiation_flood() {
+        let mut state = TelnetState::new();
+        let mut buf = Vec::new();
+        for _ in 0..TELNET_MAX_OPTION_NEGOTIATIONS + 8 {
+            buf.extend_from_slice(&[0xff, parser::TELNET_CMD_WILL, 0x01]);
+        }
+        buf.extend_from_slice(&[0xff, parser::TELNET_CMD_WILL, 0x03]);
+        assert_eq!(parse_request(&mut state, &buf), AppLayerResult::ok());
+        assert!(state.negotiation_flood);
+        assert_eq!(
+            state.transactions[0].negotiations.len(),
+            TELNET_MAX_OPTION_NEGOTIATIONS as usize
+        );
+    }
+}
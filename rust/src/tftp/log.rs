@@ -18,7 +18,20 @@
 // written by Clément Galland <clement.galland@epita.fr>
 
 use crate::jsonbuilder::{JsonBuilder, JsonError};
-use crate::tftp::tftp::TFTPTransaction;
+use crate::tftp::tftp::{TFTPOptions, TFTPTransaction};
+
+fn tftp_log_options(options: &TFTPOptions, jb: &mut JsonBuilder) -> Result<(), JsonError> {
+    if let Some(blksize) = options.blksize {
+        jb.set_uint("blksize", blksize as u64)?;
+    }
+    if let Some(timeout) = options.timeout {
+        jb.set_uint("timeout", timeout as u64)?;
+    }
+    if let Some(tsize) = options.tsize {
+        jb.set_uint("tsize", tsize)?;
+    }
+    Ok(())
+}
 
 fn tftp_log_request(tx: &mut TFTPTransaction,
                     jb: &mut JsonBuilder)
@@ -32,6 +45,33 @@ fn tftp_log_request(tx: &mut TFTPTransaction,
     };
     jb.set_string("file", tx.filename.as_str())?;
     jb.set_string("mode", tx.mode.as_str())?;
+    if !tx.options.is_empty() {
+        jb.open_object("options")?;
+        tftp_log_options(&tx.options, jb)?;
+        jb.close()?;
+    }
+    if let Some(ref oack) = tx.oack {
+        jb.open_object("oack")?;
+        tftp_log_options(oack, jb)?;
+        jb.close()?;
+    }
+    if tx.transfer {
+        jb.set_uint("blocks", tx.blocks)?;
+        jb.set_uint("size", tx.size)?;
+        if tx.retransmits > 0 {
+            jb.set_uint("retransmits", tx.retransmits)?;
+        }
+        if tx.truncated {
+            jb.set_bool("truncated", true)?;
+        }
+        jb.set_bool("complete", tx.complete && !tx.truncated && tx.error.is_none())?;
+    }
+    if let Some(ref error) = tx.error {
+        jb.open_object("error")?;
+        jb.set_uint("code", error.code as u64)?;
+        jb.set_string("message", &error.message)?;
+        jb.close()?;
+    }
     jb.close()?;
     Ok(())
 }
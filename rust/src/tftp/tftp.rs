@@ -20,23 +20,117 @@
 use std::str;
 use std;
 use nom7::IResult;
-use nom7::combinator::map_res;
+use nom7::combinator::{complete, map_res, rest};
 use nom7::bytes::streaming::{tag, take_while};
-use nom7::number::streaming::be_u8;
+use nom7::multi::many0;
+use nom7::number::streaming::{be_u8, be_u16};
+use nom7::sequence::{terminated, tuple};
 
-use crate::applayer::{AppLayerTxData,AppLayerStateData};
+use crate::applayer::{AppLayerGetFileState, AppLayerTxData, AppLayerStateData};
+use crate::core::{Direction, SuricataFileContext, STREAM_TOSERVER, STREAM_TOCLIENT};
+use crate::filecontainer::FileFlowFlagsToFlags;
+use crate::filetracker::FileTransferTracker;
 
 const READREQUEST:  u8 = 1;
 const WRITEREQUEST: u8 = 2;
 const DATA:         u8 = 3;
 const ACK:          u8 = 4;
 const ERROR:        u8 = 5;
+const OACK:         u8 = 6;
+
+/// Block size used when no blksize option was acknowledged (RFC 1350).
+const DEFAULT_BLKSIZE: u16 = 512;
+
+pub static mut SURICATA_TFTP_FILE_CONFIG: Option<&'static SuricataFileContext> = None;
+
+/// Options carried by a request or an OACK (RFC 2347).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TFTPOptions {
+    /// RFC 2348
+    pub blksize: Option<u16>,
+    /// RFC 2349
+    pub timeout: Option<u8>,
+    /// RFC 2349
+    pub tsize: Option<u64>,
+}
+
+impl TFTPOptions {
+    pub fn is_empty(&self) -> bool {
+        self.blksize.is_none() && self.timeout.is_none() && self.tsize.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TFTPError {
+    pub code: u16,
+    pub message: String,
+}
+
+/// A read or write request. This is also what gets handed over to the
+/// data channel flow through the app-layer expectation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TFTPRequest {
+    pub opcode : u8,
+    pub filename : String,
+    pub mode : String,
+    pub options : TFTPOptions,
+}
+
+impl TFTPRequest {
+    pub fn new(opcode : u8, filename : String, mode : String, options : TFTPOptions) -> TFTPRequest {
+        TFTPRequest {
+            opcode,
+            filename,
+            mode : mode.to_lowercase(),
+            options,
+        }
+    }
+    pub fn is_mode_ok(&self) -> bool {
+        match self.mode.as_str() {
+            "netascii" | "mail" | "octet" => true,
+            _ => false
+        }
+    }
+    pub fn is_opcode_ok(&self) -> bool {
+        match self.opcode {
+            READREQUEST | WRITEREQUEST => true,
+            _ => false
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq)]
+enum TFTPPacket<'a> {
+    Request(TFTPRequest),
+    Data(u16, &'a [u8]),
+    Ack(u16),
+    Error(TFTPError),
+    OptionAck(TFTPOptions),
+}
+
+#[derive(Debug)]
 pub struct TFTPTransaction {
     pub opcode : u8,
     pub filename : String,
     pub mode : String,
+    /// options sent with the request
+    pub options : TFTPOptions,
+    /// options acknowledged by the server
+    pub oack : Option<TFTPOptions>,
+    pub error : Option<TFTPError>,
+    /// set on the data channel flow, where the blocks are exchanged
+    pub transfer : bool,
+    /// number of in order DATA blocks seen
+    pub blocks : u64,
+    /// number of DATA blocks seen again or out of order
+    pub retransmits : u64,
+    /// number of data bytes transferred
+    pub size : u64,
+    /// blocks were missed, the file could not be reassembled
+    pub truncated : bool,
+    pub complete : bool,
+    file_direction : Option<Direction>,
+    file_tracker : FileTransferTracker,
     id: u64,
     tx_data: AppLayerTxData,
 }
@@ -46,9 +140,15 @@ pub struct TFTPState {
     pub transactions : Vec<TFTPTransaction>,
     /// tx counter for assigning incrementing id's to tx's
     tx_id: u64,
+    /// id of the transaction tracking the data channel transfer
+    transfer_tx_id: u64,
 }
 
 impl TFTPState {
+    fn new() -> TFTPState {
+        TFTPState { state_data: AppLayerStateData::new(), transactions : Vec::new(), tx_id: 0, transfer_tx_id: 0, }
+    }
+
     fn get_tx_by_id(&mut self, tx_id: u64) -> Option<&TFTPTransaction> {
         self.transactions.iter().find(|&tx| tx.id == tx_id + 1)
     }
@@ -60,35 +160,177 @@ impl TFTPState {
             let _ = self.transactions.remove(idx);
         }
     }
+
+    fn new_tx(&mut self, request: TFTPRequest) -> &mut TFTPTransaction {
+        let mut tx = TFTPTransaction::new(request);
+        self.tx_id += 1;
+        tx.id = self.tx_id;
+        self.transactions.push(tx);
+        return self.transactions.last_mut().unwrap();
+    }
+
+    /// Start tracking the transfer of a request seen on the control flow.
+    fn set_transfer(&mut self, request: TFTPRequest) {
+        let tx = self.new_tx(request);
+        tx.transfer = true;
+        tx.file_tracker.tx_id = tx.id - 1;
+        let id = tx.id;
+        self.transfer_tx_id = id;
+    }
+
+    fn get_transfer_tx(&mut self) -> Option<&mut TFTPTransaction> {
+        if self.transfer_tx_id == 0 {
+            return None;
+        }
+        let id = self.transfer_tx_id;
+        self.transactions.iter_mut().find(|tx| tx.id == id)
+    }
+
+    /// Parse a single datagram.
+    ///
+    /// Returns 1 if a new read or write request was seen, 0 if the packet
+    /// was valid otherwise and -1 on error.
+    fn parse(&mut self, input: &[u8], direction: Direction) -> i64 {
+        let packet = match parse_tftp_packet(input) {
+            Some(packet) => packet,
+            None => {
+                return -1;
+            }
+        };
+        let file_flags = self.state_data.file_flags;
+        match packet {
+            TFTPPacket::Request(request) => {
+                if direction != Direction::ToServer {
+                    SCLogDebug!("request seen toclient, ignoring");
+                    return 0;
+                }
+                self.new_tx(request);
+                return 1;
+            }
+            TFTPPacket::Data(block, data) => {
+                if let Some(tx) = self.get_transfer_tx() {
+                    tx.handle_data(block, data, direction, file_flags);
+                }
+            }
+            TFTPPacket::Ack(_block) => {
+                // blocks are followed on the DATA side, ACKs only drive
+                // retransmissions
+            }
+            TFTPPacket::Error(error) => {
+                if let Some(tx) = self.get_transfer_tx() {
+                    tx.handle_error(error);
+                }
+            }
+            TFTPPacket::OptionAck(options) => {
+                if let Some(tx) = self.get_transfer_tx() {
+                    tx.oack = Some(options);
+                }
+            }
+        }
+        0
+    }
 }
 
 impl TFTPTransaction {
-    pub fn new(opcode : u8, filename : String, mode : String) -> TFTPTransaction {
+    pub fn new(request : TFTPRequest) -> TFTPTransaction {
         TFTPTransaction {
-            opcode,
-            filename,
-            mode : mode.to_lowercase(),
+            opcode : request.opcode,
+            filename : request.filename,
+            mode : request.mode,
+            options : request.options,
+            oack : None,
+            error : None,
+            transfer : false,
+            blocks : 0,
+            retransmits : 0,
+            size : 0,
+            truncated : false,
+            complete : false,
+            file_direction : None,
+            file_tracker : FileTransferTracker::new(),
             id : 0,
             tx_data: AppLayerTxData::new(),
         }
     }
-    pub fn is_mode_ok(&self) -> bool {
-        match self.mode.as_str() {
-            "netascii" | "mail" | "octet" => true,
-            _ => false
+
+    /// Block size in use for the transfer: the one acknowledged by the
+    /// server if any, the default one otherwise.
+    fn blksize(&self) -> u16 {
+        self.oack.as_ref().and_then(|o| o.blksize).unwrap_or(DEFAULT_BLKSIZE)
+    }
+
+    fn handle_data(&mut self, block: u16, data: &[u8], direction: Direction, file_flags: u16) {
+        if self.complete {
+            self.retransmits += 1;
+            return;
+        }
+        match self.file_direction {
+            None => {
+                // first block: the file goes in this direction
+                let dir_flag = if direction == Direction::ToServer { STREAM_TOSERVER } else { STREAM_TOCLIENT };
+                self.file_direction = Some(direction);
+                self.tx_data.update_file_flags(file_flags);
+                self.file_tracker.file_flags = unsafe { FileFlowFlagsToFlags(self.tx_data.file_flags, dir_flag) };
+                self.tx_data.init_files_opened();
+                self.tx_data.file_tx = dir_flag;
+            }
+            Some(d) if d != direction => {
+                SCLogDebug!("DATA block {} in the wrong direction", block);
+                return;
+            }
+            _ => {}
+        }
+
+        // block numbers start at 1 and roll over to 0 after 65535
+        let expected = (self.blocks as u16).wrapping_add(1);
+        let ahead = block.wrapping_sub(expected);
+        if ahead != 0 {
+            self.retransmits += 1;
+            if ahead < 0x8000 {
+                // we missed blocks, the file can't be reassembled
+                SCLogDebug!("missed blocks: expected {} got {}", expected, block);
+                if let Some(sfcm) = unsafe { SURICATA_TFTP_FILE_CONFIG } {
+                    self.file_tracker.trunc(sfcm);
+                }
+                self.truncated = true;
+                self.complete = true;
+            }
+            return;
+        }
+
+        let is_last = data.len() < self.blksize() as usize;
+        if let Some(sfcm) = unsafe { SURICATA_TFTP_FILE_CONFIG } {
+            let xid = self.id as u32;
+            self.file_tracker.new_chunk(sfcm, self.filename.as_bytes(), data,
+                    self.size, data.len() as u32, 0, is_last, &xid);
+        }
+        self.blocks += 1;
+        self.size += data.len() as u64;
+        if is_last {
+            self.complete = true;
         }
     }
-    pub fn is_opcode_ok(&self) -> bool {
-        match self.opcode {
-            READREQUEST | WRITEREQUEST | ACK | DATA | ERROR => true,
-            _ => false
+
+    fn handle_error(&mut self, error: TFTPError) {
+        if let Some(sfcm) = unsafe { SURICATA_TFTP_FILE_CONFIG } {
+            self.file_tracker.trunc(sfcm);
+        }
+        self.error = Some(error);
+        self.complete = true;
+    }
+}
+
+impl Drop for TFTPTransaction {
+    fn drop(&mut self) {
+        if let Some(sfcm) = unsafe { SURICATA_TFTP_FILE_CONFIG } {
+            self.file_tracker.file.free(sfcm);
         }
     }
 }
 
 #[no_mangle]
 pub extern "C" fn rs_tftp_state_alloc() -> *mut std::os::raw::c_void {
-    let state = TFTPState { state_data: AppLayerStateData::new(), transactions : Vec::new(), tx_id: 0, };
+    let state = TFTPState::new();
     let boxed = Box::new(state);
     return Box::into_raw(boxed) as *mut _;
 }
@@ -118,6 +360,17 @@ pub extern "C" fn rs_tftp_get_tx_cnt(state: &mut TFTPState) -> u64 {
     return state.tx_id;
 }
 
+/// Request transactions are done as soon as they are seen, the transfer
+/// one when the last block or an error was seen.
+#[no_mangle]
+pub extern "C" fn rs_tftp_tx_get_alstate_progress(tx: &mut TFTPTransaction,
+                                                  _direction: u8) -> std::os::raw::c_int {
+    if tx.transfer && !tx.complete {
+        return 0;
+    }
+    return 1;
+}
+
 fn getstr(i: &[u8]) -> IResult<&[u8], &str> {
     map_res(
         take_while(|c| c != 0),
@@ -125,28 +378,49 @@ fn getstr(i: &[u8]) -> IResult<&[u8], &str> {
     )(i)
 }
 
-fn tftp_request(slice: &[u8]) -> IResult<&[u8], TFTPTransaction> {
+fn tftp_options(i: &[u8]) -> IResult<&[u8], TFTPOptions> {
+    let (i, pairs) = many0(complete(tuple((
+        terminated(getstr, tag([0])),
+        terminated(getstr, tag([0])),
+    ))))(i)?;
+    let mut options = TFTPOptions::default();
+    for (name, value) in pairs {
+        match name.to_lowercase().as_str() {
+            "blksize" => options.blksize = value.parse().ok(),
+            "timeout" => options.timeout = value.parse().ok(),
+            "tsize" => options.tsize = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Ok((i, options))
+}
+
+fn tftp_request(slice: &[u8]) -> IResult<&[u8], TFTPRequest> {
     let (i, _) = tag([0])(slice)?;
     let (i, opcode) = be_u8(i)?;
     let (i, filename) = getstr(i)?;
     let (i, _) = tag([0])(i)?;
     let (i, mode) = getstr(i)?;
+    let (i, options) = match tag::<_, _, nom7::error::Error<_>>([0])(i) {
+        Ok((i, _)) => tftp_options(i)?,
+        Err(_) => (i, TFTPOptions::default()),
+    };
     Ok((i,
-        TFTPTransaction::new(opcode, String::from(filename), String::from(mode))
+        TFTPRequest::new(opcode, String::from(filename), String::from(mode), options)
        )
       )
 }
 
-fn parse_tftp_request(input: &[u8]) -> Option<TFTPTransaction> {
+fn parse_tftp_request(input: &[u8]) -> Option<TFTPRequest> {
     match tftp_request(input) {
-        Ok((_, tx)) => {
-            if !tx.is_mode_ok() {
+        Ok((_, request)) => {
+            if !request.is_mode_ok() {
                 return None;
             }
-            if !tx.is_opcode_ok() {
+            if !request.is_opcode_ok() {
                 return None;
             }
-            return Some(tx);
+            return Some(request);
         }
         Err(_) => {
             return None;
@@ -154,22 +428,119 @@ fn parse_tftp_request(input: &[u8]) -> Option<TFTPTransaction> {
     }
 }
 
+fn tftp_error(i: &[u8]) -> IResult<&[u8], TFTPError> {
+    let (i, code) = be_u16(i)?;
+    let (i, message) = take_while(|c| c != 0)(i)?;
+    Ok((i, TFTPError { code, message: String::from_utf8_lossy(message).to_string() }))
+}
+
+fn tftp_packet(slice: &[u8]) -> IResult<&[u8], TFTPPacket> {
+    let (i, _) = tag([0])(slice)?;
+    let (i, opcode) = be_u8(i)?;
+    match opcode {
+        DATA => {
+            let (i, block) = be_u16(i)?;
+            let (i, data) = rest(i)?;
+            Ok((i, TFTPPacket::Data(block, data)))
+        }
+        ACK => {
+            let (i, block) = be_u16(i)?;
+            Ok((i, TFTPPacket::Ack(block)))
+        }
+        ERROR => {
+            let (i, error) = tftp_error(i)?;
+            Ok((i, TFTPPacket::Error(error)))
+        }
+        OACK => {
+            let (i, options) = tftp_options(i)?;
+            Ok((i, TFTPPacket::OptionAck(options)))
+        }
+        _ => {
+            Err(nom7::Err::Error(nom7::error::make_error(slice, nom7::error::ErrorKind::Tag)))
+        }
+    }
+}
+
+fn parse_tftp_packet(input: &[u8]) -> Option<TFTPPacket> {
+    if let Some(request) = parse_tftp_request(input) {
+        return Some(TFTPPacket::Request(request));
+    }
+    match tftp_packet(input) {
+        Ok((_, packet)) => Some(packet),
+        Err(_) => None,
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rs_tftp_request(state: &mut TFTPState,
                                   input: *const u8,
                                   len: u32) -> i64 {
     let buf = std::slice::from_raw_parts(input, len as usize);
-    match parse_tftp_request(buf) {
-        Some(mut tx) => {
-            state.tx_id += 1;
-            tx.id = state.tx_id;
-            state.transactions.push(tx);
-            0
-        },
-        None => {
-           -1
+    state.parse(buf, Direction::ToServer)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_tftp_response(state: &mut TFTPState,
+                                  input: *const u8,
+                                  len: u32) -> i64 {
+    let buf = std::slice::from_raw_parts(input, len as usize);
+    state.parse(buf, Direction::ToClient)
+}
+
+/// Get a copy of the last request, to be handed over to the data channel.
+#[no_mangle]
+pub extern "C" fn rs_tftp_state_get_transfer(state: &mut TFTPState) -> *mut TFTPRequest {
+    match state.transactions.last() {
+        Some(tx) => {
+            let request = TFTPRequest {
+                opcode: tx.opcode,
+                filename: tx.filename.clone(),
+                mode: tx.mode.clone(),
+                options: tx.options.clone(),
+            };
+            Box::into_raw(Box::new(request))
         }
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Start tracking the transfer on the data channel. Takes ownership of
+/// the request.
+#[no_mangle]
+pub unsafe extern "C" fn rs_tftp_state_set_transfer(state: &mut TFTPState,
+                                             request: *mut TFTPRequest) {
+    if request.is_null() {
+        return;
     }
+    let request = Box::from_raw(request);
+    state.set_transfer(*request);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_tftp_transfer_free(request: *mut TFTPRequest) {
+    if !request.is_null() {
+        std::mem::drop(Box::from_raw(request));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_tftp_gettxfiles(tx: *mut std::ffi::c_void, direction: u8) -> AppLayerGetFileState {
+    let tx = cast_pointer!(tx, TFTPTransaction);
+    if let Some(file_direction) = tx.file_direction {
+        let tx_dir : u8 = file_direction.into();
+        if direction & tx_dir != 0 {
+            if let Some(sfcm) = { SURICATA_TFTP_FILE_CONFIG } {
+                return AppLayerGetFileState { fc: &mut tx.file_tracker.file, cfg: sfcm.files_sbcfg }
+            }
+        }
+    }
+    AppLayerGetFileState::err()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_tftp_init(context: &'static mut SuricataFileContext)
+{
+    SURICATA_TFTP_FILE_CONFIG = Some(context);
 }
 
 #[no_mangle]
@@ -217,30 +588,28 @@ mod test {
 
     #[test]
     pub fn test_parse_tftp_read_request_1() {
-        let tx = TFTPTransaction {
+        let req = TFTPRequest {
             opcode: READREQUEST,
             filename: String::from("rfc1350.txt"),
             mode: String::from("octet"),
-            id: 0,
-            tx_data: AppLayerTxData::new(),
+            options: TFTPOptions::default(),
         };
 
         let txp = parse_tftp_request(&READ_REQUEST[..]).unwrap();
-        assert_eq!(tx, txp);
+        assert_eq!(req, txp);
     }
 
     #[test]
     pub fn test_parse_tftp_write_request_1() {
-        let tx = TFTPTransaction {
+        let req = TFTPRequest {
             opcode: WRITEREQUEST,
             filename: String::from("rfc1350.txt"),
             mode: String::from("octet"),
-            id: 0,
-            tx_data: AppLayerTxData::new(),
+            options: TFTPOptions::default(),
         };
 
         let txp = parse_tftp_request(&WRITE_REQUEST[..]).unwrap();
-        assert_eq!(tx, txp);
+        assert_eq!(req, txp);
     }
 
     // Invalid request: filename not terminated
@@ -265,4 +634,127 @@ mod test {
 
         assert_eq!(None, parse_tftp_request(&INVALID_MODE[..]));
     }
+
+    #[test]
+    pub fn test_parse_tftp_request_options() {
+        let buf: &[u8] = b"\x00\x01pxelinux.0\x00octet\x00tsize\x000\x00BLKSIZE\x001468\x00timeout\x005\x00";
+        let req = parse_tftp_request(buf).unwrap();
+        assert_eq!(req.filename, "pxelinux.0");
+        assert_eq!(req.options, TFTPOptions {
+            blksize: Some(1468),
+            timeout: Some(5),
+            tsize: Some(0),
+        });
+    }
+
+    #[test]
+    pub fn test_parse_tftp_packets() {
+        assert_eq!(parse_tftp_packet(b"\x00\x03\x00\x01abc"),
+                   Some(TFTPPacket::Data(1, b"abc")));
+        assert_eq!(parse_tftp_packet(b"\x00\x04\x00\x02"),
+                   Some(TFTPPacket::Ack(2)));
+        assert_eq!(parse_tftp_packet(b"\x00\x05\x00\x01File not found\x00"),
+                   Some(TFTPPacket::Error(TFTPError {
+                       code: 1,
+                       message: String::from("File not found"),
+                   })));
+        assert_eq!(parse_tftp_packet(b"\x00\x06tsize\x001024\x00"),
+                   Some(TFTPPacket::OptionAck(TFTPOptions {
+                       tsize: Some(1024),
+                       ..Default::default()
+                   })));
+        assert_eq!(parse_tftp_packet(b"\x00\x07\x00\x01"), None);
+    }
+
+    fn data_packet(block: u16, data: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0x00, 0x03];
+        buf.extend_from_slice(&block.to_be_bytes());
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    /// Returns the state of the data channel of a read request for a
+    /// block size of 8, which the server acknowledged
+    fn read_transfer() -> TFTPState {
+        let rrq: &[u8] = b"\x00\x01pxelinux.0\x00octet\x00blksize\x008\x00";
+        let mut control = TFTPState::new();
+        assert_eq!(control.parse(rrq, Direction::ToServer), 1);
+        assert_eq!(control.transactions[0].options.blksize, Some(8));
+
+        let mut state = TFTPState::new();
+        state.set_transfer(parse_tftp_request(rrq).unwrap());
+        assert_eq!(state.parse(b"\x00\x06blksize\x008\x00", Direction::ToClient), 0);
+        assert_eq!(state.parse(b"\x00\x04\x00\x00", Direction::ToServer), 0);
+        state
+    }
+
+    #[test]
+    pub fn test_tftp_transfer() {
+        let mut state = read_transfer();
+        // a full block is not the last one with the acknowledged block size
+        assert_eq!(state.parse(&data_packet(1, b"01234567"), Direction::ToClient), 0);
+        assert!(!state.transactions[0].complete);
+        // retransmission, and DATA in the wrong direction
+        assert_eq!(state.parse(&data_packet(1, b"01234567"), Direction::ToClient), 0);
+        assert_eq!(state.parse(&data_packet(2, b"abc"), Direction::ToServer), 0);
+        assert_eq!(state.parse(b"\x00\x04\x00\x01", Direction::ToServer), 0);
+        assert_eq!(state.parse(&data_packet(2, b"abc"), Direction::ToClient), 0);
+        let tx = &state.transactions[0];
+        assert_eq!(tx.oack.as_ref().unwrap().blksize, Some(8));
+        assert_eq!(tx.blocks, 2);
+        assert_eq!(tx.retransmits, 1);
+        assert_eq!(tx.size, 11);
+        assert!(tx.complete);
+        assert!(!tx.truncated);
+        // the last block again
+        assert_eq!(state.parse(&data_packet(2, b"abc"), Direction::ToClient), 0);
+        assert_eq!(state.transactions[0].retransmits, 2);
+        assert_eq!(state.transactions[0].blocks, 2);
+    }
+
+    #[test]
+    pub fn test_tftp_transfer_rollover() {
+        let mut state = read_transfer();
+        for block in 1..=u16::MAX {
+            state.parse(&data_packet(block, b"01234567"), Direction::ToClient);
+        }
+        // block numbers roll over to 0
+        assert_eq!(state.parse(&data_packet(0, b"01234567"), Direction::ToClient), 0);
+        assert_eq!(state.parse(&data_packet(1, b"0"), Direction::ToClient), 0);
+        let tx = &state.transactions[0];
+        assert_eq!(tx.blocks, 65537);
+        assert_eq!(tx.retransmits, 0);
+        assert_eq!(tx.size, 65536 * 8 + 1);
+        assert!(tx.complete);
+        assert!(!tx.truncated);
+    }
+
+    #[test]
+    pub fn test_tftp_transfer_gap() {
+        let mut state = read_transfer();
+        assert_eq!(state.parse(&data_packet(1, b"01234567"), Direction::ToClient), 0);
+        // block 2 was missed
+        assert_eq!(state.parse(&data_packet(3, b"01234567"), Direction::ToClient), 0);
+        let tx = &state.transactions[0];
+        assert_eq!(tx.blocks, 1);
+        assert!(tx.truncated);
+        assert!(tx.complete);
+        // nothing is added once truncated
+        assert_eq!(state.parse(&data_packet(2, b"01234567"), Direction::ToClient), 0);
+        assert_eq!(state.transactions[0].blocks, 1);
+    }
+
+    #[test]
+    pub fn test_tftp_transfer_error() {
+        let mut state = read_transfer();
+        assert_eq!(state.parse(&data_packet(1, b"01234567"), Direction::ToClient), 0);
+        assert_eq!(state.parse(b"\x00\x05\x00\x03Disk full\x00", Direction::ToServer), 0);
+        let tx = &state.transactions[0];
+        assert_eq!(tx.error, Some(TFTPError {
+            code: 3,
+            message: String::from("Disk full"),
+        }));
+        assert_eq!(tx.blocks, 1);
+        assert!(tx.complete);
+    }
 }
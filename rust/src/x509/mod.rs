@@ -26,7 +26,7 @@ use std::os::raw::c_char;
 use std::fmt;
 use x509_parser::prelude::*;
 use crate::x509::GeneralName;
-mod time;
+pub(crate) mod time;
 mod log;
 
 #[repr(u32)]
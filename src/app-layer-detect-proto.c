@@ -2041,9 +2041,13 @@ void AppLayerProtoDetectSupportedIpprotos(AppProto alproto, uint8_t *ipprotos)
     if (alproto == ALPROTO_HTTP) {
         AppLayerProtoDetectSupportedIpprotos(ALPROTO_HTTP1, ipprotos);
         AppLayerProtoDetectSupportedIpprotos(ALPROTO_HTTP2, ipprotos);
+        AppLayerProtoDetectSupportedIpprotos(ALPROTO_HTTP3, ipprotos);
     } else if (alproto == ALPROTO_DOH2) {
         // DOH2 is not detected, just HTTP2
         AppLayerProtoDetectSupportedIpprotos(ALPROTO_HTTP2, ipprotos);
+    } else if (alproto == ALPROTO_HTTP3) {
+        // HTTP3 is not detected, just QUIC
+        AppLayerProtoDetectSupportedIpprotos(ALPROTO_QUIC, ipprotos);
     } else {
         AppLayerProtoDetectPMGetIpprotos(alproto, ipprotos);
         AppLayerProtoDetectPPGetIpprotos(alproto, ipprotos);
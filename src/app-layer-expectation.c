@@ -57,18 +57,24 @@
 
 #include "ippair-storage.h"
 #include "flow-storage.h"
+#include "flow-util.h"
 
 #include "app-layer-expectation.h"
 
 #include "util-print.h"
+#include "util-unittest.h"
 
 static IPPairStorageId g_ippair_expectation_id = { .id = -1 };
 static FlowStorageId g_flow_expectation_id = { .id = -1 };
+static FlowStorageId g_flow_expectation_addr_id = { .id = -1 };
 
 SC_ATOMIC_DECLARE(uint32_t, expectation_count);
 
 #define EXPECTATION_TIMEOUT 30
 #define EXPECTATION_MAX_LEVEL 10
+/* maximum number of IP pairs, other than its own, a Flow can create
+ * expectations for */
+#define EXPECTATION_MAX_ADDR_PAIRS 64
 
 typedef struct Expectation_ {
     SCTime_t ts;
@@ -93,6 +99,16 @@ typedef struct ExpectationList_ {
     uint8_t length;
 } ExpectationList;
 
+/** IP pairs a Flow created expectations for with
+ *  AppLayerExpectationCreateAddr(), so they can be cleaned with the Flow */
+typedef struct ExpectationAddrList_ {
+    uint8_t length;
+    struct {
+        Address src;
+        Address dst;
+    } pairs[EXPECTATION_MAX_ADDR_PAIRS];
+} ExpectationAddrList;
+
 static void ExpectationDataFree(void *e)
 {
     SCLogDebug("Free expectation data");
@@ -137,6 +153,11 @@ static void ExpectationListFree(void *el)
     SCFree(exp_list);
 }
 
+static void ExpectationAddrListFree(void *al)
+{
+    SCFree(al);
+}
+
 uint64_t ExpectationGetCounter(void)
 {
     uint64_t x = SC_ATOMIC_GET(expectation_count);
@@ -149,6 +170,8 @@ void AppLayerExpectationSetup(void)
             IPPairStorageRegister("expectation", sizeof(void *), NULL, ExpectationListFree);
     g_flow_expectation_id =
             FlowStorageRegister("expectation", sizeof(void *), NULL, ExpectationDataFree);
+    g_flow_expectation_addr_id = FlowStorageRegister(
+            "expectation-addr", sizeof(void *), NULL, ExpectationAddrListFree);
     SC_ATOMIC_INIT(expectation_count);
 }
 
@@ -168,12 +191,9 @@ static inline int GetFlowAddresses(Flow *f, Address *ip_src, Address *ip_dst)
     return 0;
 }
 
-static ExpectationList *AppLayerExpectationLookup(Flow *f, IPPair **ipp)
+static ExpectationList *ExpectationLookup(Address *ip_src, Address *ip_dst, IPPair **ipp)
 {
-    Address ip_src, ip_dst;
-    if (GetFlowAddresses(f, &ip_src, &ip_dst) == -1)
-        return NULL;
-    *ipp = IPPairLookupIPPairFromHash(&ip_src, &ip_dst);
+    *ipp = IPPairLookupIPPairFromHash(ip_src, ip_dst);
     if (*ipp == NULL) {
         return NULL;
     }
@@ -181,6 +201,14 @@ static ExpectationList *AppLayerExpectationLookup(Flow *f, IPPair **ipp)
     return IPPairGetStorageById(*ipp, g_ippair_expectation_id);
 }
 
+static ExpectationList *AppLayerExpectationLookup(Flow *f, IPPair **ipp)
+{
+    Address ip_src, ip_dst;
+    if (GetFlowAddresses(f, &ip_src, &ip_dst) == -1)
+        return NULL;
+    return ExpectationLookup(&ip_src, &ip_dst, ipp);
+}
+
 
 static ExpectationList *AppLayerExpectationRemove(IPPair *ipp,
                                                   ExpectationList *exp_list,
@@ -189,6 +217,8 @@ static ExpectationList *AppLayerExpectationRemove(IPPair *ipp,
     CIRCLEQ_REMOVE(&exp_list->list, exp, entries);
     AppLayerFreeExpectation(exp);
     SC_ATOMIC_SUB(expectation_count, 1);
+    /* drop the reference taken when the expectation was created */
+    IPPairDecrUsecnt(ipp);
     exp_list->length--;
     if (exp_list->length == 0) {
         IPPairSetStorageById(ipp, g_ippair_expectation_id, NULL);
@@ -198,30 +228,11 @@ static ExpectationList *AppLayerExpectationRemove(IPPair *ipp,
     return exp_list;
 }
 
-/**
- * Create an entry in expectation list
- *
- * Create a expectation from an existing Flow. Currently, only Flow between
- * the two original IP addresses are supported. In case of success, the
- * ownership of the data pointer is taken. In case of error, the pointer
- * to data has to be freed by the caller.
- *
- * \param f a pointer to the original Flow
- * \param direction the direction of the data in the expectation flow
- * \param src source port of the expected flow, use 0 for any
- * \param dst destination port of the expected flow, use 0 for any
- * \param alproto the protocol that need to be set on the expected flow
- * \param data pointer to data that will be attached to the expected flow
- *
- * \return -1 if error
- * \return 0 if success
- */
-int AppLayerExpectationCreate(Flow *f, int direction, Port src, Port dst,
-                              AppProto alproto, void *data)
+static int ExpectationCreate(Flow *f, Address *ip_src, Address *ip_dst, int direction, Port src,
+        Port dst, AppProto alproto, void *data)
 {
     ExpectationList *exp_list = NULL;
     IPPair *ipp;
-    Address ip_src, ip_dst;
 
     Expectation *exp = SCCalloc(1, sizeof(*exp));
     if (exp == NULL)
@@ -235,9 +246,7 @@ int AppLayerExpectationCreate(Flow *f, int direction, Port src, Port dst,
     exp->data = data;
     exp->direction = direction;
 
-    if (GetFlowAddresses(f, &ip_src, &ip_dst) == -1)
-        goto error;
-    ipp = IPPairGetIPPairFromHash(&ip_src, &ip_dst);
+    ipp = IPPairGetIPPairFromHash(ip_src, ip_dst);
     if (ipp == NULL)
         goto error;
 
@@ -258,8 +267,10 @@ int AppLayerExpectationCreate(Flow *f, int direction, Port src, Port dst,
         }
     } else {
         exp_list = SCCalloc(1, sizeof(*exp_list));
-        if (exp_list == NULL)
+        if (exp_list == NULL) {
+            IPPairRelease(ipp);
             goto error;
+        }
         exp_list->length = 0;
         CIRCLEQ_INIT(&exp_list->list);
         CIRCLEQ_INSERT_HEAD(&exp_list->list, exp, entries);
@@ -280,6 +291,117 @@ error:
     return -1;
 }
 
+/**
+ * Create an entry in expectation list
+ *
+ * Create a expectation from an existing Flow. The expected Flow is between
+ * the two original IP addresses, see AppLayerExpectationCreateAddr() for
+ * other addresses. In case of success, the
+ * ownership of the data pointer is taken. In case of error, the pointer
+ * to data has to be freed by the caller.
+ *
+ * \param f a pointer to the original Flow
+ * \param direction the direction of the data in the expectation flow
+ * \param src source port of the expected flow, use 0 for any
+ * \param dst destination port of the expected flow, use 0 for any
+ * \param alproto the protocol that need to be set on the expected flow
+ * \param data pointer to data that will be attached to the expected flow
+ *
+ * \return -1 if error
+ * \return 0 if success
+ */
+int AppLayerExpectationCreate(Flow *f, int direction, Port src, Port dst,
+                              AppProto alproto, void *data)
+{
+    Address ip_src, ip_dst;
+
+    if (GetFlowAddresses(f, &ip_src, &ip_dst) == -1)
+        return -1;
+    return ExpectationCreate(f, &ip_src, &ip_dst, direction, src, dst, alproto, data);
+}
+
+/**
+ * Create an entry in expectation list for an explicit IP pair
+ *
+ * Same as AppLayerExpectationCreate() but the expected Flow is between
+ * the two given addresses instead of the addresses of the original Flow.
+ * This is used by protocols negotiating the addresses of another Flow,
+ * like SDP does for RTP. The expectation stays linked to the original
+ * Flow and is cleaned with it. A Flow can create expectations for at
+ * most EXPECTATION_MAX_ADDR_PAIRS IP pairs.
+ *
+ * \param f a pointer to the original Flow
+ * \param direction the direction of the data in the expectation flow
+ * \param src_addr address of one side of the expected flow, in network order
+ * \param dst_addr address of the other side of the expected flow, in network order
+ * \param addr_len length of the addresses, 4 for IPv4 and 16 for IPv6
+ * \param src source port of the expected flow, use 0 for any
+ * \param dst destination port of the expected flow, use 0 for any
+ * \param alproto the protocol that need to be set on the expected flow
+ * \param data pointer to data that will be attached to the expected flow
+ *
+ * \return -1 if error
+ * \return 0 if success
+ */
+int AppLayerExpectationCreateAddr(Flow *f, int direction, const uint8_t *src_addr,
+        const uint8_t *dst_addr, uint8_t addr_len, Port src, Port dst, AppProto alproto,
+        void *data)
+{
+    Address ip_src, ip_dst;
+
+    memset(&ip_src, 0, sizeof(ip_src));
+    memset(&ip_dst, 0, sizeof(ip_dst));
+    if (addr_len == 4) {
+        ip_src.family = AF_INET;
+        ip_dst.family = AF_INET;
+    } else if (addr_len == 16) {
+        ip_src.family = AF_INET6;
+        ip_dst.family = AF_INET6;
+    } else {
+        return -1;
+    }
+    memcpy(ip_src.addr_data8, src_addr, addr_len);
+    memcpy(ip_dst.addr_data8, dst_addr, addr_len);
+
+    /* remember the IP pair, AppLayerExpectationClean() only knows about
+     * the one of the Flow */
+    ExpectationAddrList *addr_list = FlowGetStorageById(f, g_flow_expectation_addr_id);
+    if (addr_list == NULL) {
+        addr_list = SCCalloc(1, sizeof(*addr_list));
+        if (addr_list == NULL)
+            return -1;
+        FlowSetStorageById(f, g_flow_expectation_addr_id, addr_list);
+    }
+    uint8_t i;
+    for (i = 0; i < addr_list->length; i++) {
+        if (CMP_ADDR(&addr_list->pairs[i].src, &ip_src) &&
+                CMP_ADDR(&addr_list->pairs[i].dst, &ip_dst))
+            break;
+    }
+    if (i == addr_list->length) {
+        if (addr_list->length >= EXPECTATION_MAX_ADDR_PAIRS) {
+            SCLogDebug("too many IP pairs with expectations for this flow");
+            return -1;
+        }
+        COPY_ADDRESS(&ip_src, &addr_list->pairs[i].src);
+        COPY_ADDRESS(&ip_dst, &addr_list->pairs[i].dst);
+        addr_list->length++;
+    }
+    return ExpectationCreate(f, &ip_src, &ip_dst, direction, src, dst, alproto, data);
+}
+
+/**
+ * Return the expectation data attached to a Flow
+ *
+ * The data is still owned by the Flow and is freed with it.
+ *
+ * \return pointer to the expectation data or NULL if there is none
+ */
+void *AppLayerExpectationGetData(Flow *f)
+{
+    return FlowGetStorageById(f, g_flow_expectation_id);
+}
+
 /**
  * Return Flow storage identifier corresponding to expectation data
  *
@@ -358,19 +480,17 @@ out:
     return alproto;
 }
 
-void AppLayerExpectationClean(Flow *f)
+/**
+ * Remove the expectations of a Flow for an IP pair
+ */
+static void ExpectationClean(Flow *f, Address *ip_src, Address *ip_dst)
 {
     IPPair *ipp = NULL;
     Expectation *exp = NULL;
     Expectation *pexp = NULL;
 
-    int x = SC_ATOMIC_GET(expectation_count);
-    if (x == 0) {
-        return;
-    }
-
     /* Call will take reference of the ip pair in 'ipp' */
-    ExpectationList *exp_list = AppLayerExpectationLookup(f, &ipp);
+    ExpectationList *exp_list = ExpectationLookup(ip_src, ip_dst, &ipp);
     if (exp_list == NULL)
         goto out;
 
@@ -388,6 +508,100 @@ out:
         IPPairRelease(ipp);
 }
 
+void AppLayerExpectationClean(Flow *f)
+{
+    Address ip_src, ip_dst;
+
+    int x = SC_ATOMIC_GET(expectation_count);
+    if (x == 0) {
+        return;
+    }
+
+    if (GetFlowAddresses(f, &ip_src, &ip_dst) == 0) {
+        ExpectationClean(f, &ip_src, &ip_dst);
+    }
+
+    /* expectations created for other IP pairs */
+    ExpectationAddrList *addr_list = FlowGetStorageById(f, g_flow_expectation_addr_id);
+    if (addr_list != NULL) {
+        for (uint8_t i = 0; i < addr_list->length; i++) {
+            ExpectationClean(f, &addr_list->pairs[i].src, &addr_list->pairs[i].dst);
+        }
+    }
+}
+
+#ifdef UNITTESTS
+/**
+ * \test The expectations of a Flow for its own IP pair and for another one
+ *       are cleaned with the Flow, and the IP pairs are not kept in use.
+ */
+static int AppLayerExpectationTest01(void)
+{
+    StorageInit();
+    AppLayerExpectationSetup();
+    FAIL_IF(StorageFinalize() < 0);
+    IPPairInitConfig(true);
+    FlowInitConfig(FLOW_QUIET);
+
+    /* SIP flow between 10.0.0.1 and 10.0.0.2 */
+    Flow *f = FlowAlloc();
+    FAIL_IF_NULL(f);
+    f->flags |= FLOW_IPV4;
+    f->src.addr_data32[0] = htonl(0x0a000001);
+    f->dst.addr_data32[0] = htonl(0x0a000002);
+
+    /* media negotiated between 192.168.0.1 and 192.168.0.2 */
+    const uint8_t media_src[4] = { 192, 168, 0, 1 };
+    const uint8_t media_dst[4] = { 192, 168, 0, 2 };
+    FAIL_IF(AppLayerExpectationCreate(f, STREAM_TOSERVER, 0, 5004, ALPROTO_FTPDATA, NULL) != 0);
+    FAIL_IF(AppLayerExpectationCreateAddr(
+                    f, STREAM_TOSERVER, media_src, media_dst, 4, 0, 5004, ALPROTO_FTPDATA, NULL) !=
+            0);
+    FAIL_IF(AppLayerExpectationCreateAddr(
+                    f, STREAM_TOCLIENT, media_src, media_dst, 4, 5004, 0, ALPROTO_FTPDATA, NULL) !=
+            0);
+    FAIL_IF(ExpectationGetCounter() != 3);
+    FAIL_IF_NOT(f->flags & FLOW_HAS_EXPECTATION);
+
+    Address a, b;
+    memset(&a, 0, sizeof(a));
+    memset(&b, 0, sizeof(b));
+    a.family = AF_INET;
+    b.family = AF_INET;
+    memcpy(a.addr_data8, media_src, sizeof(media_src));
+    memcpy(b.addr_data8, media_dst, sizeof(media_dst));
+    IPPair *ipp = IPPairLookupIPPairFromHash(&a, &b);
+    FAIL_IF_NULL(ipp);
+    FAIL_IF_NULL(IPPairGetStorageById(ipp, g_ippair_expectation_id));
+    /* one reference per expectation, and the lookup one */
+    FAIL_IF(SC_ATOMIC_GET(ipp->use_cnt) != 3);
+    IPPairRelease(ipp);
+
+    /* the SIP flow ends with the media expectations pending */
+    FlowClearMemory(f, FlowGetProtoMapping(IPPROTO_UDP));
+    FAIL_IF(ExpectationGetCounter() != 0);
+
+    ipp = IPPairLookupIPPairFromHash(&a, &b);
+    FAIL_IF_NULL(ipp);
+    FAIL_IF_NOT_NULL(IPPairGetStorageById(ipp, g_ippair_expectation_id));
+    FAIL_IF(SC_ATOMIC_GET(ipp->use_cnt) != 1);
+    IPPairRelease(ipp);
+
+    FlowFree(f);
+    FlowShutdown();
+    IPPairShutdown();
+    StorageCleanup();
+    PASS;
+}
+#endif /* UNITTESTS */
+
+void AppLayerExpectationRegisterTests(void)
+{
+#ifdef UNITTESTS
+    UtRegisterTest("AppLayerExpectationTest01", AppLayerExpectationTest01);
+#endif /* UNITTESTS */
+}
+
 /**
  * @}
  */
@@ -29,6 +29,10 @@
 void AppLayerExpectationSetup(void);
 int AppLayerExpectationCreate(Flow *f, int direction, Port src, Port dst,
                               AppProto alproto, void *data);
+int AppLayerExpectationCreateAddr(Flow *f, int direction, const uint8_t *src_addr,
+        const uint8_t *dst_addr, uint8_t addr_len, Port src, Port dst, AppProto alproto,
+        void *data);
+void *AppLayerExpectationGetData(Flow *f);
 AppProto AppLayerExpectationHandle(Flow *f, uint8_t flags);
 FlowStorageId AppLayerExpectationGetFlowId(void);
 
@@ -36,4 +40,6 @@ void AppLayerExpectationClean(Flow *f);
 
 uint64_t ExpectationGetCounter(void);
 
+void AppLayerExpectationRegisterTests(void);
+
 #endif /* SURICATA_APP_LAYER_EXPECTATION__H */
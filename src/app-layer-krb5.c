@@ -0,0 +1,98 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Host storage of the Kerberos 5 authentication timelines.
+ *
+ * The timelines themselves are kept by the Rust krb5 parser, which calls
+ * SCKrb5HostUpdate for each AS or TGS message it parses.
+ */
+
+#include "suricata-common.h"
+#include "app-layer-krb5.h"
+#include "host-storage.h"
+#include "rust.h"
+
+static HostStorageId g_krb5_host_id = { .id = -1 };
+
+static void Krb5HostTrackerFree(void *ptr)
+{
+    if (ptr != NULL)
+        rs_krb5_host_tracker_free(ptr);
+}
+
+/** \brief register the host storage, called when the krb5 parser is
+ *         registered with correlation enabled */
+void SCKrb5HostStorageRegister(void)
+{
+    g_krb5_host_id = HostStorageRegister("krb5", sizeof(void *), NULL, Krb5HostTrackerFree);
+}
+
+/** \brief update the timeline of the client of a flow with a transaction
+ *
+ *  The client is the source of the flow. Its host is locked while the
+ *  timeline is updated.
+ *
+ *  \retval true if the Kerberoasting threshold was reached
+ */
+bool SCKrb5HostUpdate(const Flow *f, void *tx)
+{
+    if (g_krb5_host_id.id == -1)
+        return false;
+
+    Address addr;
+    memset(&addr, 0, sizeof(addr));
+    if (FLOW_IS_IPV4(f)) {
+        FLOW_COPY_IPV4_ADDR_TO_PACKET(&f->src, &addr);
+    } else if (FLOW_IS_IPV6(f)) {
+        FLOW_COPY_IPV6_ADDR_TO_PACKET(&f->src, &addr);
+    } else {
+        return false;
+    }
+
+    Host *h = HostGetHostFromHash(&addr);
+    if (h == NULL)
+        return false;
+
+    void *tracker = HostGetStorageById(h, g_krb5_host_id);
+    if (tracker == NULL) {
+        tracker = rs_krb5_host_tracker_new();
+        HostSetStorageById(h, g_krb5_host_id, tracker);
+    }
+    bool r = rs_krb5_host_tracker_update(tracker, tx, SCTIME_SECS(f->lastts));
+    HostRelease(h);
+    return r;
+}
+
+/** \brief check if the timeline of a host has timed out
+ *
+ *  \param h host *LOCKED*
+ *
+ *  \retval 1 no timeline or timed out, 0 still active
+ */
+int SCKrb5HostTimeoutCheck(Host *h, SCTime_t ts)
+{
+    if (g_krb5_host_id.id == -1)
+        return 1;
+
+    void *tracker = HostGetStorageById(h, g_krb5_host_id);
+    if (tracker == NULL)
+        return 1;
+    return rs_krb5_host_tracker_timed_out(tracker, SCTIME_SECS(ts)) ? 1 : 0;
+}
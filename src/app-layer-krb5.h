@@ -0,0 +1,34 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Host storage of the Kerberos 5 authentication timelines
+ */
+
+#ifndef SURICATA_APP_LAYER_KRB5_H
+#define SURICATA_APP_LAYER_KRB5_H
+
+#include "flow.h"
+#include "host.h"
+
+void SCKrb5HostStorageRegister(void);
+bool SCKrb5HostUpdate(const Flow *f, void *tx);
+int SCKrb5HostTimeoutCheck(Host *h, SCTime_t ts);
+
+#endif /* SURICATA_APP_LAYER_KRB5_H */
@@ -1149,7 +1149,8 @@ bool AppLayerParserSupportsFiles(uint8_t ipproto, AppProto alproto)
     // Custom case for only signature-only protocol so far
     if (alproto == ALPROTO_HTTP) {
         return AppLayerParserSupportsFiles(ipproto, ALPROTO_HTTP1) ||
-               AppLayerParserSupportsFiles(ipproto, ALPROTO_HTTP2);
+               AppLayerParserSupportsFiles(ipproto, ALPROTO_HTTP2) ||
+               AppLayerParserSupportsFiles(ipproto, ALPROTO_HTTP3);
     }
     return alp_ctx.ctxs[FlowGetProtoMapping(ipproto)][alproto].GetTxFiles != NULL;
 }
@@ -1691,6 +1692,11 @@ static void ValidateParsers(void)
     }
 }
 
+static StreamingBufferConfig pgsql_sbcfg = STREAMING_BUFFER_CONFIG_INITIALIZER;
+static SuricataFileContext pgsql_sfc = { &pgsql_sbcfg };
+static StreamingBufferConfig rfb_sbcfg = STREAMING_BUFFER_CONFIG_INITIALIZER;
+static SuricataFileContext rfb_sfc = { &rfb_sbcfg };
+
 void AppLayerParserRegisterProtocolParsers(void)
 {
     SCEnter();
@@ -1718,15 +1724,19 @@ void AppLayerParserRegisterProtocolParsers(void)
     RegisterIKEParsers();
     rs_register_krb5_parser();
     rs_dhcp_register_parser();
+    rs_dhcpv6_register_parser();
     rs_register_snmp_parser();
     rs_sip_register_parser();
+    rs_rtp_register_parser();
     rs_quic_register_parser();
     rs_websocket_register_parser();
     SCRegisterLdapTcpParser();
     SCRegisterLdapUdpParser();
     rs_template_register_parser();
+    SCRfbInit(&rfb_sfc);
     SCRfbRegisterParser();
     SCMqttRegisterParser();
+    SCPgsqlInit(&pgsql_sfc);
     SCRegisterPgsqlParser();
     rs_rdp_register_parser();
     RegisterHTTP2Parsers();
@@ -64,9 +64,12 @@ enum AppProtoEnum {
     ALPROTO_HTTP2,
     ALPROTO_BITTORRENT_DHT,
     ALPROTO_POP3,
+    ALPROTO_HTTP3,
+    ALPROTO_DHCPV6,
+    ALPROTO_RTP,
 
     // signature-only (ie not seen in flow)
-    // HTTP for any version (ALPROTO_HTTP1 (version 1) or ALPROTO_HTTP2)
+    // HTTP for any version (ALPROTO_HTTP1 (version 1), ALPROTO_HTTP2 or ALPROTO_HTTP3)
     ALPROTO_HTTP,
 
     /* used by the probing parser when alproto detection fails
@@ -102,8 +105,16 @@ static inline bool AppProtoEquals(AppProto sigproto, AppProto alproto)
             // a DOH2 signature accepts dns, http2 or http generic keywords
             return (alproto == ALPROTO_DOH2) || (alproto == ALPROTO_HTTP2) ||
                    (alproto == ALPROTO_DNS) || (alproto == ALPROTO_HTTP);
+        case ALPROTO_QUIC:
+            // a QUIC signature matches on either QUIC or HTTP3 flows
+            return (alproto == ALPROTO_HTTP3) || (alproto == ALPROTO_QUIC);
+        case ALPROTO_HTTP3:
+            // a HTTP3 signature accepts quic or http generic keywords
+            return (alproto == ALPROTO_HTTP3) || (alproto == ALPROTO_QUIC) ||
+                   (alproto == ALPROTO_HTTP);
         case ALPROTO_HTTP:
-            return (alproto == ALPROTO_HTTP1) || (alproto == ALPROTO_HTTP2);
+            return (alproto == ALPROTO_HTTP1) || (alproto == ALPROTO_HTTP2) ||
+                   (alproto == ALPROTO_HTTP3);
         case ALPROTO_DCERPC:
             return (alproto == ALPROTO_SMB);
     }
@@ -126,6 +137,8 @@ static inline AppProto AppProtoCommon(AppProto sigproto, AppProto alproto)
                 return ALPROTO_HTTP1;
             } else if (alproto == ALPROTO_HTTP2) {
                 return ALPROTO_HTTP2;
+            } else if (alproto == ALPROTO_HTTP3) {
+                return ALPROTO_HTTP3;
             }
             break;
         case ALPROTO_HTTP1:
@@ -139,6 +152,12 @@ static inline AppProto AppProtoCommon(AppProto sigproto, AppProto alproto)
                 return ALPROTO_HTTP2;
             }
             break;
+        case ALPROTO_HTTP3:
+            // HTTP3 accepts quic and generic http keywords
+            if (alproto == ALPROTO_HTTP || alproto == ALPROTO_QUIC) {
+                return ALPROTO_HTTP3;
+            }
+            break;
         case ALPROTO_DOH2:
             // DOH2 accepts different protocol keywords
             if (alproto == ALPROTO_HTTP || alproto == ALPROTO_HTTP2 || alproto == ALPROTO_DNS) {
@@ -31,8 +31,10 @@
 #include "app-layer.h"
 #include "app-layer-detect-proto.h"
 #include "app-layer-parser.h"
+#include "app-layer-expectation.h"
 
 #include "app-layer-tftp.h"
+#include "rust.h"
 
 /* The default port to probe if not provided in the configuration file. */
 #define TFTP_DEFAULT_PORT "69"
@@ -41,6 +43,64 @@
  * be the size of a header. */
 #define TFTP_MIN_FRAME_LEN 4
 
+static StreamingBufferConfig sbcfg = STREAMING_BUFFER_CONFIG_INITIALIZER;
+static SuricataFileContext sfc = { &sbcfg };
+
+/** \brief Request handed over to the data channel flow. */
+typedef struct TFTPExpectationData_ {
+    /** needs to be first, see ExpectationData */
+    void (*DFree)(void *);
+    TFTPRequest *transfer;
+    int64_t flow_id;
+} TFTPExpectationData;
+
+static void TFTPExpectationDataFree(void *data)
+{
+    TFTPExpectationData *exp = data;
+    rs_tftp_transfer_free(exp->transfer);
+    SCFree(exp);
+}
+
+/**
+ * \brief Set up the expectation for the data channel of a new request.
+ *
+ * The server answers from a new port to the port the client sent the
+ * request from, so only the destination port is known.
+ */
+static void TFTPCreateExpectation(Flow *f, void *state)
+{
+    TFTPExpectationData *data = SCCalloc(1, sizeof(*data));
+    if (data == NULL)
+        return;
+    data->DFree = TFTPExpectationDataFree;
+    data->transfer = rs_tftp_state_get_transfer(state);
+    data->flow_id = FlowGetId(f);
+    if (data->transfer == NULL) {
+        SCFree(data);
+        return;
+    }
+    if (AppLayerExpectationCreate(
+                f, STREAM_TOSERVER | STREAM_TOCLIENT, 0, f->sp, ALPROTO_TFTP, data) < 0) {
+        SCLogDebug("No expectation created.");
+        TFTPExpectationDataFree(data);
+    }
+}
+
+/**
+ * \brief Pick up the request if this flow is the data channel of a
+ *        transfer.
+ */
+static void TFTPHandleExpectation(Flow *f, void *state)
+{
+    TFTPExpectationData *data = FlowGetStorageById(f, AppLayerExpectationGetFlowId());
+    if (data == NULL)
+        return;
+    rs_tftp_state_set_transfer(state, data->transfer);
+    data->transfer = NULL;
+    f->parent_id = data->flow_id;
+    FlowFreeStorageById(f, AppLayerExpectationGetFlowId());
+}
+
 static void *TFTPStateAlloc(void *orig_state, AppProto proto_orig)
 {
     return rs_tftp_state_alloc();
@@ -108,19 +168,36 @@ static AppLayerResult TFTPParseRequest(Flow *f, void *state, AppLayerParserState
         SCReturnStruct(APP_LAYER_OK);
     }
 
+    TFTPHandleExpectation(f, state);
+
     int64_t res = rs_tftp_request(state, input, input_len);
     if (res < 0) {
         SCReturnStruct(APP_LAYER_ERROR);
     }
+    if (res == 1) {
+        TFTPCreateExpectation(f, state);
+    }
     SCReturnStruct(APP_LAYER_OK);
 }
 
-/**
- * \brief Response parsing is not implemented
- */
 static AppLayerResult TFTPParseResponse(Flow *f, void *state, AppLayerParserState *pstate,
         StreamSlice stream_slice, void *local_data)
 {
+    const uint8_t *input = StreamSliceGetData(&stream_slice);
+    uint32_t input_len = StreamSliceGetDataLen(&stream_slice);
+
+    SCLogDebug("Parsing tftp response: len=%" PRIu32, input_len);
+
+    if (input == NULL || input_len == 0) {
+        SCReturnStruct(APP_LAYER_OK);
+    }
+
+    TFTPHandleExpectation(f, state);
+
+    int64_t res = rs_tftp_response(state, input, input_len);
+    if (res < 0) {
+        SCReturnStruct(APP_LAYER_ERROR);
+    }
     SCReturnStruct(APP_LAYER_OK);
 }
 
@@ -137,19 +214,14 @@ static void *TFTPGetTx(void *state, uint64_t tx_id)
 /**
  * \brief Return the state of a transaction in a given direction.
  *
- * In the case of the tftp protocol, the existence of a transaction
- * means that the request is done. However, some protocols that may
- * need multiple chunks of data to complete the request may need more
- * than just the existence of a transaction for the request to be
- * considered complete.
- *
- * For the response to be considered done, the response for a request
- * needs to be seen.  The response_done flag is set on response for
- * checking here.
+ * In the case of the tftp protocol, the existence of a request
+ * transaction means that the request is done. The transaction
+ * following the transfer on the data channel is done once the last
+ * DATA block or an ERROR has been seen.
  */
 static int TFTPGetStateProgress(void *tx, uint8_t direction)
 {
-    return 1;
+    return rs_tftp_tx_get_alstate_progress(tx, direction);
 }
 
 void RegisterTFTPParsers(void)
@@ -195,6 +267,9 @@ void RegisterTFTPParsers(void)
 
         SCLogDebug("Registering TFTP protocol parser.");
 
+        rs_tftp_init(&sfc);
+        AppLayerRegisterExpectationProto(IPPROTO_UDP, ALPROTO_TFTP);
+
         /* Register functions for state allocation and freeing. A
          * state is allocated for every new TFTP flow. */
         AppLayerParserRegisterStateFuncs(IPPROTO_UDP, ALPROTO_TFTP,
@@ -228,6 +303,8 @@ void RegisterTFTPParsers(void)
         AppLayerParserRegisterGetEventInfo(IPPROTO_UDP, ALPROTO_TFTP,
                                            TFTPStateGetEventInfo);
 
+        AppLayerParserRegisterGetTxFilesFunc(IPPROTO_UDP, ALPROTO_TFTP, rs_tftp_gettxfiles);
+
         AppLayerParserRegisterTxDataFunc(IPPROTO_UDP, ALPROTO_TFTP,
                                          rs_tftp_get_tx_data);
         AppLayerParserRegisterStateDataFunc(IPPROTO_UDP, ALPROTO_TFTP, rs_tftp_get_state_data);
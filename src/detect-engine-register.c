@@ -247,6 +247,8 @@
 #include "detect-ssh-hassh-server.h"
 #include "detect-ssh-hassh-string.h"
 #include "detect-ssh-hassh-server-string.h"
+#include "detect-ssh-kex-algorithm.h"
+#include "detect-ssh-hostkey-fingerprint.h"
 #include "detect-http-stat-code.h"
 #include "detect-ssl-version.h"
 #include "detect-ssl-state.h"
@@ -631,6 +633,8 @@ void SigTableSetup(void)
     DetectSshHasshServerRegister();
     DetectSshHasshStringRegister();
     DetectSshHasshServerStringRegister();
+    DetectSshKexAlgorithmRegister();
+    DetectSshHostkeyFingerprintRegister();
     DetectSslStateRegister();
     DetectSslVersionRegister();
     DetectByteExtractRegister();
@@ -688,12 +692,20 @@ void SigTableSetup(void)
 
     ScDetectSNMPRegister();
     ScDetectDHCPRegister();
+    ScDetectDHCPv6Register();
+    ScDetectNTPRegister();
+    ScDetectSshRegister();
+    ScDetectRdpRegister();
     ScDetectWebsocketRegister();
     ScDetectEnipRegister();
     ScDetectMqttRegister();
     ScDetectRfbRegister();
     ScDetectSipRegister();
     ScDetectTemplateRegister();
+    ScDetectTelnetRegister();
+    ScDetectPgsqlRegister();
+    ScDetectLdapRegister();
+    ScDetectKrb5Register();
 
     /* close keyword registration */
     DetectBufferTypeCloseRegistration();
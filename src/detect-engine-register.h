@@ -193,6 +193,8 @@ enum DetectKeywordId {
     DETECT_AL_SSH_HASSH_SERVER,
     DETECT_AL_SSH_HASSH_STRING,
     DETECT_AL_SSH_HASSH_SERVER_STRING,
+    DETECT_AL_SSH_KEX_ALGORITHM,
+    DETECT_AL_SSH_HOSTKEY_FINGERPRINT,
     DETECT_AL_SSL_VERSION,
     DETECT_AL_SSL_STATE,
     DETECT_FILE_DATA,
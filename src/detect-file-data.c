@@ -130,11 +130,14 @@ static int DetectFiledataSetup (DetectEngineCtx *de_ctx, Signature *s, const cha
     SCEnter();
 
     if (!DetectProtoContainsProto(&s->proto, IPPROTO_TCP)) {
-        SCLogError("The 'file_data' keyword cannot be used with non-TCP protocols");
-        return -1;
-    }
-
-    if (s->alproto != ALPROTO_UNKNOWN && !AppLayerParserSupportsFiles(IPPROTO_TCP, s->alproto)) {
+        // HTTP/3 carries files over QUIC
+        if (!DetectProtoContainsProto(&s->proto, IPPROTO_UDP) || s->alproto == ALPROTO_UNKNOWN ||
+                !AppLayerParserSupportsFiles(IPPROTO_UDP, s->alproto)) {
+            SCLogError("The 'file_data' keyword cannot be used with non-TCP protocols");
+            return -1;
+        }
+    } else if (s->alproto != ALPROTO_UNKNOWN &&
+               !AppLayerParserSupportsFiles(IPPROTO_TCP, s->alproto)) {
         SCLogError("The 'file_data' keyword cannot be used with TCP protocol %s",
                 AppLayerGetProtoName(s->alproto));
         return -1;
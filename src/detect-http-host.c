@@ -71,6 +71,9 @@ static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
 static InspectionBuffer *GetData2(DetectEngineThreadCtx *det_ctx,
         const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
         const int list_id);
+static InspectionBuffer *GetData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id);
 static int DetectHttpHRHSetup(DetectEngineCtx *, Signature *, const char *);
 static int g_http_raw_host_buffer_id = 0;
 static int DetectHttpHostRawSetupSticky(DetectEngineCtx *de_ctx, Signature *s, const char *str);
@@ -80,6 +83,9 @@ static InspectionBuffer *GetRawData(DetectEngineThreadCtx *det_ctx,
 static InspectionBuffer *GetRawData2(DetectEngineThreadCtx *det_ctx,
         const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
         const int list_id);
+static InspectionBuffer *GetRawData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id);
 static int g_http_host_buffer_id = 0;
 
 /**
@@ -117,6 +123,12 @@ void DetectHttpHHRegister(void)
     DetectAppLayerMpmRegister("http_host", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
             GetData2, ALPROTO_HTTP2, HTTP2StateDataClient);
 
+    DetectAppLayerInspectEngineRegister("http_host", ALPROTO_HTTP3, SIG_FLAG_TOSERVER,
+            HTTP2StateDataClient, DetectEngineInspectBufferGeneric, GetData3);
+
+    DetectAppLayerMpmRegister("http_host", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
+            GetData3, ALPROTO_HTTP3, HTTP2StateDataClient);
+
     DetectBufferTypeRegisterValidateCallback("http_host",
             DetectHttpHostValidateCallback);
 
@@ -152,12 +164,39 @@ void DetectHttpHHRegister(void)
     DetectAppLayerMpmRegister("http_raw_host", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
             GetRawData2, ALPROTO_HTTP2, HTTP2StateDataClient);
 
+    DetectAppLayerInspectEngineRegister("http_raw_host", ALPROTO_HTTP3, SIG_FLAG_TOSERVER,
+            HTTP2StateDataClient, DetectEngineInspectBufferGeneric, GetRawData3);
+
+    DetectAppLayerMpmRegister("http_raw_host", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
+            GetRawData3, ALPROTO_HTTP3, HTTP2StateDataClient);
+
     DetectBufferTypeSetDescriptionByName("http_raw_host",
             "http raw host header");
 
     g_http_raw_host_buffer_id = DetectBufferTypeGetByName("http_raw_host");
 }
 
+static InspectionBuffer *GetData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id)
+{
+    // HTTP3 requests use the HTTP2 representation of headers
+    void *tx_http2 = SCHttp3TxGetHttp2Tx(txv);
+    if (tx_http2 == NULL)
+        return NULL;
+    return GetData2(det_ctx, transforms, _f, _flow_flags, tx_http2, list_id);
+}
+
+static InspectionBuffer *GetRawData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id)
+{
+    void *tx_http2 = SCHttp3TxGetHttp2Tx(txv);
+    if (tx_http2 == NULL)
+        return NULL;
+    return GetRawData2(det_ctx, transforms, _f, _flow_flags, tx_http2, list_id);
+}
+
 /**
  * \brief The setup function for the http_host keyword for a signature.
  *
@@ -69,6 +69,9 @@ static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
 static InspectionBuffer *GetData2(DetectEngineThreadCtx *det_ctx,
         const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
         const int list_id);
+static InspectionBuffer *GetData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id);
 static int DetectHttpUriSetupSticky(DetectEngineCtx *de_ctx, Signature *s, const char *str);
 static int DetectHttpRawUriSetup(DetectEngineCtx *, Signature *, const char *);
 static void DetectHttpRawUriSetupCallback(const DetectEngineCtx *de_ctx,
@@ -119,6 +122,12 @@ void DetectHttpUriRegister (void)
     DetectAppLayerMpmRegister("http_uri", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
             GetData2, ALPROTO_HTTP2, HTTP2StateDataClient);
 
+    DetectAppLayerInspectEngineRegister("http_uri", ALPROTO_HTTP3, SIG_FLAG_TOSERVER,
+            HTTP2StateDataClient, DetectEngineInspectBufferGeneric, GetData3);
+
+    DetectAppLayerMpmRegister("http_uri", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
+            GetData3, ALPROTO_HTTP3, HTTP2StateDataClient);
+
     DetectBufferTypeSetDescriptionByName("http_uri",
             "http request uri");
 
@@ -158,6 +167,12 @@ void DetectHttpUriRegister (void)
     DetectAppLayerMpmRegister("http_raw_uri", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
             GetData2, ALPROTO_HTTP2, HTTP2StateDataClient);
 
+    DetectAppLayerInspectEngineRegister("http_raw_uri", ALPROTO_HTTP3, SIG_FLAG_TOSERVER,
+            HTTP2StateDataClient, DetectEngineInspectBufferGeneric, GetData3);
+
+    DetectAppLayerMpmRegister("http_raw_uri", SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
+            GetData3, ALPROTO_HTTP3, HTTP2StateDataClient);
+
     DetectBufferTypeSetDescriptionByName("http_raw_uri",
             "raw http uri");
 
@@ -170,6 +185,17 @@ void DetectHttpUriRegister (void)
     g_http_raw_uri_buffer_id = DetectBufferTypeGetByName("http_raw_uri");
 }
 
+static InspectionBuffer *GetData3(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t _flow_flags, void *txv,
+        const int list_id)
+{
+    // HTTP3 requests use the HTTP2 representation of headers
+    void *tx_http2 = SCHttp3TxGetHttp2Tx(txv);
+    if (tx_http2 == NULL)
+        return NULL;
+    return GetData2(det_ctx, transforms, _f, _flow_flags, tx_http2, list_id);
+}
+
 /**
  * \brief this function setups the http_uri modifier keyword used in the rule
  *
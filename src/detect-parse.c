@@ -98,7 +98,14 @@ void DetectFileRegisterFileProtocols(DetectFileHandlerTableElmt *reg)
                 .direction = SIG_FLAG_TOSERVER | SIG_FLAG_TOCLIENT,
                 .to_client_progress = HTTP2StateDataServer,
                 .to_server_progress = HTTP2StateDataClient },
-        { .al_proto = ALPROTO_SMTP, .direction = SIG_FLAG_TOSERVER }
+        { .al_proto = ALPROTO_HTTP3,
+                .direction = SIG_FLAG_TOSERVER | SIG_FLAG_TOCLIENT,
+                .to_client_progress = HTTP2StateDataServer,
+                .to_server_progress = HTTP2StateDataClient },
+        { .al_proto = ALPROTO_SMTP, .direction = SIG_FLAG_TOSERVER },
+        { .al_proto = ALPROTO_TFTP, .direction = SIG_FLAG_TOSERVER | SIG_FLAG_TOCLIENT },
+        { .al_proto = ALPROTO_RFB, .direction = SIG_FLAG_TOSERVER | SIG_FLAG_TOCLIENT },
+        { .al_proto = ALPROTO_PGSQL, .direction = SIG_FLAG_TOSERVER | SIG_FLAG_TOCLIENT }
     };
 
     for (size_t i = 0; i < ARRAY_SIZE(al_protocols); i++) {
@@ -0,0 +1,106 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the ssh.kex_algorithm sticky buffer, matching on the
+ * negotiated key exchange algorithm
+ */
+
+#include "suricata-common.h"
+#include "threads.h"
+#include "decode.h"
+
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-mpm.h"
+#include "detect-engine-prefilter.h"
+#include "detect-engine-content-inspection.h"
+
+#include "app-layer.h"
+#include "app-layer-parser.h"
+#include "app-layer-ssh.h"
+#include "detect-ssh-kex-algorithm.h"
+#include "rust.h"
+
+#define KEYWORD_NAME "ssh.kex_algorithm"
+#define KEYWORD_DOC "ssh-keywords.html#ssh-kex-algorithm"
+#define BUFFER_NAME "ssh.kex_algorithm"
+#define BUFFER_DESC "SSH negotiated key exchange algorithm"
+static int g_buffer_id = 0;
+
+static InspectionBuffer *GetSshData(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *_f, const uint8_t flow_flags, void *txv,
+        const int list_id)
+{
+    SCEnter();
+
+    InspectionBuffer *buffer = InspectionBufferGet(det_ctx, list_id);
+
+    if (buffer->inspect == NULL) {
+        const uint8_t *data = NULL;
+        uint32_t b_len = 0;
+
+        if (rs_ssh_tx_get_kex_algorithm(txv, &data, &b_len) != 1)
+            return NULL;
+        if (data == NULL || b_len == 0) {
+            SCLogDebug("SSH key exchange algorithm not set");
+            return NULL;
+        }
+
+        InspectionBufferSetup(det_ctx, list_id, buffer, data, b_len);
+        InspectionBufferApplyTransforms(buffer, transforms);
+    }
+
+    return buffer;
+}
+
+static int DetectSshKexAlgorithmSetup(DetectEngineCtx *de_ctx, Signature *s, const char *arg)
+{
+    if (DetectBufferSetActiveList(de_ctx, s, g_buffer_id) < 0)
+        return -1;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_SSH) < 0)
+        return -1;
+
+    return 0;
+}
+
+void DetectSshKexAlgorithmRegister(void)
+{
+    sigmatch_table[DETECT_AL_SSH_KEX_ALGORITHM].name = KEYWORD_NAME;
+    sigmatch_table[DETECT_AL_SSH_KEX_ALGORITHM].desc = BUFFER_NAME " sticky buffer";
+    sigmatch_table[DETECT_AL_SSH_KEX_ALGORITHM].url = "/rules/" KEYWORD_DOC;
+    sigmatch_table[DETECT_AL_SSH_KEX_ALGORITHM].Setup = DetectSshKexAlgorithmSetup;
+    sigmatch_table[DETECT_AL_SSH_KEX_ALGORITHM].flags |=
+            SIGMATCH_INFO_STICKY_BUFFER | SIGMATCH_NOOPT;
+
+    DetectAppLayerMpmRegister(BUFFER_NAME, SIG_FLAG_TOSERVER, 2, PrefilterGenericMpmRegister,
+            GetSshData, ALPROTO_SSH, SshStateBannerDone);
+    DetectAppLayerInspectEngineRegister(BUFFER_NAME, ALPROTO_SSH, SIG_FLAG_TOSERVER,
+            SshStateBannerDone, DetectEngineInspectBufferGeneric, GetSshData);
+    DetectAppLayerMpmRegister(BUFFER_NAME, SIG_FLAG_TOCLIENT, 2, PrefilterGenericMpmRegister,
+            GetSshData, ALPROTO_SSH, SshStateBannerDone);
+    DetectAppLayerInspectEngineRegister(BUFFER_NAME, ALPROTO_SSH, SIG_FLAG_TOCLIENT,
+            SshStateBannerDone, DetectEngineInspectBufferGeneric, GetSshData);
+
+    DetectBufferTypeSetDescriptionByName(BUFFER_NAME, BUFFER_DESC);
+
+    g_buffer_id = DetectBufferTypeGetByName(BUFFER_NAME);
+}
@@ -1074,6 +1074,12 @@ void *DetectGetInnerTx(void *tx_ptr, AppProto alproto, AppProto engine_alproto,
             // incompatible engine->alproto with flow alproto
             tx_ptr = NULL;
         }
+    } else if (unlikely(alproto == ALPROTO_HTTP3)) {
+        // http3 engines get the inner http2 tx themselves
+        if (engine_alproto != ALPROTO_HTTP3 && engine_alproto != ALPROTO_QUIC) {
+            // incompatible engine->alproto with flow alproto
+            tx_ptr = NULL;
+        }
     } else if (engine_alproto != alproto) {
         // incompatible engine->alproto with flow alproto
         tx_ptr = NULL;
@@ -1217,6 +1217,24 @@ uint16_t FlowGetDestinationPort(Flow *flow)
 {
     return flow->dp;
 }
+
+/**
+ * \brief Get flow IPv6 addresses.
+ *
+ * Copies the flow source and destination addresses into the 16 byte
+ * buffers src and dst.
+ *
+ * \retval true if the flow is IPv6, false otherwise
+ */
+bool FlowGetIPv6Addresses(const Flow *flow, uint8_t *src, uint8_t *dst)
+{
+    if (!FLOW_IS_IPV6(flow))
+        return false;
+    memcpy(src, flow->src.addr_data8, 16);
+    memcpy(dst, flow->dst.addr_data8, 16);
+    return true;
+}
+
 /**
  * \brief Get flow flags.
  *
@@ -590,6 +590,7 @@ void FlowGetLastTimeAsParts(Flow *flow, uint64_t *secs, uint64_t *usecs);
 uint32_t FlowGetFlags(Flow *flow);
 uint16_t FlowGetSourcePort(Flow *flow);
 uint16_t FlowGetDestinationPort(Flow *flow);
+bool FlowGetIPv6Addresses(const Flow *flow, uint8_t *src, uint8_t *dst);
 
 /** ----- Inline functions ----- */
 
@@ -27,6 +27,7 @@
 #include "detect-engine-tag.h"
 
 #include "host-bit.h"
+#include "app-layer-krb5.h"
 #include "host-timeout.h"
 
 #include "reputation.h"
@@ -53,6 +54,7 @@ static int HostHostTimedOut(Host *h, SCTime_t ts)
     busy |= (h->iprep && SRepHostTimedOut(h) == 0);
     busy |= (TagHostHasTag(h) && TagTimeoutCheck(h, ts) == 0);
     busy |= (HostHasHostBits(h) && HostBitsTimedoutCheck(h, ts) == 0);
+    busy |= (SCKrb5HostTimeoutCheck(h, ts) == 0);
     SCLogDebug("host %p %s", h, busy ? "still active" : "timed out");
     return !busy;
 }
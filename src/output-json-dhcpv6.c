@@ -0,0 +1,152 @@
+/* Copyright (C) 2024 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the DHCPv6 JSON logger.
+ */
+
+#include "suricata-common.h"
+#include "detect.h"
+#include "pkt-var.h"
+#include "conf.h"
+
+#include "threads.h"
+#include "threadvars.h"
+#include "tm-threads.h"
+
+#include "util-unittest.h"
+#include "util-buffer.h"
+#include "util-debug.h"
+#include "util-byte.h"
+
+#include "output.h"
+#include "output-json.h"
+
+#include "app-layer.h"
+#include "app-layer-parser.h"
+
+#include "output-json-dhcpv6.h"
+#include "rust.h"
+
+
+typedef struct LogDHCPv6FileCtx_ {
+    void       *rs_logger;
+    OutputJsonCtx *eve_ctx;
+} LogDHCPv6FileCtx;
+
+typedef struct LogDHCPv6LogThread_ {
+    LogDHCPv6FileCtx *dhcpv6log_ctx;
+    OutputJsonThreadCtx *thread;
+} LogDHCPv6LogThread;
+
+static int JsonDHCPv6Logger(ThreadVars *tv, void *thread_data,
+    const Packet *p, Flow *f, void *state, void *tx, uint64_t tx_id)
+{
+    LogDHCPv6LogThread *thread = thread_data;
+    LogDHCPv6FileCtx *ctx = thread->dhcpv6log_ctx;
+
+    if (!rs_dhcpv6_logger_do_log(ctx->rs_logger, tx)) {
+        return TM_ECODE_OK;
+    }
+
+    JsonBuilder *js = CreateEveHeader((Packet *)p, 0, "dhcpv6", NULL, ctx->eve_ctx);
+    if (unlikely(js == NULL)) {
+        return TM_ECODE_FAILED;
+    }
+
+    rs_dhcpv6_logger_log(ctx->rs_logger, tx, js);
+
+    OutputJsonBuilderBuffer(js, thread->thread);
+    jb_free(js);
+
+    return TM_ECODE_OK;
+}
+
+static void OutputDHCPv6LogDeInitCtxSub(OutputCtx *output_ctx)
+{
+    LogDHCPv6FileCtx *dhcpv6log_ctx = (LogDHCPv6FileCtx *)output_ctx->data;
+    rs_dhcpv6_logger_free(dhcpv6log_ctx->rs_logger);
+    SCFree(dhcpv6log_ctx);
+    SCFree(output_ctx);
+}
+
+static OutputInitResult OutputDHCPv6LogInitSub(ConfNode *conf,
+    OutputCtx *parent_ctx)
+{
+    OutputInitResult result = { NULL, false };
+
+    LogDHCPv6FileCtx *dhcpv6log_ctx = SCCalloc(1, sizeof(*dhcpv6log_ctx));
+    if (unlikely(dhcpv6log_ctx == NULL)) {
+        return result;
+    }
+    dhcpv6log_ctx->eve_ctx = parent_ctx->data;
+
+    OutputCtx *output_ctx = SCCalloc(1, sizeof(*output_ctx));
+    if (unlikely(output_ctx == NULL)) {
+        SCFree(dhcpv6log_ctx);
+        return result;
+    }
+    output_ctx->data = dhcpv6log_ctx;
+    output_ctx->DeInit = OutputDHCPv6LogDeInitCtxSub;
+
+    dhcpv6log_ctx->rs_logger = rs_dhcpv6_logger_new(conf);
+
+    AppLayerParserRegisterLogger(IPPROTO_UDP, ALPROTO_DHCPV6);
+
+    result.ctx = output_ctx;
+    result.ok = true;
+    return result;
+}
+
+static TmEcode JsonDHCPv6LogThreadInit(ThreadVars *t, const void *initdata, void **data)
+{
+    LogDHCPv6LogThread *thread = SCCalloc(1, sizeof(*thread));
+    if (unlikely(thread == NULL)) {
+        return TM_ECODE_FAILED;
+    }
+    LogDHCPv6FileCtx *ctx = ((OutputCtx *)initdata)->data;
+    thread->dhcpv6log_ctx = ctx;
+    thread->thread = CreateEveThreadCtx(t, ctx->eve_ctx);
+    if (thread->thread == NULL) {
+        SCFree(thread);
+        return TM_ECODE_FAILED;
+    }
+
+    *data = (void *)thread;
+    return TM_ECODE_OK;
+}
+
+static TmEcode JsonDHCPv6LogThreadDeinit(ThreadVars *t, void *data)
+{
+    LogDHCPv6LogThread *thread = (LogDHCPv6LogThread *)data;
+    if (thread == NULL) {
+        return TM_ECODE_OK;
+    }
+    FreeEveThreadCtx(thread->thread);
+    SCFree(thread);
+    return TM_ECODE_OK;
+}
+
+void JsonDHCPv6LogRegister(void)
+{
+    /* Register as an eve sub-module. */
+    OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonDHCPv6Log", "eve-log.dhcpv6",
+            OutputDHCPv6LogInitSub, ALPROTO_DHCPV6, JsonDHCPv6Logger, JsonDHCPv6LogThreadInit,
+            JsonDHCPv6LogThreadDeinit);
+}
@@ -103,6 +103,7 @@
 #define LOG_FORMAT_GROUPED     BIT_U64(60)
 #define LOG_FORMAT_DETAILED    BIT_U64(61)
 #define LOG_HTTPS              BIT_U64(62)
+#define LOG_CAA                BIT_U64(63)
 
 #define LOG_FORMAT_ALL (LOG_FORMAT_GROUPED|LOG_FORMAT_DETAILED)
 #define LOG_ALL_RRTYPES (~(uint64_t)(LOG_QUERIES|LOG_ANSWERS|LOG_FORMAT_DETAILED|LOG_FORMAT_GROUPED))
@@ -167,6 +168,7 @@ typedef enum {
     DNS_RRTYPE_MAILA,
     DNS_RRTYPE_ANY,
     DNS_RRTYPE_URI,
+    DNS_RRTYPE_CAA,
     DNS_RRTYPE_MAX,
 } DnsRRTypes;
 
@@ -233,7 +235,8 @@ static struct {
    { "tsig", LOG_TSIG },
    { "maila", LOG_MAILA },
    { "any", LOG_ANY },
-   { "uri", LOG_URI }
+   { "uri", LOG_URI },
+   { "caa", LOG_CAA }
     // clang-format on
 };
 
@@ -72,6 +72,7 @@
 #include "output-json-smb.h"
 #include "output-json-ike.h"
 #include "output-json-dhcp.h"
+#include "output-json-dhcpv6.h"
 #include "output-json-mqtt.h"
 #include "output-json-pgsql.h"
 #include "output-lua.h"
@@ -884,11 +885,14 @@ void OutputRegisterRootLoggers(void)
     RegisterSimpleJsonApplayerLogger(
             ALPROTO_KRB5, (EveJsonSimpleTxLogFunc)rs_krb5_log_json_response, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_QUIC, rs_quic_to_json, NULL);
+    RegisterSimpleJsonApplayerLogger(ALPROTO_HTTP3, rs_http3_to_json, NULL);
     // ALPROTO_DHCP TODO missing
     RegisterSimpleJsonApplayerLogger(
             ALPROTO_SNMP, (EveJsonSimpleTxLogFunc)rs_snmp_log_json_response, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_SIP, (EveJsonSimpleTxLogFunc)rs_sip_log_json, NULL);
+    RegisterSimpleJsonApplayerLogger(ALPROTO_RTP, rs_rtp_log_json, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_RFB, rs_rfb_logger_log, NULL);
+    RegisterSimpleJsonApplayerLogger(ALPROTO_NTP, rs_ntp_log_json, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_MQTT, JsonMQTTAddMetadata, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_PGSQL, JsonPgsqlAddMetadata, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_WEBSOCKET, rs_websocket_logger_log, NULL);
@@ -896,6 +900,7 @@ void OutputRegisterRootLoggers(void)
     RegisterSimpleJsonApplayerLogger(ALPROTO_DOH2, AlertJsonDoh2, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_TEMPLATE, rs_template_logger_log, NULL);
     RegisterSimpleJsonApplayerLogger(ALPROTO_RDP, (EveJsonSimpleTxLogFunc)rs_rdp_to_json, NULL);
+    RegisterSimpleJsonApplayerLogger(ALPROTO_TELNET, rs_telnet_logger_log, NULL);
     // special case : http2 is logged in http object
     RegisterSimpleJsonApplayerLogger(ALPROTO_HTTP2, rs_http2_log_json, "http");
     // underscore instead of dash for bittorrent_dht
@@ -1047,8 +1052,16 @@ void OutputRegisterLoggers(void)
             JsonLogThreadDeinit);
 
     SCLogDebug("quic json logger registered.");
+    /* HTTP3 JSON logger. */
+    OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonHttp3Log", "eve-log.http3",
+            OutputJsonLogInitSub, ALPROTO_HTTP3, JsonGenericDirPacketLogger, JsonLogThreadInit,
+            JsonLogThreadDeinit);
+
+    SCLogDebug("http3 json logger registered.");
     /* DHCP JSON logger. */
     JsonDHCPLogRegister();
+    /* DHCPv6 JSON logger. */
+    JsonDHCPv6LogRegister();
     /* SNMP JSON logger. */
     OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonSNMPLog", "eve-log.snmp",
             OutputJsonLogInitSub, ALPROTO_SNMP, JsonGenericDirPacketLogger, JsonLogThreadInit,
@@ -1061,6 +1074,18 @@ void OutputRegisterLoggers(void)
             JsonLogThreadDeinit);
 
     SCLogDebug("SIP JSON logger registered.");
+    /* RTP JSON logger. */
+    OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonRTPLog", "eve-log.rtp",
+            OutputJsonLogInitSub, ALPROTO_RTP, JsonGenericDirPacketLogger, JsonLogThreadInit,
+            JsonLogThreadDeinit);
+
+    SCLogDebug("RTP JSON logger registered.");
+    /* NTP JSON logger. */
+    OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonNTPLog", "eve-log.ntp",
+            OutputJsonLogInitSub, ALPROTO_NTP, JsonGenericDirPacketLogger, JsonLogThreadInit,
+            JsonLogThreadDeinit);
+
+    SCLogDebug("NTP JSON logger registered.");
     /* RFB JSON logger. */
     OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonRFBLog", "eve-log.rfb",
             OutputJsonLogInitSub, ALPROTO_RFB, JsonGenericDirPacketLogger, JsonLogThreadInit,
@@ -1092,6 +1117,11 @@ void OutputRegisterLoggers(void)
             OutputJsonLogInitSub, ALPROTO_RDP, JsonGenericDirPacketLogger, JsonLogThreadInit,
             JsonLogThreadDeinit);
     SCLogDebug("rdp json logger registered.");
+    /* Telnet JSON logger. */
+    OutputRegisterTxSubModule(LOGGER_JSON_TX, "eve-log", "JsonTelnetLog", "eve-log.telnet",
+            OutputJsonLogInitSub, ALPROTO_TELNET, JsonGenericDirPacketLogger, JsonLogThreadInit,
+            JsonLogThreadDeinit);
+    SCLogDebug("telnet json logger registered.");
     /* DCERPC JSON logger. */
     JsonDCERPCLogRegister();
     /* app layer frames */
@@ -55,6 +55,7 @@
 #include "stream-tcp.h"
 
 #include "app-layer-detect-proto.h"
+#include "app-layer-expectation.h"
 #include "app-layer-parser.h"
 #include "app-layer.h"
 #include "app-layer-htp.h"
@@ -143,6 +144,7 @@ static void RegisterUnittests(void)
     FlowBitRegisterTests();
     HostBitRegisterTests();
     IPPairBitRegisterTests();
+    AppLayerExpectationRegisterTests();
     StatsRegisterTests();
     DecodeEthernetRegisterTests();
     DecodeCHDLCRegisterTests();